@@ -33,6 +33,7 @@ impl From<Error> for PackageResolverError {
             Error::TypedStore(store_error) => Self::Store {
                 store: STORE,
                 error: store_error.to_string(),
+                retryable: false,
             },
         }
     }
@@ -11,6 +11,7 @@ use futures::future::join_all;
 use prometheus::Registry;
 use std::sync::Arc;
 use sui_core::test_utils::{make_cert_with_large_committee, make_dummy_tx};
+use sui_protocol_config::ProtocolConfig;
 use sui_types::committee::Committee;
 use sui_types::crypto::{get_key_pair, AccountKeyPair, AuthorityKeyPair};
 use sui_types::transaction::CertifiedTransaction;
@@ -82,6 +83,7 @@ fn async_verifier_bench(c: &mut Criterion) {
                         true,
                         true,
                         Some(30),
+                        ProtocolConfig::get_for_max_version_UNSAFE().signature_limits(),
                     ));
 
                     b.iter(|| {
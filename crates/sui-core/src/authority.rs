@@ -4783,16 +4783,15 @@ impl AuthorityState {
         &self,
         epoch_store: &Arc<AuthorityPerEpochStore>,
     ) -> Option<EndOfEpochTransactionKind> {
-        if !epoch_store.protocol_config().enable_jwk_consensus_updates() {
+        let Some(jwk_config) = epoch_store.protocol_config().jwk_config() else {
             info!("authenticator state transactions not enabled");
             return None;
-        }
+        };
 
         let authenticator_state_exists = epoch_store.authenticator_state_exists();
         let tx = if authenticator_state_exists {
             let next_epoch = epoch_store.epoch().checked_add(1).expect("epoch overflow");
-            let min_epoch =
-                next_epoch.saturating_sub(epoch_store.protocol_config().max_age_of_jwk_in_epochs());
+            let min_epoch = next_epoch.saturating_sub(jwk_config.max_age_in_epochs);
             let authenticator_obj_initial_shared_version = epoch_store
                 .epoch_start_config()
                 .authenticator_obj_initial_shared_version()
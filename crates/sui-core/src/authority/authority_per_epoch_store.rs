@@ -776,21 +776,14 @@ impl AuthorityEpochTables {
         protocol_config: &ProtocolConfig,
         transactions: &[VerifiedSequencedConsensusTransaction],
     ) -> SuiResult<impl IntoIterator<Item = (ObjectID, u64)>> {
-        let default_per_commit_budget = protocol_config
-            .max_accumulated_txn_cost_per_object_in_mysticeti_commit_as_option()
+        let per_commit_budget = protocol_config
+            .congestion_control_params()
+            .max_accumulated_txn_cost_per_object(for_randomness)
             .unwrap_or(0);
-        let (table, per_commit_budget) = if for_randomness {
-            (
-                &self.congestion_control_randomness_object_debts,
-                protocol_config
-                    .max_accumulated_randomness_txn_cost_per_object_in_mysticeti_commit_as_option()
-                    .unwrap_or(default_per_commit_budget),
-            )
+        let table = if for_randomness {
+            &self.congestion_control_randomness_object_debts
         } else {
-            (
-                &self.congestion_control_object_debts,
-                default_per_commit_budget,
-            )
+            &self.congestion_control_object_debts
         };
 
         let shared_input_object_ids: BTreeSet<_> = transactions
@@ -919,6 +912,7 @@ impl AuthorityPerEpochStore {
             protocol_config.verify_legacy_zklogin_address(),
             protocol_config.accept_zklogin_in_multisig(),
             protocol_config.zklogin_max_epoch_upper_bound_delta(),
+            protocol_config.signature_limits(),
         );
 
         let authenticator_state_exists = epoch_start_configuration
@@ -2359,14 +2353,15 @@ impl AuthorityPerEpochStore {
             return Ok(());
         }
 
+        let jwk_config = self
+            .protocol_config()
+            .jwk_config()
+            .expect("authenticator state is only enabled with jwk consensus updates");
+
         let mut jwk_aggregator = self.jwk_aggregator.lock();
 
         let votes = jwk_aggregator.votes_for_authority(authority);
-        if votes
-            >= self
-                .protocol_config()
-                .max_jwk_votes_per_validator_per_epoch()
-        {
+        if votes >= jwk_config.max_votes_per_validator_per_epoch {
             warn!(
                 "validator {:?} has already voted {} times this epoch, ignoring vote",
                 authority, votes,
@@ -2853,11 +2848,13 @@ impl AuthorityPerEpochStore {
 
         PostConsensusTxReorder::reorder(
             &mut sequenced_transactions,
-            self.protocol_config.consensus_transaction_ordering(),
+            self.protocol_config
+                .consensus_transaction_ordering_strategy(),
         );
         PostConsensusTxReorder::reorder(
             &mut sequenced_randomness_transactions,
-            self.protocol_config.consensus_transaction_ordering(),
+            self.protocol_config
+                .consensus_transaction_ordering_strategy(),
         );
 
         // We track transaction execution cost separately for regular transactions and transactions using randomness, since
@@ -93,8 +93,7 @@ impl SharedObjectCongestionTracker {
         for_randomness: bool,
         transactions: &[VerifiedSequencedConsensusTransaction],
     ) -> SuiResult<Self> {
-        let max_accumulated_txn_cost_per_object_in_commit =
-            protocol_config.max_accumulated_txn_cost_per_object_in_mysticeti_commit_as_option();
+        let params = protocol_config.congestion_control_params();
         Ok(Self::new(
             tables.load_initial_object_debts(
                 round,
@@ -102,22 +101,12 @@ impl SharedObjectCongestionTracker {
                 protocol_config,
                 transactions,
             )?,
-            protocol_config.per_object_congestion_control_mode(),
-            if for_randomness {
-                protocol_config
-                    .max_accumulated_randomness_txn_cost_per_object_in_mysticeti_commit_as_option()
-                    .or(max_accumulated_txn_cost_per_object_in_commit)
-            } else {
-                max_accumulated_txn_cost_per_object_in_commit
-            },
-            protocol_config.gas_budget_based_txn_cost_cap_factor_as_option(),
-            protocol_config.gas_budget_based_txn_cost_absolute_cap_commit_count_as_option(),
-            protocol_config
-                .max_txn_cost_overage_per_object_in_commit_as_option()
-                .unwrap_or(0),
-            protocol_config
-                .allowed_txn_cost_overage_burst_per_object_in_commit_as_option()
-                .unwrap_or(0),
+            params.mode,
+            params.max_accumulated_txn_cost_per_object(for_randomness),
+            params.gas_budget_based_txn_cost_cap_factor,
+            params.gas_budget_based_txn_cost_absolute_cap_commit_count,
+            params.max_txn_cost_overage_per_object_in_commit,
+            params.allowed_txn_cost_overage_burst_per_object_in_commit,
         ))
     }
 
@@ -1076,10 +1076,10 @@ fn get_unexecuted_transactions(
         // transactions in a checkpoint that includes a randomness state update are causally
         // dependent on it.
         assert_eq!(
-            0,
+            Duration::ZERO,
             epoch_store
                 .protocol_config()
-                .min_checkpoint_interval_ms_as_option()
+                .min_checkpoint_interval_duration_as_option()
                 .unwrap_or_default(),
         );
         if let Some(first_digest) = execution_digests.first() {
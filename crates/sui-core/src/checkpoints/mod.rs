@@ -939,10 +939,10 @@ impl CheckpointBuilder {
         let mut last_height = summary.clone().and_then(|s| s.checkpoint_height);
         let mut last_timestamp = summary.map(|s| s.summary.timestamp_ms);
 
-        let min_checkpoint_interval_ms = self
+        let min_checkpoint_interval = self
             .epoch_store
             .protocol_config()
-            .min_checkpoint_interval_ms_as_option()
+            .min_checkpoint_interval_duration_as_option()
             .unwrap_or_default();
         let mut grouped_pending_checkpoints = Vec::new();
         let mut checkpoints_iter = self
@@ -956,9 +956,11 @@ impl CheckpointBuilder {
             // - minimum interval has elapsed ...
             let current_timestamp = pending.details().timestamp_ms;
             let can_build = match last_timestamp {
-                    Some(last_timestamp) => {
-                        current_timestamp >= last_timestamp + min_checkpoint_interval_ms
-                    }
+                    Some(last_timestamp) => current_timestamp
+                        .checked_sub(last_timestamp)
+                        .is_some_and(|elapsed| {
+                            Duration::from_millis(elapsed) >= min_checkpoint_interval
+                        }),
                     None => true,
                 // - or, next PendingCheckpoint is last-of-epoch (since the last-of-epoch checkpoint
                 //   should be written separately) ...
@@ -991,9 +991,9 @@ mod tests {
     };
     use futures::pin_mut;
     use prometheus::Registry;
-    use sui_protocol_config::ConsensusTransactionOrdering;
+    use sui_protocol_config::{ConsensusTransactionOrderingStrategy, GasPriceTieBreak};
     use sui_types::{
-        base_types::{random_object_ref, AuthorityName, SuiAddress},
+        base_types::{dbg_addr, random_object_ref, AuthorityName, SuiAddress},
         committee::Committee,
         crypto::deterministic_random_account_key,
         messages_consensus::{
@@ -1361,10 +1361,15 @@ mod tests {
         }
     }
 
+    const BY_GAS_PRICE: ConsensusTransactionOrderingStrategy =
+        ConsensusTransactionOrderingStrategy::ByGasPrice {
+            tie_break: GasPriceTieBreak::SubmissionOrder,
+        };
+
     #[test]
     fn test_order_by_gas_price() {
         let mut v = vec![cap_txn(10), user_txn(42), user_txn(100), cap_txn(1)];
-        PostConsensusTxReorder::reorder(&mut v, ConsensusTransactionOrdering::ByGasPrice);
+        PostConsensusTxReorder::reorder(&mut v, BY_GAS_PRICE);
         assert_eq!(
             extract(v),
             vec![
@@ -1385,7 +1390,7 @@ mod tests {
             cap_txn(1),
             user_txn(1000),
         ];
-        PostConsensusTxReorder::reorder(&mut v, ConsensusTransactionOrdering::ByGasPrice);
+        PostConsensusTxReorder::reorder(&mut v, BY_GAS_PRICE);
         assert_eq!(
             extract(v),
             vec![
@@ -1408,7 +1413,7 @@ mod tests {
             cap_txn(1),
             eop_txn(11),
         ];
-        PostConsensusTxReorder::reorder(&mut v, ConsensusTransactionOrdering::ByGasPrice);
+        PostConsensusTxReorder::reorder(&mut v, BY_GAS_PRICE);
         assert_eq!(
             extract(v),
             vec![
@@ -1421,6 +1426,109 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_order_by_gas_price_digest_tie_break() {
+        let mut v = vec![
+            user_txn(42),
+            user_txn(100),
+            user_txn(42),
+            cap_txn(1),
+            user_txn(42),
+        ];
+        let mut expected: Vec<_> = v.iter().filter_map(certified_digest).collect();
+        expected.remove(1);
+        expected.sort();
+
+        PostConsensusTxReorder::reorder(
+            &mut v,
+            ConsensusTransactionOrderingStrategy::ByGasPrice {
+                tie_break: GasPriceTieBreak::Digest,
+            },
+        );
+
+        let actual: Vec<_> = v[2..].iter().filter_map(certified_digest).collect();
+        assert_eq!(
+            extract(v),
+            vec![
+                "cap(1)".to_string(),
+                "certified(100)".to_string(),
+                "certified(42)".to_string(),
+                "certified(42)".to_string(),
+                "certified(42)".to_string(),
+            ]
+        );
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_order_fairly() {
+        // Gas prices are only used to tell transactions from the same sender apart.
+        let mut v = vec![
+            user_txn_from(1, 10),
+            user_txn_from(1, 11),
+            cap_txn(1),
+            user_txn_from(1, 12),
+            user_txn_from(2, 20),
+            user_txn_from(1, 13),
+            user_txn_from(3, 30),
+            user_txn_from(2, 21),
+            user_txn_from(2, 22),
+        ];
+
+        PostConsensusTxReorder::reorder(
+            &mut v,
+            ConsensusTransactionOrderingStrategy::Fair {
+                per_sender_quota: 2,
+            },
+        );
+
+        assert_eq!(
+            extract(v),
+            vec![
+                "certified(10)".to_string(),
+                "certified(11)".to_string(),
+                "cap(1)".to_string(),
+                "certified(20)".to_string(),
+                "certified(30)".to_string(),
+                "certified(21)".to_string(),
+                "certified(12)".to_string(),
+                "certified(13)".to_string(),
+                "certified(22)".to_string(),
+            ]
+        );
+
+        // A transaction sequenced twice only counts once towards its sender's quota.
+        let txn = user_txn_from(1, 10);
+        let mut v = vec![txn.clone(), txn, user_txn_from(1, 11), user_txn_from(2, 20)];
+
+        PostConsensusTxReorder::reorder(
+            &mut v,
+            ConsensusTransactionOrderingStrategy::Fair {
+                per_sender_quota: 2,
+            },
+        );
+
+        assert_eq!(
+            extract(v),
+            vec![
+                "certified(10)".to_string(),
+                "certified(10)".to_string(),
+                "certified(11)".to_string(),
+                "certified(20)".to_string(),
+            ]
+        );
+    }
+
+    fn certified_digest(t: &VerifiedSequencedConsensusTransaction) -> Option<TransactionDigest> {
+        match &t.0.transaction {
+            SequencedConsensusTransactionKind::External(ConsensusTransaction {
+                kind: ConsensusTransactionKind::CertifiedTransaction(cert),
+                ..
+            }) => Some(*cert.digest()),
+            _ => None,
+        }
+    }
+
     fn extract(v: Vec<VerifiedSequencedConsensusTransaction>) -> Vec<String> {
         v.into_iter().map(extract_one).collect()
     }
@@ -1464,12 +1572,23 @@ mod tests {
     }
 
     fn user_txn(gas_price: u64) -> VerifiedSequencedConsensusTransaction {
+        user_txn_with_sender(SuiAddress::default(), gas_price)
+    }
+
+    fn user_txn_from(sender: u8, gas_price: u64) -> VerifiedSequencedConsensusTransaction {
+        user_txn_with_sender(dbg_addr(sender), gas_price)
+    }
+
+    fn user_txn_with_sender(
+        sender: SuiAddress,
+        gas_price: u64,
+    ) -> VerifiedSequencedConsensusTransaction {
         let (committee, keypairs) = Committee::new_simple_test_committee();
         let data = SenderSignedData::new(
             TransactionData::new_transfer(
                 SuiAddress::default(),
                 random_object_ref(),
-                SuiAddress::default(),
+                sender,
                 random_object_ref(),
                 1000 * gas_price,
                 gas_price,
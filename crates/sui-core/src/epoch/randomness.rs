@@ -16,7 +16,7 @@ use rand::SeedableRng;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, Weak};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use sui_macros::fail_point_if;
 use sui_network::randomness;
 use sui_types::base_types::AuthorityName;
@@ -676,10 +676,10 @@ impl RandomnessManager {
             .get(&SINGLETON_KEY)
             .expect("typed_store should not fail");
         if let Some(last_round_timestamp) = last_round_timestamp {
-            if commit_timestamp - last_round_timestamp
+            if Duration::from_millis(commit_timestamp - last_round_timestamp)
                 < epoch_store
                     .protocol_config()
-                    .random_beacon_min_round_interval_ms()
+                    .random_beacon_min_round_interval_duration()
             {
                 return Ok(None);
             }
@@ -1,12 +1,16 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use std::collections::HashMap;
+
 use crate::consensus_handler::{
     SequencedConsensusTransactionKind, VerifiedSequencedConsensusTransaction,
 };
 use mysten_metrics::monitored_scope;
-use sui_protocol_config::ConsensusTransactionOrdering;
+use sui_protocol_config::{ConsensusTransactionOrderingStrategy, GasPriceTieBreak};
 use sui_types::{
+    base_types::SuiAddress,
+    digests::TransactionDigest,
     messages_consensus::{ConsensusTransaction, ConsensusTransactionKind},
     transaction::TransactionDataAPI as _,
 };
@@ -16,36 +20,99 @@ pub struct PostConsensusTxReorder {}
 impl PostConsensusTxReorder {
     pub fn reorder(
         transactions: &mut [VerifiedSequencedConsensusTransaction],
-        kind: ConsensusTransactionOrdering,
+        strategy: ConsensusTransactionOrderingStrategy,
     ) {
         // TODO: make the reordering algorithm richer and depend on object hotness as well.
         // Order transactions based on their gas prices. System transactions without gas price
         // are put to the beginning of the sequenced_transactions vector.
-        match kind {
-            ConsensusTransactionOrdering::ByGasPrice => Self::order_by_gas_price(transactions),
-            ConsensusTransactionOrdering::None => (),
+        match strategy {
+            ConsensusTransactionOrderingStrategy::ByGasPrice { tie_break } => {
+                Self::order_by_gas_price(transactions, tie_break)
+            }
+            ConsensusTransactionOrderingStrategy::Fair { per_sender_quota } => {
+                Self::order_fairly(transactions, per_sender_quota)
+            }
+            ConsensusTransactionOrderingStrategy::None => (),
         }
     }
 
-    fn order_by_gas_price(transactions: &mut [VerifiedSequencedConsensusTransaction]) {
+    fn order_by_gas_price(
+        transactions: &mut [VerifiedSequencedConsensusTransaction],
+        tie_break: GasPriceTieBreak,
+    ) {
         let _scope = monitored_scope("ConsensusCommitHandler::order_by_gas_price");
-        transactions.sort_by_key(|txn| {
+        transactions.sort_by_cached_key(|txn| {
+            let user = user_transaction(txn);
+
             // Reverse order, so that transactions with higher gas price are put to the beginning.
-            std::cmp::Reverse({
-                match &txn.0.transaction {
-                    SequencedConsensusTransactionKind::External(ConsensusTransaction {
-                        tracking_id: _,
-                        kind: ConsensusTransactionKind::CertifiedTransaction(cert),
-                    }) => cert.gas_price(),
-                    SequencedConsensusTransactionKind::External(ConsensusTransaction {
-                        tracking_id: _,
-                        kind: ConsensusTransactionKind::UserTransaction(txn),
-                    }) => txn.transaction_data().gas_price(),
-                    // Non-user transactions are considered to have gas price of MAX u64 and are
-                    // put to the beginning.
-                    _ => u64::MAX,
-                }
-            })
+            // Non-user transactions are considered to have gas price of MAX u64 and are put to the
+            // beginning.
+            let gas_price = std::cmp::Reverse(user.as_ref().map_or(u64::MAX, |u| u.gas_price));
+
+            // Sorting is stable, so leaving the digest out preserves submission order among
+            // transactions with the same gas price.
+            let digest = match tie_break {
+                GasPriceTieBreak::SubmissionOrder => None,
+                GasPriceTieBreak::Digest => user.map(|u| u.digest),
+            };
+
+            (gas_price, digest)
         })
     }
+
+    fn order_fairly(
+        transactions: &mut [VerifiedSequencedConsensusTransaction],
+        per_sender_quota: u64,
+    ) {
+        let _scope = monitored_scope("ConsensusCommitHandler::order_fairly");
+
+        // Find the transactions that exceed their sender's quota, counting in consensus order.
+        // Transactions sequenced more than once only count towards the quota once, and their copies
+        // are either all held back, or none are.
+        let mut seen: HashMap<SuiAddress, u64> = HashMap::new();
+        let mut over_quota: HashMap<TransactionDigest, bool> = HashMap::new();
+        for user in transactions.iter().filter_map(user_transaction) {
+            over_quota.entry(user.digest).or_insert_with(|| {
+                let count = seen.entry(user.sender).or_default();
+                *count += 1;
+                *count > per_sender_quota
+            });
+        }
+
+        // Sorting is stable, so transactions within quota keep their relative order, followed by
+        // the rest in their original relative order. Non-user transactions have no sender, and are
+        // never held back.
+        transactions.sort_by_cached_key(|txn| {
+            user_transaction(txn).is_some_and(|user| over_quota[&user.digest])
+        });
+    }
+}
+
+/// The parts of a user transaction that ordering strategies consider.
+struct UserTransaction {
+    sender: SuiAddress,
+    gas_price: u64,
+    digest: TransactionDigest,
+}
+
+fn user_transaction(txn: &VerifiedSequencedConsensusTransaction) -> Option<UserTransaction> {
+    match &txn.0.transaction {
+        SequencedConsensusTransactionKind::External(ConsensusTransaction {
+            tracking_id: _,
+            kind: ConsensusTransactionKind::CertifiedTransaction(cert),
+        }) => Some(UserTransaction {
+            sender: cert.transaction_data().sender(),
+            gas_price: cert.transaction_data().gas_price(),
+            digest: *cert.digest(),
+        }),
+        SequencedConsensusTransactionKind::External(ConsensusTransaction {
+            tracking_id: _,
+            kind: ConsensusTransactionKind::UserTransaction(txn),
+        }) => Some(UserTransaction {
+            sender: txn.transaction_data().sender(),
+            gas_price: txn.transaction_data().gas_price(),
+            digest: *txn.digest(),
+        }),
+        _ => None,
+    }
 }
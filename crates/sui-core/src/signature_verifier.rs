@@ -13,6 +13,7 @@ use parking_lot::{Mutex, MutexGuard, RwLock};
 use prometheus::{register_int_counter_with_registry, IntCounter, Registry};
 use shared_crypto::intent::Intent;
 use std::sync::Arc;
+use sui_protocol_config::SignatureLimits;
 use sui_types::digests::SenderSignedDataDigest;
 use sui_types::digests::ZKLoginInputsDigest;
 use sui_types::signature_verification::{
@@ -106,6 +107,9 @@ pub struct SignatureVerifier {
     /// Params that contains a list of supported providers for ZKLogin and the environment (prod/test) the code runs in.
     zk_login_params: ZkLoginParams,
 
+    /// Limits on the signatures of transactions, from the epoch's protocol config.
+    signature_limits: SignatureLimits,
+
     queue: Mutex<CertBuffer>,
     pub metrics: Arc<SignatureVerifierMetrics>,
 }
@@ -135,6 +139,7 @@ impl SignatureVerifier {
         verify_legacy_zklogin_address: bool,
         accept_zklogin_in_multisig: bool,
         zklogin_max_epoch_upper_bound_delta: Option<u64>,
+        signature_limits: SignatureLimits,
     ) -> Self {
         Self {
             committee,
@@ -163,6 +168,7 @@ impl SignatureVerifier {
                 accept_zklogin_in_multisig,
                 zklogin_max_epoch_upper_bound_delta,
             },
+            signature_limits,
         }
     }
 
@@ -174,6 +180,7 @@ impl SignatureVerifier {
         verify_legacy_zklogin_address: bool,
         accept_zklogin_in_multisig: bool,
         zklogin_max_epoch_upper_bound_delta: Option<u64>,
+        signature_limits: SignatureLimits,
     ) -> Self {
         Self::new_with_batch_size(
             committee,
@@ -184,6 +191,7 @@ impl SignatureVerifier {
             verify_legacy_zklogin_address,
             accept_zklogin_in_multisig,
             zklogin_max_epoch_upper_bound_delta,
+            signature_limits,
         )
     }
 
@@ -390,6 +398,7 @@ impl SignatureVerifier {
                     self.zk_login_params.verify_legacy_zklogin_address,
                     self.zk_login_params.accept_zklogin_in_multisig,
                     self.zk_login_params.zklogin_max_epoch_upper_bound_delta,
+                    self.signature_limits.clone(),
                 );
                 verify_sender_signed_data_message_signatures(
                     signed_tx,
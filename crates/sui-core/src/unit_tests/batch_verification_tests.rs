@@ -146,6 +146,7 @@ async fn test_async_verifier() {
         true,
         true,
         Some(30),
+        ProtocolConfig::get_for_max_version_UNSAFE().signature_limits(),
     ));
 
     let tasks: Vec<_> = (0..32)
@@ -12,6 +12,7 @@ use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 use sui_config::genesis::{
     Genesis, GenesisCeremonyParameters, GenesisChainParameters, TokenDistributionSchedule,
     UnsignedGenesis,
@@ -52,7 +53,7 @@ use sui_types::transaction::{
     CallArg, CheckedInputObjects, Command, InputObjectKind, ObjectReadResult, Transaction,
 };
 use sui_types::{BRIDGE_ADDRESS, SUI_BRIDGE_OBJECT_ID, SUI_FRAMEWORK_ADDRESS, SUI_SYSTEM_ADDRESS};
-use tracing::trace;
+use tracing::{trace, warn};
 use validator_info::{GenesisValidatorInfo, GenesisValidatorMetadata, ValidatorInfo};
 
 pub mod validator_info;
@@ -63,6 +64,11 @@ const GENESIS_BUILDER_TOKEN_DISTRIBUTION_SCHEDULE_FILE: &str = "token-distributi
 const GENESIS_BUILDER_SIGNATURE_DIR: &str = "signatures";
 const GENESIS_BUILDER_UNSIGNED_GENESIS_FILE: &str = "unsigned-genesis";
 
+/// JWKs are only removed from the authenticator state object once they expire, so keeping them for
+/// longer grows the object. This is not a protocol limit, only a point past which the genesis
+/// parameters are worth a second look.
+const JWK_RETENTION_WARNING_THRESHOLD: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
 pub struct Builder {
     parameters: GenesisCeremonyParameters,
     token_distribution_schedule: Option<TokenDistributionSchedule>,
@@ -262,6 +268,21 @@ impl Builder {
             bail!("extra objects are disallowed");
         }
 
+        let protocol_config = get_genesis_protocol_config(self.parameters.protocol_version);
+        let epoch_duration = Duration::from_millis(self.parameters.epoch_duration_ms);
+        if let Err(e) = protocol_config.check_jwk_config(epoch_duration) {
+            bail!("jwk parameters are invalid for the epoch duration: {}", e.0);
+        }
+
+        if let Some(retention) = protocol_config.jwk_retention(epoch_duration) {
+            if retention > JWK_RETENTION_WARNING_THRESHOLD {
+                warn!(
+                    "jwks will be kept for {retention:?}, longer than \
+                     {JWK_RETENTION_WARNING_THRESHOLD:?}, with epochs of {epoch_duration:?}"
+                );
+            }
+        }
+
         for validator in self.validators.values() {
             validator.validate().with_context(|| {
                 format!(
@@ -435,6 +435,8 @@ impl Limits {
             max_type_argument_width: self.max_type_argument_width as usize,
             max_type_nodes: self.max_type_nodes as usize,
             max_move_value_depth: self.max_move_value_depth as usize,
+            max_linkage_entries: sui_package_resolver::DEFAULT_MAX_LINKAGE_ENTRIES,
+            max_identifier_len: None,
         }
     }
 }
@@ -72,6 +72,7 @@ impl Loader<PackageKey> for Db {
             .map_err(|e| PackageResolverError::Store {
                 store: STORE,
                 error: e.to_string(),
+                retryable: true,
             })?;
 
         let mut id_to_package = HashMap::new();
@@ -5,9 +5,9 @@ use std::sync::Arc;
 
 use crate::database::ConnectionPool;
 use crate::schema::objects;
-use anyhow::anyhow;
 use async_trait::async_trait;
 use diesel::ExpressionMethods;
+use diesel::OptionalExtension;
 use diesel::QueryDsl;
 use diesel_async::RunQueryDsl;
 use move_core_types::account_address::AccountAddress;
@@ -29,30 +29,40 @@ impl IndexerStorePackageResolver {
 #[async_trait]
 impl PackageStore for IndexerStorePackageResolver {
     async fn fetch(&self, id: AccountAddress) -> Result<Arc<Package>, PackageResolverError> {
-        let pkg = self
-            .get_package_from_db(id)
-            .await
-            .map_err(|e| PackageResolverError::Store {
-                store: "PostgresDB",
-                error: e.to_string(),
-            })?;
+        let pkg = self.get_package_from_db(id).await?;
         Ok(Arc::new(pkg))
     }
 }
 
 impl IndexerStorePackageResolver {
-    async fn get_package_from_db(&self, id: AccountAddress) -> Result<Package, anyhow::Error> {
-        let mut connection = self.pool.get().await?;
+    async fn get_package_from_db(
+        &self,
+        id: AccountAddress,
+    ) -> Result<Package, PackageResolverError> {
+        // Failing to reach the database, or to run the query, may be transient.
+        let store_error = |e: &dyn std::fmt::Display| PackageResolverError::Store {
+            store: "PostgresDB",
+            error: e.to_string(),
+            retryable: true,
+        };
+
+        let mut connection = self.pool.get().await.map_err(|e| store_error(&e))?;
 
         let bcs = objects::dsl::objects
             .select(objects::dsl::serialized_object)
             .filter(objects::dsl::object_id.eq(id.to_vec()))
             .get_result::<Vec<u8>>(&mut connection)
             .await
-            .map_err(|e| anyhow!("Package not found in DB: {e}"))?;
+            .optional()
+            .map_err(|e| store_error(&e))?
+            .ok_or(PackageResolverError::PackageNotFound(id))?;
 
-        let object = bcs::from_bytes::<Object>(&bcs)?;
+        // A row that fails to decode is corrupt, so retrying will not help.
+        let object = bcs::from_bytes::<Object>(&bcs).map_err(|e| PackageResolverError::Store {
+            store: "PostgresDB",
+            error: format!("Failed to decode object {id}: {e}"),
+            retryable: false,
+        })?;
         Package::read_from_object(&object)
-            .map_err(|e| anyhow!("Failed parsing object to package: {e}"))
     }
 }
@@ -68,6 +68,10 @@ pub struct ProtocolConfigResponse {
     pub protocol_version: ProtocolVersion,
     pub feature_flags: BTreeMap<String, bool>,
     pub attributes: BTreeMap<String, Option<SuiProtocolConfigValue>>,
+    /// The stable address of each attribute and feature flag (e.g. `/consensus/gc_depth`), which
+    /// does not change if it is renamed.
+    #[serde(default)]
+    pub addresses: BTreeMap<String, String>,
 }
 
 impl From<ProtocolConfig> for ProtocolConfigResponse {
@@ -82,6 +86,7 @@ impl From<ProtocolConfig> for ProtocolConfigResponse {
             min_supported_protocol_version: ProtocolVersion::MIN,
             max_supported_protocol_version: ProtocolVersion::MAX,
             feature_flags: config.feature_map(),
+            addresses: config.addresses(),
         }
     }
 }
@@ -16,6 +16,7 @@ use im::hashmap::HashMap as ImHashMap;
 use shared_crypto::intent::{
     AppId, Intent, IntentMessage, IntentScope, IntentVersion, PersonalMessage,
 };
+use sui_protocol_config::{Chain, ProtocolConfig, ProtocolVersion};
 use sui_types::authenticator_state::{ActiveJwk, AuthenticatorStateInner};
 use sui_types::crypto::ToFromBytes;
 use sui_types::dynamic_field::{DynamicFieldType, Field};
@@ -62,8 +63,16 @@ pub(crate) async fn verify_zklogin_signature(
             "Cannot get current epoch from db".to_string(),
         ));
     };
+    let protocol_version = ProtocolVersion::new(curr_epoch.protocol_version());
     let curr_epoch = curr_epoch.stored.epoch as u64;
 
+    // Use the signature limits of the current epoch's protocol version, or of the latest version
+    // this build supports if the network is ahead of it. The limits do not vary by chain.
+    let signature_limits =
+        ProtocolConfig::get_for_version_if_supported(protocol_version, Chain::Unknown)
+            .unwrap_or_else(ProtocolConfig::get_for_max_version_UNSAFE)
+            .signature_limits();
+
     // get the zklogin_env that will be needed for verify the signature.
     let cfg: &ZkLoginConfig = ctx.data_unchecked();
     let zklogin_env_native = cfg.env;
@@ -120,6 +129,7 @@ pub(crate) async fn verify_zklogin_signature(
         true,
         true,
         Some(30),
+        signature_limits,
     );
 
     let bytes = bytes.0;
@@ -0,0 +1,412 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Structural comparison of two packages, typically two versions of the same package, e.g. to
+//! review a proposed upgrade without decompiling and diffing both versions.
+//!
+//! Modules, datatypes and functions are matched by name, and fields and variants are matched by
+//! name within their definitions. Signatures refer to datatypes by their runtime IDs, so types
+//! from the package itself compare equal across its versions, and types from dependencies compare
+//! equal regardless of which version of the dependency each package links against. Function
+//! bodies are not compared.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use move_binary_format::file_format::{AbilitySet, DatatypeTyParameter, Visibility};
+
+use crate::{
+    DataDef, FunctionDef, Module, MoveData, OpenSignature, OpenSignatureBody, Package, Result,
+    VariantDef,
+};
+
+/// Differences between two packages, from the first (old) package to the second (new) one.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PackageDiff {
+    /// Names of modules only in the new package.
+    pub added_modules: Vec<String>,
+
+    /// Names of modules only in the old package.
+    pub removed_modules: Vec<String>,
+
+    /// Modules in both packages whose datatypes or functions differ, by name.
+    pub changed_modules: BTreeMap<String, ModuleDiff>,
+}
+
+/// Differences between two versions of a module.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ModuleDiff {
+    /// Names of datatypes only in the new module. A struct that has been replaced by an enum of
+    /// the same name (or vice versa) is reported as both removed and added.
+    pub added_datatypes: Vec<String>,
+
+    /// Names of datatypes only in the old module.
+    pub removed_datatypes: Vec<String>,
+
+    /// Datatypes in both modules whose definitions differ, by name.
+    pub changed_datatypes: BTreeMap<String, DatatypeDiff>,
+
+    /// Names of functions only in the new module.
+    pub added_functions: Vec<String>,
+
+    /// Names of functions only in the old module.
+    pub removed_functions: Vec<String>,
+
+    /// Functions in both modules whose signatures differ, by name.
+    pub changed_functions: BTreeMap<String, FunctionDiff>,
+}
+
+/// Differences between two definitions of a struct, or of an enum.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DatatypeDiff {
+    pub abilities: Option<Change<AbilitySet>>,
+    pub type_params: Option<Change<Vec<DatatypeTyParameter>>>,
+
+    /// Changes to a struct's fields.
+    pub fields: Vec<FieldChange>,
+
+    /// Changes to an enum's variants.
+    pub variants: Vec<VariantChange>,
+}
+
+/// Differences between two signatures of a function.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FunctionDiff {
+    pub visibility: Option<Change<Visibility>>,
+    pub is_entry: Option<Change<bool>>,
+    pub type_params: Option<Change<Vec<AbilitySet>>>,
+    pub parameters: Option<Change<Vec<OpenSignature>>>,
+    pub return_: Option<Change<Vec<OpenSignature>>>,
+}
+
+/// A property that differs between the old and new definitions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change<T> {
+    pub old: T,
+    pub new: T,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FieldChange {
+    Added {
+        name: String,
+        type_: OpenSignatureBody,
+    },
+
+    Removed {
+        name: String,
+        type_: OpenSignatureBody,
+    },
+
+    /// The field's type changed.
+    Retyped {
+        name: String,
+        old: OpenSignatureBody,
+        new: OpenSignatureBody,
+    },
+
+    /// The field's position changed relative to the other fields in both definitions, which
+    /// changes the datatype's layout. Positions are indices into each definition's fields.
+    Moved {
+        name: String,
+        old: usize,
+        new: usize,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VariantChange {
+    Added {
+        name: String,
+    },
+
+    Removed {
+        name: String,
+    },
+
+    /// The variant's fields changed.
+    Changed {
+        name: String,
+        fields: Vec<FieldChange>,
+    },
+
+    /// The variant's position changed relative to the other variants in both definitions, which
+    /// changes its tag. Positions are indices into each definition's variants.
+    Moved {
+        name: String,
+        old: usize,
+        new: usize,
+    },
+}
+
+impl Package {
+    /// Compare this package with `other`, treating this package as the old version and `other` as
+    /// the new one. Fails if any of the definitions in either package cannot be deserialized.
+    pub fn diff(&self, other: &Package) -> Result<PackageDiff> {
+        let mut diff = PackageDiff::default();
+
+        for (name, module) in &self.modules {
+            let Some(other) = other.modules.get(name) else {
+                diff.removed_modules.push(name.clone());
+                continue;
+            };
+
+            let module_diff = module.diff(other)?;
+            if !module_diff.is_empty() {
+                diff.changed_modules.insert(name.clone(), module_diff);
+            }
+        }
+
+        diff.added_modules = other
+            .modules
+            .keys()
+            .filter(|name| !self.modules.contains_key(*name))
+            .cloned()
+            .collect();
+
+        Ok(diff)
+    }
+}
+
+impl Module {
+    /// Compare this module's datatypes and function signatures with `other`'s, treating this
+    /// module as the old version and `other` as the new one.
+    pub fn diff(&self, other: &Module) -> Result<ModuleDiff> {
+        let mut diff = ModuleDiff::default();
+
+        let datatypes: BTreeSet<_> = self
+            .datatypes(None, None)
+            .chain(other.datatypes(None, None))
+            .collect();
+
+        for name in datatypes {
+            let (old, new) = match (self.data_def(name)?, other.data_def(name)?) {
+                (Some(old), Some(new)) => (old, new),
+                (Some(_), None) => {
+                    diff.removed_datatypes.push(name.to_owned());
+                    continue;
+                }
+                (None, Some(_)) => {
+                    diff.added_datatypes.push(name.to_owned());
+                    continue;
+                }
+                (None, None) => continue,
+            };
+
+            match DatatypeDiff::new(&old, &new) {
+                Some(datatype_diff) if datatype_diff.is_empty() => {}
+                Some(datatype_diff) => {
+                    diff.changed_datatypes
+                        .insert(name.to_owned(), datatype_diff);
+                }
+                None => {
+                    diff.removed_datatypes.push(name.to_owned());
+                    diff.added_datatypes.push(name.to_owned());
+                }
+            }
+        }
+
+        let functions: BTreeSet<_> = self
+            .functions(None, None)
+            .chain(other.functions(None, None))
+            .collect();
+
+        for name in functions {
+            let (old, new) = match (self.function_def(name)?, other.function_def(name)?) {
+                (Some(old), Some(new)) => (old, new),
+                (Some(_), None) => {
+                    diff.removed_functions.push(name.to_owned());
+                    continue;
+                }
+                (None, Some(_)) => {
+                    diff.added_functions.push(name.to_owned());
+                    continue;
+                }
+                (None, None) => continue,
+            };
+
+            let function_diff = FunctionDiff::new(old, new);
+            if !function_diff.is_empty() {
+                diff.changed_functions
+                    .insert(name.to_owned(), function_diff);
+            }
+        }
+
+        Ok(diff)
+    }
+}
+
+impl PackageDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_modules.is_empty()
+            && self.removed_modules.is_empty()
+            && self.changed_modules.is_empty()
+    }
+}
+
+impl ModuleDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_datatypes.is_empty()
+            && self.removed_datatypes.is_empty()
+            && self.changed_datatypes.is_empty()
+            && self.added_functions.is_empty()
+            && self.removed_functions.is_empty()
+            && self.changed_functions.is_empty()
+    }
+}
+
+impl DatatypeDiff {
+    /// Compare two definitions of a datatype. Returns `None` if one is a struct and the other is
+    /// an enum, in which case they cannot be compared field by field.
+    fn new(old: &DataDef, new: &DataDef) -> Option<Self> {
+        let mut diff = DatatypeDiff {
+            abilities: change(old.abilities, new.abilities),
+            type_params: change(old.type_params.clone(), new.type_params.clone()),
+            ..Default::default()
+        };
+
+        match (&old.data, &new.data) {
+            (MoveData::Struct(old), MoveData::Struct(new)) => diff.fields = diff_fields(old, new),
+            (MoveData::Enum(old), MoveData::Enum(new)) => diff.variants = diff_variants(old, new),
+            _ => return None,
+        }
+
+        Some(diff)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.abilities.is_none()
+            && self.type_params.is_none()
+            && self.fields.is_empty()
+            && self.variants.is_empty()
+    }
+}
+
+impl FunctionDiff {
+    fn new(old: FunctionDef, new: FunctionDef) -> Self {
+        FunctionDiff {
+            visibility: change(old.visibility, new.visibility),
+            is_entry: change(old.is_entry, new.is_entry),
+            type_params: change(old.type_params, new.type_params),
+            parameters: change(old.parameters, new.parameters),
+            return_: change(old.return_, new.return_),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.visibility.is_none()
+            && self.is_entry.is_none()
+            && self.type_params.is_none()
+            && self.parameters.is_none()
+            && self.return_.is_none()
+    }
+}
+
+fn change<T: PartialEq>(old: T, new: T) -> Option<Change<T>> {
+    (old != new).then_some(Change { old, new })
+}
+
+fn diff_fields(
+    old: &[(String, OpenSignatureBody)],
+    new: &[(String, OpenSignatureBody)],
+) -> Vec<FieldChange> {
+    let mut changes = vec![];
+
+    for (name, old_type) in old {
+        match new.iter().find(|(n, _)| n == name) {
+            None => changes.push(FieldChange::Removed {
+                name: name.clone(),
+                type_: old_type.clone(),
+            }),
+
+            Some((_, new_type)) if new_type != old_type => changes.push(FieldChange::Retyped {
+                name: name.clone(),
+                old: old_type.clone(),
+                new: new_type.clone(),
+            }),
+
+            Some(_) => {}
+        }
+    }
+
+    for (name, new_type) in new {
+        if !old.iter().any(|(n, _)| n == name) {
+            changes.push(FieldChange::Added {
+                name: name.clone(),
+                type_: new_type.clone(),
+            });
+        }
+    }
+
+    let old_names: Vec<_> = old.iter().map(|(n, _)| n.as_str()).collect();
+    let new_names: Vec<_> = new.iter().map(|(n, _)| n.as_str()).collect();
+    for (name, old, new) in moves(&old_names, &new_names) {
+        changes.push(FieldChange::Moved { name, old, new });
+    }
+
+    changes
+}
+
+fn diff_variants(old: &[VariantDef], new: &[VariantDef]) -> Vec<VariantChange> {
+    let mut changes = vec![];
+
+    for old_variant in old {
+        let name = &old_variant.name;
+        let Some(new_variant) = new.iter().find(|v| &v.name == name) else {
+            changes.push(VariantChange::Removed { name: name.clone() });
+            continue;
+        };
+
+        let fields = diff_fields(&old_variant.signatures, &new_variant.signatures);
+        if !fields.is_empty() {
+            changes.push(VariantChange::Changed {
+                name: name.clone(),
+                fields,
+            });
+        }
+    }
+
+    for new_variant in new {
+        if !old.iter().any(|v| v.name == new_variant.name) {
+            changes.push(VariantChange::Added {
+                name: new_variant.name.clone(),
+            });
+        }
+    }
+
+    let old_names: Vec<_> = old.iter().map(|v| v.name.as_str()).collect();
+    let new_names: Vec<_> = new.iter().map(|v| v.name.as_str()).collect();
+    for (name, old, new) in moves(&old_names, &new_names) {
+        changes.push(VariantChange::Moved { name, old, new });
+    }
+
+    changes
+}
+
+/// Names that appear in both `old` and `new`, but in a different order relative to the other
+/// names that appear in both, with their positions in `old` and `new`. Names that only shift
+/// because others were added or removed around them are not reported.
+fn moves(old: &[&str], new: &[&str]) -> Vec<(String, usize, usize)> {
+    let common_old: Vec<(usize, &str)> = old
+        .iter()
+        .copied()
+        .enumerate()
+        .filter(|(_, name)| new.contains(name))
+        .collect();
+
+    let common_new: Vec<(usize, &str)> = new
+        .iter()
+        .copied()
+        .enumerate()
+        .filter(|(_, name)| old.contains(name))
+        .collect();
+
+    common_old
+        .iter()
+        .zip(&common_new)
+        .filter(|((_, old_name), (_, new_name))| old_name != new_name)
+        .filter_map(|(&(old_ix, name), _)| {
+            let &(new_ix, _) = common_new.iter().find(|(_, n)| *n == name)?;
+            Some((name.to_owned(), old_ix, new_ix))
+        })
+        .collect()
+}
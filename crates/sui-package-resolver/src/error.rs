@@ -4,7 +4,11 @@
 use std::sync::Arc;
 
 use move_binary_format::errors::VMError;
+use move_binary_format::file_format::AbilitySet;
 use move_core_types::account_address::AccountAddress;
+use move_core_types::language_storage::StructTag;
+use sui_types::base_types::SequenceNumber;
+use sui_types::digests::Digest;
 use sui_types::TypeTag;
 use thiserror::Error;
 
@@ -14,17 +18,29 @@ pub enum Error {
     Bcs(#[from] bcs::Error),
 
     #[error("Store {} error: {}", store, error)]
-    Store { store: &'static str, error: String },
+    Store {
+        store: &'static str,
+        error: String,
+        /// Whether the failure may be transient (e.g. a timeout or a dropped connection), so that
+        /// the same request could succeed if it is retried.
+        retryable: bool,
+    },
 
     #[error("{0}")]
     Deserialize(VMError),
 
+    #[error("Failed to disassemble {0}: {1}")]
+    Disassembly(String, String),
+
     #[error("Package has no modules: {0}")]
     EmptyPackage(AccountAddress),
 
     #[error("Function not found: {0}::{1}::{2}")]
     FunctionNotFound(AccountAddress, String, String),
 
+    #[error("Identifier '{0}' is longer than the limit of {1} characters")]
+    IdentifierTooLong(String, usize),
+
     #[error(
         "Conflicting types for input {0}: {} and {}",
         .1.to_canonical_display(/* with_prefix */ true),
@@ -32,9 +48,43 @@ pub enum Error {
     )]
     InputTypeConflict(u16, TypeTag, TypeTag),
 
+    #[error("Invalid cursor: '{0}'")]
+    InvalidCursor(String),
+
+    #[error("Invalid transaction: {0}")]
+    InvalidTransaction(bcs::Error),
+
+    #[error("Package store cannot look up the latest version of package: {0}")]
+    LatestPackageUnsupported(AccountAddress),
+
+    #[error("Linkage for package {0} refers back to the package itself: {1}")]
+    LinkageCycle(AccountAddress, AccountAddress),
+
+    #[error("Linkage maps package {0} to {1}, which has runtime ID {2}")]
+    LinkageMismatch(AccountAddress, AccountAddress, AccountAddress),
+
     #[error("Linkage not found for package: {0}")]
     LinkageNotFound(AccountAddress),
 
+    #[error(
+        "Type argument {} for type parameter {}{} is missing abilities: {}",
+        .type_.to_canonical_display(/* with_prefix */ true),
+        .index,
+        .owner.as_ref().map_or(String::new(), |o| format!(
+            " of {}",
+            o.to_canonical_display(/* with_prefix */ true),
+        )),
+        display_abilities(*.missing),
+    )]
+    MissingAbilities {
+        /// The datatype whose type parameter is being instantiated, or `None` for a type parameter
+        /// of the signature being instantiated.
+        owner: Option<StructTag>,
+        index: usize,
+        type_: TypeTag,
+        missing: AbilitySet,
+    },
+
     #[error("Module not found: {0}::{1}")]
     ModuleNotFound(AccountAddress, String),
 
@@ -47,12 +97,33 @@ pub enum Error {
     #[error("Not an identifier: '{0}'")]
     NotAnIdentifier(String),
 
+    #[error("Not a programmable transaction")]
+    NotAProgrammableTransaction,
+
+    #[error("Package {0} has digest {2}, but expected {1}")]
+    PackageDigestMismatch(AccountAddress, Digest, Digest),
+
+    #[error("Package {0} was not read with the bytes needed to compute its digest")]
+    PackageDigestUnavailable(AccountAddress),
+
     #[error("Package not found: {0}")]
     PackageNotFound(AccountAddress),
 
+    #[error("Package not yet available: {0}")]
+    PackagePending(AccountAddress),
+
+    #[error("Package {0} is not available at version {1}")]
+    PackageVersionUnavailable(AccountAddress, SequenceNumber),
+
+    #[error("Package store cannot list the versions of package: {0}")]
+    PackageVersionsUnsupported(AccountAddress),
+
     #[error("Datatype not found: {0}::{1}::{2}")]
     DatatypeNotFound(AccountAddress, String, String),
 
+    #[error("Expected at most {0} linkage entries, got {1}")]
+    TooManyLinkageEntries(usize, usize),
+
     #[error("More than {0} struct definitions required to resolve type")]
     TooManyTypeNodes(usize, usize),
 
@@ -77,6 +148,118 @@ pub enum Error {
     #[error("Unexpected error: {0}")]
     UnexpectedError(Arc<dyn std::error::Error + Send + Sync + 'static>),
 
+    #[error(
+        "Bytecode version {found} is not supported, the maximum supported version is {max_supported}"
+    )]
+    UnsupportedBytecodeVersion { found: u32, max_supported: u32 },
+
+    #[error(
+        "Failed to decode value of type {}: {}",
+        .0.to_canonical_display(/* with_prefix */ true),
+        .1,
+    )]
+    ValueDecode(TypeTag, String),
+
     #[error("Type layout nesting exceeded limit of {0}")]
     ValueNesting(usize),
 }
+
+/// Broad categories of `Error`, for callers (such as RPC services) that need to decide how to
+/// report a failure, without matching on individual variants or their messages.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorClass {
+    /// The request was malformed, or is inconsistent with the packages it refers to.
+    UserInput,
+
+    /// The request refers to a package, module, function or type that does not exist (or is not
+    /// available at the requested version).
+    NotFound,
+
+    /// Serving the request would exceed one of the resolver's limits.
+    LimitExceeded,
+
+    /// The package store failed, or returned data that could not be trusted. Such failures may be
+    /// transient.
+    StoreFailure,
+
+    /// On-chain data was not in the expected shape, or the resolver is misconfigured.
+    Internal,
+}
+
+impl Error {
+    /// Whether the request that failed with this error could succeed if it is retried unchanged:
+    /// the store failed in a way that may be transient, or the package is not available yet.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Error::PackagePending(_)
+                | Error::Store {
+                    retryable: true,
+                    ..
+                }
+        )
+    }
+
+    /// The broad category this error falls into.
+    pub fn classification(&self) -> ErrorClass {
+        use Error as E;
+        use ErrorClass as C;
+
+        match self {
+            E::IdentifierTooLong(_, _)
+            | E::InputTypeConflict(_, _, _)
+            | E::InvalidCursor(_)
+            | E::InvalidTransaction(_)
+            | E::MissingAbilities { .. }
+            | E::NotAPackage(_)
+            | E::NotAnIdentifier(_)
+            | E::NotAProgrammableTransaction
+            | E::TypeArityMismatch(_, _)
+            | E::TypeParamOOB(_, _)
+            | E::UnexpectedReference
+            | E::UnexpectedSigner
+            | E::ValueDecode(_, _) => C::UserInput,
+
+            E::DatatypeNotFound(_, _, _)
+            | E::FunctionNotFound(_, _, _)
+            | E::ModuleNotFound(_, _)
+            | E::PackageNotFound(_)
+            | E::PackageVersionUnavailable(_, _) => C::NotFound,
+
+            E::TooManyLinkageEntries(_, _)
+            | E::TooManyTypeNodes(_, _)
+            | E::TooManyTypeParams(_, _)
+            | E::TypeParamNesting(_, _)
+            | E::ValueNesting(_) => C::LimitExceeded,
+
+            // A pending package is one the store has not caught up to yet.
+            E::PackageDigestMismatch(_, _, _) | E::PackagePending(_) | E::Store { .. } => {
+                C::StoreFailure
+            }
+
+            // Failures to decode user-supplied bytes are reported with more specific variants, so
+            // the remaining BCS errors come from data that the resolver or its stores rely on.
+            E::Bcs(_)
+            | E::Deserialize(_)
+            | E::Disassembly(_, _)
+            | E::EmptyPackage(_)
+            | E::LatestPackageUnsupported(_)
+            | E::LinkageCycle(_, _)
+            | E::LinkageMismatch(_, _, _)
+            | E::LinkageNotFound(_)
+            | E::NoTypeOrigin(_, _, _)
+            | E::PackageDigestUnavailable(_)
+            | E::PackageVersionsUnsupported(_)
+            | E::UnexpectedError(_)
+            | E::UnsupportedBytecodeVersion { .. } => C::Internal,
+        }
+    }
+}
+
+fn display_abilities(abilities: AbilitySet) -> String {
+    abilities
+        .into_iter()
+        .map(|a| a.to_string())
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
@@ -2,29 +2,45 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use async_trait::async_trait;
+use futures::stream::{self, Stream, StreamExt};
 use lru::LruCache;
 use move_binary_format::file_format::{
-    AbilitySet, DatatypeTyParameter, EnumDefinitionIndex, FunctionDefinitionIndex,
-    Signature as MoveSignature, SignatureIndex, Visibility,
+    AbilitySet, Bytecode, Constant, DatatypeTyParameter, EnumDefinitionIndex,
+    FunctionDefinitionIndex, Signature as MoveSignature, SignatureIndex, Visibility,
 };
+use move_bytecode_source_map::{mapping::SourceMapping, source_map::SourceMap};
 use move_command_line_common::display::RenderResult;
 use move_command_line_common::{display::try_render_constant, error_bitset::ErrorBitset};
-use move_core_types::annotated_value::MoveEnumLayout;
+use move_core_types::annotated_value::{MoveEnumLayout, MoveValue};
 use move_core_types::language_storage::ModuleId;
+use move_disassembler::disassembler::{Disassembler, DisassemblerOptions};
+use move_ir_types::location::Loc;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeSet;
 use std::num::NonZeroUsize;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 use std::{borrow::Cow, collections::BTreeMap};
-use sui_types::base_types::is_primitive_type_tag;
-use sui_types::transaction::{Argument, CallArg, Command, ProgrammableTransaction};
+use sui_types::base_types::{
+    is_primitive_type_tag, ObjectID, SuiAddress, RESOLVED_ASCII_STR, RESOLVED_UTF8_STR,
+};
+use sui_types::digests::Digest;
+use sui_types::effects::{TransactionEffects, TransactionEffectsAPI};
+use sui_types::event::Event;
+use sui_types::supported_protocol_versions::ProtocolConfig;
+use sui_types::transaction::{
+    Argument, CallArg, Command, ObjectArg, ProgrammableMoveCall, ProgrammableTransaction,
+    TransactionData, TransactionDataAPI, TransactionKind,
+};
 use sui_types::type_input::{StructInput, TypeInput};
 
 use crate::error::Error;
 use move_binary_format::errors::Location;
+use move_binary_format::file_format_common::{BinaryConstants, BinaryFlavor, VERSION_MAX};
 use move_binary_format::{
     file_format::{
-        DatatypeHandleIndex, SignatureToken, StructDefinitionIndex, StructFieldInformation,
-        TableIndex,
+        DatatypeHandleIndex, FieldDefinition, SignatureToken, StructDefinitionIndex,
+        StructFieldInformation, TableIndex,
     },
     CompiledModule,
 };
@@ -33,16 +49,29 @@ use move_core_types::{
     annotated_value::{MoveFieldLayout, MoveStructLayout, MoveTypeLayout},
     language_storage::{StructTag, TypeTag},
 };
-use sui_types::move_package::{MovePackage, TypeOrigin};
-use sui_types::object::Object;
+use sui_types::coin::Coin;
+use sui_types::gas_coin::GasCoin;
+use sui_types::move_package::{MovePackage, TypeOrigin, UpgradeCap, UpgradeReceipt, UpgradeTicket};
+use sui_types::object::{Object, Owner};
 use sui_types::{base_types::SequenceNumber, Identifier};
+use sui_types::{is_system_package, MOVE_STDLIB_ADDRESS, SUI_FRAMEWORK_ADDRESS};
 
+pub mod diff;
 pub mod error;
+pub mod pretty;
 
 // TODO Move to ServiceConfig
 
 const PACKAGE_CACHE_SIZE: NonZeroUsize = unsafe { NonZeroUsize::new_unchecked(1024) };
 
+/// Maximum size of the ULEB128-encoded length prefix of a BCS sequence (whose length is at most
+/// 2^31 - 1).
+const MAX_SEQUENCE_LENGTH_PREFIX: usize = 5;
+
+/// A value for `Limits::max_linkage_entries` for services that don't configure it separately,
+/// comfortably above the number of dependencies that a package published on-chain can have.
+pub const DEFAULT_MAX_LINKAGE_ENTRIES: usize = 1024;
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// The Resolver is responsible for providing information about types. It relies on its internal
@@ -51,6 +80,7 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub struct Resolver<S> {
     package_store: S,
     limits: Option<Limits>,
+    layout_cache: Option<Arc<dyn LayoutCache>>,
 }
 
 /// Optional configuration that imposes limits on the work that the resolver can do for each
@@ -65,14 +95,183 @@ pub struct Limits {
     pub max_type_nodes: usize,
     /// Maximum recursion depth through struct fields.
     pub max_move_value_depth: usize,
+    /// Maximum number of entries in the linkage table of a package that types are relocated
+    /// through.
+    pub max_linkage_entries: usize,
+    /// Maximum length of identifiers (module and datatype names) in type inputs, if any. See
+    /// `Limits::max_identifier_len_for` to match the limit the protocol enforces on Move code.
+    pub max_identifier_len: Option<usize>,
+}
+
+/// Bounds on the size of a value decoded for display (see `Resolver::decode_value_bounded`), so
+/// that services showing values to untrusted clients produce bounded responses.
+#[derive(Clone, Debug)]
+pub struct DecodeLimits {
+    /// Maximum number of elements shown for each vector.
+    pub max_vector_elements: usize,
+    /// Maximum number of characters shown for each string (`std::string::String` or
+    /// `std::ascii::String`).
+    pub max_string_length: usize,
+    /// Maximum number of bytes of primitive and string data shown in total, across the whole
+    /// value. Parts of the value after this budget runs out are replaced by
+    /// `DisplayValue::Truncated`.
+    pub max_total_bytes: usize,
+}
+
+/// Which parts of a type's layout to resolve, for callers that only need part of the layout of a
+/// large or deeply nested type (see `Resolver::type_layout_with_filter`).
+///
+/// Datatypes that the filter excludes are truncated: they are represented by a struct layout with
+/// no fields, or an enum layout with no variants, that still carries the datatype's type. Move
+/// structs always have at least one field and enums at least one variant, so truncated layouts
+/// can be told apart from complete ones (see `LayoutFilter::is_truncated`).
+#[derive(Clone, Debug, Default)]
+pub struct LayoutFilter {
+    /// Number of levels of datatypes to resolve the fields of. The outermost datatype is at level
+    /// 1, datatypes in its fields (including inside vectors) are at level 2, and so on. Datatypes
+    /// below this level are truncated. `None` means there is no limit.
+    pub max_depth: Option<usize>,
+    /// Paths to fields whose datatypes are truncated. A path lists field names starting from the
+    /// outermost datatype, with a variant's name preceding the names of its fields, e.g.
+    /// `["pool", "Active", "balances"]`.
+    pub pruned_paths: BTreeSet<Vec<String>>,
+}
+
+/// Usage statistics for a single resolution request, returned by the `*_with_stats` variants of
+/// the resolver's methods, so that services can account for the cost of each request.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResolutionStats {
+    /// Number of packages requested from the package store.
+    pub packages_fetched: usize,
+    /// Number of datatype references that were served from definitions already gathered during
+    /// this request, rather than being loaded again.
+    pub cache_hits: usize,
+    /// Number of distinct datatype definitions that were gathered to serve the request.
+    pub datatypes_resolved: usize,
+    /// Deepest nesting reached, either through type arguments while gathering definitions, or
+    /// through fields when computing a layout.
+    pub max_depth: usize,
+    /// Time spent serving the request, including time spent waiting on the package store.
+    pub elapsed: Duration,
+    /// Whether a layout was served from the resolver's layout cache.
+    pub layout_cached: bool,
+}
+
+/// Second-level cache for the layouts computed by `Resolver::type_layout`, keyed by canonical type
+/// tags (whose package IDs are all defining IDs). A type's layout never changes once its defining
+/// package is published, so entries never need to be invalidated. Layouts are cached without
+/// regard to the resolver's limits, so a cache should not be shared by resolvers with different
+/// limits.
+#[async_trait]
+pub trait LayoutCache: Send + Sync + 'static {
+    async fn get(&self, tag: &TypeTag) -> Result<Option<MoveTypeLayout>>;
+
+    async fn insert(&self, tag: TypeTag, layout: MoveTypeLayout) -> Result<()>;
+}
+
+/// A `LayoutCache` that keeps the most recently used layouts in memory.
+pub struct LayoutLruCache {
+    layouts: Mutex<LruCache<TypeTag, MoveTypeLayout>>,
 }
 
 /// Store which fetches package for the given address from the backend db and caches it
 /// locally in an lru cache. On every call to `fetch` it checks backend db and if package
 /// version is stale locally, it updates the local state before returning to the user
+///
+/// Only packages are cached: failures (including transient store errors) are never cached, so a
+/// failed fetch is always attempted again against the backend.
 pub struct PackageStoreWithLruCache<T> {
     pub(crate) packages: Mutex<LruCache<AccountAddress, Arc<Package>>>,
     pub(crate) inner: T,
+    prefetch: PrefetchPolicy,
+    /// Packages loaded into the cache whose dependencies have yet to be prefetched, under
+    /// `PrefetchPolicy::Lazy`.
+    pending_prefetch: Mutex<BTreeSet<AccountAddress>>,
+}
+
+/// Keeps a `PackageStoreWithLruCache` consistent with the chain, by evicting packages from it as
+/// they are modified on-chain. Packages are immutable once published (upgrades publish a new
+/// package at a new storage ID), except for system packages, which are upgraded in place at epoch
+/// boundaries. Without a watcher, long-running services continue to serve the old versions of
+/// system packages until the cache is evicted manually.
+pub struct PackageWatcher<T> {
+    store: Arc<PackageStoreWithLruCache<T>>,
+}
+
+/// Controls whether `PackageStoreWithLruCache` warms the cache with a package's dependencies (the
+/// storage IDs in its linkage table) when it loads that package, in anticipation of them being
+/// needed to resolve the signatures and layouts that mention their types. Prefetching is
+/// best-effort: failures to fetch dependencies are not reported.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PrefetchPolicy {
+    /// Only fetch packages as they are requested.
+    #[default]
+    Off,
+    /// Fetch a package's dependencies, at most `concurrency` at a time, as soon as it is loaded,
+    /// before returning it.
+    Immediate { concurrency: NonZeroUsize },
+    /// Fetch a package's dependencies, at most `concurrency` at a time, the first time the package
+    /// is served from the cache after it has been loaded, so that packages that are only requested
+    /// once do not pay for their dependencies.
+    Lazy { concurrency: NonZeroUsize },
+}
+
+/// Store which records every package fetched through it in a `DependencyIndex`, so that the index
+/// grows to cover the packages that the service using it has encountered.
+pub struct PackageStoreWithDependencyIndex<T> {
+    inner: T,
+    index: Arc<DependencyIndex>,
+}
+
+/// Inverted index from packages to the packages that depend on them, built incrementally from
+/// packages' linkage tables. Packages are identified by their storage IDs, so each version of a
+/// package is tracked separately.
+#[derive(Default)]
+pub struct DependencyIndex {
+    inner: Mutex<DependencyIndexInner>,
+}
+
+#[derive(Default)]
+struct DependencyIndexInner {
+    /// Storage IDs of the dependencies of each indexed package (the values of its linkage table).
+    dependencies: BTreeMap<AccountAddress, BTreeSet<AccountAddress>>,
+
+    /// Storage IDs of the indexed packages that depend on each package.
+    dependents: BTreeMap<AccountAddress, BTreeSet<AccountAddress>>,
+
+    /// The version of each package that its dependencies were recorded at.
+    versions: BTreeMap<AccountAddress, SequenceNumber>,
+}
+
+/// Store which serves system packages as they were at a particular version, using
+/// `PackageStore::fetch_at`, and all other packages as usual.
+pub struct PackageStoreAtVersion<T> {
+    inner: T,
+    version: SequenceNumber,
+}
+
+/// Store which retries fetches from its inner store while the package being fetched is not
+/// available yet, or the inner store fails in a way that may be transient (see
+/// `Error::is_retryable`), backing off between attempts. Intended for stores backed by nodes that
+/// may not have synced every package yet.
+pub struct PackageStoreWithRetry<T> {
+    inner: T,
+    policy: RetryPolicy,
+}
+
+/// Configures how `PackageStoreWithRetry` behaves when a package is not available.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Maximum number of times to retry a fetch, after the initial attempt.
+    pub max_retries: usize,
+    /// Delay before the first retry. The delay doubles after each retry.
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between retries.
+    pub max_backoff: Duration,
+    /// Whether a package that the inner store could not find might still become available (e.g.
+    /// because the store is still catching up). If so, such fetches are retried, and fail with
+    /// `Error::PackagePending` rather than `Error::PackageNotFound` once retries are exhausted.
+    pub not_found_is_pending: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -92,7 +291,62 @@ pub struct Package {
     /// loading system packages.
     version: SequenceNumber,
 
+    /// The package's modules, keyed (and ordered) by name.
     modules: BTreeMap<String, Module>,
+
+    /// Modules that could not be read, keyed by name, with the reason why. Only packages read
+    /// leniently (see `Package::read_lenient`) have entries here.
+    module_errors: BTreeMap<String, Error>,
+
+    /// Digests of the package, computed from the serialized modules and linkage it was read from.
+    /// Only packages read with `Package::read_with_digests` retain what is needed to compute them.
+    digests: Option<PackageDigests>,
+}
+
+/// The package digest under each of the schemes the protocol has used to compute it: over the
+/// modules' bytes directly, or over the hashes of each module (see the `package_digest_hash_module`
+/// feature flag). Digests are only computed the first time they are needed, from the serialized
+/// modules and dependencies they cover, so holding onto them keeps a copy of the package's
+/// bytecode in memory.
+#[derive(Clone, Debug)]
+struct PackageDigests {
+    modules: Vec<Vec<u8>>,
+    deps: Vec<ObjectID>,
+    whole_modules: OnceLock<[u8; 32]>,
+    hashed_modules: OnceLock<[u8; 32]>,
+}
+
+/// The parts of a package that can be read without deserializing its modules, for displaying
+/// packages that cannot be fully read (e.g. because they were published with a bytecode version
+/// newer than this binary supports).
+#[derive(Clone, Debug)]
+pub struct PackageSummary {
+    pub storage_id: AccountAddress,
+    pub version: SequenceNumber,
+    pub modules: BTreeMap<String, ModuleSummary>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ModuleSummary {
+    /// The bytecode version declared in the module's header, if it has a well-formed header.
+    pub bytecode_version: Option<u32>,
+
+    /// Names of the datatypes defined in this module, according to the package's type origin
+    /// table.
+    pub datatypes: BTreeSet<String>,
+}
+
+/// Source information for a module, used to make its disassembly more readable, and to name its
+/// constants.
+#[derive(Clone, Debug)]
+pub struct ModuleSource {
+    /// Source map produced when the module was compiled, used to recover the names of parameters,
+    /// locals, and constants.
+    pub source_map: SourceMap,
+
+    /// Path and contents of the source file that the module was compiled from, used to annotate
+    /// instructions with the line they were compiled from.
+    pub source_file: Option<(String, String)>,
 }
 
 type Linkage = BTreeMap<AccountAddress, AccountAddress>;
@@ -188,11 +442,13 @@ pub struct DataDef {
 
 #[derive(Debug)]
 pub enum MoveData {
-    /// Serialized representation of fields (names and deserialized signatures). Signatures refer to
-    /// packages at their runtime IDs (not their storage ID or defining ID).
+    /// Serialized representation of fields (names and deserialized signatures), in declaration
+    /// order, which is the order they are serialized in. Signatures refer to packages at their
+    /// runtime IDs (not their storage ID or defining ID).
     Struct(Vec<(String, OpenSignatureBody)>),
 
-    /// Serialized representation of variants (names and deserialized signatures).
+    /// Serialized representation of variants (names and deserialized signatures), in declaration
+    /// order, so that a variant's position is its tag.
     Enum(Vec<VariantDef>),
 }
 
@@ -202,8 +458,8 @@ pub struct VariantDef {
     /// The name of the enum variant
     pub name: String,
 
-    /// The serialized representation of the variant's signature. Signatures refer to packages at
-    /// their runtime IDs (not their storage ID or defining ID).
+    /// The serialized representation of the variant's fields, in declaration order. Signatures
+    /// refer to packages at their runtime IDs (not their storage ID or defining ID).
     pub signatures: Vec<(String, OpenSignatureBody)>,
 }
 
@@ -226,6 +482,429 @@ pub struct FunctionDef {
     pub return_: Vec<OpenSignature>,
 }
 
+/// Deserialized representation of a constant from a module's constant pool.
+#[derive(Clone, Debug)]
+pub struct ConstantDef {
+    /// The constant's name, if it could be recovered (see `Module::constants`).
+    pub name: Option<String>,
+
+    /// The constant's type.
+    pub type_: OpenSignatureBody,
+
+    /// The constant's value rendered as a string, if it is one of the types that can be rendered
+    /// (see `ErrorConstants::Rendered`).
+    pub rendered: Option<String>,
+
+    /// The constant's value, BCS-encoded.
+    pub bytes: Vec<u8>,
+}
+
+/// The signature tokens that a `FunctionDef` was read from, as they appear in the module's
+/// bytecode (referring to datatypes by their handle indices), for tools that need the structure
+/// that is lost when signatures are resolved.
+#[derive(Clone, Debug)]
+pub struct RawFunctionSignature {
+    /// Index of the formal parameter types in the module's signature pool.
+    pub parameters_index: SignatureIndex,
+
+    /// Formal parameter types.
+    pub parameters: Vec<SignatureToken>,
+
+    /// Index of the return types in the module's signature pool.
+    pub return_index: SignatureIndex,
+
+    /// Return types.
+    pub return_: Vec<SignatureToken>,
+}
+
+/// The signature tokens that a `DataDef` was read from, as they appear in the module's bytecode.
+#[derive(Clone, Debug)]
+pub struct RawDataSignature {
+    /// The index of the datatype's handle in the module.
+    pub handle: DatatypeHandleIndex,
+
+    /// The signature tokens for the datatype's fields, in the same order as its `DataDef`.
+    pub data: RawMoveData,
+}
+
+#[derive(Clone, Debug)]
+pub enum RawMoveData {
+    /// The signature token for each of a struct's fields.
+    Struct(Vec<SignatureToken>),
+
+    /// The signature tokens for the fields of each of an enum's variants.
+    Enum(Vec<Vec<SignatureToken>>),
+}
+
+/// An entry function in a package, alongside the kinds of arguments a transaction needs to supply
+/// to call it.
+#[derive(Debug)]
+pub struct EntryPoint {
+    /// The name of the module the function is defined in.
+    pub module: String,
+
+    /// The function's name.
+    pub name: String,
+
+    /// The function's definition.
+    pub function: FunctionDef,
+
+    /// The kind of argument expected for each of the function's parameters, in order.
+    pub argument_kinds: Vec<ArgumentKind>,
+}
+
+/// How a transaction supplies the argument for a function parameter.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ArgumentKind {
+    /// A pure (BCS-encoded) input: primitives, `ID`s, strings, and vectors or options of these.
+    Pure,
+
+    /// An object input, passed by value or by (mutable) reference.
+    Object(Option<Reference>),
+
+    /// A `sui::transfer::Receiving<T>` input.
+    Receiving,
+
+    /// The transaction context, which is supplied by the runtime rather than by the transaction.
+    TxContext,
+
+    /// The kind of argument depends on how the function's type parameters are instantiated.
+    TypeParameter,
+}
+
+/// Criteria for finding functions in a package by the shape of their signatures, with
+/// `Package::find_functions`. The default filter matches every function.
+#[derive(Clone, Debug, Default)]
+pub struct FunctionFilter {
+    /// Only match functions with this visibility.
+    pub visibility: Option<Visibility>,
+
+    /// Only match functions that are (`Some(true)`) or are not (`Some(false)`) marked `entry`.
+    pub is_entry: Option<bool>,
+
+    /// Only match functions that have a parameter matching each of these patterns.
+    pub parameters: Vec<SignaturePattern>,
+
+    /// Only match functions that return a value matching each of these patterns.
+    pub returns: Vec<SignaturePattern>,
+}
+
+/// A pattern for the type of a function parameter or return value.
+#[derive(Clone, Debug)]
+pub struct SignaturePattern {
+    /// The reference the value must be passed by, or `None` if it must be passed by value.
+    pub ref_: Option<Reference>,
+    pub body: TypePattern,
+}
+
+/// A pattern for a type in a function signature. Datatypes are matched by the package addresses
+/// that signatures refer to them by, which are runtime IDs.
+#[derive(Clone, Debug)]
+pub enum TypePattern {
+    /// Matches any type.
+    Any,
+
+    /// Matches any of the function's type parameters.
+    TypeParameter,
+
+    /// Matches exactly this type.
+    Exact(OpenSignatureBody),
+
+    /// Matches vectors whose element type matches the pattern.
+    Vector(Box<TypePattern>),
+
+    /// Matches instances of the datatype whose type arguments match these patterns.
+    Datatype(DatatypeKey, Vec<TypePattern>),
+}
+
+/// A programmable transaction decoded from its `TransactionData`, alongside everything needed to
+/// display it: layouts for its pure inputs, and the signatures of the functions it calls.
+#[derive(Clone, Debug)]
+pub struct ResolvedTransaction {
+    /// The transaction's sender.
+    pub sender: SuiAddress,
+
+    /// The decoded transaction.
+    pub transaction: ProgrammableTransaction,
+
+    /// Resolution information for each of the transaction's inputs, in order.
+    pub inputs: Vec<ResolvedInput>,
+
+    /// Resolution information for each `MoveCall` command in the transaction, in order.
+    pub move_calls: Vec<ResolvedMoveCall>,
+}
+
+/// An input to a programmable transaction, with the information needed to interpret it.
+#[derive(Clone, Debug)]
+pub enum ResolvedInput {
+    /// A pure input, with its layout, if one could be inferred from how the input is used.
+    Pure {
+        bytes: Vec<u8>,
+        layout: Option<MoveTypeLayout>,
+    },
+
+    /// An object input, with the kind of argument it is used as, if it is passed directly to a
+    /// Move function (when passed to multiple functions, the first use wins).
+    Object {
+        arg: ObjectArg,
+        kind: Option<ArgumentKind>,
+    },
+}
+
+/// A `MoveCall` command in a programmable transaction, with its type arguments in canonical form
+/// and the signatures of the called function's parameters.
+#[derive(Clone, Debug)]
+pub struct ResolvedMoveCall {
+    /// Index of the command in the transaction.
+    pub command: usize,
+
+    /// The package, module and name of the function being called.
+    pub package: AccountAddress,
+    pub module: String,
+    pub function: String,
+
+    /// The call's type arguments, referring to types by their defining IDs.
+    pub type_arguments: Vec<TypeTag>,
+
+    /// The signatures of the function's parameters, before type arguments are substituted.
+    pub parameters: Vec<OpenSignature>,
+}
+
+/// The outcome of type-checking a programmable transaction offline, with `Resolver::check_ptb`.
+#[derive(Clone, Debug, Default)]
+pub struct PtbTypeReport {
+    /// The types of the values that each command produces, in order. `None` if the command's
+    /// results could not be determined (because it could not be resolved), and an individual type
+    /// is `None` if it depends on the type of an object input, which is not known offline. Types
+    /// refer to datatypes by their defining IDs.
+    pub results: Vec<Option<Vec<Option<TypeTag>>>>,
+
+    /// Problems found with the transaction, in command order. If there are any, the transaction
+    /// is expected to fail at signing or execution.
+    pub errors: Vec<PtbTypeError>,
+}
+
+/// A problem found while type-checking a programmable transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PtbTypeError {
+    /// Index of the command the problem was found in.
+    pub command: usize,
+    pub kind: PtbTypeErrorKind,
+}
+
+/// Kinds of problem found while type-checking a programmable transaction. Arguments are
+/// identified by their position among the command's arguments (for `TransferObjects` the address
+/// comes after the objects, and for `SplitCoins` and `MergeCoins`, the coin comes first).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PtbTypeErrorKind {
+    /// The function being called, or one of its type arguments, refers to a package, module,
+    /// function or type that does not exist.
+    Unresolved(String),
+
+    /// The function being called is neither public nor an entry function.
+    NotCallable,
+
+    /// The function being called returns a reference, which a transaction cannot hold on to.
+    ReferenceReturn,
+
+    TypeArityMismatch {
+        expected: usize,
+        actual: usize,
+    },
+
+    /// A type argument is missing abilities that its type parameter requires.
+    MissingAbilities {
+        type_argument: usize,
+        required: AbilitySet,
+        actual: AbilitySet,
+    },
+
+    /// The number of arguments does not match the number of parameters the function takes
+    /// (excluding a trailing `TxContext`, which is supplied by the runtime).
+    ArityMismatch {
+        expected: usize,
+        actual: usize,
+    },
+
+    InputOutOfBounds {
+        argument: usize,
+        input: u16,
+    },
+
+    /// The argument refers to the results of this command or a later one.
+    ResultOutOfBounds {
+        argument: usize,
+        result: u16,
+    },
+
+    NestedResultOutOfBounds {
+        argument: usize,
+        result: u16,
+        nested: u16,
+    },
+
+    /// The argument refers to all the results of a command that does not produce exactly one.
+    InvalidResultArity {
+        argument: usize,
+        result: u16,
+        arity: usize,
+    },
+
+    TypeMismatch {
+        argument: usize,
+        expected: TypeTag,
+        actual: TypeTag,
+    },
+
+    /// A pure input was passed where an object is expected.
+    UnexpectedPureInput {
+        argument: usize,
+    },
+
+    /// An object input was passed where a pure value is expected.
+    UnexpectedObjectInput {
+        argument: usize,
+    },
+
+    /// A pure input is used as values of different types.
+    PureInputConflict {
+        argument: usize,
+        input: u16,
+        first: TypeTag,
+        second: TypeTag,
+    },
+
+    /// A value that is not a coin was passed where a coin is expected.
+    NotACoin {
+        argument: usize,
+        actual: TypeTag,
+    },
+
+    /// A value without `key` and `store` was passed to `TransferObjects`.
+    NotTransferable {
+        argument: usize,
+        actual: TypeTag,
+    },
+}
+
+/// The first phase of resolving type layouts: the types to resolve, and the packages and
+/// datatypes they name directly, gathered without fetching anything from the package store.
+///
+/// Plans for different requests can be merged, inspected (e.g. to check fetches against a quota),
+/// and cached, before being passed to `Resolver::execute` to produce layouts.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResolutionPlan {
+    /// The types whose layouts will be produced, in order.
+    tags: Vec<TypeTag>,
+
+    /// Packages named by `tags`.
+    packages: BTreeSet<AccountAddress>,
+
+    /// Datatypes named by `tags`, keyed by the package IDs that `tags` use to refer to them.
+    datatypes: BTreeSet<DatatypeKey>,
+}
+
+/// Identifies a module, or a member (datatype or function) of a module, in a package, for
+/// paginating through listings that span all the modules in the package. Cursors order by module
+/// name, and then by member name, with a module's cursor ordering before its members' cursors.
+///
+/// Cursors can be exchanged with clients as opaque tokens, using `encode` and `decode`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct PackageCursor {
+    /// The name of the module.
+    pub module: String,
+
+    /// The name of the member within the module, or `None` for the module itself.
+    pub name: Option<String>,
+}
+
+/// A page of members (datatypes or functions) from across the modules of a package, produced by
+/// `Package::datatypes_paginated` or `Package::functions_paginated`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackagePage {
+    /// Cursors for the members on this page, in order.
+    pub cursors: Vec<PackageCursor>,
+
+    /// Opaque token to fetch the next page with, or `None` if this is the last page.
+    pub next: Option<String>,
+}
+
+/// An upper bound on the size of the BCS encoding of values of some type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SerializedSize {
+    /// Values of the type serialize to at most this many bytes.
+    Bounded(usize),
+
+    /// Values of the type contain vectors, so their size is unbounded. A value serializes to at
+    /// most `fixed` bytes, plus, for each vector it contains, its length times the size of its
+    /// elements.
+    Unbounded {
+        /// Bound on the size of the value, excluding the elements of its vectors, but including
+        /// their length prefixes.
+        fixed: usize,
+
+        /// Bounds on the sizes of the elements of each vector in the value, in layout order. For
+        /// enums, this includes the vectors of all variants.
+        vectors: Vec<SerializedSize>,
+    },
+}
+
+/// A variant of an enum's layout, borrowed from a `MoveEnumLayout`. That layout keys its variants
+/// by name and tag, so iterating over it visits variants in name order. `VariantLayout::of` lists
+/// them in tag (declaration) order instead, which matches how values are serialized.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VariantLayout<'l> {
+    pub name: &'l Identifier,
+    pub tag: u16,
+    pub fields: &'l [MoveFieldLayout],
+}
+
+/// A decoded value, bounded by `DecodeLimits`. Parts of the value that were cut off to stay within
+/// the limits are marked explicitly.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DisplayValue {
+    /// A number, boolean or address.
+    Primitive(MoveValue),
+
+    /// A `std::string::String` or `std::ascii::String`, with the number of characters cut off the
+    /// end of it.
+    String { value: String, omitted: usize },
+
+    /// A vector, with the number of elements cut off the end of it.
+    Vector {
+        elements: Vec<DisplayValue>,
+        omitted: usize,
+    },
+
+    Struct {
+        type_: StructTag,
+        fields: Vec<(Identifier, DisplayValue)>,
+    },
+
+    Variant {
+        type_: StructTag,
+        name: Identifier,
+        tag: u16,
+        fields: Vec<(Identifier, DisplayValue)>,
+    },
+
+    /// A value that was not shown because the budget of bytes to show ran out.
+    Truncated,
+}
+
+/// A datatype's definition, as of one version of the package that contains it.
+#[derive(Debug)]
+pub struct DatatypeRevision {
+    /// The storage ID of this version of the package.
+    pub storage_id: AccountAddress,
+
+    /// This version of the package.
+    pub version: SequenceNumber,
+
+    /// The datatype's definition in this version of the package.
+    pub def: DataDef,
+}
+
 /// Fully qualified struct identifier.  Uses copy-on-write strings so that when it is used as a key
 /// to a map, an instance can be created to query the map without having to allocate strings on the
 /// heap.
@@ -239,7 +918,7 @@ pub struct DatatypeRef<'m, 'n> {
 /// A `StructRef` that owns its strings.
 pub type DatatypeKey = DatatypeRef<'static, 'static>;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Reference {
     Immutable,
     Mutable,
@@ -254,14 +933,14 @@ pub struct Signature {
 
 /// Deserialized representation of a type signature that could appear as a function parameter or
 /// return.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct OpenSignature {
     pub ref_: Option<Reference>,
     pub body: OpenSignatureBody,
 }
 
 /// Deserialized representation of a type signature that could appear as a field type for a struct.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum OpenSignatureBody {
     Address,
     Bool,
@@ -276,6 +955,34 @@ pub enum OpenSignatureBody {
     TypeParameter(u16),
 }
 
+/// What is known offline about the value that an argument to a transaction command refers to.
+enum PtbValue {
+    /// A pure input, whose type is determined by how it is used.
+    Pure(u16),
+
+    /// An object input, whose type is not known offline.
+    Object,
+
+    /// A value of a known type (a result of an earlier command, or the gas coin).
+    Typed(TypeTag),
+
+    /// A value whose type could not be determined.
+    Unknown,
+}
+
+/// State gathered while type-checking the commands of a programmable transaction, in order.
+struct PtbChecker<'t> {
+    tx: &'t ProgrammableTransaction,
+
+    /// The type that each input has been used as so far, if it is a pure input.
+    pure_types: Vec<Option<TypeTag>>,
+
+    /// The types of the results of the commands checked so far.
+    results: Vec<Option<Vec<Option<TypeTag>>>>,
+
+    errors: Vec<PtbTypeError>,
+}
+
 /// Information necessary to convert a type tag into a type layout.
 #[derive(Debug, Default)]
 struct ResolutionContext<'l> {
@@ -284,6 +991,15 @@ struct ResolutionContext<'l> {
 
     /// Limits configuration from the calling resolver.
     limits: Option<&'l Limits>,
+
+    /// Number of packages fetched from the store while adding to this context.
+    fetches: usize,
+
+    /// Number of datatype references that were already present in `datatypes` when visited.
+    hits: usize,
+
+    /// Deepest type argument nesting seen while adding to this context.
+    max_depth: usize,
 }
 
 /// Interface to abstract over access to a store of live packages.  Used to override the default
@@ -293,6 +1009,29 @@ pub trait PackageStore: Send + Sync + 'static {
     /// Read package contents. Fails if `id` is not an object, not a package, or is malformed in
     /// some way.
     async fn fetch(&self, id: AccountAddress) -> Result<Arc<Package>>;
+
+    /// Find the storage ID of the latest version of the package whose original (first version) ID
+    /// is `original_id`. Stores are not required to support this lookup, and fail with
+    /// `Error::LatestPackageUnsupported` by default.
+    async fn latest_package_id(&self, original_id: AccountAddress) -> Result<AccountAddress> {
+        Err(Error::LatestPackageUnsupported(original_id))
+    }
+
+    /// List the storage IDs of every version of the package whose original (first version) ID is
+    /// `original_id`, ordered by version. Stores are not required to support this lookup, and fail
+    /// with `Error::PackageVersionsUnsupported` by default.
+    async fn package_versions(&self, original_id: AccountAddress) -> Result<Vec<AccountAddress>> {
+        Err(Error::PackageVersionsUnsupported(original_id))
+    }
+
+    /// Read the contents of the package at `id` as of `version`, i.e. the latest version of that
+    /// package that is at most `version`. Only system packages are upgraded in place (other
+    /// packages are given a new ID when they are upgraded), so this only differs from `fetch` for
+    /// system packages. By default, stores only serve the latest version of a package, and fail
+    /// with `Error::PackageVersionUnavailable` if it is newer than `version`.
+    async fn fetch_at(&self, id: AccountAddress, version: SequenceNumber) -> Result<Arc<Package>> {
+        check_package_version(self.fetch(id).await?, version)
+    }
 }
 
 macro_rules! as_ref_impl {
@@ -302,6 +1041,28 @@ macro_rules! as_ref_impl {
             async fn fetch(&self, id: AccountAddress) -> Result<Arc<Package>> {
                 self.as_ref().fetch(id).await
             }
+
+            async fn latest_package_id(
+                &self,
+                original_id: AccountAddress,
+            ) -> Result<AccountAddress> {
+                self.as_ref().latest_package_id(original_id).await
+            }
+
+            async fn package_versions(
+                &self,
+                original_id: AccountAddress,
+            ) -> Result<Vec<AccountAddress>> {
+                self.as_ref().package_versions(original_id).await
+            }
+
+            async fn fetch_at(
+                &self,
+                id: AccountAddress,
+                version: SequenceNumber,
+            ) -> Result<Arc<Package>> {
+                self.as_ref().fetch_at(id, version).await
+            }
         }
     };
 }
@@ -328,6 +1089,7 @@ impl<S> Resolver<S> {
         Self {
             package_store,
             limits: None,
+            layout_cache: None,
         }
     }
 
@@ -335,9 +1097,17 @@ impl<S> Resolver<S> {
         Self {
             package_store,
             limits: Some(limits),
+            layout_cache: None,
         }
     }
 
+    /// Serve layouts from `cache` where possible, and populate it with the layouts this resolver
+    /// computes.
+    pub fn with_layout_cache(mut self, cache: Arc<dyn LayoutCache>) -> Self {
+        self.layout_cache = Some(cache);
+        self
+    }
+
     pub fn package_store(&self) -> &S {
         &self.package_store
     }
@@ -345,6 +1115,17 @@ impl<S> Resolver<S> {
     pub fn package_store_mut(&mut self) -> &mut S {
         &mut self.package_store
     }
+
+    /// A resolver that sees system packages as they were at `version`, for resolving types as
+    /// they were when a historical transaction executed. The layout cache is not carried over,
+    /// because it holds layouts for the latest versions of system types.
+    pub fn at_version(self, version: SequenceNumber) -> Resolver<PackageStoreAtVersion<S>> {
+        Resolver {
+            package_store: PackageStoreAtVersion::new(self.package_store, version),
+            limits: self.limits,
+            layout_cache: None,
+        }
+    }
 }
 
 impl<S: PackageStore> Resolver<S> {
@@ -356,7 +1137,16 @@ impl<S: PackageStore> Resolver<S> {
     /// on-chain, containing a move package that includes `module`, and that module must define the
     /// `datatype`. In practice this means the input type `tag` can refer to types at or after
     /// their defining IDs.
-    pub async fn canonical_type(&self, mut tag: TypeTag) -> Result<TypeTag> {
+    pub async fn canonical_type(&self, tag: TypeTag) -> Result<TypeTag> {
+        Ok(self.canonical_type_with_stats(tag).await?.0)
+    }
+
+    /// Like `canonical_type`, but also returns statistics about the work done to resolve `tag`.
+    pub async fn canonical_type_with_stats(
+        &self,
+        mut tag: TypeTag,
+    ) -> Result<(TypeTag, ResolutionStats)> {
+        let start = Instant::now();
         let mut context = ResolutionContext::new(self.limits.as_ref());
 
         // (1). Fetch all the information from this store that is necessary to relocate package IDs
@@ -372,13 +1162,61 @@ impl<S: PackageStore> Resolver<S> {
 
         // (2). Use that information to relocate package IDs in the type.
         context.canonicalize_type(&mut tag)?;
-        Ok(tag)
+        Ok((tag, context.stats(start)))
     }
 
     /// Return the type layout corresponding to the given type tag.  The layout always refers to
     /// structs in terms of their defining ID (i.e. their package ID always points to the first
     /// package that introduced them).
-    pub async fn type_layout(&self, mut tag: TypeTag) -> Result<MoveTypeLayout> {
+    pub async fn type_layout(&self, tag: TypeTag) -> Result<MoveTypeLayout> {
+        Ok(self.type_layout_with_stats(tag).await?.0)
+    }
+
+    /// Like `type_layout`, but the package addresses in `tag` are runtime IDs, interpreted
+    /// according to the linkage table of the package at storage ID `context` (the same way the VM
+    /// interprets them when executing code from that package).
+    pub async fn type_layout_in_context(
+        &self,
+        tag: TypeTag,
+        context: AccountAddress,
+    ) -> Result<MoveTypeLayout> {
+        let tag = self.relocate_in_context(tag, context).await?;
+        self.type_layout(tag).await
+    }
+
+    /// Like `type_layout`, but also returns statistics about the work done to resolve `tag`.
+    ///
+    /// If the resolver has a layout cache, `tag` is first canonicalized, and the layout is served
+    /// from the cache if it is there, or added to the cache once it has been computed otherwise.
+    pub async fn type_layout_with_stats(
+        &self,
+        tag: TypeTag,
+    ) -> Result<(MoveTypeLayout, ResolutionStats)> {
+        let Some(cache) = &self.layout_cache else {
+            return self.uncached_type_layout_with_stats(tag).await;
+        };
+
+        let start = Instant::now();
+        let (tag, mut stats) = self.canonical_type_with_stats(tag).await?;
+        if let Some(layout) = cache.get(&tag).await? {
+            stats.layout_cached = true;
+            stats.elapsed = start.elapsed();
+            return Ok((layout, stats));
+        }
+
+        let (layout, mut layout_stats) = self.uncached_type_layout_with_stats(tag.clone()).await?;
+        cache.insert(tag, layout.clone()).await?;
+
+        layout_stats.packages_fetched += stats.packages_fetched;
+        layout_stats.elapsed = start.elapsed();
+        Ok((layout, layout_stats))
+    }
+
+    async fn uncached_type_layout_with_stats(
+        &self,
+        mut tag: TypeTag,
+    ) -> Result<(MoveTypeLayout, ResolutionStats)> {
+        let start = Instant::now();
         let mut context = ResolutionContext::new(self.limits.as_ref());
 
         // (1). Fetch all the information from this store that is necessary to resolve types
@@ -398,32 +1236,293 @@ impl<S: PackageStore> Resolver<S> {
             .as_ref()
             .map_or(usize::MAX, |l| l.max_move_value_depth);
 
-        Ok(context.resolve_type_layout(&tag, max_depth)?.0)
+        let (layout, depth) = context.resolve_type_layout(&tag, max_depth)?;
+        let mut stats = context.stats(start);
+        stats.max_depth = stats.max_depth.max(depth);
+        Ok((layout, stats))
     }
 
-    /// Return the abilities of a concrete type, based on the abilities in its type definition, and
-    /// the abilities of its concrete type parameters: An instance of a generic type has `store`,
-    /// `copy, or `drop` if its definition has the ability, and all its non-phantom type parameters
-    /// have the ability as well. Similar rules apply for `key` except that it requires its type
-    /// parameters to have `store`.
-    pub async fn abilities(&self, mut tag: TypeTag) -> Result<AbilitySet> {
+    /// Like `type_layout`, but only resolves the parts of the layout that `filter` selects,
+    /// truncating datatypes that it excludes (see `LayoutFilter`). Packages are only fetched for
+    /// the parts of the layout that are kept, and for the types of truncated datatypes. Layouts
+    /// for non-empty filters are neither served from, nor added to, the resolver's layout cache.
+    pub async fn type_layout_with_filter(
+        &self,
+        mut tag: TypeTag,
+        filter: &LayoutFilter,
+    ) -> Result<MoveTypeLayout> {
+        if filter.is_empty() {
+            return self.type_layout(tag).await;
+        }
+
         let mut context = ResolutionContext::new(self.limits.as_ref());
 
-        // (1). Fetch all the information from this store that is necessary to resolve types
-        // referenced by this tag.
+        // (1). Fetch the information necessary to resolve the parts of the layout that are kept.
+        context
+            .add_filtered_type_tag(tag.clone(), &self.package_store, filter)
+            .await?;
+
+        // (2). Normalize package IDs in the tag to runtime IDs, to match the keys in the context.
         context
             .add_type_tag(
                 &mut tag,
                 &self.package_store,
                 /* visit_fields */ false,
-                /* visit_phantoms */ false,
+                /* visit_phantoms */ true,
             )
             .await?;
 
-        // (2). Use that information to calculate the type's abilities.
-        context.resolve_abilities(&tag)
-    }
-
+        // (3). Use that information to resolve the tag into a layout.
+        let max_depth = self
+            .limits
+            .as_ref()
+            .map_or(usize::MAX, |l| l.max_move_value_depth);
+
+        let (layout, _) =
+            context.resolve_filtered_layout(&tag, filter, 1, &mut vec![], max_depth)?;
+        Ok(layout)
+    }
+
+    /// Plan the resolution of the layout for `tag`, without fetching any packages. Fails if `tag`
+    /// exceeds the resolver's limits on type argument nesting or width.
+    pub fn plan(&self, tag: TypeTag) -> Result<ResolutionPlan> {
+        use TypeTag as T;
+
+        let mut plan = ResolutionPlan::default();
+        let mut frontier = vec![(&tag, 0)];
+        while let Some((tag, depth)) = frontier.pop() {
+            match tag {
+                T::Address
+                | T::Bool
+                | T::U8
+                | T::U16
+                | T::U32
+                | T::U64
+                | T::U128
+                | T::U256
+                | T::Signer => {}
+
+                T::Vector(tag) => {
+                    check_max_limit!(
+                        TypeParamNesting, self.limits.as_ref();
+                        max_type_argument_depth > depth
+                    );
+
+                    frontier.push((tag.as_ref(), depth + 1));
+                }
+
+                T::Struct(s) => {
+                    check_max_limit!(
+                        TooManyTypeParams, self.limits.as_ref();
+                        max_type_argument_width >= s.type_params.len()
+                    );
+
+                    plan.packages.insert(s.address);
+                    plan.datatypes
+                        .insert(DatatypeRef::from(s.as_ref()).as_key());
+
+                    for param in &s.type_params {
+                        check_max_limit!(
+                            TypeParamNesting, self.limits.as_ref();
+                            max_type_argument_depth > depth
+                        );
+
+                        frontier.push((param, depth + 1));
+                    }
+                }
+            }
+        }
+
+        plan.tags.push(tag);
+        Ok(plan)
+    }
+
+    /// Execute a plan produced by `plan` (and possibly merged with other plans), returning the
+    /// layout of each type in the plan, in order. All types in the plan are resolved together, so
+    /// the resolver's limits on the number of type nodes apply to the plan as a whole.
+    pub async fn execute(&self, plan: &ResolutionPlan) -> Result<Vec<MoveTypeLayout>> {
+        let mut context = ResolutionContext::new(self.limits.as_ref());
+
+        // (1). Fetch all the information from this store that is necessary to resolve types
+        // referenced by the plan.
+        let mut tags = plan.tags.clone();
+        for tag in &mut tags {
+            context
+                .add_type_tag(
+                    tag,
+                    &self.package_store,
+                    /* visit_fields */ true,
+                    /* visit_phantoms */ true,
+                )
+                .await?;
+        }
+
+        // (2). Use that information to resolve each tag into a layout.
+        let max_depth = self
+            .limits
+            .as_ref()
+            .map_or(usize::MAX, |l| l.max_move_value_depth);
+
+        tags.iter()
+            .map(|tag| Ok(context.resolve_type_layout(tag, max_depth)?.0))
+            .collect()
+    }
+
+    /// Return an upper bound on the size of the BCS encoding of values of type `tag`. Fails for the
+    /// same reasons as `type_layout`.
+    pub async fn max_serialized_size(&self, tag: TypeTag) -> Result<SerializedSize> {
+        Ok(SerializedSize::of(&self.type_layout(tag).await?))
+    }
+
+    /// Deserialize `bytes` as a BCS-encoded value of type `tag`, annotated with field names and
+    /// types from its layout. Fails for the same reasons as `type_layout` (including if the type
+    /// is nested too deeply), or if `bytes` is not exactly the encoding of a value of that type.
+    pub async fn decode_value(&self, tag: TypeTag, bytes: &[u8]) -> Result<MoveValue> {
+        let layout = self.type_layout(tag.clone()).await?;
+        deserialize_value(&tag, &layout, bytes)
+    }
+
+    /// Like `decode_value`, but bounds the decoded value by `limits`, for display to untrusted
+    /// clients.
+    pub async fn decode_value_bounded(
+        &self,
+        tag: TypeTag,
+        bytes: &[u8],
+        limits: &DecodeLimits,
+    ) -> Result<DisplayValue> {
+        let value = self.decode_value(tag, bytes).await?;
+        Ok(DisplayValue::bounded(value, limits))
+    }
+
+    /// Deserialize the contents of each of `events`, in order. The layout of each distinct event
+    /// type is resolved once for the whole batch. Fails if any event fails to decode, for the same
+    /// reasons as `decode_value`.
+    pub async fn decode_events(&self, events: &[Event]) -> Result<Vec<MoveValue>> {
+        self.decode_events_with(events, Ok).await
+    }
+
+    /// Like `decode_events`, but bounds each decoded event by `limits`, for display to untrusted
+    /// clients.
+    pub async fn decode_events_bounded(
+        &self,
+        events: &[Event],
+        limits: &DecodeLimits,
+    ) -> Result<Vec<DisplayValue>> {
+        self.decode_events_with(events, |value| Ok(DisplayValue::bounded(value, limits)))
+            .await
+    }
+
+    /// Decode the contents of each of `events`, and pass them through `f`.
+    async fn decode_events_with<T>(
+        &self,
+        events: &[Event],
+        f: impl Fn(MoveValue) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        let tags: Vec<_> = events
+            .iter()
+            .map(|e| TypeTag::Struct(Box::new(e.type_.clone())))
+            .collect();
+
+        let mut layouts = BTreeMap::new();
+        for tag in tags.iter().collect::<BTreeSet<_>>() {
+            let layout = self.type_layout(tag.clone()).await?;
+            layouts.insert(tag, layout);
+        }
+
+        tags.iter()
+            .zip(events)
+            .map(|(tag, event)| f(deserialize_value(tag, &layouts[tag], &event.contents)?))
+            .collect()
+    }
+
+    /// Return the abilities of a concrete type, based on the abilities in its type definition, and
+    /// the abilities of its concrete type parameters: An instance of a generic type has `store`,
+    /// `copy, or `drop` if its definition has the ability, and all its non-phantom type parameters
+    /// have the ability as well. Similar rules apply for `key` except that it requires its type
+    /// parameters to have `store`.
+    pub async fn abilities(&self, tag: TypeTag) -> Result<AbilitySet> {
+        Ok(self.abilities_with_stats(tag).await?.0)
+    }
+
+    /// Like `abilities`, but the package addresses in `tag` are runtime IDs, interpreted according
+    /// to the linkage table of the package at storage ID `context`.
+    pub async fn abilities_in_context(
+        &self,
+        tag: TypeTag,
+        context: AccountAddress,
+    ) -> Result<AbilitySet> {
+        let tag = self.relocate_in_context(tag, context).await?;
+        self.abilities(tag).await
+    }
+
+    /// Like `abilities`, but also returns statistics about the work done to resolve `tag`.
+    pub async fn abilities_with_stats(
+        &self,
+        mut tag: TypeTag,
+    ) -> Result<(AbilitySet, ResolutionStats)> {
+        let start = Instant::now();
+        let mut context = ResolutionContext::new(self.limits.as_ref());
+
+        // (1). Fetch all the information from this store that is necessary to resolve types
+        // referenced by this tag.
+        context
+            .add_type_tag(
+                &mut tag,
+                &self.package_store,
+                /* visit_fields */ false,
+                /* visit_phantoms */ false,
+            )
+            .await?;
+
+        // (2). Use that information to calculate the type's abilities.
+        let abilities = context.resolve_abilities(&tag)?;
+        Ok((abilities, context.stats(start)))
+    }
+
+    /// Check that `type_args` are valid instantiations for type parameters with the given ability
+    /// `constraints` (e.g. a function's `FunctionDef::type_params`, or the constraints from a
+    /// datatype's `DataDef::type_params`). Each type argument must have the abilities its
+    /// parameter requires, and every datatype it mentions must in turn be instantiated with type
+    /// arguments that satisfy that datatype's constraints, including in phantom positions.
+    pub async fn check_type_arguments(
+        &self,
+        type_args: &[TypeInput],
+        constraints: &[AbilitySet],
+    ) -> Result<()> {
+        if type_args.len() != constraints.len() {
+            return Err(Error::TypeArityMismatch(constraints.len(), type_args.len()));
+        }
+
+        let mut context = ResolutionContext::new(self.limits.as_ref());
+
+        // (1). Fetch the datatypes mentioned by the type arguments, including in phantom
+        // positions, whose constraints also need to be checked.
+        let mut tags = type_args
+            .iter()
+            .map(|t| as_type_tag_with_limits(t, self.limits.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+
+        for tag in &mut tags {
+            context
+                .add_type_tag(
+                    tag,
+                    &self.package_store,
+                    /* visit_fields */ false,
+                    /* visit_phantoms */ true,
+                )
+                .await?;
+        }
+
+        // (2). Check each type argument, and then its abilities against its parameter's.
+        for (index, (tag, &required)) in tags.iter().zip(constraints).enumerate() {
+            context.check_type_constraints(tag)?;
+            let actual = context.resolve_abilities(tag)?;
+            check_abilities(None, index, tag, required, actual)?;
+        }
+
+        Ok(())
+    }
+
     /// Returns the signatures of parameters to function `pkg::module::function` in the package
     /// store, assuming the function exists.
     pub async fn function_parameters(
@@ -432,8 +1531,38 @@ impl<S: PackageStore> Resolver<S> {
         module: &str,
         function: &str,
     ) -> Result<Vec<OpenSignature>> {
+        Ok(self
+            .function_parameters_with_stats(pkg, module, function)
+            .await?
+            .0)
+    }
+
+    /// Like `function_parameters`, but the function is identified by a `module_id` whose address is
+    /// a runtime ID, interpreted according to the linkage table of the package at storage ID
+    /// `context`. See `resolve_module_id`.
+    pub async fn function_parameters_in_context(
+        &self,
+        module_id: ModuleId,
+        function: &str,
+        context: AccountAddress,
+    ) -> Result<Vec<OpenSignature>> {
+        let module_id = self.resolve_module_id(module_id, context).await?;
+        self.function_parameters(*module_id.address(), module_id.name().as_str(), function)
+            .await
+    }
+
+    /// Like `function_parameters`, but also returns statistics about the work done to resolve the
+    /// function's signature.
+    pub async fn function_parameters_with_stats(
+        &self,
+        pkg: AccountAddress,
+        module: &str,
+        function: &str,
+    ) -> Result<(Vec<OpenSignature>, ResolutionStats)> {
+        let start = Instant::now();
         let mut context = ResolutionContext::new(self.limits.as_ref());
 
+        context.fetches += 1;
         let package = self.package_store.fetch(pkg).await?;
         let Some(def) = package.module(module)?.function_def(function)? else {
             return Err(Error::FunctionNotFound(
@@ -463,7 +1592,7 @@ impl<S: PackageStore> Resolver<S> {
             context.relocate_signature(&mut sig.body)?;
         }
 
-        Ok(sigs)
+        Ok((sigs, context.stats(start)))
     }
 
     /// Attempts to infer the type layouts for pure inputs to the programmable transaction.
@@ -532,7 +1661,7 @@ impl<S: PackageStore> Resolver<S> {
                 }
 
                 Command::MakeMoveVec(Some(tag), elems) => {
-                    let tag = as_type_tag(tag)?;
+                    let tag = as_type_tag_with_limits(tag, self.limits.as_ref())?;
                     if is_primitive_type_tag(&tag) {
                         for elem in elems {
                             register_type(elem, &tag)?;
@@ -562,1833 +1691,6040 @@ impl<S: PackageStore> Resolver<S> {
             .collect())
     }
 
-    /// Resolves a runtime address in a `ModuleId` to a storage `ModuleId` according to the linkage
-    /// table in the `context` which must refer to a package.
-    /// * Will fail if the wrong context is provided, i.e., is not a package, or
-    ///   does not exist.
-    /// * Will fail if an invalid `context` is provided for the `location`, i.e., the package at
-    ///   `context` does not contain the module that `location` refers to.
-    pub async fn resolve_module_id(
-        &self,
-        module_id: ModuleId,
-        context: AccountAddress,
-    ) -> Result<ModuleId> {
-        let package = self.package_store.fetch(context).await?;
-        let storage_id = package.relocate(*module_id.address())?;
-        Ok(ModuleId::new(storage_id, module_id.name().to_owned()))
-    }
-
-    /// Resolves an abort code following the clever error format to a `CleverError` enum.
-    /// The `module_id` must be the storage ID of the module (which can e.g., be gotten from the
-    /// `resolve_module_id` function) and not the runtime ID.
-    ///
-    /// If the `abort_code` is not a clever error (i.e., does not follow the tagging and layout as
-    /// defined in `ErrorBitset`), this function will return `None`.
-    ///
-    /// In the case where it is a clever error but only a line number is present (i.e., the error
-    /// is the result of an `assert!(<cond>)` source expression) a `CleverError::LineNumberOnly` is
-    /// returned. Otherwise a `CleverError::CompleteError` is returned.
+    /// Decodes BCS-encoded `TransactionData` and resolves everything needed to display it: the
+    /// layouts of its pure inputs, the kinds of its object inputs, and the (canonical) type
+    /// arguments and parameter signatures of the functions it calls.
     ///
-    /// If for any reason we are unable to resolve the abort code to a `CleverError`, this function
-    /// will return `None`.
-    pub async fn resolve_clever_error(
-        &self,
-        module_id: ModuleId,
-        abort_code: u64,
-    ) -> Option<CleverError> {
-        let bitset = ErrorBitset::from_u64(abort_code)?;
-        let package = self.package_store.fetch(*module_id.address()).await.ok()?;
-        let module = package.module(module_id.name().as_str()).ok()?.bytecode();
-        let source_line_number = bitset.line_number()?;
+    /// Fails if `bytes` does not decode to a programmable transaction, or if resolving any part of
+    /// it fails, for the same reasons as `pure_input_layouts`, `canonical_type` and
+    /// `function_parameters`.
+    pub async fn resolve_transaction(&self, bytes: &[u8]) -> Result<ResolvedTransaction> {
+        let tx: TransactionData = bcs::from_bytes(bytes).map_err(Error::InvalidTransaction)?;
+        let TransactionKind::ProgrammableTransaction(ptb) = tx.kind() else {
+            return Err(Error::NotAProgrammableTransaction);
+        };
 
-        // We only have a line number in our clever error, so return early.
-        if bitset.identifier_index().is_none() && bitset.constant_index().is_none() {
-            return Some(CleverError {
-                module_id,
-                error_info: ErrorConstants::None,
-                source_line_number,
-            });
-        } else if bitset.identifier_index().is_none() || bitset.constant_index().is_none() {
-            return None;
-        }
+        // (1). Infer layouts for pure inputs.
+        let layouts = self.pure_input_layouts(ptb).await?;
 
-        let error_identifier_constant = module
-            .constant_pool()
-            .get(bitset.identifier_index()? as usize)?;
-        let error_value_constant = module
-            .constant_pool()
-            .get(bitset.constant_index()? as usize)?;
+        // (2). Resolve the functions being called, and note the kinds of object inputs that are
+        // passed to them directly.
+        let mut kinds = vec![None; ptb.inputs.len()];
+        let mut move_calls = vec![];
+        for (command, cmd) in ptb.commands.iter().enumerate() {
+            let Command::MoveCall(call) = cmd else {
+                continue;
+            };
 
-        if !matches!(&error_identifier_constant.type_, SignatureToken::Vector(x) if x.as_ref() == &SignatureToken::U8)
-        {
-            return None;
-        };
+            let package = call.package.into();
+            let parameters = self
+                .function_parameters(package, call.module.as_str(), call.function.as_str())
+                .await?;
 
-        let error_identifier = bcs::from_bytes::<Vec<u8>>(&error_identifier_constant.data)
-            .ok()
-            .and_then(|x| String::from_utf8(x).ok())?;
-        let bytes = error_value_constant.data.clone();
+            let mut type_arguments = Vec::with_capacity(call.type_arguments.len());
+            for type_argument in &call.type_arguments {
+                let tag = as_type_tag_with_limits(type_argument, self.limits.as_ref())?;
+                type_arguments.push(self.canonical_type(tag).await?);
+            }
 
-        let rendered = try_render_constant(error_value_constant);
+            for (param, arg) in parameters.iter().zip(call.arguments.iter()) {
+                let &Argument::Input(ix) = arg else {
+                    continue;
+                };
 
-        let error_info = match rendered {
-            RenderResult::NotRendered => ErrorConstants::Raw {
-                identifier: error_identifier,
-                bytes,
-            },
-            RenderResult::AsString(s) | RenderResult::AsValue(s) => ErrorConstants::Rendered {
-                identifier: error_identifier,
-                constant: s,
-            },
-        };
+                if let Some(kind) = kinds.get_mut(ix as usize) {
+                    kind.get_or_insert(param.argument_kind());
+                }
+            }
 
-        Some(CleverError {
-            module_id,
-            error_info,
-            source_line_number,
+            move_calls.push(ResolvedMoveCall {
+                command,
+                package,
+                module: call.module.clone(),
+                function: call.function.clone(),
+                type_arguments,
+                parameters,
+            });
+        }
+
+        // (3). Combine the information gathered for each input.
+        let inputs = ptb
+            .inputs
+            .iter()
+            .zip(layouts)
+            .zip(kinds)
+            .map(|((input, layout), kind)| match input {
+                CallArg::Pure(bytes) => ResolvedInput::Pure {
+                    bytes: bytes.clone(),
+                    layout,
+                },
+                CallArg::Object(arg) => ResolvedInput::Object {
+                    arg: arg.clone(),
+                    kind,
+                },
+            })
+            .collect();
+
+        Ok(ResolvedTransaction {
+            sender: tx.sender(),
+            transaction: ptb.clone(),
+            inputs,
+            move_calls,
         })
     }
-}
 
-impl<T> PackageStoreWithLruCache<T> {
-    pub fn new(inner: T) -> Self {
-        let packages = Mutex::new(LruCache::new(PACKAGE_CACHE_SIZE));
-        Self { packages, inner }
-    }
+    /// Type-checks the commands of programmable transaction `tx` without running it: checks that
+    /// the functions it calls exist and can be called, that type arguments satisfy their ability
+    /// constraints, and that arguments refer to values that exist and have the expected types as
+    /// they flow from inputs and the results of earlier commands into later commands.
+    ///
+    /// The types of object inputs are not known offline, so uses of them are only checked as far
+    /// as they can be without knowing their types, and the check does not track whether values
+    /// are used after they have been moved. Problems with the transaction are listed in the
+    /// returned report, while failures to access the package store, or hitting the resolver's
+    /// limits, are returned as errors.
+    pub async fn check_ptb(&self, tx: &ProgrammableTransaction) -> Result<PtbTypeReport> {
+        use PtbTypeErrorKind as K;
+
+        let mut checker = PtbChecker::new(tx);
+        for cmd in &tx.commands {
+            let results = match cmd {
+                Command::MoveCall(call) => self.check_move_call(&mut checker, call).await?,
+
+                Command::TransferObjects(objs, recipient) => {
+                    for (argument, obj) in objs.iter().enumerate() {
+                        match checker.value(argument, obj) {
+                            PtbValue::Pure(_) => checker.error(K::UnexpectedPureInput { argument }),
+                            PtbValue::Typed(actual) => {
+                                let abilities = self.abilities(actual.clone()).await?;
+                                if !abilities.has_key() || !abilities.has_store() {
+                                    checker.error(K::NotTransferable { argument, actual });
+                                }
+                            }
+                            PtbValue::Object | PtbValue::Unknown => {}
+                        }
+                    }
 
-    /// Removes all packages with ids in `ids` from the cache, if they exist. Does nothing for ids
-    /// that are not in the cache. Accepts `self` immutably as it operates under the lock.
-    pub fn evict(&self, ids: impl IntoIterator<Item = AccountAddress>) {
-        let mut packages = self.packages.lock().unwrap();
-        for id in ids {
-            packages.pop(&id);
-        }
-    }
-}
+                    checker.expect(objs.len(), recipient, &TypeTag::Address);
+                    Some(vec![])
+                }
 
-#[async_trait]
-impl<T: PackageStore> PackageStore for PackageStoreWithLruCache<T> {
-    async fn fetch(&self, id: AccountAddress) -> Result<Arc<Package>> {
-        if let Some(package) = {
-            // Release the lock after getting the package
-            let mut packages = self.packages.lock().unwrap();
-            packages.get(&id).map(Arc::clone)
-        } {
-            return Ok(package);
-        };
+                Command::SplitCoins(coin, amounts) => {
+                    let coin = checker.coin(0, coin);
+                    for (ix, amount) in amounts.iter().enumerate() {
+                        checker.expect(ix + 1, amount, &TypeTag::U64);
+                    }
 
-        let package = self.inner.fetch(id).await?;
+                    Some(vec![coin; amounts.len()])
+                }
 
-        // Try and insert the package into the cache, accounting for races.  In most cases the
-        // racing fetches will produce the same package, but for system packages, they may not, so
-        // favour the package that has the newer version, or if they are the same, the package that
-        // is already in the cache.
+                Command::MergeCoins(target, sources) => {
+                    let target = checker.coin(0, target);
+                    for (ix, source) in sources.iter().enumerate() {
+                        let source = checker.coin(ix + 1, source);
+                        if let (Some(expected), Some(actual)) = (&target, source) {
+                            if expected != &actual {
+                                checker.error(K::TypeMismatch {
+                                    argument: ix + 1,
+                                    expected: expected.clone(),
+                                    actual,
+                                });
+                            }
+                        }
+                    }
 
-        let mut packages = self.packages.lock().unwrap();
-        Ok(match packages.peek(&id) {
-            Some(prev) if package.version <= prev.version => {
-                let package = prev.clone();
-                packages.promote(&id);
-                package
-            }
+                    Some(vec![])
+                }
 
-            Some(_) | None => {
-                packages.push(id, package.clone());
-                package
-            }
-        })
-    }
-}
+                Command::MakeMoveVec(Some(type_), elems) => match self.ptb_type(type_).await {
+                    Ok(tag) => {
+                        for (argument, elem) in elems.iter().enumerate() {
+                            checker.expect(argument, elem, &tag);
+                        }
 
-impl Package {
-    pub fn read_from_object(object: &Object) -> Result<Self> {
-        let storage_id = AccountAddress::from(object.id());
-        let Some(package) = object.data.try_as_package() else {
-            return Err(Error::NotAPackage(storage_id));
-        };
+                        Some(vec![Some(TypeTag::Vector(Box::new(tag)))])
+                    }
 
-        Self::read_from_package(package)
+                    Err(e) if is_unresolved(&e) => {
+                        checker.error(K::Unresolved(e.to_string()));
+                        None
+                    }
+
+                    Err(e) => return Err(e),
+                },
+
+                Command::MakeMoveVec(None, elems) => {
+                    // Without an explicit type, the elements must be objects of the same type.
+                    let mut elem_type: Option<TypeTag> = None;
+                    for (argument, elem) in elems.iter().enumerate() {
+                        match checker.value(argument, elem) {
+                            PtbValue::Pure(_) => checker.error(K::UnexpectedPureInput { argument }),
+                            PtbValue::Typed(actual) => match &elem_type {
+                                Some(expected) if expected != &actual => {
+                                    checker.error(K::TypeMismatch {
+                                        argument,
+                                        expected: expected.clone(),
+                                        actual,
+                                    });
+                                }
+                                Some(_) => {}
+                                None => elem_type = Some(actual),
+                            },
+                            PtbValue::Object | PtbValue::Unknown => {}
+                        }
+                    }
+
+                    Some(vec![elem_type.map(|t| TypeTag::Vector(Box::new(t)))])
+                }
+
+                Command::Publish(_, _) => Some(vec![Some(UpgradeCap::type_().into())]),
+
+                Command::Upgrade(_, _, _, ticket) => {
+                    checker.expect(0, ticket, &UpgradeTicket::type_().into());
+                    Some(vec![Some(UpgradeReceipt::type_().into())])
+                }
+            };
+
+            checker.results.push(results);
+        }
+
+        Ok(checker.report())
     }
 
-    pub fn read_from_package(package: &MovePackage) -> Result<Self> {
-        let storage_id = AccountAddress::from(package.id());
-        let mut type_origins: BTreeMap<String, BTreeMap<String, AccountAddress>> = BTreeMap::new();
-        for TypeOrigin {
-            module_name,
-            datatype_name,
-            package,
-        } in package.type_origin_table()
+    /// Type-check a `MoveCall` command, returning the types of its results.
+    async fn check_move_call(
+        &self,
+        checker: &mut PtbChecker<'_>,
+        call: &ProgrammableMoveCall,
+    ) -> Result<Option<Vec<Option<TypeTag>>>> {
+        use PtbTypeErrorKind as K;
+
+        let def = match self
+            .resolved_function_def(call.package.into(), &call.module, &call.function)
+            .await
         {
-            type_origins
-                .entry(module_name.to_string())
-                .or_default()
-                .insert(datatype_name.to_string(), AccountAddress::from(*package));
+            Ok(def) => def,
+            Err(e) if is_unresolved(&e) => {
+                checker.error(K::Unresolved(e.to_string()));
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+
+        if def.visibility != Visibility::Public && !def.is_entry {
+            checker.error(K::NotCallable);
         }
 
-        let mut runtime_id = None;
-        let mut modules = BTreeMap::new();
-        for (name, bytes) in package.serialized_module_map() {
-            let origins = type_origins.remove(name).unwrap_or_default();
-            let bytecode = CompiledModule::deserialize_with_defaults(bytes)
-                .map_err(|e| Error::Deserialize(e.finish(Location::Undefined)))?;
+        if def.return_.iter().any(|sig| sig.ref_.is_some()) {
+            checker.error(K::ReferenceReturn);
+        }
 
-            runtime_id = Some(*bytecode.address());
+        let mut type_arguments = Vec::with_capacity(call.type_arguments.len());
+        for type_argument in &call.type_arguments {
+            match self.ptb_type(type_argument).await {
+                Ok(tag) => type_arguments.push(tag),
+                Err(e) if is_unresolved(&e) => {
+                    checker.error(K::Unresolved(e.to_string()));
+                    return Ok(None);
+                }
+                Err(e) => return Err(e),
+            }
+        }
 
-            let name = name.clone();
-            match Module::read(bytecode, origins) {
-                Ok(module) => modules.insert(name, module),
-                Err(struct_) => return Err(Error::NoTypeOrigin(storage_id, name, struct_)),
-            };
+        if type_arguments.len() != def.type_params.len() {
+            checker.error(K::TypeArityMismatch {
+                expected: def.type_params.len(),
+                actual: type_arguments.len(),
+            });
+            return Ok(None);
         }
 
-        let Some(runtime_id) = runtime_id else {
-            return Err(Error::EmptyPackage(storage_id));
-        };
+        for (type_argument, (tag, &required)) in
+            type_arguments.iter().zip(&def.type_params).enumerate()
+        {
+            let actual = self.abilities(tag.clone()).await?;
+            if !required.is_subset(actual) {
+                checker.error(K::MissingAbilities {
+                    type_argument,
+                    required,
+                    actual,
+                });
+            }
+        }
 
-        let linkage = package
-            .linkage_table()
-            .iter()
-            .map(|(&dep, linkage)| (dep.into(), linkage.upgraded_id.into()))
-            .collect();
+        // The trailing `TxContext` parameter, if there is one, is supplied by the runtime.
+        let mut params = def.parameters.as_slice();
+        if let Some((last, rest)) = params.split_last() {
+            if last.argument_kind() == ArgumentKind::TxContext {
+                params = rest;
+            }
+        }
 
-        Ok(Package {
-            storage_id,
-            runtime_id,
-            version: package.version(),
-            modules,
-            linkage,
-        })
-    }
+        if params.len() != call.arguments.len() {
+            checker.error(K::ArityMismatch {
+                expected: params.len(),
+                actual: call.arguments.len(),
+            });
+        }
 
-    pub fn module(&self, module: &str) -> Result<&Module> {
-        self.modules
-            .get(module)
-            .ok_or_else(|| Error::ModuleNotFound(self.storage_id, module.to_string()))
-    }
+        let type_inputs: Vec<TypeInput> = type_arguments.into_iter().map(Into::into).collect();
+        for (argument, (param, arg)) in params.iter().zip(&call.arguments).enumerate() {
+            let expected = param.body.instantiate(&type_inputs)?;
+            checker.expect(argument, arg, &expected);
+        }
 
-    pub fn modules(&self) -> &BTreeMap<String, Module> {
-        &self.modules
+        let results = def
+            .return_
+            .iter()
+            .map(|sig| sig.body.instantiate(&type_inputs).map(Some))
+            .collect::<Result<_>>()?;
+
+        Ok(Some(results))
     }
 
-    fn data_def(&self, module_name: &str, datatype_name: &str) -> Result<DataDef> {
-        let module = self.module(module_name)?;
-        let Some(data_def) = module.data_def(datatype_name)? else {
-            return Err(Error::DatatypeNotFound(
-                self.storage_id,
-                module_name.to_string(),
-                datatype_name.to_string(),
+    /// Fetch the definition of function `pkg::module::function`, with the package IDs in its
+    /// parameter and return types relocated to defining IDs.
+    async fn resolved_function_def(
+        &self,
+        pkg: AccountAddress,
+        module: &str,
+        function: &str,
+    ) -> Result<FunctionDef> {
+        let mut context = ResolutionContext::new(self.limits.as_ref());
+
+        let package = self.package_store.fetch(pkg).await?;
+        let Some(mut def) = package.module(module)?.function_def(function)? else {
+            return Err(Error::FunctionNotFound(
+                pkg,
+                module.to_string(),
+                function.to_string(),
             ));
         };
-        Ok(data_def)
-    }
 
-    /// Translate the `runtime_id` of a package to a specific storage ID using this package's
-    /// linkage table.  Returns an error if the package in question is not present in the linkage
-    /// table.
-    fn relocate(&self, runtime_id: AccountAddress) -> Result<AccountAddress> {
-        // Special case the current package, because it doesn't get an entry in the linkage table.
-        if runtime_id == self.runtime_id {
-            return Ok(self.storage_id);
+        for sig in def.parameters.iter().chain(&def.return_) {
+            context
+                .add_signature(
+                    sig.body.clone(),
+                    &self.package_store,
+                    package.as_ref(),
+                    /* visit_fields */ false,
+                )
+                .await?;
         }
 
-        self.linkage
-            .get(&runtime_id)
-            .ok_or_else(|| Error::LinkageNotFound(runtime_id))
-            .copied()
-    }
-}
+        for sig in def.parameters.iter_mut().chain(def.return_.iter_mut()) {
+            context.relocate_signature(&mut sig.body)?;
+        }
 
-impl Module {
-    /// Deserialize a module from its bytecode, and a table containing the origins of its structs.
-    /// Fails if the origin table is missing an entry for one of its types, returning the name of
-    /// the type in that case.
-    fn read(
-        bytecode: CompiledModule,
-        mut origins: BTreeMap<String, AccountAddress>,
-    ) -> std::result::Result<Self, String> {
-        let mut struct_index = BTreeMap::new();
-        for (index, def) in bytecode.struct_defs.iter().enumerate() {
-            let sh = bytecode.datatype_handle_at(def.struct_handle);
-            let struct_ = bytecode.identifier_at(sh.name).to_string();
-            let index = StructDefinitionIndex::new(index as TableIndex);
+        Ok(def)
+    }
 
-            let Some(defining_id) = origins.remove(&struct_) else {
-                return Err(struct_);
-            };
+    /// The canonical type tag for a type argument in a programmable transaction.
+    async fn ptb_type(&self, type_input: &TypeInput) -> Result<TypeTag> {
+        let tag = as_type_tag_with_limits(type_input, self.limits.as_ref())?;
+        self.canonical_type(tag).await
+    }
 
-            struct_index.insert(struct_, (defining_id, index));
-        }
+    /// Resolve the definition of datatype `module::name` in the latest version of the package whose
+    /// original ID is `original_id`, without the caller needing to know that version's storage ID.
+    /// Requires the package store to support looking up the latest version of a package.
+    pub async fn resolve_from_original(
+        &self,
+        original_id: AccountAddress,
+        module: &str,
+        name: &str,
+    ) -> Result<DataDef> {
+        let storage_id = self.package_store.latest_package_id(original_id).await?;
+        let package = self.package_store.fetch(storage_id).await?;
+        package.data_def(module, name)
+    }
 
-        let mut enum_index = BTreeMap::new();
-        for (index, def) in bytecode.enum_defs.iter().enumerate() {
-            let eh = bytecode.datatype_handle_at(def.enum_handle);
-            let enum_ = bytecode.identifier_at(eh.name).to_string();
-            let index = EnumDefinitionIndex::new(index as TableIndex);
+    /// The definition of the datatype `key` (whose package is the original ID of the package that
+    /// contains it) in each version of that package, from the version that introduced it onwards,
+    /// ordered by version. Useful for auditing how a type changed across upgrades. Requires the
+    /// package store to support listing the versions of a package.
+    pub async fn datatype_history(&self, key: DatatypeKey) -> Result<Vec<DatatypeRevision>> {
+        let storage_ids = self.package_store.package_versions(key.package).await?;
+
+        let mut history = vec![];
+        for storage_id in storage_ids {
+            let package = self.package_store.fetch(storage_id).await?;
+
+            // Modules and datatypes can be added by upgrades, so they will be missing from
+            // versions of the package before the one that introduced them.
+            let Some(module) = package.modules.get(key.module.as_ref()) else {
+                continue;
+            };
 
-            let Some(defining_id) = origins.remove(&enum_) else {
-                return Err(enum_);
+            let Some(def) = module.data_def(&key.name)? else {
+                continue;
             };
 
-            enum_index.insert(enum_, (defining_id, index));
+            history.push(DatatypeRevision {
+                storage_id,
+                version: package.version,
+                def,
+            });
         }
 
-        let mut function_index = BTreeMap::new();
-        for (index, def) in bytecode.function_defs.iter().enumerate() {
-            let fh = bytecode.function_handle_at(def.function);
-            let function = bytecode.identifier_at(fh.name).to_string();
-            let index = FunctionDefinitionIndex::new(index as TableIndex);
-
-            function_index.insert(function, index);
+        if history.is_empty() {
+            return Err(Error::DatatypeNotFound(
+                key.package,
+                key.module.to_string(),
+                key.name.to_string(),
+            ));
         }
 
-        Ok(Module {
-            bytecode,
-            struct_index,
-            enum_index,
-            function_index,
-        })
+        history.sort_by_key(|r| r.version);
+        Ok(history)
     }
 
-    pub fn bytecode(&self) -> &CompiledModule {
-        &self.bytecode
+    /// Fetch the package at `storage_id` and check that its contents match the `expected` digest,
+    /// computed according to the package digest scheme of `config`'s protocol version. Guards
+    /// against a package store serving bytecode that differs from what is on-chain. The store must
+    /// read its packages with `Package::read_with_digests`.
+    pub async fn verify_package_digest(
+        &self,
+        storage_id: AccountAddress,
+        expected: [u8; 32],
+        config: &ProtocolConfig,
+    ) -> Result<()> {
+        let package = self.package_store.fetch(storage_id).await?;
+        package.verify_digest(expected, config)
     }
 
-    /// The module's name
-    pub fn name(&self) -> &str {
-        self.bytecode
-            .identifier_at(self.bytecode.self_handle().name)
-            .as_str()
+    /// Render the bytecode of function `name` in the module identified by `module_id`, whose
+    /// address is the storage ID of the package to load it from. See `Module::disassemble_function`
+    /// for how `source` is used.
+    pub async fn disassemble_function(
+        &self,
+        module_id: &ModuleId,
+        name: &str,
+        source: Option<&ModuleSource>,
+    ) -> Result<String> {
+        let package = self.package_store.fetch(*module_id.address()).await?;
+        package
+            .module(module_id.name().as_str())?
+            .disassemble_function(name, source)
     }
 
-    /// Iterate over the structs with names strictly after `after` (or from the beginning), and
-    /// strictly before `before` (or to the end).
-    pub fn structs(
+    /// Resolves a runtime address in a `ModuleId` to a storage `ModuleId` according to the linkage
+    /// table in the `context` which must refer to a package.
+    /// * Will fail if the wrong context is provided, i.e., is not a package, or
+    ///   does not exist.
+    /// * Will fail if an invalid `context` is provided for the `location`, i.e., the package at
+    ///   `context` does not contain the module that `location` refers to.
+    pub async fn resolve_module_id(
         &self,
-        after: Option<&str>,
-        before: Option<&str>,
-    ) -> impl DoubleEndedIterator<Item = &str> + Clone {
-        use std::ops::Bound as B;
-        self.struct_index
-            .range::<str, _>((
-                after.map_or(B::Unbounded, B::Excluded),
-                before.map_or(B::Unbounded, B::Excluded),
-            ))
-            .map(|(name, _)| name.as_str())
+        module_id: ModuleId,
+        context: AccountAddress,
+    ) -> Result<ModuleId> {
+        let package = self.linkage_context(context).await?;
+        let storage_id = package.relocate(*module_id.address())?;
+        Ok(ModuleId::new(storage_id, module_id.name().to_owned()))
     }
 
-    /// Iterate over the enums with names strictly after `after` (or from the beginning), and
-    /// strictly before `before` (or to the end).
-    pub fn enums(
+    /// Rewrite the runtime IDs in `tag` to storage IDs, according to the linkage table of the
+    /// package at storage ID `context`.
+    async fn relocate_in_context(
         &self,
-        after: Option<&str>,
-        before: Option<&str>,
-    ) -> impl DoubleEndedIterator<Item = &str> + Clone {
-        use std::ops::Bound as B;
-        self.enum_index
-            .range::<str, _>((
-                after.map_or(B::Unbounded, B::Excluded),
-                before.map_or(B::Unbounded, B::Excluded),
-            ))
-            .map(|(name, _)| name.as_str())
+        mut tag: TypeTag,
+        context: AccountAddress,
+    ) -> Result<TypeTag> {
+        let package = self.linkage_context(context).await?;
+        package.relocate_type(&mut tag)?;
+        Ok(tag)
     }
 
-    /// Iterate over the datatypes with names strictly after `after` (or from the beginning), and
-    /// strictly before `before` (or to the end). Enums and structs will be interleaved, and will
-    /// be sorted by their names.
-    pub fn datatypes(
-        &self,
-        after: Option<&str>,
-        before: Option<&str>,
-    ) -> impl DoubleEndedIterator<Item = &str> + Clone {
-        let mut names = self
-            .structs(after, before)
-            .chain(self.enums(after, before))
-            .collect::<Vec<_>>();
-        names.sort();
-        names.into_iter()
+    /// Fetch the package at storage ID `context`, to relocate runtime IDs with, checking that its
+    /// linkage table is within the resolver's limits.
+    async fn linkage_context(&self, context: AccountAddress) -> Result<Arc<Package>> {
+        let package = self.package_store.fetch(context).await?;
+        check_max_limit!(
+            TooManyLinkageEntries, self.limits.as_ref();
+            max_linkage_entries >= package.linkage.len()
+        );
+
+        Ok(package)
     }
 
-    /// Get the struct definition corresponding to the struct with name `name` in this module.
-    /// Returns `Ok(None)` if the struct cannot be found in this module, `Err(...)` if there was an
-    /// error deserializing it, and `Ok(Some(def))` on success.
-    pub fn struct_def(&self, name: &str) -> Result<Option<DataDef>> {
-        let Some(&(defining_id, index)) = self.struct_index.get(name) else {
-            return Ok(None);
-        };
+    /// Resolves an abort code following the clever error format to a `CleverError` enum.
+    /// The `module_id` must be the storage ID of the module (which can e.g., be gotten from the
+    /// `resolve_module_id` function) and not the runtime ID.
+    ///
+    /// If the `abort_code` is not a clever error (i.e., does not follow the tagging and layout as
+    /// defined in `ErrorBitset`), this function will return `None`.
+    ///
+    /// In the case where it is a clever error but only a line number is present (i.e., the error
+    /// is the result of an `assert!(<cond>)` source expression) a `CleverError::LineNumberOnly` is
+    /// returned. Otherwise a `CleverError::CompleteError` is returned.
+    ///
+    /// If for any reason we are unable to resolve the abort code to a `CleverError`, this function
+    /// will return `None`.
+    pub async fn resolve_clever_error(
+        &self,
+        module_id: ModuleId,
+        abort_code: u64,
+    ) -> Option<CleverError> {
+        let bitset = ErrorBitset::from_u64(abort_code)?;
+        let package = self.package_store.fetch(*module_id.address()).await.ok()?;
+        let module = package.module(module_id.name().as_str()).ok()?.bytecode();
+        let source_line_number = bitset.line_number()?;
 
-        let struct_def = self.bytecode.struct_def_at(index);
-        let struct_handle = self.bytecode.datatype_handle_at(struct_def.struct_handle);
-        let abilities = struct_handle.abilities;
-        let type_params = struct_handle.type_parameters.clone();
+        // We only have a line number in our clever error, so return early.
+        if bitset.identifier_index().is_none() && bitset.constant_index().is_none() {
+            return Some(CleverError {
+                module_id,
+                error_info: ErrorConstants::None,
+                source_line_number,
+            });
+        } else if bitset.identifier_index().is_none() || bitset.constant_index().is_none() {
+            return None;
+        }
 
-        let fields = match &struct_def.field_information {
-            StructFieldInformation::Native => vec![],
-            StructFieldInformation::Declared(fields) => fields
-                .iter()
-                .map(|f| {
-                    Ok((
-                        self.bytecode.identifier_at(f.name).to_string(),
-                        OpenSignatureBody::read(&f.signature.0, &self.bytecode)?,
-                    ))
-                })
-                .collect::<Result<_>>()?,
+        let error_identifier_constant = module
+            .constant_pool()
+            .get(bitset.identifier_index()? as usize)?;
+        let error_value_constant = module
+            .constant_pool()
+            .get(bitset.constant_index()? as usize)?;
+
+        let error_identifier = constant_string(error_identifier_constant)?;
+        let bytes = error_value_constant.data.clone();
+
+        let rendered = try_render_constant(error_value_constant);
+
+        let error_info = match rendered {
+            RenderResult::NotRendered => ErrorConstants::Raw {
+                identifier: error_identifier,
+                bytes,
+            },
+            RenderResult::AsString(s) | RenderResult::AsValue(s) => ErrorConstants::Rendered {
+                identifier: error_identifier,
+                constant: s,
+            },
         };
 
-        Ok(Some(DataDef {
-            defining_id,
-            abilities,
-            type_params,
-            data: MoveData::Struct(fields),
-        }))
+        Some(CleverError {
+            module_id,
+            error_info,
+            source_line_number,
+        })
     }
+}
 
-    /// Get the enum definition corresponding to the enum with name `name` in this module.
-    /// Returns `Ok(None)` if the enum cannot be found in this module, `Err(...)` if there was an
-    /// error deserializing it, and `Ok(Some(def))` on success.
-    pub fn enum_def(&self, name: &str) -> Result<Option<DataDef>> {
-        let Some(&(defining_id, index)) = self.enum_index.get(name) else {
-            return Ok(None);
-        };
+impl ResolutionPlan {
+    /// The types whose layouts this plan resolves, in order.
+    pub fn tags(&self) -> &[TypeTag] {
+        &self.tags
+    }
 
-        let enum_def = self.bytecode.enum_def_at(index);
-        let enum_handle = self.bytecode.datatype_handle_at(enum_def.enum_handle);
-        let abilities = enum_handle.abilities;
-        let type_params = enum_handle.type_parameters.clone();
+    /// The packages named directly by the plan's types. Executing the plan fetches these, and
+    /// any packages that the datatypes they define depend on.
+    pub fn packages(&self) -> &BTreeSet<AccountAddress> {
+        &self.packages
+    }
 
-        let variants = enum_def
-            .variants
-            .iter()
-            .map(|variant| {
-                let name = self
-                    .bytecode
-                    .identifier_at(variant.variant_name)
-                    .to_string();
-                let signatures = variant
-                    .fields
-                    .iter()
-                    .map(|f| {
-                        Ok((
-                            self.bytecode.identifier_at(f.name).to_string(),
-                            OpenSignatureBody::read(&f.signature.0, &self.bytecode)?,
-                        ))
-                    })
-                    .collect::<Result<_>>()?;
+    /// The datatypes named directly by the plan's types.
+    pub fn datatypes(&self) -> &BTreeSet<DatatypeKey> {
+        &self.datatypes
+    }
 
-                Ok(VariantDef { name, signatures })
-            })
-            .collect::<Result<_>>()?;
+    /// Add the types from `other` to this plan, after the types already in it.
+    pub fn merge(&mut self, other: ResolutionPlan) {
+        self.tags.extend(other.tags);
+        self.packages.extend(other.packages);
+        self.datatypes.extend(other.datatypes);
+    }
+}
 
-        Ok(Some(DataDef {
-            defining_id,
-            abilities,
-            type_params,
-            data: MoveData::Enum(variants),
-        }))
+impl PackageCursor {
+    pub fn new(module: &str, name: Option<&str>) -> Self {
+        Self {
+            module: module.to_string(),
+            name: name.map(str::to_string),
+        }
     }
 
-    /// Get the data definition corresponding to the data type with name `name` in this module.
-    /// Returns `Ok(None)` if the datatype cannot be found in this module, `Err(...)` if there was an
-    /// error deserializing it, and `Ok(Some(def))` on success.
-    pub fn data_def(&self, name: &str) -> Result<Option<DataDef>> {
-        self.struct_def(name)
-            .transpose()
-            .or_else(|| self.enum_def(name).transpose())
-            .transpose()
+    /// Encode this cursor as an opaque token (hex-encoded BCS).
+    pub fn encode(&self) -> String {
+        bcs::to_bytes(self)
+            .expect("cursors can always be serialized")
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
     }
 
-    /// Iterate over the functions with names strictly after `after` (or from the beginning), and
-    /// strictly before `before` (or to the end).
-    pub fn functions(
-        &self,
-        after: Option<&str>,
-        before: Option<&str>,
-    ) -> impl DoubleEndedIterator<Item = &str> + Clone {
-        use std::ops::Bound as B;
-        self.function_index
-            .range::<str, _>((
-                after.map_or(B::Unbounded, B::Excluded),
-                before.map_or(B::Unbounded, B::Excluded),
-            ))
-            .map(|(name, _)| name.as_str())
+    /// Decode a token produced by `encode`.
+    pub fn decode(token: &str) -> Result<Self> {
+        let invalid = || Error::InvalidCursor(token.to_string());
+        if token.len() % 2 != 0 || !token.is_ascii() {
+            return Err(invalid());
+        }
+
+        let bytes = (0..token.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&token[i..i + 2], 16).map_err(|_| invalid()))
+            .collect::<Result<Vec<_>>>()?;
+
+        bcs::from_bytes(&bytes).map_err(|_| invalid())
     }
 
-    /// Get the function definition corresponding to the function with name `name` in this module.
-    /// Returns `Ok(None)` if the function cannot be found in this module, `Err(...)` if there was
-    /// an error deserializing it, and `Ok(Some(def))` on success.
-    pub fn function_def(&self, name: &str) -> Result<Option<FunctionDef>> {
-        let Some(&index) = self.function_index.get(name) else {
-            return Ok(None);
-        };
+    /// Whether this cursor is strictly between `after` and `before` (where a missing bound is
+    /// unbounded).
+    fn in_range(&self, after: Option<&PackageCursor>, before: Option<&PackageCursor>) -> bool {
+        after.map_or(true, |a| a < self) && before.map_or(true, |b| self < b)
+    }
+}
 
-        let function_def = self.bytecode.function_def_at(index);
-        let function_handle = self.bytecode.function_handle_at(function_def.function);
+impl<'l> VariantLayout<'l> {
+    /// The variants of the enum with the given `layout`, in tag order.
+    pub fn of(layout: &'l MoveEnumLayout) -> Vec<Self> {
+        let mut variants: Vec<_> = layout
+            .variants
+            .iter()
+            .map(|((name, tag), fields)| VariantLayout {
+                name,
+                tag: *tag,
+                fields: fields.as_slice(),
+            })
+            .collect();
 
-        Ok(Some(FunctionDef {
-            visibility: function_def.visibility,
-            is_entry: function_def.is_entry,
-            type_params: function_handle.type_parameters.clone(),
-            parameters: read_signature(function_handle.parameters, &self.bytecode)?,
-            return_: read_signature(function_handle.return_, &self.bytecode)?,
-        }))
+        variants.sort_by_key(|v| v.tag);
+        variants
     }
 }
 
-impl OpenSignature {
-    fn read(sig: &SignatureToken, bytecode: &CompiledModule) -> Result<Self> {
-        use SignatureToken as S;
-        Ok(match sig {
-            S::Reference(sig) => OpenSignature {
-                ref_: Some(Reference::Immutable),
-                body: OpenSignatureBody::read(sig, bytecode)?,
-            },
+impl DisplayValue {
+    /// Convert `value` into a value for display, bounded by `limits`.
+    pub fn bounded(value: MoveValue, limits: &DecodeLimits) -> Self {
+        let mut budget = limits.max_total_bytes;
+        Self::bounded_impl(value, limits, &mut budget)
+    }
 
-            S::MutableReference(sig) => OpenSignature {
-                ref_: Some(Reference::Mutable),
-                body: OpenSignatureBody::read(sig, bytecode)?,
+    fn bounded_impl(value: MoveValue, limits: &DecodeLimits, budget: &mut usize) -> Self {
+        use DisplayValue as D;
+        use MoveValue as V;
+
+        match value {
+            V::Struct(s) if is_string(&s.type_) => {
+                let bytes: Vec<u8> = s
+                    .fields
+                    .into_iter()
+                    .flat_map(|(_, bytes)| match bytes {
+                        V::Vector(bytes) => bytes,
+                        _ => vec![],
+                    })
+                    .filter_map(|b| match b {
+                        V::U8(b) => Some(b),
+                        _ => None,
+                    })
+                    .collect();
+
+                let string = String::from_utf8_lossy(&bytes);
+                let value: String = string.chars().take(limits.max_string_length).collect();
+                let omitted = string.chars().count() - value.chars().count();
+
+                if charge(budget, value.len()) {
+                    D::String { value, omitted }
+                } else {
+                    D::Truncated
+                }
+            }
+
+            V::Struct(s) => D::Struct {
+                type_: s.type_,
+                fields: Self::bounded_fields(s.fields, limits, budget),
             },
 
-            sig => OpenSignature {
-                ref_: None,
-                body: OpenSignatureBody::read(sig, bytecode)?,
+            V::Variant(v) => D::Variant {
+                type_: v.type_,
+                name: v.variant_name,
+                tag: v.tag,
+                fields: Self::bounded_fields(v.fields, limits, budget),
             },
-        })
+
+            V::Vector(values) => {
+                let mut omitted = values.len();
+                let mut elements = vec![];
+                for value in values.into_iter().take(limits.max_vector_elements) {
+                    if *budget == 0 {
+                        break;
+                    }
+
+                    elements.push(Self::bounded_impl(value, limits, budget));
+                    omitted -= 1;
+                }
+
+                D::Vector { elements, omitted }
+            }
+
+            V::Bool(_) | V::U8(_) => D::primitive(value, 1, budget),
+            V::U16(_) => D::primitive(value, 2, budget),
+            V::U32(_) => D::primitive(value, 4, budget),
+            V::U64(_) => D::primitive(value, 8, budget),
+            V::U128(_) => D::primitive(value, 16, budget),
+            V::U256(_) => D::primitive(value, 32, budget),
+            V::Address(_) | V::Signer(_) => D::primitive(value, AccountAddress::LENGTH, budget),
+        }
     }
 
-    /// Return a specific instantiation of this signature, with `type_params` as the actual type
-    /// parameters. This function does not check that the supplied type parameters are valid (meet
-    /// the ability constraints of the struct or function this signature is part of), but will
-    /// produce an error if the signature references a type parameter that is out of bounds.
-    pub fn instantiate(&self, type_params: &[TypeInput]) -> Result<Signature> {
-        Ok(Signature {
-            ref_: self.ref_,
-            body: self.body.instantiate(type_params)?,
-        })
+    /// A primitive `value` that takes `size` bytes to show, if there is enough budget left.
+    fn primitive(value: MoveValue, size: usize, budget: &mut usize) -> Self {
+        if charge(budget, size) {
+            DisplayValue::Primitive(value)
+        } else {
+            DisplayValue::Truncated
+        }
     }
-}
 
-impl OpenSignatureBody {
-    fn read(sig: &SignatureToken, bytecode: &CompiledModule) -> Result<Self> {
-        use OpenSignatureBody as O;
-        use SignatureToken as S;
+    fn bounded_fields(
+        fields: Vec<(Identifier, MoveValue)>,
+        limits: &DecodeLimits,
+        budget: &mut usize,
+    ) -> Vec<(Identifier, DisplayValue)> {
+        fields
+            .into_iter()
+            .map(|(name, value)| (name, Self::bounded_impl(value, limits, budget)))
+            .collect()
+    }
+}
 
-        Ok(match sig {
-            S::Signer => return Err(Error::UnexpectedSigner),
-            S::Reference(_) | S::MutableReference(_) => return Err(Error::UnexpectedReference),
+impl SerializedSize {
+    /// The upper bound on the serialized size of values with the given `layout`.
+    pub fn of(layout: &MoveTypeLayout) -> Self {
+        use MoveTypeLayout as L;
+        use SerializedSize as S;
+
+        match layout {
+            L::Bool | L::U8 => S::Bounded(1),
+            L::U16 => S::Bounded(2),
+            L::U32 => S::Bounded(4),
+            L::U64 => S::Bounded(8),
+            L::U128 => S::Bounded(16),
+            L::U256 => S::Bounded(32),
+            L::Address | L::Signer => S::Bounded(AccountAddress::LENGTH),
+
+            L::Vector(element) => S::Unbounded {
+                fixed: MAX_SEQUENCE_LENGTH_PREFIX,
+                vectors: vec![S::of(element)],
+            },
 
-            S::Address => O::Address,
-            S::Bool => O::Bool,
-            S::U8 => O::U8,
-            S::U16 => O::U16,
-            S::U32 => O::U32,
-            S::U64 => O::U64,
-            S::U128 => O::U128,
-            S::U256 => O::U256,
-            S::TypeParameter(ix) => O::TypeParameter(*ix),
+            L::Struct(s) => Self::of_fields(&s.fields),
 
-            S::Vector(sig) => O::Vector(Box::new(OpenSignatureBody::read(sig, bytecode)?)),
+            L::Enum(e) => {
+                // The variant tag is ULEB128-encoded, taking 7 bits per byte.
+                let mut tag_size = 1;
+                while e.variants.len() > 1 << (7 * tag_size) {
+                    tag_size += 1;
+                }
 
-            S::Datatype(ix) => O::Datatype(DatatypeKey::read(*ix, bytecode), vec![]),
-            S::DatatypeInstantiation(inst) => {
-                let (ix, params) = &**inst;
-                O::Datatype(
-                    DatatypeKey::read(*ix, bytecode),
-                    params
-                        .iter()
-                        .map(|sig| OpenSignatureBody::read(sig, bytecode))
-                        .collect::<Result<_>>()?,
-                )
+                let mut largest = 0;
+                let mut vectors = vec![];
+                for fields in e.variants.values() {
+                    match Self::of_fields(fields) {
+                        S::Bounded(size) => largest = largest.max(size),
+                        S::Unbounded { fixed, vectors: vs } => {
+                            largest = largest.max(fixed);
+                            vectors.extend(vs);
+                        }
+                    }
+                }
+
+                if vectors.is_empty() {
+                    S::Bounded(tag_size + largest)
+                } else {
+                    S::Unbounded {
+                        fixed: tag_size + largest,
+                        vectors,
+                    }
+                }
             }
-        })
+        }
     }
 
-    fn instantiate(&self, type_params: &[TypeInput]) -> Result<TypeTag> {
-        use OpenSignatureBody as O;
-        use TypeTag as T;
+    /// Whether values of this type serialize to a bounded number of bytes.
+    pub fn is_bounded(&self) -> bool {
+        matches!(self, SerializedSize::Bounded(_))
+    }
 
-        Ok(match self {
-            O::Address => T::Address,
-            O::Bool => T::Bool,
-            O::U8 => T::U8,
-            O::U16 => T::U16,
-            O::U32 => T::U32,
-            O::U64 => T::U64,
-            O::U128 => T::U128,
-            O::U256 => T::U256,
-            O::Vector(s) => T::Vector(Box::new(s.instantiate(type_params)?)),
+    /// The bound on the serialized size of a sequence of fields, which is the sum of the bounds of
+    /// each field.
+    fn of_fields(fields: &[MoveFieldLayout]) -> Self {
+        let mut fixed = 0;
+        let mut vectors = vec![];
+        for field in fields {
+            match Self::of(&field.layout) {
+                SerializedSize::Bounded(size) => fixed += size,
+                SerializedSize::Unbounded {
+                    fixed: f,
+                    vectors: vs,
+                } => {
+                    fixed += f;
+                    vectors.extend(vs);
+                }
+            }
+        }
 
-            O::Datatype(key, dty_params) => T::Struct(Box::new(StructTag {
-                address: key.package,
-                module: ident(&key.module)?,
-                name: ident(&key.name)?,
-                type_params: dty_params
-                    .iter()
-                    .map(|p| p.instantiate(type_params))
-                    .collect::<Result<_>>()?,
-            })),
+        if vectors.is_empty() {
+            SerializedSize::Bounded(fixed)
+        } else {
+            SerializedSize::Unbounded { fixed, vectors }
+        }
+    }
+}
 
-            O::TypeParameter(ix) => as_type_tag(
-                type_params
-                    .get(*ix as usize)
-                    .ok_or_else(|| Error::TypeParamOOB(*ix, type_params.len()))?,
-            )?,
-        })
+impl LayoutFilter {
+    /// Whether `layout` is the layout of a datatype that was truncated by a filter.
+    pub fn is_truncated(layout: &MoveTypeLayout) -> bool {
+        match layout {
+            MoveTypeLayout::Struct(s) => s.fields.is_empty(),
+            MoveTypeLayout::Enum(e) => e.variants.is_empty(),
+            _ => false,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.max_depth.is_none() && self.pruned_paths.is_empty()
+    }
+
+    /// Whether a datatype at `level`, reached through the fields in `path`, is truncated.
+    fn truncates(&self, level: usize, path: &[String]) -> bool {
+        self.max_depth.is_some_and(|max| level > max) || self.pruned_paths.contains(path)
     }
 }
 
-impl<'m, 'n> DatatypeRef<'m, 'n> {
-    pub fn as_key(&self) -> DatatypeKey {
-        DatatypeKey {
-            package: self.package,
-            module: self.module.to_string().into(),
-            name: self.name.to_string().into(),
+impl Limits {
+    /// The limit on identifier length that `config` enforces on Move code, for use as
+    /// `max_identifier_len`.
+    pub fn max_identifier_len_for(config: &ProtocolConfig) -> Option<usize> {
+        config
+            .max_move_identifier_len_as_option()
+            .map(|len| len as usize)
+    }
+}
+
+impl LayoutLruCache {
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            layouts: Mutex::new(LruCache::new(capacity)),
         }
     }
 }
 
-impl DatatypeKey {
-    fn read(ix: DatatypeHandleIndex, bytecode: &CompiledModule) -> Self {
-        let sh = bytecode.datatype_handle_at(ix);
-        let mh = bytecode.module_handle_at(sh.module);
+impl std::fmt::Debug for dyn LayoutCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("LayoutCache")
+    }
+}
 
-        let package = *bytecode.address_identifier_at(mh.address);
-        let module = bytecode.identifier_at(mh.name).to_string().into();
-        let name = bytecode.identifier_at(sh.name).to_string().into();
+#[async_trait]
+impl LayoutCache for LayoutLruCache {
+    async fn get(&self, tag: &TypeTag) -> Result<Option<MoveTypeLayout>> {
+        Ok(self.layouts.lock().unwrap().get(tag).cloned())
+    }
 
-        DatatypeKey {
-            package,
-            module,
-            name,
-        }
+    async fn insert(&self, tag: TypeTag, layout: MoveTypeLayout) -> Result<()> {
+        self.layouts.lock().unwrap().put(tag, layout);
+        Ok(())
     }
 }
 
-impl<'l> ResolutionContext<'l> {
-    fn new(limits: Option<&'l Limits>) -> Self {
-        ResolutionContext {
-            datatypes: BTreeMap::new(),
-            limits,
+impl<T> PackageStoreWithLruCache<T> {
+    pub fn new(inner: T) -> Self {
+        let packages = Mutex::new(LruCache::new(PACKAGE_CACHE_SIZE));
+        Self {
+            packages,
+            inner,
+            prefetch: PrefetchPolicy::Off,
+            pending_prefetch: Mutex::new(BTreeSet::new()),
         }
     }
 
-    /// Gather definitions for types that contribute to the definition of `tag` into this resolution
-    /// context, fetching data from the `store` as necessary. Also updates package addresses in
-    /// `tag` to point to runtime IDs instead of storage IDs to ensure queries made using these
-    /// addresses during the subsequent resolution phase find the relevant type information in the
-    /// context.
-    ///
-    /// The `visit_fields` flag controls whether the traversal looks inside types at their fields
-    /// (which is necessary for layout resolution) or not (only explores the outer type and any type
-    /// parameters).
-    ///
-    /// The `visit_phantoms` flag controls whether the traversal recurses through phantom type
-    /// parameters (which is also necessary for type resolution) or not.
-    async fn add_type_tag<S: PackageStore + ?Sized>(
-        &mut self,
-        tag: &mut TypeTag,
-        store: &S,
-        visit_fields: bool,
-        visit_phantoms: bool,
-    ) -> Result<()> {
-        use TypeTag as T;
+    /// Configure whether and when this store prefetches the dependencies of the packages it loads.
+    pub fn with_prefetch(mut self, prefetch: PrefetchPolicy) -> Self {
+        self.prefetch = prefetch;
+        self
+    }
 
-        struct ToVisit<'t> {
-            tag: &'t mut TypeTag,
-            depth: usize,
+    /// Removes all packages with ids in `ids` from the cache, if they exist. Does nothing for ids
+    /// that are not in the cache. Accepts `self` immutably as it operates under the lock.
+    pub fn evict(&self, ids: impl IntoIterator<Item = AccountAddress>) {
+        let mut packages = self.packages.lock().unwrap();
+        let mut pending = self.pending_prefetch.lock().unwrap();
+        for id in ids {
+            packages.pop(&id);
+            pending.remove(&id);
         }
+    }
+}
 
-        let mut frontier = vec![ToVisit { tag, depth: 0 }];
-        while let Some(ToVisit { tag, depth }) = frontier.pop() {
-            macro_rules! push_ty_param {
-                ($tag:expr) => {{
-                    check_max_limit!(
-                        TypeParamNesting, self.limits;
-                        max_type_argument_depth > depth
-                    );
+impl<T> PackageWatcher<T> {
+    pub fn new(store: Arc<PackageStoreWithLruCache<T>>) -> Self {
+        Self { store }
+    }
 
-                    frontier.push(ToVisit { tag: $tag, depth: depth + 1 })
-                }}
-            }
+    /// Evict the packages that `effects` modified from the cache, so that their new versions are
+    /// fetched the next time they are requested.
+    pub fn process_effects(&self, effects: &TransactionEffects) {
+        self.store.evict(
+            effects
+                .mutated()
+                .into_iter()
+                .filter(|(_, owner)| matches!(owner, Owner::Immutable))
+                .map(|((id, _, _), _)| AccountAddress::from(id)),
+        );
+    }
 
-            match tag {
-                T::Address
-                | T::Bool
-                | T::U8
-                | T::U16
-                | T::U32
-                | T::U64
-                | T::U128
-                | T::U256
-                | T::Signer => {
-                    // Nothing further to add to context
-                }
+    /// Process the effects of transactions from `effects`, in order, until the stream is
+    /// exhausted. Intended to be spawned as a task alongside the service that owns the cache, fed
+    /// with the effects of every transaction (or at least every end-of-epoch transaction) as they
+    /// are executed or indexed.
+    pub async fn run(self, effects: impl Stream<Item = TransactionEffects>) {
+        let mut effects = std::pin::pin!(effects);
+        while let Some(effects) = effects.next().await {
+            self.process_effects(&effects);
+        }
+    }
+}
 
-                T::Vector(tag) => push_ty_param!(tag),
+impl<T: PackageStore> PackageStoreWithLruCache<T> {
+    /// Fetch the package at `id`, from the cache if possible, otherwise from the inner store, in
+    /// which case it is added to the cache. Also returns whether the package was served from the
+    /// cache.
+    async fn fetch_cached(&self, id: AccountAddress) -> Result<(Arc<Package>, bool)> {
+        if let Some(package) = {
+            // Release the lock after getting the package
+            let mut packages = self.packages.lock().unwrap();
+            packages.get(&id).map(Arc::clone)
+        } {
+            return Ok((package, true));
+        };
 
-                T::Struct(s) => {
-                    let context = store.fetch(s.address).await?;
-                    let def = context
-                        .clone()
-                        .data_def(s.module.as_str(), s.name.as_str())?;
+        let package = self.inner.fetch(id).await?;
 
-                    // Normalize `address` (the ID of a package that contains the definition of this
-                    // struct) to be a runtime ID, because that's what the resolution context uses
-                    // for keys.  Take care to do this before generating the key that is used to
-                    // query and/or write into `self.structs.
-                    s.address = context.runtime_id;
-                    let key = DatatypeRef::from(s.as_ref()).as_key();
+        // Try and insert the package into the cache, accounting for races.  In most cases the
+        // racing fetches will produce the same package, but for system packages, they may not, so
+        // favour the package that has the newer version, or if they are the same, the package that
+        // is already in the cache.
 
-                    if def.type_params.len() != s.type_params.len() {
-                        return Err(Error::TypeArityMismatch(
-                            def.type_params.len(),
-                            s.type_params.len(),
-                        ));
+        let mut packages = self.packages.lock().unwrap();
+        Ok(match packages.peek(&id) {
+            Some(prev) if package.version <= prev.version => {
+                let package = prev.clone();
+                packages.promote(&id);
+                (package, true)
+            }
+
+            Some(_) | None => {
+                // A package that makes way for this one will need to be fetched again before its
+                // dependencies are prefetched, so stop tracking it.
+                if let Some((evicted, _)) = packages.push(id, package.clone()) {
+                    if evicted != id {
+                        self.pending_prefetch.lock().unwrap().remove(&evicted);
                     }
+                }
 
-                    check_max_limit!(
-                        TooManyTypeParams, self.limits;
-                        max_type_argument_width >= s.type_params.len()
-                    );
+                (package, false)
+            }
+        })
+    }
 
-                    for (param, def) in s.type_params.iter_mut().zip(def.type_params.iter()) {
-                        if !def.is_phantom || visit_phantoms {
-                            push_ty_param!(param);
-                        }
-                    }
+    /// Load the dependencies of `package` that are not already cached into the cache, fetching at
+    /// most `concurrency` of them at a time. Linkage tables are transitive, so there is no need to
+    /// prefetch the dependencies of dependencies as well.
+    async fn prefetch_dependencies(&self, package: &Package, concurrency: NonZeroUsize) {
+        let missing: Vec<_> = {
+            let packages = self.packages.lock().unwrap();
+            package
+                .linkage
+                .values()
+                .filter(|id| !packages.contains(*id))
+                .copied()
+                .collect()
+        };
 
-                    if self.datatypes.contains_key(&key) {
-                        continue;
-                    }
+        stream::iter(missing)
+            .map(|id| self.fetch_cached(id))
+            .buffer_unordered(concurrency.get())
+            .for_each(|_| async {})
+            .await;
+    }
+}
 
-                    if visit_fields {
-                        match &def.data {
-                            MoveData::Struct(fields) => {
-                                for (_, sig) in fields {
-                                    self.add_signature(sig.clone(), store, &context, visit_fields)
-                                        .await?;
-                                }
-                            }
-                            MoveData::Enum(variants) => {
-                                for variant in variants {
-                                    for (_, sig) in &variant.signatures {
-                                        self.add_signature(
-                                            sig.clone(),
-                                            store,
-                                            &context,
-                                            visit_fields,
-                                        )
-                                        .await?;
-                                    }
-                                }
-                            }
-                        };
-                    }
+impl<T> PackageStoreWithDependencyIndex<T> {
+    pub fn new(inner: T, index: Arc<DependencyIndex>) -> Self {
+        Self { inner, index }
+    }
 
-                    check_max_limit!(
-                        TooManyTypeNodes, self.limits;
-                        max_type_nodes > self.datatypes.len()
-                    );
+    pub fn index(&self) -> &Arc<DependencyIndex> {
+        &self.index
+    }
+}
 
-                    self.datatypes.insert(key, def);
+impl DependencyIndex {
+    /// Record `package`'s dependencies, replacing any previously recorded for its storage ID (which
+    /// can change for system packages, which are upgraded in place). Does nothing if the package
+    /// has already been recorded at the same version.
+    pub fn insert(&self, package: &Package) {
+        let mut inner = self.inner.lock().unwrap();
+        let DependencyIndexInner {
+            dependencies,
+            dependents,
+            versions,
+        } = &mut *inner;
+
+        if versions.insert(package.storage_id, package.version) == Some(package.version) {
+            return;
+        }
+
+        let deps: BTreeSet<_> = package.linkage.values().copied().collect();
+        let prev = dependencies.insert(package.storage_id, deps.clone());
+        for dep in prev.iter().flatten() {
+            if let Some(ds) = dependents.get_mut(dep) {
+                ds.remove(&package.storage_id);
+                if ds.is_empty() {
+                    dependents.remove(dep);
                 }
             }
         }
 
-        Ok(())
+        for dep in deps {
+            dependents
+                .entry(dep)
+                .or_default()
+                .insert(package.storage_id);
+        }
     }
 
-    // Like `add_type_tag` but for type signatures.  Needs a linkage table to translate runtime IDs
-    // into storage IDs.
-    async fn add_signature<T: PackageStore + ?Sized>(
-        &mut self,
-        sig: OpenSignatureBody,
-        store: &T,
-        context: &Package,
-        visit_fields: bool,
-    ) -> Result<()> {
-        use OpenSignatureBody as O;
+    /// Storage IDs of the indexed packages that link against the package at `storage_id`. Linkage
+    /// tables list indirect dependencies as well as direct ones, so this includes every indexed
+    /// package that would load code from `storage_id`.
+    pub fn dependents_of(&self, storage_id: AccountAddress) -> BTreeSet<AccountAddress> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .dependents
+            .get(&storage_id)
+            .cloned()
+            .unwrap_or_default()
+    }
 
-        let mut frontier = vec![sig];
-        while let Some(sig) = frontier.pop() {
-            match sig {
-                O::Address
-                | O::Bool
-                | O::U8
-                | O::U16
-                | O::U32
-                | O::U64
-                | O::U128
-                | O::U256
-                | O::TypeParameter(_) => {
-                    // Nothing further to add to context
-                }
+    /// Storage IDs of all the packages that the package at `storage_id` depends on, directly or
+    /// indirectly, at the versions that it links against, or `None` if it has not been indexed.
+    pub fn transitive_closure(
+        &self,
+        storage_id: AccountAddress,
+    ) -> Option<BTreeSet<AccountAddress>> {
+        let inner = self.inner.lock().unwrap();
+        inner.dependencies.get(&storage_id).cloned()
+    }
+}
 
-                O::Vector(sig) => frontier.push(*sig),
+impl<T> PackageStoreWithRetry<T> {
+    pub fn new(inner: T, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
 
-                O::Datatype(key, params) => {
-                    check_max_limit!(
-                        TooManyTypeParams, self.limits;
-                        max_type_argument_width >= params.len()
-                    );
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+}
 
-                    let params_count = params.len();
-                    let data_count = self.datatypes.len();
-                    frontier.extend(params.into_iter());
+impl<T: PackageStore> PackageStoreWithRetry<T> {
+    /// Run `fetch` until it succeeds, fails with an error that is not worth retrying, or retries
+    /// are exhausted.
+    async fn with_retries<F>(
+        &self,
+        id: AccountAddress,
+        fetch: impl Fn() -> F,
+    ) -> Result<Arc<Package>>
+    where
+        F: std::future::Future<Output = Result<Arc<Package>>>,
+    {
+        let mut backoff = self.policy.initial_backoff;
+        let mut retries = 0;
+
+        loop {
+            let err = match fetch().await {
+                Err(Error::PackagePending(_)) => Error::PackagePending(id),
+                Err(Error::PackageNotFound(_)) if self.policy.not_found_is_pending => {
+                    Error::PackagePending(id)
+                }
+                Err(err) if err.is_retryable() => err,
+                result => return result,
+            };
 
-                    let type_params = if let Some(def) = self.datatypes.get(&key) {
-                        &def.type_params
-                    } else {
-                        check_max_limit!(
-                            TooManyTypeNodes, self.limits;
-                            max_type_nodes > data_count
-                        );
+            if retries >= self.policy.max_retries {
+                return Err(err);
+            }
 
-                        // Need to resolve the datatype, so fetch the package that contains it.
-                        let storage_id = context.relocate(key.package)?;
-                        let package = store.fetch(storage_id).await?;
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(self.policy.max_backoff);
+            retries += 1;
+        }
+    }
+}
 
-                        let def = package.data_def(&key.module, &key.name)?;
-                        if visit_fields {
-                            match &def.data {
-                                MoveData::Struct(fields) => {
-                                    frontier.extend(fields.iter().map(|f| &f.1).cloned());
-                                }
-                                MoveData::Enum(variants) => {
-                                    frontier.extend(
-                                        variants
-                                            .iter()
-                                            .flat_map(|v| v.signatures.iter().map(|(_, s)| s))
-                                            .cloned(),
-                                    );
-                                }
-                            };
-                        }
+impl<T> PackageStoreAtVersion<T> {
+    pub fn new(inner: T, version: SequenceNumber) -> Self {
+        Self { inner, version }
+    }
 
-                        &self.datatypes.entry(key).or_insert(def).type_params
-                    };
+    pub fn version(&self) -> SequenceNumber {
+        self.version
+    }
+}
 
-                    if type_params.len() != params_count {
-                        return Err(Error::TypeArityMismatch(type_params.len(), params_count));
-                    }
-                }
-            }
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+            not_found_is_pending: false,
         }
+    }
+}
 
-        Ok(())
+#[async_trait]
+impl<T: PackageStore> PackageStore for PackageStoreWithRetry<T> {
+    async fn fetch(&self, id: AccountAddress) -> Result<Arc<Package>> {
+        self.with_retries(id, || self.inner.fetch(id)).await
     }
 
-    /// Translate runtime IDs in a type `tag` into defining IDs using only the information
-    /// contained in this context. Requires that the necessary information was added to the context
-    /// through calls to `add_type_tag`.
-    fn canonicalize_type(&self, tag: &mut TypeTag) -> Result<()> {
-        use TypeTag as T;
+    async fn latest_package_id(&self, original_id: AccountAddress) -> Result<AccountAddress> {
+        self.inner.latest_package_id(original_id).await
+    }
 
-        match tag {
-            T::Signer => return Err(Error::UnexpectedSigner),
-            T::Address | T::Bool | T::U8 | T::U16 | T::U32 | T::U64 | T::U128 | T::U256 => {
-                /* nop */
-            }
+    async fn package_versions(&self, original_id: AccountAddress) -> Result<Vec<AccountAddress>> {
+        self.inner.package_versions(original_id).await
+    }
 
-            T::Vector(tag) => self.canonicalize_type(tag.as_mut())?,
+    async fn fetch_at(&self, id: AccountAddress, version: SequenceNumber) -> Result<Arc<Package>> {
+        self.with_retries(id, || self.inner.fetch_at(id, version))
+            .await
+    }
+}
 
-            T::Struct(s) => {
-                for tag in &mut s.type_params {
-                    self.canonicalize_type(tag)?;
-                }
+/// The latest version of a package at some point in time is not known, so `latest_package_id` and
+/// `package_versions` are not supported.
+#[async_trait]
+impl<T: PackageStore> PackageStore for PackageStoreAtVersion<T> {
+    async fn fetch(&self, id: AccountAddress) -> Result<Arc<Package>> {
+        if is_system_package(id) {
+            self.inner.fetch_at(id, self.version).await
+        } else {
+            self.inner.fetch(id).await
+        }
+    }
 
-                // SAFETY: `add_type_tag` ensures `datatyps` has an element with this key.
-                let key = DatatypeRef::from(s.as_ref());
-                let def = &self.datatypes[&key];
+    async fn fetch_at(&self, id: AccountAddress, version: SequenceNumber) -> Result<Arc<Package>> {
+        self.inner.fetch_at(id, version.min(self.version)).await
+    }
+}
 
-                s.address = def.defining_id;
+#[async_trait]
+impl<T: PackageStore> PackageStore for PackageStoreWithLruCache<T> {
+    async fn fetch(&self, id: AccountAddress) -> Result<Arc<Package>> {
+        let (package, cached) = self.fetch_cached(id).await?;
+
+        match self.prefetch {
+            PrefetchPolicy::Off => {}
+
+            PrefetchPolicy::Immediate { concurrency } => {
+                if !cached {
+                    self.prefetch_dependencies(&package, concurrency).await;
+                }
+            }
+
+            PrefetchPolicy::Lazy { concurrency } => {
+                if !cached {
+                    self.pending_prefetch.lock().unwrap().insert(id);
+                } else if self.pending_prefetch.lock().unwrap().remove(&id) {
+                    self.prefetch_dependencies(&package, concurrency).await;
+                }
             }
         }
 
-        Ok(())
+        Ok(package)
     }
 
-    /// Translate a type `tag` into its layout using only the information contained in this context.
-    /// Requires that the necessary information was added to the context through calls to
-    /// `add_type_tag` and `add_signature` before being called.
-    ///
-    /// `max_depth` controls how deep the layout is allowed to grow to. The actual depth reached is
-    /// returned alongside the layout (assuming it does not exceed `max_depth`).
-    fn resolve_type_layout(
-        &self,
-        tag: &TypeTag,
-        max_depth: usize,
-    ) -> Result<(MoveTypeLayout, usize)> {
-        use MoveTypeLayout as L;
-        use TypeTag as T;
+    /// Not cached, because the latest version of a package changes as it is upgraded.
+    async fn latest_package_id(&self, original_id: AccountAddress) -> Result<AccountAddress> {
+        self.inner.latest_package_id(original_id).await
+    }
 
-        if max_depth == 0 {
-            return Err(Error::ValueNesting(
-                self.limits.map_or(0, |l| l.max_move_value_depth),
-            ));
+    /// Not cached, because a package gains versions as it is upgraded.
+    async fn package_versions(&self, original_id: AccountAddress) -> Result<Vec<AccountAddress>> {
+        self.inner.package_versions(original_id).await
+    }
+
+    /// The cache only holds the latest version of each package, so historical versions are
+    /// fetched from the inner store, and not cached.
+    async fn fetch_at(&self, id: AccountAddress, version: SequenceNumber) -> Result<Arc<Package>> {
+        let latest = self.fetch(id).await?;
+        if latest.version <= version {
+            Ok(latest)
+        } else {
+            self.inner.fetch_at(id, version).await
         }
+    }
+}
 
-        Ok(match tag {
-            T::Signer => return Err(Error::UnexpectedSigner),
+#[async_trait]
+impl<T: PackageStore> PackageStore for PackageStoreWithDependencyIndex<T> {
+    async fn fetch(&self, id: AccountAddress) -> Result<Arc<Package>> {
+        let package = self.inner.fetch(id).await?;
+        self.index.insert(&package);
+        Ok(package)
+    }
 
-            T::Address => (L::Address, 1),
-            T::Bool => (L::Bool, 1),
-            T::U8 => (L::U8, 1),
-            T::U16 => (L::U16, 1),
-            T::U32 => (L::U32, 1),
-            T::U64 => (L::U64, 1),
-            T::U128 => (L::U128, 1),
-            T::U256 => (L::U256, 1),
+    async fn latest_package_id(&self, original_id: AccountAddress) -> Result<AccountAddress> {
+        self.inner.latest_package_id(original_id).await
+    }
 
-            T::Vector(tag) => {
-                let (layout, depth) = self.resolve_type_layout(tag, max_depth - 1)?;
-                (L::Vector(Box::new(layout)), depth + 1)
-            }
+    async fn package_versions(&self, original_id: AccountAddress) -> Result<Vec<AccountAddress>> {
+        self.inner.package_versions(original_id).await
+    }
 
-            T::Struct(s) => {
-                // TODO (optimization): Could introduce a layout cache to further speed up
-                // resolution.  Relevant entries in that cache would need to be gathered in the
-                // ResolutionContext as it is built, and then used here to avoid the recursive
-                // exploration.  This optimisation is complicated by the fact that in the cache,
-                // these layouts are naturally keyed based on defining ID, but during resolution,
-                // they are keyed by runtime IDs.
+    /// Historical versions of packages are not indexed, so that they do not replace the
+    /// dependencies recorded for the latest version.
+    async fn fetch_at(&self, id: AccountAddress, version: SequenceNumber) -> Result<Arc<Package>> {
+        self.inner.fetch_at(id, version).await
+    }
+}
 
-                // TODO (optimization): This could be made more efficient by only generating layouts
-                // for non-phantom types.  This efficiency could be extended to the exploration
-                // phase (i.e. only explore layouts of non-phantom types). But this optimisation is
-                // complicated by the fact that we still need to create a correct type tag for a
-                // phantom parameter, which is currently done by converting a type layout into a
-                // tag.
-                let param_layouts = s
-                    .type_params
-                    .iter()
-                    // Reduce the max depth because we know these type parameters will be nested
-                    // within this struct.
-                    .map(|tag| self.resolve_type_layout(tag, max_depth - 1))
-                    .collect::<Result<Vec<_>>>()?;
+/// Whether `error` means that something a transaction refers to does not exist, as opposed to a
+/// failure to access the package store, or a limit being hit.
+fn is_unresolved(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::PackageNotFound(_)
+            | Error::NotAPackage(_)
+            | Error::ModuleNotFound(_, _)
+            | Error::FunctionNotFound(_, _, _)
+            | Error::DatatypeNotFound(_, _, _)
+            | Error::NotAnIdentifier(_)
+            | Error::TypeArityMismatch(_, _)
+    )
+}
 
-                // SAFETY: `param_layouts` contains `MoveTypeLayout`-s that are generated by this
-                // `ResolutionContext`, which guarantees that struct layouts come with types, which
-                // is necessary to avoid errors when converting layouts into type tags.
-                let type_params = param_layouts.iter().map(|l| TypeTag::from(&l.0)).collect();
+/// Check that `package` is no newer than `version`, for stores that can only serve the latest
+/// version of a package.
+fn check_package_version(package: Arc<Package>, version: SequenceNumber) -> Result<Arc<Package>> {
+    if package.version > version {
+        Err(Error::PackageVersionUnavailable(
+            package.storage_id,
+            version,
+        ))
+    } else {
+        Ok(package)
+    }
+}
 
-                // SAFETY: `add_type_tag` ensures `datatyps` has an element with this key.
-                let key = DatatypeRef::from(s.as_ref());
-                let def = &self.datatypes[&key];
+impl PackageSummary {
+    /// Best-effort counterpart to `Package::read_from_object`, which succeeds as long as `object`
+    /// is a package, regardless of whether its modules can be deserialized.
+    pub fn read_from_object(object: &Object) -> Result<Self> {
+        let storage_id = AccountAddress::from(object.id());
+        let Some(package) = object.data.try_as_package() else {
+            return Err(Error::NotAPackage(storage_id));
+        };
 
-                let type_ = StructTag {
-                    address: def.defining_id,
-                    module: s.module.clone(),
-                    name: s.name.clone(),
-                    type_params,
+        Ok(Self::read_from_package(package))
+    }
+
+    /// Best-effort counterpart to `Package::read_from_package`. Module names come from the
+    /// package's module map and datatype names from its type origin table, neither of which
+    /// depends on the bytecode format.
+    pub fn read_from_package(package: &MovePackage) -> Self {
+        let mut modules: BTreeMap<_, _> = package
+            .serialized_module_map()
+            .iter()
+            .map(|(name, bytes)| {
+                let summary = ModuleSummary {
+                    bytecode_version: bytecode_version(bytes),
+                    datatypes: BTreeSet::new(),
                 };
+                (name.clone(), summary)
+            })
+            .collect();
 
-                self.resolve_datatype_signature(def, type_, param_layouts, max_depth)?
+        for origin in package.type_origin_table() {
+            if let Some(module) = modules.get_mut(&origin.module_name) {
+                module.datatypes.insert(origin.datatype_name.clone());
             }
-        })
+        }
+
+        PackageSummary {
+            storage_id: AccountAddress::from(package.id()),
+            version: package.version(),
+            modules,
+        }
     }
+}
 
-    /// Translates a datatype definition into a type layout.  Needs to be provided the layouts of type
-    /// parameters which are substituted when a type parameter is encountered.
-    ///
-    /// `max_depth` controls how deep the layout is allowed to grow to. The actual depth reached is
-    /// returned alongside the layout (assuming it does not exceed `max_depth`).
-    fn resolve_datatype_signature(
-        &self,
-        data_def: &DataDef,
-        type_: StructTag,
-        param_layouts: Vec<(MoveTypeLayout, usize)>,
-        max_depth: usize,
-    ) -> Result<(MoveTypeLayout, usize)> {
-        Ok(match &data_def.data {
-            MoveData::Struct(fields) => {
-                let mut resolved_fields = Vec::with_capacity(fields.len());
-                let mut field_depth = 0;
+impl Package {
+    pub fn read_from_object(object: &Object) -> Result<Self> {
+        let storage_id = AccountAddress::from(object.id());
+        let Some(package) = object.data.try_as_package() else {
+            return Err(Error::NotAPackage(storage_id));
+        };
 
-                for (name, sig) in fields {
-                    let (layout, depth) =
-                        self.resolve_signature_layout(sig, &param_layouts, max_depth - 1)?;
+        Self::read_from_package(package)
+    }
 
-                    field_depth = field_depth.max(depth);
-                    resolved_fields.push(MoveFieldLayout {
-                        name: ident(name.as_str())?,
-                        layout,
-                    })
-                }
+    pub fn read_from_package(package: &MovePackage) -> Result<Self> {
+        Self::read(package, /* lenient */ false)
+    }
 
-                (
-                    MoveTypeLayout::Struct(Box::new(MoveStructLayout {
-                        type_,
-                        fields: Box::new(resolved_fields),
-                    })),
-                    field_depth + 1,
-                )
-            }
-            MoveData::Enum(variants) => {
-                let mut field_depth = 0;
-                let mut resolved_variants = BTreeMap::new();
+    /// Like `Package::read_from_package`, but also keeps the package's serialized modules and
+    /// linkage, so that its digest can be checked (see `Package::verify_digest`). This roughly
+    /// doubles the memory the package takes up, so it is only worth doing for packages that will
+    /// be verified.
+    pub fn read_with_digests(package: &MovePackage) -> Result<Self> {
+        let mut read = Self::read(package, /* lenient */ false)?;
+        read.digests = Some(PackageDigests::new(
+            package.serialized_module_map().values().cloned().collect(),
+            package
+                .linkage_table()
+                .values()
+                .map(|linkage| linkage.upgraded_id)
+                .collect(),
+        ));
 
-                for (tag, variant) in variants.iter().enumerate() {
-                    let mut fields = Vec::with_capacity(variant.signatures.len());
-                    for (name, sig) in &variant.signatures {
-                        // Note: We decrement the depth here because we're already under the variant
-                        let (layout, depth) =
-                            self.resolve_signature_layout(sig, &param_layouts, max_depth - 1)?;
+        Ok(read)
+    }
 
-                        field_depth = field_depth.max(depth);
-                        fields.push(MoveFieldLayout {
-                            name: ident(name.as_str())?,
-                            layout,
-                        })
-                    }
-                    resolved_variants.insert((ident(variant.name.as_str())?, tag as u16), fields);
+    /// Like `Package::read_from_package`, but modules that fail to deserialize (or are missing
+    /// type origins) are skipped rather than failing the whole package. Their errors are recorded
+    /// (see `Package::module_errors`) and returned when they are looked up. Still fails if none of
+    /// the package's modules can be read, because its runtime ID can't be determined, returning
+    /// the error from the first module (in name order).
+    pub fn read_lenient(package: &MovePackage) -> Result<Self> {
+        Self::read(package, /* lenient */ true)
+    }
+
+    fn read(package: &MovePackage, lenient: bool) -> Result<Self> {
+        let storage_id = AccountAddress::from(package.id());
+        let mut type_origins: BTreeMap<String, BTreeMap<String, AccountAddress>> = BTreeMap::new();
+        for TypeOrigin {
+            module_name,
+            datatype_name,
+            package,
+        } in package.type_origin_table()
+        {
+            type_origins
+                .entry(module_name.to_string())
+                .or_default()
+                .insert(datatype_name.to_string(), AccountAddress::from(*package));
+        }
+
+        let mut runtime_id = None;
+        let mut modules = BTreeMap::new();
+        let mut module_errors = BTreeMap::new();
+        for (name, bytes) in package.serialized_module_map() {
+            let origins = type_origins.remove(name).unwrap_or_default();
+            let module = deserialize_module(bytes).and_then(|bytecode| {
+                let address = *bytecode.address();
+                let module = Module::read(bytecode, origins)
+                    .map_err(|struct_| Error::NoTypeOrigin(storage_id, name.clone(), struct_))?;
+
+                Ok((address, module))
+            });
+
+            match module {
+                Ok((address, module)) => {
+                    runtime_id = Some(address);
+                    modules.insert(name.clone(), module);
                 }
 
-                (
-                    MoveTypeLayout::Enum(Box::new(MoveEnumLayout {
-                        type_,
-                        variants: resolved_variants,
-                    })),
-                    field_depth + 1,
-                )
+                Err(e) if lenient => {
+                    module_errors.insert(name.clone(), e);
+                }
+
+                Err(e) => return Err(e),
             }
+        }
+
+        let Some(runtime_id) = runtime_id else {
+            return Err(module_errors
+                .into_values()
+                .next()
+                .unwrap_or(Error::EmptyPackage(storage_id)));
+        };
+
+        let linkage = package
+            .linkage_table()
+            .iter()
+            .map(|(&dep, linkage)| (dep.into(), linkage.upgraded_id.into()))
+            .collect();
+
+        Ok(Package {
+            storage_id,
+            runtime_id,
+            version: package.version(),
+            modules,
+            module_errors,
+            linkage,
+            digests: None,
         })
     }
 
-    /// Like `resolve_type_tag` but for signatures.  Needs to be provided the layouts of type
-    /// parameters which are substituted when a type parameter is encountered.
-    ///
-    /// `max_depth` controls how deep the layout is allowed to grow to. The actual depth reached is
-    /// returned alongside the layout (assuming it does not exceed `max_depth`).
-    fn resolve_signature_layout(
-        &self,
-        sig: &OpenSignatureBody,
-        param_layouts: &[(MoveTypeLayout, usize)],
-        max_depth: usize,
-    ) -> Result<(MoveTypeLayout, usize)> {
-        use MoveTypeLayout as L;
-        use OpenSignatureBody as O;
+    /// The digest of this package, computed from the bytes it was read from, following the
+    /// hashing scheme that `config`'s protocol version uses for package digests. Fails unless the
+    /// package was read with `Package::read_with_digests`.
+    pub fn digest(&self, config: &ProtocolConfig) -> Result<[u8; 32]> {
+        let digests = self
+            .digests
+            .as_ref()
+            .ok_or(Error::PackageDigestUnavailable(self.storage_id))?;
 
-        if max_depth == 0 {
-            return Err(Error::ValueNesting(
-                self.limits.map_or(0, |l| l.max_move_value_depth),
+        Ok(digests.get(config.package_digest_hash_module()))
+    }
+
+    /// Check that this package's contents match the `expected` digest (e.g. the digest recorded
+    /// on-chain for the package), returning an error if they do not.
+    pub fn verify_digest(&self, expected: [u8; 32], config: &ProtocolConfig) -> Result<()> {
+        let actual = self.digest(config)?;
+        if actual != expected {
+            return Err(Error::PackageDigestMismatch(
+                self.storage_id,
+                Digest::new(expected),
+                Digest::new(actual),
             ));
         }
 
-        Ok(match sig {
-            O::Address => (L::Address, 1),
-            O::Bool => (L::Bool, 1),
-            O::U8 => (L::U8, 1),
-            O::U16 => (L::U16, 1),
-            O::U32 => (L::U32, 1),
-            O::U64 => (L::U64, 1),
-            O::U128 => (L::U128, 1),
-            O::U256 => (L::U256, 1),
+        Ok(())
+    }
 
-            O::TypeParameter(ix) => {
-                let (layout, depth) = param_layouts
-                    .get(*ix as usize)
-                    .ok_or_else(|| Error::TypeParamOOB(*ix, param_layouts.len()))
-                    .cloned()?;
+    pub fn module(&self, module: &str) -> Result<&Module> {
+        if let Some(err) = self.module_errors.get(module) {
+            return Err(err.clone());
+        }
 
-                // We need to re-check the type parameter before we use it because it might have
-                // been fine when it was created, but result in too deep a layout when we use it at
-                // this position.
-                if depth > max_depth {
-                    return Err(Error::ValueNesting(
-                        self.limits.map_or(0, |l| l.max_move_value_depth),
-                    ));
-                }
+        self.modules
+            .get(module)
+            .ok_or_else(|| Error::ModuleNotFound(self.storage_id, module.to_string()))
+    }
 
-                (layout, depth)
-            }
+    pub fn modules(&self) -> &BTreeMap<String, Module> {
+        &self.modules
+    }
 
-            O::Vector(sig) => {
-                let (layout, depth) =
-                    self.resolve_signature_layout(sig.as_ref(), param_layouts, max_depth - 1)?;
+    /// Modules that were skipped because they could not be read, and the reason why. Always empty
+    /// unless the package was read with `Package::read_lenient`.
+    pub fn module_errors(&self) -> &BTreeMap<String, Error> {
+        &self.module_errors
+    }
 
-                (L::Vector(Box::new(layout)), depth + 1)
-            }
+    /// Cursors for the modules in this package strictly after `after` (or from the beginning), and
+    /// strictly before `before` (or to the end), in order.
+    pub fn paginate_modules(
+        &self,
+        after: Option<&PackageCursor>,
+        before: Option<&PackageCursor>,
+    ) -> impl DoubleEndedIterator<Item = PackageCursor> {
+        let cursors: Vec<_> = self
+            .modules
+            .keys()
+            .map(|module| PackageCursor::new(module, None))
+            .filter(|c| c.in_range(after, before))
+            .collect();
 
-            O::Datatype(key, params) => {
-                // SAFETY: `add_signature` ensures `datatypes` has an element with this key.
-                let def = &self.datatypes[key];
+        cursors.into_iter()
+    }
 
-                let param_layouts = params
+    /// Cursors for the datatypes (structs and enums) across all modules in this package, strictly
+    /// after `after` (or from the beginning), and strictly before `before` (or to the end), in
+    /// order.
+    pub fn paginate_datatypes(
+        &self,
+        after: Option<&PackageCursor>,
+        before: Option<&PackageCursor>,
+    ) -> impl DoubleEndedIterator<Item = PackageCursor> {
+        self.paginate_members(after, before, |m| m.datatypes(None, None).collect())
+    }
+
+    /// Cursors for the functions across all modules in this package, strictly after `after` (or
+    /// from the beginning), and strictly before `before` (or to the end), in order.
+    pub fn paginate_functions(
+        &self,
+        after: Option<&PackageCursor>,
+        before: Option<&PackageCursor>,
+    ) -> impl DoubleEndedIterator<Item = PackageCursor> {
+        self.paginate_members(after, before, |m| m.functions(None, None).collect())
+    }
+
+    /// A page of at most `limit` datatypes (structs and enums) from across all modules in this
+    /// package, in order, starting strictly after `cursor` (an opaque token from a previous page,
+    /// or from `PackageCursor::encode`), or from the beginning if there is no cursor. Only the
+    /// modules that the page spans are visited.
+    pub fn datatypes_paginated(
+        &self,
+        cursor: Option<&str>,
+        limit: NonZeroUsize,
+    ) -> Result<PackagePage> {
+        self.members_paginated(cursor, limit, |m, after| m.datatypes(after, None))
+    }
+
+    /// Like `datatypes_paginated`, but for the functions across all modules in this package.
+    pub fn functions_paginated(
+        &self,
+        cursor: Option<&str>,
+        limit: NonZeroUsize,
+    ) -> Result<PackagePage> {
+        self.members_paginated(cursor, limit, |m, after| m.functions(after, None))
+    }
+
+    /// All the entry functions in this package, ordered by module name, and then by function name.
+    pub fn entry_points(&self) -> Result<Vec<EntryPoint>> {
+        let mut entry_points = vec![];
+        for (module_name, module) in &self.modules {
+            for (name, &index) in &module.function_index {
+                if !module.bytecode.function_def_at(index).is_entry {
+                    continue;
+                }
+
+                let function = module.function_def_at(index)?;
+                let argument_kinds = function
+                    .parameters
                     .iter()
-                    .map(|sig| self.resolve_signature_layout(sig, param_layouts, max_depth - 1))
-                    .collect::<Result<Vec<_>>>()?;
+                    .map(OpenSignature::argument_kind)
+                    .collect();
 
-                // SAFETY: `param_layouts` contains `MoveTypeLayout`-s that are generated by this
-                // `ResolutionContext`, which guarantees that struct layouts come with types, which
-                // is necessary to avoid errors when converting layouts into type tags.
-                let type_params: Vec<TypeTag> =
-                    param_layouts.iter().map(|l| TypeTag::from(&l.0)).collect();
+                entry_points.push(EntryPoint {
+                    module: module_name.clone(),
+                    name: name.clone(),
+                    function,
+                    argument_kinds,
+                });
+            }
+        }
 
-                let type_ = StructTag {
-                    address: def.defining_id,
-                    module: ident(&key.module)?,
-                    name: ident(&key.name)?,
-                    type_params,
-                };
+        Ok(entry_points)
+    }
 
-                self.resolve_datatype_signature(def, type_, param_layouts, max_depth)?
+    /// The `(module, name)` of every function in this package that matches `filter`, ordered by
+    /// module name, and then by function name.
+    pub fn find_functions(&self, filter: &FunctionFilter) -> Result<Vec<(String, String)>> {
+        let mut found = vec![];
+        for (module_name, module) in &self.modules {
+            for (name, &index) in &module.function_index {
+                if filter.matches(&module.function_def_at(index)?) {
+                    found.push((module_name.clone(), name.clone()));
+                }
             }
-        })
+        }
+
+        Ok(found)
     }
 
-    /// Calculate the abilities for a concrete type `tag`. Requires that the necessary information
-    /// was added to the context through calls to `add_type_tag` before being called.
-    fn resolve_abilities(&self, tag: &TypeTag) -> Result<AbilitySet> {
-        use TypeTag as T;
-        Ok(match tag {
-            T::Signer => return Err(Error::UnexpectedSigner),
+    /// Cursors for the members of modules in this package (as listed by `members`) that fall
+    /// strictly between `after` and `before`, in order. Only modules that could contain such
+    /// members are listed.
+    fn paginate_members<'p>(
+        &'p self,
+        after: Option<&PackageCursor>,
+        before: Option<&PackageCursor>,
+        members: impl Fn(&'p Module) -> Vec<&'p str>,
+    ) -> impl DoubleEndedIterator<Item = PackageCursor> {
+        use std::ops::Bound as B;
+        let mut cursors = vec![];
 
-            T::Bool | T::U8 | T::U16 | T::U32 | T::U64 | T::U128 | T::U256 | T::Address => {
-                AbilitySet::PRIMITIVES
+        // Nothing falls between bounds that are out of order, and `BTreeMap::range` panics if its
+        // bounds are inverted.
+        if let (Some(after), Some(before)) = (after, before) {
+            if after >= before {
+                return cursors.into_iter();
             }
+        }
 
-            T::Vector(tag) => self.resolve_abilities(tag)?.intersect(AbilitySet::VECTOR),
-
-            T::Struct(s) => {
-                // SAFETY: `add_type_tag` ensures `datatypes` has an element with this key.
-                let key = DatatypeRef::from(s.as_ref());
-                let def = &self.datatypes[&key];
+        let modules = self.modules.range::<str, _>((
+            after.map_or(B::Unbounded, |c| B::Included(c.module.as_str())),
+            before.map_or(B::Unbounded, |c| B::Included(c.module.as_str())),
+        ));
 
-                if def.type_params.len() != s.type_params.len() {
-                    return Err(Error::TypeArityMismatch(
-                        def.type_params.len(),
-                        s.type_params.len(),
-                    ));
+        for (module_name, module) in modules {
+            for name in members(module) {
+                let cursor = PackageCursor::new(module_name, Some(name));
+                if cursor.in_range(after, before) {
+                    cursors.push(cursor);
                 }
+            }
+        }
 
-                let param_abilities: Result<Vec<AbilitySet>> = s
-                    .type_params
-                    .iter()
-                    .zip(def.type_params.iter())
-                    .map(|(p, d)| {
-                        if d.is_phantom {
-                            Ok(AbilitySet::EMPTY)
-                        } else {
-                            self.resolve_abilities(p)
-                        }
-                    })
-                    .collect();
+        cursors.into_iter()
+    }
 
-                AbilitySet::polymorphic_abilities(
-                    def.abilities,
-                    def.type_params.iter().map(|p| p.is_phantom),
-                    param_abilities?.into_iter(),
-                )
-                // This error is unexpected because the only reason it would fail is because of a
-                // type parameter arity mismatch, which we check for above.
-                .map_err(|e| Error::UnexpectedError(Arc::new(e)))?
+    /// A page of at most `limit` members of modules in this package (as listed by `members`,
+    /// which is given the name to list members strictly after, if any), starting strictly after
+    /// the position encoded by `cursor`.
+    fn members_paginated<'p, I: Iterator<Item = &'p str>>(
+        &'p self,
+        cursor: Option<&str>,
+        limit: NonZeroUsize,
+        members: impl Fn(&'p Module, Option<&str>) -> I,
+    ) -> Result<PackagePage> {
+        use std::ops::Bound as B;
+        let after = cursor.map(PackageCursor::decode).transpose()?;
+        let modules = self.modules.range::<str, _>((
+            after
+                .as_ref()
+                .map_or(B::Unbounded, |c| B::Included(c.module.as_str())),
+            B::Unbounded,
+        ));
+
+        let mut cursors = vec![];
+        for (module_name, module) in modules {
+            // Only the cursor's own module needs to skip members, and a module's cursor orders
+            // before all its members.
+            let after_name = after
+                .as_ref()
+                .filter(|c| &c.module == module_name)
+                .and_then(|c| c.name.as_deref());
+
+            for name in members(module, after_name) {
+                if cursors.len() == limit.get() {
+                    let next = cursors.last().map(PackageCursor::encode);
+                    return Ok(PackagePage { cursors, next });
+                }
+
+                cursors.push(PackageCursor::new(module_name, Some(name)));
             }
+        }
+
+        Ok(PackagePage {
+            cursors,
+            next: None,
         })
     }
 
-    /// Translate the (runtime) package IDs in `sig` to defining IDs using only the information
-    /// contained in this context. Requires that the necessary information was added to the context
-    /// through calls to `add_signature` before being called.
-    fn relocate_signature(&self, sig: &mut OpenSignatureBody) -> Result<()> {
-        use OpenSignatureBody as O;
+    fn data_def(&self, module_name: &str, datatype_name: &str) -> Result<DataDef> {
+        let module = self.module(module_name)?;
+        let Some(data_def) = module.data_def(datatype_name)? else {
+            return Err(Error::DatatypeNotFound(
+                self.storage_id,
+                module_name.to_string(),
+                datatype_name.to_string(),
+            ));
+        };
+        Ok(data_def)
+    }
+
+    /// Translate the `runtime_id` of a package to a specific storage ID using this package's
+    /// linkage table.  Returns an error if the package in question is not present in the linkage
+    /// table, or if the linkage table maps it back to this package.
+    fn relocate(&self, runtime_id: AccountAddress) -> Result<AccountAddress> {
+        // Special case the current package, because it doesn't get an entry in the linkage table.
+        if runtime_id == self.runtime_id {
+            return Ok(self.storage_id);
+        }
+
+        let storage_id = *self
+            .linkage
+            .get(&runtime_id)
+            .ok_or_else(|| Error::LinkageNotFound(runtime_id))?;
+
+        if storage_id == self.storage_id {
+            return Err(Error::LinkageCycle(runtime_id, self.storage_id));
+        }
+
+        Ok(storage_id)
+    }
+
+    /// Translate every runtime ID in `tag` to a storage ID using this package's linkage table.
+    fn relocate_type(&self, tag: &mut TypeTag) -> Result<()> {
+        use TypeTag as T;
+        let mut frontier = vec![tag];
+        while let Some(tag) = frontier.pop() {
+            match tag {
+                T::Vector(tag) => frontier.push(tag.as_mut()),
+                T::Struct(s) => {
+                    s.address = self.relocate(s.address)?;
+                    frontier.extend(s.type_params.iter_mut());
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Translate every runtime ID in `sig` to a storage ID using this package's linkage table.
+    fn relocate_signature(&self, sig: &mut OpenSignatureBody) -> Result<()> {
+        use OpenSignatureBody as O;
+        let mut frontier = vec![sig];
+        while let Some(sig) = frontier.pop() {
+            match sig {
+                O::Vector(sig) => frontier.push(sig.as_mut()),
+                O::Datatype(key, params) => {
+                    key.package = self.relocate(key.package)?;
+                    frontier.extend(params.iter_mut());
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PackageDigests {
+    fn new(modules: Vec<Vec<u8>>, deps: Vec<ObjectID>) -> Self {
+        Self {
+            modules,
+            deps,
+            whole_modules: OnceLock::new(),
+            hashed_modules: OnceLock::new(),
+        }
+    }
+
+    /// The package digest, computed over the hashes of each module if `hash_modules` is set, or
+    /// over the modules' bytes otherwise.
+    fn get(&self, hash_modules: bool) -> [u8; 32] {
+        let digest = if hash_modules {
+            &self.hashed_modules
+        } else {
+            &self.whole_modules
+        };
+
+        *digest.get_or_init(|| {
+            MovePackage::compute_digest_for_modules_and_deps(
+                &self.modules,
+                &self.deps,
+                hash_modules,
+            )
+        })
+    }
+}
+
+impl Module {
+    /// Deserialize a module from its bytecode, and a table containing the origins of its structs.
+    /// Fails if the origin table is missing an entry for one of its types, returning the name of
+    /// the type in that case.
+    fn read(
+        bytecode: CompiledModule,
+        mut origins: BTreeMap<String, AccountAddress>,
+    ) -> std::result::Result<Self, String> {
+        let mut struct_index = BTreeMap::new();
+        for (index, def) in bytecode.struct_defs.iter().enumerate() {
+            let sh = bytecode.datatype_handle_at(def.struct_handle);
+            let struct_ = bytecode.identifier_at(sh.name).to_string();
+            let index = StructDefinitionIndex::new(index as TableIndex);
+
+            let Some(defining_id) = origins.remove(&struct_) else {
+                return Err(struct_);
+            };
+
+            struct_index.insert(struct_, (defining_id, index));
+        }
+
+        let mut enum_index = BTreeMap::new();
+        for (index, def) in bytecode.enum_defs.iter().enumerate() {
+            let eh = bytecode.datatype_handle_at(def.enum_handle);
+            let enum_ = bytecode.identifier_at(eh.name).to_string();
+            let index = EnumDefinitionIndex::new(index as TableIndex);
+
+            let Some(defining_id) = origins.remove(&enum_) else {
+                return Err(enum_);
+            };
+
+            enum_index.insert(enum_, (defining_id, index));
+        }
+
+        let mut function_index = BTreeMap::new();
+        for (index, def) in bytecode.function_defs.iter().enumerate() {
+            let fh = bytecode.function_handle_at(def.function);
+            let function = bytecode.identifier_at(fh.name).to_string();
+            let index = FunctionDefinitionIndex::new(index as TableIndex);
+
+            function_index.insert(function, index);
+        }
+
+        Ok(Module {
+            bytecode,
+            struct_index,
+            enum_index,
+            function_index,
+        })
+    }
+
+    pub fn bytecode(&self) -> &CompiledModule {
+        &self.bytecode
+    }
+
+    /// The module's name
+    pub fn name(&self) -> &str {
+        self.bytecode
+            .identifier_at(self.bytecode.self_handle().name)
+            .as_str()
+    }
+
+    /// The modules declared as friends of this module, ordered by address and then by name
+    /// (rather than in the order the compiler declared them in). Addresses in the returned module
+    /// IDs are runtime IDs.
+    pub fn friends(&self) -> impl Iterator<Item = ModuleId> + '_ {
+        let mut friends: Vec<_> = self
+            .bytecode
+            .friend_decls()
+            .iter()
+            .map(|handle| self.bytecode.module_id_for_handle(handle))
+            .collect();
+
+        friends.sort();
+        friends.into_iter()
+    }
+
+    /// Iterate over the structs with names strictly after `after` (or from the beginning), and
+    /// strictly before `before` (or to the end).
+    pub fn structs(
+        &self,
+        after: Option<&str>,
+        before: Option<&str>,
+    ) -> impl DoubleEndedIterator<Item = &str> + Clone {
+        use std::ops::Bound as B;
+        self.struct_index
+            .range::<str, _>((
+                after.map_or(B::Unbounded, B::Excluded),
+                before.map_or(B::Unbounded, B::Excluded),
+            ))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Iterate over the enums with names strictly after `after` (or from the beginning), and
+    /// strictly before `before` (or to the end).
+    pub fn enums(
+        &self,
+        after: Option<&str>,
+        before: Option<&str>,
+    ) -> impl DoubleEndedIterator<Item = &str> + Clone {
+        use std::ops::Bound as B;
+        self.enum_index
+            .range::<str, _>((
+                after.map_or(B::Unbounded, B::Excluded),
+                before.map_or(B::Unbounded, B::Excluded),
+            ))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Iterate over the datatypes with names strictly after `after` (or from the beginning), and
+    /// strictly before `before` (or to the end). Enums and structs will be interleaved, and will
+    /// be sorted by their names.
+    pub fn datatypes(
+        &self,
+        after: Option<&str>,
+        before: Option<&str>,
+    ) -> impl DoubleEndedIterator<Item = &str> + Clone {
+        let mut names = self
+            .structs(after, before)
+            .chain(self.enums(after, before))
+            .collect::<Vec<_>>();
+        names.sort();
+        names.into_iter()
+    }
+
+    /// Get the struct definition corresponding to the struct with name `name` in this module.
+    /// Returns `Ok(None)` if the struct cannot be found in this module, `Err(...)` if there was an
+    /// error deserializing it, and `Ok(Some(def))` on success.
+    pub fn struct_def(&self, name: &str) -> Result<Option<DataDef>> {
+        let Some(&(defining_id, index)) = self.struct_index.get(name) else {
+            return Ok(None);
+        };
+
+        let struct_def = self.bytecode.struct_def_at(index);
+        let struct_handle = self.bytecode.datatype_handle_at(struct_def.struct_handle);
+        let abilities = struct_handle.abilities;
+        let type_params = struct_handle.type_parameters.clone();
+
+        let fields = match &struct_def.field_information {
+            StructFieldInformation::Native => vec![],
+            StructFieldInformation::Declared(fields) => fields
+                .iter()
+                .map(|f| {
+                    Ok((
+                        self.bytecode.identifier_at(f.name).to_string(),
+                        OpenSignatureBody::read(&f.signature.0, &self.bytecode)?,
+                    ))
+                })
+                .collect::<Result<_>>()?,
+        };
+
+        Ok(Some(DataDef {
+            defining_id,
+            abilities,
+            type_params,
+            data: MoveData::Struct(fields),
+        }))
+    }
+
+    /// Get the enum definition corresponding to the enum with name `name` in this module.
+    /// Returns `Ok(None)` if the enum cannot be found in this module, `Err(...)` if there was an
+    /// error deserializing it, and `Ok(Some(def))` on success.
+    pub fn enum_def(&self, name: &str) -> Result<Option<DataDef>> {
+        let Some(&(defining_id, index)) = self.enum_index.get(name) else {
+            return Ok(None);
+        };
+
+        let enum_def = self.bytecode.enum_def_at(index);
+        let enum_handle = self.bytecode.datatype_handle_at(enum_def.enum_handle);
+        let abilities = enum_handle.abilities;
+        let type_params = enum_handle.type_parameters.clone();
+
+        let variants = enum_def
+            .variants
+            .iter()
+            .map(|variant| {
+                let name = self
+                    .bytecode
+                    .identifier_at(variant.variant_name)
+                    .to_string();
+                let signatures = variant
+                    .fields
+                    .iter()
+                    .map(|f| {
+                        Ok((
+                            self.bytecode.identifier_at(f.name).to_string(),
+                            OpenSignatureBody::read(&f.signature.0, &self.bytecode)?,
+                        ))
+                    })
+                    .collect::<Result<_>>()?;
+
+                Ok(VariantDef { name, signatures })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Some(DataDef {
+            defining_id,
+            abilities,
+            type_params,
+            data: MoveData::Enum(variants),
+        }))
+    }
+
+    /// Get the data definition corresponding to the data type with name `name` in this module.
+    /// Returns `Ok(None)` if the datatype cannot be found in this module, `Err(...)` if there was an
+    /// error deserializing it, and `Ok(Some(def))` on success.
+    pub fn data_def(&self, name: &str) -> Result<Option<DataDef>> {
+        self.struct_def(name)
+            .transpose()
+            .or_else(|| self.enum_def(name).transpose())
+            .transpose()
+    }
+
+    /// Iterate over the functions with names strictly after `after` (or from the beginning), and
+    /// strictly before `before` (or to the end).
+    pub fn functions(
+        &self,
+        after: Option<&str>,
+        before: Option<&str>,
+    ) -> impl DoubleEndedIterator<Item = &str> + Clone {
+        use std::ops::Bound as B;
+        self.function_index
+            .range::<str, _>((
+                after.map_or(B::Unbounded, B::Excluded),
+                before.map_or(B::Unbounded, B::Excluded),
+            ))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Get the function definition corresponding to the function with name `name` in this module.
+    /// Returns `Ok(None)` if the function cannot be found in this module, `Err(...)` if there was
+    /// an error deserializing it, and `Ok(Some(def))` on success.
+    pub fn function_def(&self, name: &str) -> Result<Option<FunctionDef>> {
+        let Some(&index) = self.function_index.get(name) else {
+            return Ok(None);
+        };
+
+        Ok(Some(self.function_def_at(index)?))
+    }
+
+    /// Like `data_def`, but also returns the signature tokens that the definition was read from.
+    pub fn data_def_with_tokens(&self, name: &str) -> Result<Option<(DataDef, RawDataSignature)>> {
+        let Some(def) = self.data_def(name)? else {
+            return Ok(None);
+        };
+
+        let tokens = |fields: &[FieldDefinition]| -> Vec<_> {
+            fields.iter().map(|f| f.signature.0.clone()).collect()
+        };
+
+        let raw = if let Some(&(_, index)) = self.struct_index.get(name) {
+            let struct_def = self.bytecode.struct_def_at(index);
+            RawDataSignature {
+                handle: struct_def.struct_handle,
+                data: RawMoveData::Struct(match &struct_def.field_information {
+                    StructFieldInformation::Native => vec![],
+                    StructFieldInformation::Declared(fields) => tokens(fields),
+                }),
+            }
+        } else {
+            let (_, index) = self.enum_index[name];
+            let enum_def = self.bytecode.enum_def_at(index);
+            RawDataSignature {
+                handle: enum_def.enum_handle,
+                data: RawMoveData::Enum(
+                    enum_def
+                        .variants
+                        .iter()
+                        .map(|v| tokens(&v.fields))
+                        .collect(),
+                ),
+            }
+        };
+
+        Ok(Some((def, raw)))
+    }
+
+    /// Like `function_def`, but also returns the signature tokens that the definition was read
+    /// from.
+    pub fn function_def_with_tokens(
+        &self,
+        name: &str,
+    ) -> Result<Option<(FunctionDef, RawFunctionSignature)>> {
+        let Some(&index) = self.function_index.get(name) else {
+            return Ok(None);
+        };
+
+        let function_def = self.bytecode.function_def_at(index);
+        let function_handle = self.bytecode.function_handle_at(function_def.function);
+        let raw = RawFunctionSignature {
+            parameters_index: function_handle.parameters,
+            parameters: self
+                .bytecode
+                .signature_at(function_handle.parameters)
+                .0
+                .clone(),
+            return_index: function_handle.return_,
+            return_: self
+                .bytecode
+                .signature_at(function_handle.return_)
+                .0
+                .clone(),
+        };
+
+        Ok(Some((self.function_def_at(index)?, raw)))
+    }
+
+    /// Render the bytecode of the function with name `name` in this module as text. If `source` is
+    /// provided, it is used to name parameters and locals, and to annotate instructions with the
+    /// source line they were compiled from.
+    pub fn disassemble_function(
+        &self,
+        name: &str,
+        source: Option<&ModuleSource>,
+    ) -> Result<String> {
+        let id = self.bytecode.self_id();
+        if !self.function_index.contains_key(name) {
+            return Err(Error::FunctionNotFound(
+                *id.address(),
+                id.name().to_string(),
+                name.to_string(),
+            ));
+        }
+
+        let mut mapping = match source {
+            Some(source) => SourceMapping::new(source.source_map.clone(), &self.bytecode),
+            None => SourceMapping::new_without_source_map(&self.bytecode, Loc::invalid())
+                .map_err(|e| Error::Disassembly(format!("{id}::{name}"), e.to_string()))?,
+        };
+
+        if let Some((path, code)) = source.and_then(|s| s.source_file.clone()) {
+            mapping.with_source_code((path, code));
+        }
+
+        let options = DisassemblerOptions {
+            print_source_lines: true,
+            ..DisassemblerOptions::new()
+        };
+
+        Disassembler::new(mapping, options)
+            .disassemble_function(&ident(name)?)
+            .map_err(|e| Error::Disassembly(format!("{id}::{name}"), e.to_string()))
+    }
+
+    /// The constants in this module's constant pool, in pool order. Bytecode does not record the
+    /// names of constants, so constants are named by `source`'s source map, if it is provided.
+    /// Otherwise only error constants (declared with `#[error]`) that the module aborts with are
+    /// named, because the abort codes the compiler generates for them refer to a constant holding
+    /// their name.
+    pub fn constants(&self, source: Option<&ModuleSource>) -> Result<Vec<ConstantDef>> {
+        let pool = self.bytecode.constant_pool();
+        let mut names = BTreeMap::new();
+
+        for code in self
+            .bytecode
+            .function_defs()
+            .iter()
+            .filter_map(|f| f.code.as_ref())
+        {
+            for instr in &code.code {
+                let Bytecode::LdU64(bits) = instr else {
+                    continue;
+                };
+
+                let Some(bitset) = ErrorBitset::from_u64(*bits) else {
+                    continue;
+                };
+
+                let (Some(identifier), Some(constant)) =
+                    (bitset.identifier_index(), bitset.constant_index())
+                else {
+                    continue;
+                };
+
+                if let Some(name) = pool.get(identifier as usize).and_then(constant_string) {
+                    names.insert(constant as usize, name);
+                }
+            }
+        }
+
+        if let Some(source) = source {
+            for (name, &index) in &source.source_map.constant_map {
+                names.insert(index as usize, name.0.to_string());
+            }
+        }
+
+        pool.iter()
+            .enumerate()
+            .map(|(index, constant)| {
+                let rendered = match try_render_constant(constant) {
+                    RenderResult::NotRendered => None,
+                    RenderResult::AsString(s) | RenderResult::AsValue(s) => Some(s),
+                };
+
+                Ok(ConstantDef {
+                    name: names.remove(&index),
+                    type_: OpenSignatureBody::read(&constant.type_, &self.bytecode)?,
+                    rendered,
+                    bytes: constant.data.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Deserialize the function definition at `index` in this module's bytecode.
+    fn function_def_at(&self, index: FunctionDefinitionIndex) -> Result<FunctionDef> {
+        let function_def = self.bytecode.function_def_at(index);
+        let function_handle = self.bytecode.function_handle_at(function_def.function);
+
+        Ok(FunctionDef {
+            visibility: function_def.visibility,
+            is_entry: function_def.is_entry,
+            type_params: function_handle.type_parameters.clone(),
+            parameters: read_signature(function_handle.parameters, &self.bytecode)?,
+            return_: read_signature(function_handle.return_, &self.bytecode)?,
+        })
+    }
+}
+
+impl OpenSignature {
+    fn read(sig: &SignatureToken, bytecode: &CompiledModule) -> Result<Self> {
+        use SignatureToken as S;
+        Ok(match sig {
+            S::Reference(sig) => OpenSignature {
+                ref_: Some(Reference::Immutable),
+                body: OpenSignatureBody::read(sig, bytecode)?,
+            },
+
+            S::MutableReference(sig) => OpenSignature {
+                ref_: Some(Reference::Mutable),
+                body: OpenSignatureBody::read(sig, bytecode)?,
+            },
+
+            sig => OpenSignature {
+                ref_: None,
+                body: OpenSignatureBody::read(sig, bytecode)?,
+            },
+        })
+    }
+
+    /// How a transaction would supply an argument for a parameter with this signature. Assumes
+    /// the signature is a parameter of an entry function, so that any datatype that is not a pure
+    /// type, `TxContext`, or `Receiving<T>` must be an object.
+    pub fn argument_kind(&self) -> ArgumentKind {
+        if let OpenSignatureBody::Datatype(key, _) = &self.body {
+            if key.package == SUI_FRAMEWORK_ADDRESS
+                && key.module == "tx_context"
+                && key.name == "TxContext"
+            {
+                return ArgumentKind::TxContext;
+            }
+
+            if key.package == SUI_FRAMEWORK_ADDRESS
+                && key.module == "transfer"
+                && key.name == "Receiving"
+            {
+                return ArgumentKind::Receiving;
+            }
+        }
+
+        match self.body.is_pure() {
+            Some(true) => ArgumentKind::Pure,
+            Some(false) => ArgumentKind::Object(self.ref_),
+            None => ArgumentKind::TypeParameter,
+        }
+    }
+
+    /// Return a specific instantiation of this signature, with `type_params` as the actual type
+    /// parameters. This function does not check that the supplied type parameters are valid (meet
+    /// the ability constraints of the struct or function this signature is part of, see
+    /// `instantiate_checked`), but will produce an error if the signature references a type
+    /// parameter that is out of bounds.
+    pub fn instantiate(&self, type_params: &[TypeInput]) -> Result<Signature> {
+        Ok(Signature {
+            ref_: self.ref_,
+            body: self.body.instantiate(type_params)?,
+        })
+    }
+
+    /// Like `instantiate`, but first checks that `type_params` satisfy the ability `constraints`
+    /// on the type parameters of the function or datatype this signature is part of, using
+    /// `resolver` to look up the abilities of the types involved (see
+    /// `Resolver::check_type_arguments`).
+    pub async fn instantiate_checked<S: PackageStore>(
+        &self,
+        type_params: &[TypeInput],
+        constraints: &[AbilitySet],
+        resolver: &Resolver<S>,
+    ) -> Result<Signature> {
+        resolver
+            .check_type_arguments(type_params, constraints)
+            .await?;
+        self.instantiate(type_params)
+    }
+}
+
+impl OpenSignatureBody {
+    fn read(sig: &SignatureToken, bytecode: &CompiledModule) -> Result<Self> {
+        use OpenSignatureBody as O;
+        use SignatureToken as S;
+
+        Ok(match sig {
+            S::Signer => return Err(Error::UnexpectedSigner),
+            S::Reference(_) | S::MutableReference(_) => return Err(Error::UnexpectedReference),
+
+            S::Address => O::Address,
+            S::Bool => O::Bool,
+            S::U8 => O::U8,
+            S::U16 => O::U16,
+            S::U32 => O::U32,
+            S::U64 => O::U64,
+            S::U128 => O::U128,
+            S::U256 => O::U256,
+            S::TypeParameter(ix) => O::TypeParameter(*ix),
+
+            S::Vector(sig) => O::Vector(Box::new(OpenSignatureBody::read(sig, bytecode)?)),
+
+            S::Datatype(ix) => O::Datatype(DatatypeKey::read(*ix, bytecode), vec![]),
+            S::DatatypeInstantiation(inst) => {
+                let (ix, params) = &**inst;
+                O::Datatype(
+                    DatatypeKey::read(*ix, bytecode),
+                    params
+                        .iter()
+                        .map(|sig| OpenSignatureBody::read(sig, bytecode))
+                        .collect::<Result<_>>()?,
+                )
+            }
+        })
+    }
+
+    /// Whether values of this type can be passed as pure inputs to a transaction (mirroring
+    /// `is_primitive_type_tag`), or `None` if that depends on how its type parameters are
+    /// instantiated.
+    fn is_pure(&self) -> Option<bool> {
+        use OpenSignatureBody as O;
+
+        match self {
+            O::Address | O::Bool | O::U8 | O::U16 | O::U32 | O::U64 | O::U128 | O::U256 => {
+                Some(true)
+            }
+
+            O::TypeParameter(_) => None,
+
+            O::Vector(sig) => sig.is_pure(),
+
+            O::Datatype(key, params) => {
+                let is = |package: AccountAddress, module: &str, name: &str| {
+                    key.package == package && key.module == module && key.name == name
+                };
+
+                if is(SUI_FRAMEWORK_ADDRESS, "object", "ID")
+                    || is(MOVE_STDLIB_ADDRESS, "string", "String")
+                    || is(MOVE_STDLIB_ADDRESS, "ascii", "String")
+                {
+                    Some(true)
+                } else if is(MOVE_STDLIB_ADDRESS, "option", "Option") {
+                    match params.as_slice() {
+                        [param] => param.is_pure(),
+                        _ => Some(false),
+                    }
+                } else {
+                    Some(false)
+                }
+            }
+        }
+    }
+
+    fn instantiate(&self, type_params: &[TypeInput]) -> Result<TypeTag> {
+        use OpenSignatureBody as O;
+        use TypeTag as T;
+
+        Ok(match self {
+            O::Address => T::Address,
+            O::Bool => T::Bool,
+            O::U8 => T::U8,
+            O::U16 => T::U16,
+            O::U32 => T::U32,
+            O::U64 => T::U64,
+            O::U128 => T::U128,
+            O::U256 => T::U256,
+            O::Vector(s) => T::Vector(Box::new(s.instantiate(type_params)?)),
+
+            O::Datatype(key, dty_params) => T::Struct(Box::new(StructTag {
+                address: key.package,
+                module: ident(&key.module)?,
+                name: ident(&key.name)?,
+                type_params: dty_params
+                    .iter()
+                    .map(|p| p.instantiate(type_params))
+                    .collect::<Result<_>>()?,
+            })),
+
+            O::TypeParameter(ix) => as_type_tag(
+                type_params
+                    .get(*ix as usize)
+                    .ok_or_else(|| Error::TypeParamOOB(*ix, type_params.len()))?,
+            )?,
+        })
+    }
+}
+
+impl FunctionFilter {
+    /// Whether `function` satisfies all the criteria in this filter.
+    pub fn matches(&self, function: &FunctionDef) -> bool {
+        let matches_any = |pattern: &SignaturePattern, sigs: &[OpenSignature]| {
+            sigs.iter().any(|sig| pattern.matches(sig))
+        };
+
+        self.visibility.map_or(true, |v| v == function.visibility)
+            && self.is_entry.map_or(true, |e| e == function.is_entry)
+            && self
+                .parameters
+                .iter()
+                .all(|p| matches_any(p, &function.parameters))
+            && self
+                .returns
+                .iter()
+                .all(|p| matches_any(p, &function.return_))
+    }
+}
+
+impl SignaturePattern {
+    /// Whether `sig` is passed by the expected reference, with a type matching the pattern.
+    pub fn matches(&self, sig: &OpenSignature) -> bool {
+        self.ref_ == sig.ref_ && self.body.matches(&sig.body)
+    }
+}
+
+impl TypePattern {
+    pub fn matches(&self, body: &OpenSignatureBody) -> bool {
+        use OpenSignatureBody as O;
+        use TypePattern as P;
+
+        match (self, body) {
+            (P::Any, _) => true,
+            (P::TypeParameter, O::TypeParameter(_)) => true,
+            (P::Exact(expect), body) => expect == body,
+            (P::Vector(pattern), O::Vector(body)) => pattern.matches(body),
+            (P::Datatype(key, patterns), O::Datatype(actual, params)) => {
+                key == actual
+                    && patterns.len() == params.len()
+                    && patterns.iter().zip(params).all(|(p, b)| p.matches(b))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<'m, 'n> DatatypeRef<'m, 'n> {
+    pub fn as_key(&self) -> DatatypeKey {
+        DatatypeKey {
+            package: self.package,
+            module: self.module.to_string().into(),
+            name: self.name.to_string().into(),
+        }
+    }
+}
+
+impl DatatypeKey {
+    fn read(ix: DatatypeHandleIndex, bytecode: &CompiledModule) -> Self {
+        let sh = bytecode.datatype_handle_at(ix);
+        let mh = bytecode.module_handle_at(sh.module);
+
+        let package = *bytecode.address_identifier_at(mh.address);
+        let module = bytecode.identifier_at(mh.name).to_string().into();
+        let name = bytecode.identifier_at(sh.name).to_string().into();
+
+        DatatypeKey {
+            package,
+            module,
+            name,
+        }
+    }
+}
+
+impl<'t> PtbChecker<'t> {
+    fn new(tx: &'t ProgrammableTransaction) -> Self {
+        Self {
+            tx,
+            pure_types: vec![None; tx.inputs.len()],
+            results: vec![],
+            errors: vec![],
+        }
+    }
+
+    /// Record a problem with the command currently being checked.
+    fn error(&mut self, kind: PtbTypeErrorKind) {
+        self.errors.push(PtbTypeError {
+            command: self.results.len(),
+            kind,
+        });
+    }
+
+    /// What is known about the value `arg` (the `argument`-th argument of the current command)
+    /// refers to, recording a problem if it does not refer to a value.
+    fn value(&mut self, argument: usize, arg: &Argument) -> PtbValue {
+        match self.lookup(argument, arg) {
+            Ok(value) => value,
+            Err(kind) => {
+                self.error(kind);
+                PtbValue::Unknown
+            }
+        }
+    }
+
+    fn lookup(
+        &self,
+        argument: usize,
+        arg: &Argument,
+    ) -> std::result::Result<PtbValue, PtbTypeErrorKind> {
+        use PtbTypeErrorKind as K;
+
+        let typed = |tag: &Option<TypeTag>| tag.clone().map_or(PtbValue::Unknown, PtbValue::Typed);
+
+        Ok(match *arg {
+            Argument::GasCoin => PtbValue::Typed(GasCoin::type_().into()),
+
+            Argument::Input(input) => match self.tx.inputs.get(input as usize) {
+                Some(CallArg::Pure(_)) => PtbValue::Pure(input),
+                Some(CallArg::Object(_)) => PtbValue::Object,
+                None => return Err(K::InputOutOfBounds { argument, input }),
+            },
+
+            Argument::Result(result) => match self.results.get(result as usize) {
+                None => return Err(K::ResultOutOfBounds { argument, result }),
+                Some(None) => PtbValue::Unknown,
+                Some(Some(values)) => match values.as_slice() {
+                    [value] => typed(value),
+                    _ => {
+                        return Err(K::InvalidResultArity {
+                            argument,
+                            result,
+                            arity: values.len(),
+                        })
+                    }
+                },
+            },
+
+            Argument::NestedResult(result, nested) => match self.results.get(result as usize) {
+                None => return Err(K::ResultOutOfBounds { argument, result }),
+                Some(None) => PtbValue::Unknown,
+                Some(Some(values)) => match values.get(nested as usize) {
+                    Some(value) => typed(value),
+                    None => {
+                        return Err(K::NestedResultOutOfBounds {
+                            argument,
+                            result,
+                            nested,
+                        })
+                    }
+                },
+            },
+        })
+    }
+
+    /// Check that `arg` can be passed where a value of type `expected` is expected.
+    fn expect(&mut self, argument: usize, arg: &Argument, expected: &TypeTag) {
+        use PtbTypeErrorKind as K;
+
+        match self.value(argument, arg) {
+            PtbValue::Pure(input) => {
+                if !is_primitive_type_tag(expected) {
+                    self.error(K::UnexpectedPureInput { argument });
+                    return;
+                }
+
+                // A pure input takes on the type of its first use.
+                let first = self.pure_types[input as usize]
+                    .get_or_insert_with(|| expected.clone())
+                    .clone();
+
+                if &first != expected {
+                    self.error(K::PureInputConflict {
+                        argument,
+                        input,
+                        first,
+                        second: expected.clone(),
+                    });
+                }
+            }
+
+            PtbValue::Object => {
+                if is_primitive_type_tag(expected) {
+                    self.error(K::UnexpectedObjectInput { argument });
+                }
+            }
+
+            PtbValue::Typed(actual) => {
+                if &actual != expected {
+                    self.error(K::TypeMismatch {
+                        argument,
+                        expected: expected.clone(),
+                        actual,
+                    });
+                }
+            }
+
+            PtbValue::Unknown => {}
+        }
+    }
+
+    /// Check that `arg` refers to a coin, returning its type, if known.
+    fn coin(&mut self, argument: usize, arg: &Argument) -> Option<TypeTag> {
+        use PtbTypeErrorKind as K;
+
+        match self.value(argument, arg) {
+            PtbValue::Pure(_) => {
+                self.error(K::UnexpectedPureInput { argument });
+                None
+            }
+
+            PtbValue::Typed(actual) => match &actual {
+                TypeTag::Struct(tag) if Coin::is_coin(tag) => Some(actual),
+                _ => {
+                    self.error(K::NotACoin { argument, actual });
+                    None
+                }
+            },
+
+            PtbValue::Object | PtbValue::Unknown => None,
+        }
+    }
+
+    fn report(self) -> PtbTypeReport {
+        PtbTypeReport {
+            results: self.results,
+            errors: self.errors,
+        }
+    }
+}
+
+impl<'l> ResolutionContext<'l> {
+    fn new(limits: Option<&'l Limits>) -> Self {
+        ResolutionContext {
+            datatypes: BTreeMap::new(),
+            limits,
+            fetches: 0,
+            hits: 0,
+            max_depth: 0,
+        }
+    }
+
+    /// Summarise the work done by this context, for a request that started at `start`.
+    fn stats(&self, start: Instant) -> ResolutionStats {
+        ResolutionStats {
+            packages_fetched: self.fetches,
+            cache_hits: self.hits,
+            datatypes_resolved: self.datatypes.len(),
+            max_depth: self.max_depth,
+            elapsed: start.elapsed(),
+            layout_cached: false,
+        }
+    }
+
+    /// Gather definitions for types that contribute to the definition of `tag` into this resolution
+    /// context, fetching data from the `store` as necessary. Also updates package addresses in
+    /// `tag` to point to runtime IDs instead of storage IDs to ensure queries made using these
+    /// addresses during the subsequent resolution phase find the relevant type information in the
+    /// context.
+    ///
+    /// The `visit_fields` flag controls whether the traversal looks inside types at their fields
+    /// (which is necessary for layout resolution) or not (only explores the outer type and any type
+    /// parameters).
+    ///
+    /// The `visit_phantoms` flag controls whether the traversal recurses through phantom type
+    /// parameters (which is also necessary for type resolution) or not.
+    async fn add_type_tag<S: PackageStore + ?Sized>(
+        &mut self,
+        tag: &mut TypeTag,
+        store: &S,
+        visit_fields: bool,
+        visit_phantoms: bool,
+    ) -> Result<()> {
+        use TypeTag as T;
+
+        struct ToVisit<'t> {
+            tag: &'t mut TypeTag,
+            depth: usize,
+        }
+
+        let mut frontier = vec![ToVisit { tag, depth: 0 }];
+        while let Some(ToVisit { tag, depth }) = frontier.pop() {
+            self.max_depth = self.max_depth.max(depth);
+            macro_rules! push_ty_param {
+                ($tag:expr) => {{
+                    check_max_limit!(
+                        TypeParamNesting, self.limits;
+                        max_type_argument_depth > depth
+                    );
+
+                    frontier.push(ToVisit { tag: $tag, depth: depth + 1 })
+                }}
+            }
+
+            match tag {
+                T::Address
+                | T::Bool
+                | T::U8
+                | T::U16
+                | T::U32
+                | T::U64
+                | T::U128
+                | T::U256
+                | T::Signer => {
+                    // Nothing further to add to context
+                }
+
+                T::Vector(tag) => push_ty_param!(tag),
+
+                T::Struct(s) => {
+                    self.fetches += 1;
+                    let context = store.fetch(s.address).await?;
+                    let def = context
+                        .clone()
+                        .data_def(s.module.as_str(), s.name.as_str())?;
+
+                    // Normalize `address` (the ID of a package that contains the definition of this
+                    // struct) to be a runtime ID, because that's what the resolution context uses
+                    // for keys.  Take care to do this before generating the key that is used to
+                    // query and/or write into `self.structs.
+                    s.address = context.runtime_id;
+                    let key = DatatypeRef::from(s.as_ref()).as_key();
+
+                    if def.type_params.len() != s.type_params.len() {
+                        return Err(Error::TypeArityMismatch(
+                            def.type_params.len(),
+                            s.type_params.len(),
+                        ));
+                    }
+
+                    check_max_limit!(
+                        TooManyTypeParams, self.limits;
+                        max_type_argument_width >= s.type_params.len()
+                    );
+
+                    for (param, def) in s.type_params.iter_mut().zip(def.type_params.iter()) {
+                        if !def.is_phantom || visit_phantoms {
+                            push_ty_param!(param);
+                        }
+                    }
+
+                    if self.datatypes.contains_key(&key) {
+                        self.hits += 1;
+                        continue;
+                    }
+
+                    if visit_fields {
+                        match &def.data {
+                            MoveData::Struct(fields) => {
+                                for (_, sig) in fields {
+                                    self.add_signature(sig.clone(), store, &context, visit_fields)
+                                        .await?;
+                                }
+                            }
+                            MoveData::Enum(variants) => {
+                                for variant in variants {
+                                    for (_, sig) in &variant.signatures {
+                                        self.add_signature(
+                                            sig.clone(),
+                                            store,
+                                            &context,
+                                            visit_fields,
+                                        )
+                                        .await?;
+                                    }
+                                }
+                            }
+                        };
+                    }
+
+                    check_max_limit!(
+                        TooManyTypeNodes, self.limits;
+                        max_type_nodes > self.datatypes.len()
+                    );
+
+                    self.datatypes.insert(key, def);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Like `add_type_tag` (visiting fields and phantoms), but only visits the fields of datatypes
+    /// that `filter` keeps. The type parameters of truncated datatypes are still added (without
+    /// their fields), because they are needed to canonicalize the truncated datatype's type.
+    ///
+    /// Unlike `add_type_tag`, fields are visited as instantiated type tags rather than signatures,
+    /// because whether a datatype is truncated depends on the path to it, and not just its type.
+    /// Types are only visited once per path that the filter prunes below them. Elsewhere, they are
+    /// visited once per level (to respect the filter's depth), or handed to `add_type_tag` if the
+    /// filter has no depth.
+    async fn add_filtered_type_tag<S: PackageStore + ?Sized>(
+        &mut self,
+        tag: TypeTag,
+        store: &S,
+        filter: &LayoutFilter,
+    ) -> Result<()> {
+        use TypeTag as T;
+
+        let max_value_depth = self.limits.map_or(usize::MAX, |l| l.max_move_value_depth);
+
+        // The shallowest level that each type has been visited at, outside of pruned paths.
+        let mut visited: BTreeMap<StructTag, usize> = BTreeMap::new();
+
+        let mut frontier = vec![(tag, 1, vec![])];
+        while let Some((tag, level, path)) = frontier.pop() {
+            let s = match tag {
+                T::Address
+                | T::Bool
+                | T::U8
+                | T::U16
+                | T::U32
+                | T::U64
+                | T::U128
+                | T::U256
+                | T::Signer => continue,
+
+                T::Vector(tag) => {
+                    frontier.push((*tag, level, path));
+                    continue;
+                }
+
+                T::Struct(s) => s,
+            };
+
+            // Every level of datatypes adds at least one level to the value's depth, so this
+            // bounds the traversal even when there is no limit on the filter's depth.
+            if level > max_value_depth {
+                return Err(Error::ValueNesting(max_value_depth));
+            }
+
+            // Below this point, only the filter's depth can truncate datatypes, so the path to this
+            // type no longer matters, and visiting it again only adds something if it is at a
+            // shallower level than before.
+            if !filter.pruned_paths.iter().any(|p| p.starts_with(&path)) {
+                if filter.max_depth.is_none() {
+                    self.add_type_tag(
+                        &mut T::Struct(s),
+                        store,
+                        /* visit_fields */ true,
+                        /* visit_phantoms */ true,
+                    )
+                    .await?;
+                    continue;
+                }
+
+                match visited.get(s.as_ref()) {
+                    Some(prev) if *prev <= level => continue,
+                    _ => visited.insert(s.as_ref().clone(), level),
+                };
+            }
+
+            check_max_limit!(
+                TooManyTypeParams, self.limits;
+                max_type_argument_width >= s.type_params.len()
+            );
+
+            self.fetches += 1;
+            let package = store.fetch(s.address).await?;
+            let def = package.data_def(s.module.as_str(), s.name.as_str())?;
+
+            if def.type_params.len() != s.type_params.len() {
+                return Err(Error::TypeArityMismatch(
+                    def.type_params.len(),
+                    s.type_params.len(),
+                ));
+            }
+
+            for param in &s.type_params {
+                self.add_type_tag(
+                    &mut param.clone(),
+                    store,
+                    /* visit_fields */ false,
+                    /* visit_phantoms */ true,
+                )
+                .await?;
+            }
+
+            if !filter.truncates(level, &path) {
+                let params: Vec<_> = s.type_params.iter().cloned().map(TypeInput::from).collect();
+                let fields: Vec<(Vec<&String>, &OpenSignatureBody)> = match &def.data {
+                    MoveData::Struct(fields) => {
+                        fields.iter().map(|(n, sig)| (vec![n], sig)).collect()
+                    }
+                    MoveData::Enum(variants) => variants
+                        .iter()
+                        .flat_map(|v| v.signatures.iter().map(|(n, sig)| (vec![&v.name, n], sig)))
+                        .collect(),
+                };
+
+                for (names, sig) in fields {
+                    let mut sig = sig.clone();
+                    package.relocate_signature(&mut sig)?;
+
+                    let mut field_path = path.clone();
+                    field_path.extend(names.into_iter().cloned());
+                    frontier.push((sig.instantiate(&params)?, level + 1, field_path));
+                }
+            }
+
+            let key = DatatypeKey {
+                package: package.runtime_id,
+                module: s.module.to_string().into(),
+                name: s.name.to_string().into(),
+            };
+
+            if self.datatypes.contains_key(&key) {
+                self.hits += 1;
+                continue;
+            }
+
+            check_max_limit!(
+                TooManyTypeNodes, self.limits;
+                max_type_nodes > self.datatypes.len()
+            );
+
+            self.datatypes.insert(key, def);
+        }
+
+        Ok(())
+    }
+
+    // Like `add_type_tag` but for type signatures.  Needs a linkage table to translate runtime IDs
+    // into storage IDs.
+    async fn add_signature<T: PackageStore + ?Sized>(
+        &mut self,
+        sig: OpenSignatureBody,
+        store: &T,
+        context: &Package,
+        visit_fields: bool,
+    ) -> Result<()> {
+        use OpenSignatureBody as O;
+
+        let mut frontier = vec![sig];
+        while let Some(sig) = frontier.pop() {
+            match sig {
+                O::Address
+                | O::Bool
+                | O::U8
+                | O::U16
+                | O::U32
+                | O::U64
+                | O::U128
+                | O::U256
+                | O::TypeParameter(_) => {
+                    // Nothing further to add to context
+                }
+
+                O::Vector(sig) => frontier.push(*sig),
+
+                O::Datatype(key, params) => {
+                    check_max_limit!(
+                        TooManyTypeParams, self.limits;
+                        max_type_argument_width >= params.len()
+                    );
+
+                    let params_count = params.len();
+                    let data_count = self.datatypes.len();
+                    frontier.extend(params.into_iter());
+
+                    let type_params = if let Some(def) = self.datatypes.get(&key) {
+                        self.hits += 1;
+                        &def.type_params
+                    } else {
+                        check_max_limit!(
+                            TooManyTypeNodes, self.limits;
+                            max_type_nodes > data_count
+                        );
+
+                        check_max_limit!(
+                            TooManyLinkageEntries, self.limits;
+                            max_linkage_entries >= context.linkage.len()
+                        );
+
+                        // Need to resolve the datatype, so fetch the package that contains it.
+                        let storage_id = context.relocate(key.package)?;
+                        self.fetches += 1;
+                        let package = store.fetch(storage_id).await?;
+
+                        // Guard against linkage tables that send relocation to an unrelated
+                        // package, which could otherwise chain resolution through arbitrary
+                        // packages.
+                        if package.runtime_id != key.package {
+                            return Err(Error::LinkageMismatch(
+                                key.package,
+                                storage_id,
+                                package.runtime_id,
+                            ));
+                        }
+
+                        let def = package.data_def(&key.module, &key.name)?;
+                        if visit_fields {
+                            match &def.data {
+                                MoveData::Struct(fields) => {
+                                    frontier.extend(fields.iter().map(|f| &f.1).cloned());
+                                }
+                                MoveData::Enum(variants) => {
+                                    frontier.extend(
+                                        variants
+                                            .iter()
+                                            .flat_map(|v| v.signatures.iter().map(|(_, s)| s))
+                                            .cloned(),
+                                    );
+                                }
+                            };
+                        }
+
+                        &self.datatypes.entry(key).or_insert(def).type_params
+                    };
+
+                    if type_params.len() != params_count {
+                        return Err(Error::TypeArityMismatch(type_params.len(), params_count));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Translate runtime IDs in a type `tag` into defining IDs using only the information
+    /// contained in this context. Requires that the necessary information was added to the context
+    /// through calls to `add_type_tag`.
+    fn canonicalize_type(&self, tag: &mut TypeTag) -> Result<()> {
+        use TypeTag as T;
+
+        match tag {
+            T::Signer => return Err(Error::UnexpectedSigner),
+            T::Address | T::Bool | T::U8 | T::U16 | T::U32 | T::U64 | T::U128 | T::U256 => {
+                /* nop */
+            }
+
+            T::Vector(tag) => self.canonicalize_type(tag.as_mut())?,
+
+            T::Struct(s) => {
+                for tag in &mut s.type_params {
+                    self.canonicalize_type(tag)?;
+                }
+
+                // SAFETY: `add_type_tag` ensures `datatyps` has an element with this key.
+                let key = DatatypeRef::from(s.as_ref());
+                let def = &self.datatypes[&key];
+
+                s.address = def.defining_id;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Translate a type `tag` into its layout using only the information contained in this context.
+    /// Requires that the necessary information was added to the context through calls to
+    /// `add_type_tag` and `add_signature` before being called.
+    ///
+    /// `max_depth` controls how deep the layout is allowed to grow to. The actual depth reached is
+    /// returned alongside the layout (assuming it does not exceed `max_depth`).
+    fn resolve_type_layout(
+        &self,
+        tag: &TypeTag,
+        max_depth: usize,
+    ) -> Result<(MoveTypeLayout, usize)> {
+        use MoveTypeLayout as L;
+        use TypeTag as T;
+
+        if max_depth == 0 {
+            return Err(Error::ValueNesting(
+                self.limits.map_or(0, |l| l.max_move_value_depth),
+            ));
+        }
+
+        Ok(match tag {
+            T::Signer => return Err(Error::UnexpectedSigner),
+
+            T::Address => (L::Address, 1),
+            T::Bool => (L::Bool, 1),
+            T::U8 => (L::U8, 1),
+            T::U16 => (L::U16, 1),
+            T::U32 => (L::U32, 1),
+            T::U64 => (L::U64, 1),
+            T::U128 => (L::U128, 1),
+            T::U256 => (L::U256, 1),
+
+            T::Vector(tag) => {
+                let (layout, depth) = self.resolve_type_layout(tag, max_depth - 1)?;
+                (L::Vector(Box::new(layout)), depth + 1)
+            }
+
+            T::Struct(s) => {
+                // TODO (optimization): Could introduce a layout cache to further speed up
+                // resolution.  Relevant entries in that cache would need to be gathered in the
+                // ResolutionContext as it is built, and then used here to avoid the recursive
+                // exploration.  This optimisation is complicated by the fact that in the cache,
+                // these layouts are naturally keyed based on defining ID, but during resolution,
+                // they are keyed by runtime IDs.
+
+                // TODO (optimization): This could be made more efficient by only generating layouts
+                // for non-phantom types.  This efficiency could be extended to the exploration
+                // phase (i.e. only explore layouts of non-phantom types). But this optimisation is
+                // complicated by the fact that we still need to create a correct type tag for a
+                // phantom parameter, which is currently done by converting a type layout into a
+                // tag.
+                let param_layouts = s
+                    .type_params
+                    .iter()
+                    // Reduce the max depth because we know these type parameters will be nested
+                    // within this struct.
+                    .map(|tag| self.resolve_type_layout(tag, max_depth - 1))
+                    .collect::<Result<Vec<_>>>()?;
+
+                // SAFETY: `param_layouts` contains `MoveTypeLayout`-s that are generated by this
+                // `ResolutionContext`, which guarantees that struct layouts come with types, which
+                // is necessary to avoid errors when converting layouts into type tags.
+                let type_params = param_layouts.iter().map(|l| TypeTag::from(&l.0)).collect();
+
+                // SAFETY: `add_type_tag` ensures `datatyps` has an element with this key.
+                let key = DatatypeRef::from(s.as_ref());
+                let def = &self.datatypes[&key];
+
+                let type_ = StructTag {
+                    address: def.defining_id,
+                    module: s.module.clone(),
+                    name: s.name.clone(),
+                    type_params,
+                };
+
+                self.resolve_datatype_signature(def, type_, param_layouts, max_depth)?
+            }
+        })
+    }
+
+    /// Like `resolve_type_layout`, but truncates datatypes that `filter` excludes. Requires that
+    /// the necessary information was added to the context through a call to
+    /// `add_filtered_type_tag`, and that package IDs in `tag` are runtime IDs.
+    ///
+    /// `level` and `path` locate `tag` in the outermost type, as described in `LayoutFilter`.
+    fn resolve_filtered_layout(
+        &self,
+        tag: &TypeTag,
+        filter: &LayoutFilter,
+        level: usize,
+        path: &mut Vec<String>,
+        max_depth: usize,
+    ) -> Result<(MoveTypeLayout, usize)> {
+        use MoveTypeLayout as L;
+        use TypeTag as T;
+
+        if max_depth == 0 {
+            return Err(Error::ValueNesting(
+                self.limits.map_or(0, |l| l.max_move_value_depth),
+            ));
+        }
+
+        let s = match tag {
+            T::Signer => return Err(Error::UnexpectedSigner),
+
+            T::Address => return Ok((L::Address, 1)),
+            T::Bool => return Ok((L::Bool, 1)),
+            T::U8 => return Ok((L::U8, 1)),
+            T::U16 => return Ok((L::U16, 1)),
+            T::U32 => return Ok((L::U32, 1)),
+            T::U64 => return Ok((L::U64, 1)),
+            T::U128 => return Ok((L::U128, 1)),
+            T::U256 => return Ok((L::U256, 1)),
+
+            T::Vector(tag) => {
+                let (layout, depth) =
+                    self.resolve_filtered_layout(tag, filter, level, path, max_depth - 1)?;
+                return Ok((L::Vector(Box::new(layout)), depth + 1));
+            }
+
+            T::Struct(s) => s,
+        };
+
+        // SAFETY: `add_filtered_type_tag` ensures `datatypes` has an element with this key.
+        let key = DatatypeRef::from(s.as_ref());
+        let def = &self.datatypes[&key];
+
+        let mut type_ = s.as_ref().clone();
+        for param in &mut type_.type_params {
+            self.canonicalize_type(param)?;
+        }
+        type_.address = def.defining_id;
+
+        if filter.truncates(level, path) {
+            let layout = match &def.data {
+                MoveData::Struct(_) => L::Struct(Box::new(MoveStructLayout {
+                    type_,
+                    fields: Box::new(vec![]),
+                })),
+                MoveData::Enum(_) => L::Enum(Box::new(MoveEnumLayout {
+                    type_,
+                    variants: BTreeMap::new(),
+                })),
+            };
+
+            return Ok((layout, 1));
+        }
+
+        let params: Vec<_> = s.type_params.iter().cloned().map(TypeInput::from).collect();
+        Ok(match &def.data {
+            MoveData::Struct(fields) => {
+                let (fields, depth) =
+                    self.resolve_filtered_fields(fields, &params, filter, level, path, max_depth)?;
+
+                let layout = L::Struct(Box::new(MoveStructLayout {
+                    type_,
+                    fields: Box::new(fields),
+                }));
+
+                (layout, depth + 1)
+            }
+
+            MoveData::Enum(variants) => {
+                let mut field_depth = 0;
+                let mut resolved_variants = BTreeMap::new();
+                for (tag, variant) in variants.iter().enumerate() {
+                    path.push(variant.name.clone());
+                    let (fields, depth) = self.resolve_filtered_fields(
+                        &variant.signatures,
+                        &params,
+                        filter,
+                        level,
+                        path,
+                        max_depth,
+                    )?;
+                    path.pop();
+
+                    field_depth = field_depth.max(depth);
+                    resolved_variants.insert((ident(variant.name.as_str())?, tag as u16), fields);
+                }
+
+                let layout = L::Enum(Box::new(MoveEnumLayout {
+                    type_,
+                    variants: resolved_variants,
+                }));
+
+                (layout, field_depth + 1)
+            }
+        })
+    }
+
+    /// Resolve the layouts of the `fields` of a datatype at `level` and `path` that is
+    /// instantiated with `params`, for `resolve_filtered_layout`. Returns the layouts, and the
+    /// depth of the deepest field.
+    fn resolve_filtered_fields(
+        &self,
+        fields: &[(String, OpenSignatureBody)],
+        params: &[TypeInput],
+        filter: &LayoutFilter,
+        level: usize,
+        path: &mut Vec<String>,
+        max_depth: usize,
+    ) -> Result<(Vec<MoveFieldLayout>, usize)> {
+        let mut resolved = Vec::with_capacity(fields.len());
+        let mut field_depth = 0;
+
+        for (name, sig) in fields {
+            path.push(name.clone());
+            let (layout, depth) = self.resolve_filtered_layout(
+                &sig.instantiate(params)?,
+                filter,
+                level + 1,
+                path,
+                max_depth - 1,
+            )?;
+            path.pop();
+
+            field_depth = field_depth.max(depth);
+            resolved.push(MoveFieldLayout {
+                name: ident(name.as_str())?,
+                layout,
+            });
+        }
+
+        Ok((resolved, field_depth))
+    }
+
+    /// Translates a datatype definition into a type layout.  Needs to be provided the layouts of type
+    /// parameters which are substituted when a type parameter is encountered.
+    ///
+    /// `max_depth` controls how deep the layout is allowed to grow to. The actual depth reached is
+    /// returned alongside the layout (assuming it does not exceed `max_depth`).
+    fn resolve_datatype_signature(
+        &self,
+        data_def: &DataDef,
+        type_: StructTag,
+        param_layouts: Vec<(MoveTypeLayout, usize)>,
+        max_depth: usize,
+    ) -> Result<(MoveTypeLayout, usize)> {
+        Ok(match &data_def.data {
+            MoveData::Struct(fields) => {
+                let mut resolved_fields = Vec::with_capacity(fields.len());
+                let mut field_depth = 0;
+
+                for (name, sig) in fields {
+                    let (layout, depth) =
+                        self.resolve_signature_layout(sig, &param_layouts, max_depth - 1)?;
+
+                    field_depth = field_depth.max(depth);
+                    resolved_fields.push(MoveFieldLayout {
+                        name: ident(name.as_str())?,
+                        layout,
+                    })
+                }
+
+                (
+                    MoveTypeLayout::Struct(Box::new(MoveStructLayout {
+                        type_,
+                        fields: Box::new(resolved_fields),
+                    })),
+                    field_depth + 1,
+                )
+            }
+            MoveData::Enum(variants) => {
+                let mut field_depth = 0;
+                let mut resolved_variants = BTreeMap::new();
+
+                for (tag, variant) in variants.iter().enumerate() {
+                    let mut fields = Vec::with_capacity(variant.signatures.len());
+                    for (name, sig) in &variant.signatures {
+                        // Note: We decrement the depth here because we're already under the variant
+                        let (layout, depth) =
+                            self.resolve_signature_layout(sig, &param_layouts, max_depth - 1)?;
+
+                        field_depth = field_depth.max(depth);
+                        fields.push(MoveFieldLayout {
+                            name: ident(name.as_str())?,
+                            layout,
+                        })
+                    }
+                    resolved_variants.insert((ident(variant.name.as_str())?, tag as u16), fields);
+                }
+
+                (
+                    MoveTypeLayout::Enum(Box::new(MoveEnumLayout {
+                        type_,
+                        variants: resolved_variants,
+                    })),
+                    field_depth + 1,
+                )
+            }
+        })
+    }
+
+    /// Like `resolve_type_tag` but for signatures.  Needs to be provided the layouts of type
+    /// parameters which are substituted when a type parameter is encountered.
+    ///
+    /// `max_depth` controls how deep the layout is allowed to grow to. The actual depth reached is
+    /// returned alongside the layout (assuming it does not exceed `max_depth`).
+    fn resolve_signature_layout(
+        &self,
+        sig: &OpenSignatureBody,
+        param_layouts: &[(MoveTypeLayout, usize)],
+        max_depth: usize,
+    ) -> Result<(MoveTypeLayout, usize)> {
+        use MoveTypeLayout as L;
+        use OpenSignatureBody as O;
+
+        if max_depth == 0 {
+            return Err(Error::ValueNesting(
+                self.limits.map_or(0, |l| l.max_move_value_depth),
+            ));
+        }
+
+        Ok(match sig {
+            O::Address => (L::Address, 1),
+            O::Bool => (L::Bool, 1),
+            O::U8 => (L::U8, 1),
+            O::U16 => (L::U16, 1),
+            O::U32 => (L::U32, 1),
+            O::U64 => (L::U64, 1),
+            O::U128 => (L::U128, 1),
+            O::U256 => (L::U256, 1),
+
+            O::TypeParameter(ix) => {
+                let (layout, depth) = param_layouts
+                    .get(*ix as usize)
+                    .ok_or_else(|| Error::TypeParamOOB(*ix, param_layouts.len()))
+                    .cloned()?;
+
+                // We need to re-check the type parameter before we use it because it might have
+                // been fine when it was created, but result in too deep a layout when we use it at
+                // this position.
+                if depth > max_depth {
+                    return Err(Error::ValueNesting(
+                        self.limits.map_or(0, |l| l.max_move_value_depth),
+                    ));
+                }
+
+                (layout, depth)
+            }
+
+            O::Vector(sig) => {
+                let (layout, depth) =
+                    self.resolve_signature_layout(sig.as_ref(), param_layouts, max_depth - 1)?;
+
+                (L::Vector(Box::new(layout)), depth + 1)
+            }
+
+            O::Datatype(key, params) => {
+                // SAFETY: `add_signature` ensures `datatypes` has an element with this key.
+                let def = &self.datatypes[key];
+
+                let param_layouts = params
+                    .iter()
+                    .map(|sig| self.resolve_signature_layout(sig, param_layouts, max_depth - 1))
+                    .collect::<Result<Vec<_>>>()?;
+
+                // SAFETY: `param_layouts` contains `MoveTypeLayout`-s that are generated by this
+                // `ResolutionContext`, which guarantees that struct layouts come with types, which
+                // is necessary to avoid errors when converting layouts into type tags.
+                let type_params: Vec<TypeTag> =
+                    param_layouts.iter().map(|l| TypeTag::from(&l.0)).collect();
+
+                let type_ = StructTag {
+                    address: def.defining_id,
+                    module: ident(&key.module)?,
+                    name: ident(&key.name)?,
+                    type_params,
+                };
+
+                self.resolve_datatype_signature(def, type_, param_layouts, max_depth)?
+            }
+        })
+    }
+
+    /// Calculate the abilities for a concrete type `tag`. Requires that the necessary information
+    /// was added to the context through calls to `add_type_tag` before being called.
+    fn resolve_abilities(&self, tag: &TypeTag) -> Result<AbilitySet> {
+        use TypeTag as T;
+        Ok(match tag {
+            T::Signer => return Err(Error::UnexpectedSigner),
+
+            T::Bool | T::U8 | T::U16 | T::U32 | T::U64 | T::U128 | T::U256 | T::Address => {
+                AbilitySet::PRIMITIVES
+            }
+
+            T::Vector(tag) => self.resolve_abilities(tag)?.intersect(AbilitySet::VECTOR),
+
+            T::Struct(s) => {
+                // SAFETY: `add_type_tag` ensures `datatypes` has an element with this key.
+                let key = DatatypeRef::from(s.as_ref());
+                let def = &self.datatypes[&key];
+
+                if def.type_params.len() != s.type_params.len() {
+                    return Err(Error::TypeArityMismatch(
+                        def.type_params.len(),
+                        s.type_params.len(),
+                    ));
+                }
+
+                let param_abilities: Result<Vec<AbilitySet>> = s
+                    .type_params
+                    .iter()
+                    .zip(def.type_params.iter())
+                    .map(|(p, d)| {
+                        if d.is_phantom {
+                            Ok(AbilitySet::EMPTY)
+                        } else {
+                            self.resolve_abilities(p)
+                        }
+                    })
+                    .collect();
+
+                AbilitySet::polymorphic_abilities(
+                    def.abilities,
+                    def.type_params.iter().map(|p| p.is_phantom),
+                    param_abilities?.into_iter(),
+                )
+                // This error is unexpected because the only reason it would fail is because of a
+                // type parameter arity mismatch, which we check for above.
+                .map_err(|e| Error::UnexpectedError(Arc::new(e)))?
+            }
+        })
+    }
+
+    /// Check that every datatype in `tag` is instantiated with type arguments that satisfy the
+    /// ability constraints on its type parameters (phantom or not). Requires that `tag` was added
+    /// to the context, visiting phantom type parameters.
+    fn check_type_constraints(&self, tag: &TypeTag) -> Result<()> {
+        use TypeTag as T;
+        match tag {
+            T::Signer => Err(Error::UnexpectedSigner),
+
+            T::Bool | T::U8 | T::U16 | T::U32 | T::U64 | T::U128 | T::U256 | T::Address => Ok(()),
+
+            T::Vector(tag) => self.check_type_constraints(tag),
+
+            T::Struct(s) => {
+                // SAFETY: `add_type_tag` ensures `datatypes` has an element with this key.
+                let key = DatatypeRef::from(s.as_ref());
+                let def = &self.datatypes[&key];
+
+                if def.type_params.len() != s.type_params.len() {
+                    return Err(Error::TypeArityMismatch(
+                        def.type_params.len(),
+                        s.type_params.len(),
+                    ));
+                }
+
+                for (index, (param, decl)) in s.type_params.iter().zip(&def.type_params).enumerate()
+                {
+                    self.check_type_constraints(param)?;
+                    let actual = self.resolve_abilities(param)?;
+                    check_abilities(Some(s), index, param, decl.constraints, actual)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Translate the (runtime) package IDs in `sig` to defining IDs using only the information
+    /// contained in this context. Requires that the necessary information was added to the context
+    /// through calls to `add_signature` before being called.
+    fn relocate_signature(&self, sig: &mut OpenSignatureBody) -> Result<()> {
+        use OpenSignatureBody as O;
+
+        match sig {
+            O::Address | O::Bool | O::U8 | O::U16 | O::U32 | O::U64 | O::U128 | O::U256 => {
+                /* nop */
+            }
+
+            O::TypeParameter(_) => { /* nop */ }
+
+            O::Vector(sig) => self.relocate_signature(sig.as_mut())?,
+
+            O::Datatype(key, params) => {
+                // SAFETY: `add_signature` ensures `datatypes` has an element with this key.
+                let defining_id = &self.datatypes[key].defining_id;
+                for param in params {
+                    self.relocate_signature(param)?;
+                }
+
+                key.package = *defining_id;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'s> From<&'s StructTag> for DatatypeRef<'s, 's> {
+    fn from(tag: &'s StructTag) -> Self {
+        DatatypeRef {
+            package: tag.address,
+            module: tag.module.as_str().into(),
+            name: tag.name.as_str().into(),
+        }
+    }
+}
+
+/// Translate a string into an `Identifier`, but translating errors into this module's error type.
+fn ident(s: &str) -> Result<Identifier> {
+    Identifier::new(s).map_err(|_| Error::NotAnIdentifier(s.to_string()))
+}
+
+/// Like `ident`, but also enforces the cap on identifier length in `limits`, if there is one.
+fn checked_ident(s: &str, limits: Option<&Limits>) -> Result<Identifier> {
+    if let Some(max) = limits.and_then(|l| l.max_identifier_len) {
+        if s.len() > max {
+            return Err(Error::IdentifierTooLong(s.to_string(), max));
+        }
+    }
+
+    ident(s)
+}
+
+/// The value of `constant` as a string, if it is a UTF-8 encoded `vector<u8>`.
+fn constant_string(constant: &Constant) -> Option<String> {
+    if !matches!(&constant.type_, SignatureToken::Vector(x) if x.as_ref() == &SignatureToken::U8) {
+        return None;
+    }
+
+    let bytes = bcs::from_bytes::<Vec<u8>>(&constant.data).ok()?;
+    String::from_utf8(bytes).ok()
+}
+
+pub fn as_type_tag(type_input: &TypeInput) -> Result<TypeTag> {
+    as_type_tag_with_limits(type_input, None)
+}
+
+/// Like `as_type_tag`, but rejecting identifiers that are longer than `limits` allow.
+pub fn as_type_tag_with_limits(type_input: &TypeInput, limits: Option<&Limits>) -> Result<TypeTag> {
+    use TypeInput as I;
+    use TypeTag as T;
+    Ok(match type_input {
+        I::Bool => T::Bool,
+        I::U8 => T::U8,
+        I::U16 => T::U16,
+        I::U32 => T::U32,
+        I::U64 => T::U64,
+        I::U128 => T::U128,
+        I::U256 => T::U256,
+        I::Address => T::Address,
+        I::Signer => T::Signer,
+        I::Vector(t) => T::Vector(Box::new(as_type_tag_with_limits(t, limits)?)),
+        I::Struct(s) => {
+            let StructInput {
+                address,
+                module,
+                name,
+                type_params,
+            } = s.as_ref();
+            let type_params = type_params
+                .iter()
+                .map(|t| as_type_tag_with_limits(t, limits))
+                .collect::<Result<_>>()?;
+            T::Struct(Box::new(StructTag {
+                address: *address,
+                module: checked_ident(module, limits)?,
+                name: checked_ident(name, limits)?,
+                type_params,
+            }))
+        }
+    })
+}
+
+/// Read and deserialize a signature index (from function parameter or return types) into a vector
+/// of signatures.
+fn read_signature(idx: SignatureIndex, bytecode: &CompiledModule) -> Result<Vec<OpenSignature>> {
+    let MoveSignature(tokens) = bytecode.signature_at(idx);
+    let mut sigs = Vec::with_capacity(tokens.len());
+
+    for token in tokens {
+        sigs.push(OpenSignature::read(token, bytecode)?);
+    }
+
+    Ok(sigs)
+}
+
+/// Deserialize a module, distinguishing modules that are too new for this binary to read from ones
+/// that are malformed.
+fn deserialize_module(bytes: &[u8]) -> Result<CompiledModule> {
+    let error = match CompiledModule::deserialize_with_defaults(bytes) {
+        Ok(module) => return Ok(module),
+        Err(error) => error,
+    };
+
+    match bytecode_version(bytes) {
+        Some(found) if found > VERSION_MAX => Err(Error::UnsupportedBytecodeVersion {
+            found,
+            max_supported: VERSION_MAX,
+        }),
+        _ => Err(Error::Deserialize(error.finish(Location::Undefined))),
+    }
+}
+
+/// Check that `tag`, with abilities `actual`, has the `required` abilities of the `index`-th type
+/// parameter of `owner` (or of the signature being instantiated, if there is no owner).
+fn check_abilities(
+    owner: Option<&StructTag>,
+    index: usize,
+    tag: &TypeTag,
+    required: AbilitySet,
+    actual: AbilitySet,
+) -> Result<()> {
+    if required.is_subset(actual) {
+        return Ok(());
+    }
+
+    Err(Error::MissingAbilities {
+        owner: owner.cloned(),
+        index,
+        type_: tag.clone(),
+        missing: required.difference(actual),
+    })
+}
+
+/// Deduct `size` bytes from `budget`, if there is enough left. Otherwise, exhaust it, so that
+/// nothing after this point is shown either.
+fn charge(budget: &mut usize, size: usize) -> bool {
+    if size > *budget {
+        *budget = 0;
+        false
+    } else {
+        *budget -= size;
+        true
+    }
+}
+
+/// Whether `tag` is a `std::string::String` or a `std::ascii::String`.
+fn is_string(tag: &StructTag) -> bool {
+    let resolved = (
+        &tag.address,
+        tag.module.as_ident_str(),
+        tag.name.as_ident_str(),
+    );
+    resolved == RESOLVED_UTF8_STR || resolved == RESOLVED_ASCII_STR
+}
+
+/// Deserialize `bytes` as a value with the given `layout`, which was resolved for `tag`.
+fn deserialize_value(tag: &TypeTag, layout: &MoveTypeLayout, bytes: &[u8]) -> Result<MoveValue> {
+    MoveValue::simple_deserialize(bytes, layout)
+        .map_err(|e| Error::ValueDecode(tag.clone(), e.to_string()))
+}
+
+/// The bytecode version declared in the header of serialized module `bytes`, if it starts with a
+/// well-formed header.
+fn bytecode_version(bytes: &[u8]) -> Option<u32> {
+    let (magic, rest) = bytes.split_at_checked(BinaryConstants::MOVE_MAGIC_SIZE)?;
+    if magic != BinaryConstants::MOVE_MAGIC {
+        return None;
+    }
+
+    let version = rest.first_chunk::<4>()?;
+    Some(BinaryFlavor::decode_version(u32::from_le_bytes(*version)))
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use move_binary_format::file_format::Ability;
+    use move_core_types::ident_str;
+    use std::sync::Arc;
+    use std::{path::PathBuf, str::FromStr, sync::RwLock};
+    use sui_types::base_types::{random_object_ref, ObjectID};
+    use sui_types::supported_protocol_versions::{Chain, ProtocolVersion};
+    use sui_types::transaction::ObjectArg;
+
+    use move_compiler::compiled_unit::NamedCompiledModule;
+    use sui_move_build::{BuildConfig, CompiledPackage};
+
+    use super::*;
+    use crate::error::ErrorClass;
+
+    fn fmt(struct_layout: MoveTypeLayout, enum_layout: MoveTypeLayout) -> String {
+        format!("struct:\n{struct_layout:#}\n\nenum:\n{enum_layout:#}",)
+    }
+
+    #[tokio::test]
+    async fn test_simple_canonical_type() {
+        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
+        let package_resolver = Resolver::new(cache);
+
+        let input = type_("0xa0::m::T0");
+        let expect = input.clone();
+        let actual = package_resolver.canonical_type(input).await.unwrap();
+        assert_eq!(expect, actual);
+    }
+
+    #[tokio::test]
+    async fn test_upgraded_canonical_type() {
+        let (_, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (2, build_package("a1"), a1_types()),
+        ]);
+
+        let package_resolver = Resolver::new(cache);
+
+        let input = type_("0xa1::m::T3");
+        let expect = input.clone();
+        let actual = package_resolver.canonical_type(input).await.unwrap();
+        assert_eq!(expect, actual);
+    }
+
+    #[tokio::test]
+    async fn test_latest_canonical_type() {
+        let (_, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (2, build_package("a1"), a1_types()),
+        ]);
+
+        let package_resolver = Resolver::new(cache);
+
+        let input = type_("0xa1::m::T0");
+        let expect = type_("0xa0::m::T0");
+        let actual = package_resolver.canonical_type(input).await.unwrap();
+        assert_eq!(expect, actual);
+    }
+
+    #[tokio::test]
+    async fn test_type_param_canonical_type() {
+        let (_, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (2, build_package("a1"), a1_types()),
+        ]);
+
+        let package_resolver = Resolver::new(cache);
+
+        let input = type_("0xa1::m::T1<0xa1::m::T0, 0xa1::m::T3>");
+        let expect = type_("0xa0::m::T1<0xa0::m::T0, 0xa1::m::T3>");
+        let actual = package_resolver.canonical_type(input).await.unwrap();
+        assert_eq!(expect, actual);
+    }
+
+    #[tokio::test]
+    async fn test_canonical_err_package_too_old() {
+        let (_, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (2, build_package("a1"), a1_types()),
+        ]);
+
+        let package_resolver = Resolver::new(cache);
+
+        let input = type_("0xa0::m::T3");
+        let err = package_resolver.canonical_type(input).await.unwrap_err();
+        assert!(matches!(err, Error::DatatypeNotFound(_, _, _)));
+    }
+
+    #[tokio::test]
+    async fn test_canonical_err_signer() {
+        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
+
+        let package_resolver = Resolver::new(cache);
+
+        let input = type_("0xa0::m::T1<0xa0::m::T0, signer>");
+        let err = package_resolver.canonical_type(input).await.unwrap_err();
+        assert!(matches!(err, Error::UnexpectedSigner));
+    }
+
+    /// Layout for a type that only refers to base types or other types in the same module.
+    #[tokio::test]
+    async fn test_simple_type_layout() {
+        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
+        let package_resolver = Resolver::new(cache);
+        let struct_layout = package_resolver
+            .type_layout(type_("0xa0::m::T0"))
+            .await
+            .unwrap();
+        let enum_layout = package_resolver
+            .type_layout(type_("0xa0::m::E0"))
+            .await
+            .unwrap();
+        insta::assert_snapshot!(fmt(struct_layout, enum_layout));
+    }
+
+    /// A type that refers to types from other modules in the same package.
+    #[tokio::test]
+    async fn test_cross_module_layout() {
+        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
+        let resolver = Resolver::new(cache);
+        let struct_layout = resolver.type_layout(type_("0xa0::n::T0")).await.unwrap();
+        let enum_layout = resolver.type_layout(type_("0xa0::n::E0")).await.unwrap();
+        insta::assert_snapshot!(fmt(struct_layout, enum_layout));
+    }
+
+    /// A type that refers to types a different package.
+    #[tokio::test]
+    async fn test_cross_package_layout() {
+        let (_, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (1, build_package("b0"), b0_types()),
+        ]);
+        let resolver = Resolver::new(cache);
+
+        let struct_layout = resolver.type_layout(type_("0xb0::m::T0")).await.unwrap();
+        let enum_layout = resolver.type_layout(type_("0xb0::m::E0")).await.unwrap();
+        insta::assert_snapshot!(fmt(struct_layout, enum_layout));
+    }
+
+    /// Filtering a layout truncates datatypes below a certain depth, or at certain paths.
+    #[tokio::test]
+    async fn test_filtered_layout() {
+        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
+        let resolver = Resolver::new(cache);
+
+        let depth = |max_depth| LayoutFilter {
+            max_depth: Some(max_depth),
+            ..Default::default()
+        };
+
+        let prune = |path: &[&str]| LayoutFilter {
+            pruned_paths: BTreeSet::from([path.iter().map(|s| s.to_string()).collect()]),
+            ..Default::default()
+        };
+
+        let layout = |tag, filter| {
+            let resolver = &resolver;
+            async move {
+                resolver
+                    .type_layout_with_filter(type_(tag), &filter)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let field = |layout: &MoveTypeLayout, name: &str| {
+            let MoveTypeLayout::Struct(s) = layout else {
+                panic!("Expected struct, got: {layout:#}");
+            };
+
+            s.fields
+                .iter()
+                .find(|f| f.name.as_str() == name)
+                .unwrap()
+                .layout
+                .clone()
+        };
+
+        let element = |layout: MoveTypeLayout| {
+            let MoveTypeLayout::Vector(element) = layout else {
+                panic!("Expected vector, got: {layout:#}");
+            };
+
+            *element
+        };
+
+        // Datatypes in the outermost datatype's fields are truncated, but keep their full types.
+        let t0 = layout("0xa0::m::T0", depth(1)).await;
+        let t1 = element(field(&t0, "v"));
+        assert!(!LayoutFilter::is_truncated(&t0));
+        assert!(LayoutFilter::is_truncated(&t1));
+        assert_eq!(TypeTag::from(&t1), type_("0xa0::m::T1<0xa0::m::T2, u128>"),);
+
+        // Pruning the path to a field is equivalent to limiting the depth, when it is the only
+        // datatype at that depth.
+        let t0 = layout("0xa0::m::T0", depth(2)).await;
+        let t1 = element(field(&t0, "v"));
+        assert!(!LayoutFilter::is_truncated(&t1));
+        assert!(LayoutFilter::is_truncated(&field(&t1, "p")));
+        assert_eq!(t0, layout("0xa0::m::T0", prune(&["v", "p"])).await);
+
+        // Paths into enums include the variant name.
+        let MoveTypeLayout::Enum(e0) = layout("0xa0::m::E0", prune(&["V", "v"])).await else {
+            panic!("Expected enum");
+        };
+
+        let fields = e0.variants.values().next().unwrap();
+        let e1 = element(fields[1].layout.clone());
+        assert!(LayoutFilter::is_truncated(&e1));
+        assert_eq!(
+            TypeTag::from(&e1),
+            type_("0xa0::m::E1<0xa0::m::T1<0xa0::m::T2, u128>, u128>"),
+        );
+
+        // Filters that don't truncate anything produce the full layout.
+        for tag in ["0xa0::m::T0", "0xa0::m::E0", "0xa0::n::T0"] {
+            assert_eq!(
+                layout(tag, depth(10)).await,
+                resolver.type_layout(type_(tag)).await.unwrap(),
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_filtered_layout_dag() {
+        let (_, cache) = package_cache([(1, build_package("i0"), i0_types())]);
+
+        let depth = |max_depth| LayoutFilter {
+            max_depth: Some(max_depth),
+            ..Default::default()
+        };
+
+        let prune = |path: &[&str]| LayoutFilter {
+            pruned_paths: BTreeSet::from([path.iter().map(|s| s.to_string()).collect()]),
+            ..Default::default()
+        };
+
+        let fetches = |filter| {
+            let cache = &cache;
+            async move {
+                let mut context = ResolutionContext::new(None);
+                context
+                    .add_filtered_type_tag(type_("0x80::m::D0"), cache, &filter)
+                    .await
+                    .unwrap();
+                context.fetches
+            }
+        };
+
+        // D0's layout is a tree with 511 datatypes in it, but there are only 9 distinct ones, and
+        // they are each fetched once, regardless of how many paths lead to them.
+        assert_eq!(fetches(depth(16)).await, 9);
+
+        // Datatypes past the filter's depth are only fetched for their types.
+        assert_eq!(fetches(depth(3)).await, 4);
+
+        // D1 is fetched once more, at the pruned path.
+        assert_eq!(fetches(prune(&["l"])).await, 10);
+
+        let (_, cache) = package_cache([(1, build_package("i0"), i0_types())]);
+        let resolver = Resolver::new(cache);
+        assert_eq!(
+            resolver
+                .type_layout_with_filter(type_("0x80::m::D0"), &depth(16))
+                .await
+                .unwrap(),
+            resolver.type_layout(type_("0x80::m::D0")).await.unwrap(),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolution_stats() {
+        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
+        let resolver = Resolver::new(cache);
+
+        // T0 refers to T1 and T2 through its fields, and the deepest path through its layout is
+        // T0 -> vector -> T1 -> vector -> u128.
+        let (_, stats) = resolver
+            .type_layout_with_stats(type_("0xa0::m::T0"))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            stats,
+            ResolutionStats {
+                packages_fetched: 3,
+                cache_hits: 0,
+                datatypes_resolved: 3,
+                max_depth: 5,
+                elapsed: stats.elapsed,
+                layout_cached: false,
+            }
+        );
+
+        // The second occurrence of T2 is served from the definitions already gathered.
+        let (_, stats) = resolver
+            .canonical_type_with_stats(type_("0xa0::m::T1<0xa0::m::T2, 0xa0::m::T2>"))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            stats,
+            ResolutionStats {
+                packages_fetched: 3,
+                cache_hits: 1,
+                datatypes_resolved: 2,
+                max_depth: 1,
+                elapsed: stats.elapsed,
+                layout_cached: false,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_layout_cache() {
+        let (_, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (2, build_package("a1"), a1_types()),
+        ]);
+
+        let layouts = Arc::new(LayoutLruCache::new(NonZeroUsize::new(16).unwrap()));
+        let resolver = Resolver::new(cache).with_layout_cache(layouts.clone());
+
+        let (expect, stats) = resolver
+            .type_layout_with_stats(type_("0xa0::m::T0"))
+            .await
+            .unwrap();
+        assert!(!stats.layout_cached);
+
+        // The same type, referred to via the upgraded package, is served from the cache.
+        let (layout, stats) = resolver
+            .type_layout_with_stats(type_("0xa1::m::T0"))
+            .await
+            .unwrap();
+        assert!(stats.layout_cached);
+        assert_eq!(layout, expect);
+
+        // Entries are keyed by defining ID.
+        let cached = layouts.get(&type_("0xa0::m::T0")).await.unwrap();
+        assert_eq!(cached, Some(expect));
+        let cached = layouts.get(&type_("0xa1::m::T0")).await.unwrap();
+        assert_eq!(cached, None);
+
+        // Types that could not be resolved are not cached.
+        resolver
+            .type_layout(type_("0xa0::m::T9"))
+            .await
+            .unwrap_err();
+        let cached = layouts.get(&type_("0xa0::m::T9")).await.unwrap();
+        assert_eq!(cached, None);
+    }
+
+    #[tokio::test]
+    async fn test_disassemble_function() {
+        let c0 = build_package("c0");
+        let (_, cache) = package_cache([(1, c0.clone(), c0_types())]);
+        let resolver = Resolver::new(cache);
+        let m = ModuleId::new(addr("0xc0"), ident_str!("m").to_owned());
+
+        let plain = resolver
+            .disassemble_function(&m, "baz", None)
+            .await
+            .unwrap();
+        assert!(plain.starts_with("baz("), "{plain}");
+        assert!(plain.contains("CastU16"), "{plain}");
+        assert!(!plain.contains("// line"), "{plain}");
+
+        // With the source map and source code, parameters are named and lines are annotated.
+        let unit = c0
+            .package
+            .root_compiled_units
+            .iter()
+            .find(|unit| unit.unit.name.as_str() == "m")
+            .unwrap();
+
+        let source = ModuleSource {
+            source_map: unit.unit.source_map.clone(),
+            source_file: Some((
+                unit.source_path.display().to_string(),
+                std::fs::read_to_string(&unit.source_path).unwrap(),
+            )),
+        };
+
+        let annotated = resolver
+            .disassemble_function(&m, "baz", Some(&source))
+            .await
+            .unwrap();
+        assert!(annotated.starts_with("baz(x: u8)"), "{annotated}");
+        assert!(annotated.contains("// line 48"), "{annotated}");
+
+        let err = resolver
+            .disassemble_function(&m, "qux", None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::FunctionNotFound(_, _, _)));
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_immediate() {
+        let (inner, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (2, build_package("a1"), a1_types()),
+            (1, build_package("b0"), b0_types()),
+            (1, build_package("c0"), c0_types()),
+        ]);
+
+        let concurrency = NonZeroUsize::new(2).unwrap();
+        let store = cache.with_prefetch(PrefetchPolicy::Immediate { concurrency });
+
+        // Loading C also loads its dependencies: A1 and B0.
+        store.fetch(addr("0xc0")).await.unwrap();
+        assert_eq!(inner.read().unwrap().fetches, 3);
+
+        // ...which are then served from the cache.
+        store.fetch(addr("0xa1")).await.unwrap();
+        store.fetch(addr("0xb0")).await.unwrap();
+        assert_eq!(inner.read().unwrap().fetches, 3);
+
+        // B0 was loaded by prefetching, so its own dependencies are not fetched.
+        let packages = store.packages.lock().unwrap();
+        assert!(!packages.contains(&addr("0xa0")));
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_lazy() {
+        let (inner, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (2, build_package("a1"), a1_types()),
+            (1, build_package("b0"), b0_types()),
+            (1, build_package("c0"), c0_types()),
+        ]);
+
+        let concurrency = NonZeroUsize::new(2).unwrap();
+        let store = cache.with_prefetch(PrefetchPolicy::Lazy { concurrency });
+
+        // The first fetch only loads C.
+        store.fetch(addr("0xc0")).await.unwrap();
+        assert_eq!(inner.read().unwrap().fetches, 1);
+
+        // Using it again triggers the prefetch of its dependencies, once.
+        store.fetch(addr("0xc0")).await.unwrap();
+        assert_eq!(inner.read().unwrap().fetches, 3);
+
+        store.fetch(addr("0xc0")).await.unwrap();
+        store.fetch(addr("0xb0")).await.unwrap();
+        assert_eq!(inner.read().unwrap().fetches, 3);
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_lazy_eviction() {
+        let (_, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (1, build_package("b0"), b0_types()),
+            (1, build_package("d0"), d0_types()),
+            (1, build_package("e0"), e0_types()),
+        ]);
+
+        let capacity = NonZeroUsize::new(2).unwrap();
+        let concurrency = NonZeroUsize::new(2).unwrap();
+        let store = cache.with_prefetch(PrefetchPolicy::Lazy { concurrency });
+        store.packages.lock().unwrap().resize(capacity);
+
+        // Packages evicted from the cache are no longer waiting for their dependencies to be
+        // prefetched, so the set of pending packages never outgrows the cache.
+        for id in ["0xa0", "0xb0", "0xd0", "0xe0"] {
+            store.fetch(addr(id)).await.unwrap();
+            let pending = store.pending_prefetch.lock().unwrap();
+            assert!(pending.len() <= capacity.get());
+            assert!(pending.contains(&addr(id)));
+        }
+
+        let pending = store.pending_prefetch.lock().unwrap().clone();
+        assert_eq!(pending, BTreeSet::from([addr("0xd0"), addr("0xe0")]));
+    }
+
+    #[tokio::test]
+    async fn test_dependency_index() {
+        let (_, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (2, build_package("a1"), a1_types()),
+            (1, build_package("b0"), b0_types()),
+            (1, build_package("c0"), c0_types()),
+        ]);
+
+        let index = Arc::new(DependencyIndex::default());
+        let store = PackageStoreWithDependencyIndex::new(cache, index.clone());
+
+        // Nothing is known about packages until they pass through the store.
+        assert!(index.dependents_of(addr("0xa0")).is_empty());
+        assert_eq!(index.transitive_closure(addr("0xc0")), None);
+
+        store.fetch(addr("0xb0")).await.unwrap();
+        store.fetch(addr("0xc0")).await.unwrap();
+
+        let set = |ids: &[&str]| ids.iter().copied().map(addr).collect::<BTreeSet<_>>();
+
+        // C links against the upgraded version of A, so it does not depend on its original version.
+        assert_eq!(index.dependents_of(addr("0xa0")), set(&["0xb0"]));
+        assert_eq!(index.dependents_of(addr("0xa1")), set(&["0xc0"]));
+        assert_eq!(index.dependents_of(addr("0xb0")), set(&["0xc0"]));
+        assert_eq!(index.dependents_of(addr("0xc0")), set(&[]));
+
+        let deps = index.transitive_closure(addr("0xc0")).unwrap();
+        assert!(deps.is_superset(&set(&["0xa1", "0xb0"])));
+        assert!(!deps.contains(&addr("0xa0")));
+
+        // Re-indexing a package replaces its edges rather than duplicating them.
+        store.fetch(addr("0xc0")).await.unwrap();
+        assert_eq!(index.dependents_of(addr("0xb0")), set(&["0xc0"]));
+
+        // Packages are only re-indexed if their version changes (e.g. a system package that was
+        // upgraded in place).
+        let mut c0 = (*store.fetch(addr("0xc0")).await.unwrap()).clone();
+        c0.linkage.clear();
+        index.insert(&c0);
+        assert_eq!(index.dependents_of(addr("0xb0")), set(&["0xc0"]));
+
+        c0.version = SequenceNumber::from_u64(c0.version.value() + 1);
+        index.insert(&c0);
+        assert_eq!(index.dependents_of(addr("0xb0")), set(&[]));
+        assert_eq!(index.transitive_closure(addr("0xc0")), Some(set(&[])));
+    }
+
+    /// A type from an upgraded package, mixing structs defined in the original package and the
+    /// upgraded package.
+    #[tokio::test]
+    async fn test_upgraded_package_layout() {
+        let (_, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (2, build_package("a1"), a1_types()),
+        ]);
+        let resolver = Resolver::new(cache);
+
+        let struct_layout = resolver.type_layout(type_("0xa1::n::T1")).await.unwrap();
+        let enum_layout = resolver.type_layout(type_("0xa1::n::E1")).await.unwrap();
+        insta::assert_snapshot!(fmt(struct_layout, enum_layout));
+    }
+
+    /// A generic type instantiation where the type parameters are resolved relative to linkage
+    /// contexts from different versions of the same package.
+    #[tokio::test]
+    async fn test_multiple_linkage_contexts_layout() {
+        let (_, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (2, build_package("a1"), a1_types()),
+        ]);
+        let resolver = Resolver::new(cache);
+
+        let struct_layout = resolver
+            .type_layout(type_("0xa0::m::T1<0xa0::m::T0, 0xa1::m::T3>"))
+            .await
+            .unwrap();
+        let enum_layout = resolver
+            .type_layout(type_("0xa0::m::E1<0xa0::m::E0, 0xa1::m::E3>"))
+            .await
+            .unwrap();
+        insta::assert_snapshot!(fmt(struct_layout, enum_layout));
+    }
+
+    /// Types and functions named by runtime IDs, resolved through the linkage table of a package
+    /// that depends on an upgraded package.
+    #[tokio::test]
+    async fn test_resolve_in_context() {
+        let (_, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (2, build_package("a1"), a1_types()),
+            (1, build_package("b0"), b0_types()),
+            (1, build_package("c0"), c0_types()),
+        ]);
+        let resolver = Resolver::new(cache);
+        let c0 = addr("0xc0");
+
+        // `a::n::T1` was introduced in `a1`, so it can't be found by treating its runtime ID as a
+        // storage ID, but `c0` links against `a1`.
+        let runtime = type_("0xa0::n::T1");
+        let storage = type_("0xa1::n::T1");
+        assert!(resolver.type_layout(runtime.clone()).await.is_err());
+
+        let in_context = resolver
+            .type_layout_in_context(runtime.clone(), c0)
+            .await
+            .unwrap();
+        let expect = resolver.type_layout(storage.clone()).await.unwrap();
+        assert_eq!(in_context, expect);
+
+        let in_context = resolver.abilities_in_context(runtime, c0).await.unwrap();
+        let expect = resolver.abilities(storage).await.unwrap();
+        assert_eq!(in_context, expect);
+
+        // Type parameters are relocated too.
+        let in_context = resolver
+            .type_layout_in_context(type_("vector<0xa0::m::T1<0xa0::m::T3, u8>>"), c0)
+            .await
+            .unwrap();
+        let expect = resolver
+            .type_layout(type_("vector<0xa1::m::T1<0xa1::m::T3, u8>>"))
+            .await
+            .unwrap();
+        assert_eq!(in_context, expect);
+
+        let module_id = ModuleId::new(c0, Identifier::new("m").unwrap());
+        let in_context = resolver
+            .function_parameters_in_context(module_id, "bar", c0)
+            .await
+            .unwrap();
+        let expect = resolver.function_parameters(c0, "m", "bar").await.unwrap();
+        assert_eq!(format!("{in_context:?}"), format!("{expect:?}"));
+
+        // `b0` links against `a0`, which doesn't define `a::n::T1`.
+        let err = resolver
+            .type_layout_in_context(type_("0xa0::n::T1"), addr("0xb0"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::DatatypeNotFound(..)));
+
+        // Packages that the context doesn't link against can't be relocated.
+        let err = resolver
+            .type_layout_in_context(type_("0xc0::m::T0"), addr("0xb0"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::LinkageNotFound(a) if a == c0));
+    }
+
+    #[tokio::test]
+    async fn test_plan_execute_layout() {
+        let (inner, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (2, build_package("a1"), a1_types()),
+        ]);
+        let resolver = Resolver::new(cache);
+
+        let t0 = type_("0xa0::m::T1<0xa0::m::T0, 0xa1::m::T3>");
+        let t1 = type_("0xa0::m::E0");
+
+        let mut plan = resolver.plan(t0.clone()).unwrap();
+        plan.merge(resolver.plan(t1.clone()).unwrap());
+
+        // Planning does not touch the store.
+        assert_eq!(inner.read().unwrap().fetches, 0);
+
+        assert_eq!(plan.tags(), &[t0.clone(), t1.clone()]);
+        assert_eq!(
+            plan.packages(),
+            &BTreeSet::from([addr("0xa0"), addr("0xa1")]),
+        );
+        assert_eq!(
+            plan.datatypes(),
+            &BTreeSet::from([
+                key("0xa0::m::T0"),
+                key("0xa0::m::T1"),
+                key("0xa0::m::E0"),
+                key("0xa1::m::T3"),
+            ]),
+        );
+
+        let layouts = resolver.execute(&plan).await.unwrap();
+        assert_eq!(layouts.len(), 2);
+        assert_eq!(
+            format!("{:#}", layouts[0]),
+            format!("{:#}", resolver.type_layout(t0).await.unwrap()),
+        );
+        assert_eq!(
+            format!("{:#}", layouts[1]),
+            format!("{:#}", resolver.type_layout(t1).await.unwrap()),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_plan_err_type_param_nesting() {
+        let (_, cache) = package_cache([]);
+        let resolver = Resolver::new_with_limits(
+            cache,
+            Limits {
+                max_type_argument_width: 100,
+                max_type_argument_depth: 1,
+                max_type_nodes: 100,
+                max_move_value_depth: 100,
+                max_linkage_entries: 100,
+                max_identifier_len: None,
+            },
+        );
+
+        let err = resolver.plan(type_("vector<vector<u8>>")).unwrap_err();
+        assert!(matches!(err, Error::TypeParamNesting(1, 1)));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_from_original() {
+        let (_, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (2, build_package("a1"), a1_types()),
+        ]);
+
+        let resolver = Resolver::new(cache);
+
+        // A type introduced in the first version of the package...
+        let t0 = resolver
+            .resolve_from_original(addr("0xa0"), "m", "T0")
+            .await
+            .unwrap();
+        assert_eq!(t0.defining_id, addr("0xa0"));
+
+        // ...and one that only exists in the latest version.
+        let t3 = resolver
+            .resolve_from_original(addr("0xa0"), "m", "T3")
+            .await
+            .unwrap();
+        assert_eq!(t3.defining_id, addr("0xa1"));
+
+        let err = resolver
+            .resolve_from_original(addr("0xa0"), "m", "T9")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::DatatypeNotFound(_, _, _)));
+    }
+
+    #[tokio::test]
+    async fn test_verify_package_digest() {
+        let package = move_package("0xa0", serialized_modules(&build_package("a0")), a0_types());
+
+        let legacy = ProtocolConfig::get_for_version(ProtocolVersion::new(6), Chain::Unknown);
+        let latest = ProtocolConfig::get_for_max_version_UNSAFE();
+        assert!(!legacy.package_digest_hash_module());
+        assert!(latest.package_digest_hash_module());
+
+        // Packages only keep what is needed to compute their digest if asked to.
+        let read = Package::read_from_package(&package).unwrap();
+        assert!(read.digests.is_none());
+        let err = read
+            .verify_digest(package.digest(true), &latest)
+            .unwrap_err();
+        assert!(matches!(err, Error::PackageDigestUnavailable(id) if id == addr("0xa0")));
+
+        // Digests are not computed until they are needed.
+        let read = Package::read_with_digests(&package).unwrap();
+        let digests = read.digests.as_ref().unwrap();
+        assert!(digests.whole_modules.get().is_none());
+        assert!(digests.hashed_modules.get().is_none());
+
+        // The digest follows the scheme of the protocol version it is checked against.
+        read.verify_digest(package.digest(false), &legacy).unwrap();
+        assert!(digests.hashed_modules.get().is_none());
+        read.verify_digest(package.digest(true), &latest).unwrap();
+
+        let err = read
+            .verify_digest(package.digest(false), &latest)
+            .unwrap_err();
+        assert!(matches!(err, Error::PackageDigestMismatch(id, _, _) if id == addr("0xa0")));
+
+        let err = read.verify_digest([0; 32], &legacy).unwrap_err();
+        assert!(matches!(err, Error::PackageDigestMismatch(_, _, _)));
+    }
+
+    #[test]
+    fn test_unsupported_bytecode_version() {
+        let mut modules = serialized_modules(&build_package("a0"));
+
+        // Bump the version of one module past the maximum this binary supports.
+        let found = VERSION_MAX + 1;
+        let header = BinaryConstants::MOVE_MAGIC_SIZE..BinaryConstants::MOVE_MAGIC_SIZE + 4;
+        let bytes = modules.get_mut("n").unwrap();
+        bytes[header].copy_from_slice(&BinaryFlavor::encode_version(found).to_le_bytes());
+
+        let package = move_package("0xa0", modules, a0_types());
+        let err = Package::read_from_package(&package).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::UnsupportedBytecodeVersion { found: f, max_supported: VERSION_MAX } if f == found,
+        ));
+
+        // Names are still available from the best-effort summary.
+        let summary = PackageSummary::read_from_package(&package);
+        assert_eq!(summary.storage_id, addr("0xa0"));
+
+        let m = &summary.modules["m"];
+        let n = &summary.modules["n"];
+        assert!(m.bytecode_version.is_some_and(|v| v <= VERSION_MAX));
+        assert_eq!(n.bytecode_version, Some(found));
+        assert_eq!(
+            m.datatypes.iter().map(String::as_str).collect::<Vec<_>>(),
+            vec!["E0", "E1", "E2", "T0", "T1", "T2"],
+        );
+        assert_eq!(
+            n.datatypes.iter().map(String::as_str).collect::<Vec<_>>(),
+            vec!["E0", "T0"],
+        );
+    }
+
+    #[test]
+    fn test_malformed_bytecode() {
+        let mut modules = serialized_modules(&build_package("a0"));
+        modules.get_mut("m").unwrap().truncate(16);
+
+        let package = move_package("0xa0", modules, a0_types());
+        let err = Package::read_from_package(&package).unwrap_err();
+        assert!(matches!(err, Error::Deserialize(_)));
+    }
+
+    #[test]
+    fn test_package_diff() {
+        use diff::{Change, FieldChange as F, VariantChange as V};
+        use OpenSignatureBody as O;
+
+        let a0 = cached_package(1, BTreeMap::new(), &build_package("a0"), &a0_types());
+        let a1 = cached_package(2, BTreeMap::new(), &build_package("a1"), &a1_types());
+
+        assert!(a0.diff(&a0).unwrap().is_empty());
+
+        // Additive upgrade: only new datatypes.
+        let diff = a0.diff(&a1).unwrap();
+        assert!(diff.added_modules.is_empty());
+        assert!(diff.removed_modules.is_empty());
+        assert_eq!(
+            diff.changed_modules["m"].added_datatypes,
+            vec!["E3", "E4", "T3", "T4"],
+        );
+        assert_eq!(diff.changed_modules["n"].added_datatypes, vec!["E1", "T1"]);
+
+        // ...which look like removals in the other direction.
+        let diff = a1.diff(&a0).unwrap();
+        assert_eq!(
+            diff.changed_modules["n"].removed_datatypes,
+            vec!["E1", "T1"]
+        );
+        assert!(diff.changed_modules["n"].added_datatypes.is_empty());
+
+        let h0 = cached_package(1, BTreeMap::new(), &build_package("h0"), &h0_types());
+        let h1 = cached_package(2, BTreeMap::new(), &build_package("h1"), &h1_types());
+        let diff = h0.diff(&h1).unwrap();
+
+        assert_eq!(diff.added_modules, vec!["o"]);
+        assert_eq!(diff.removed_modules, vec!["n"]);
+
+        let m = &diff.changed_modules["m"];
+        assert!(m.added_datatypes.is_empty());
+        assert!(m.removed_datatypes.is_empty());
+        assert_eq!(m.added_functions, vec!["withdraw"]);
+        assert_eq!(m.removed_functions, vec!["helper"]);
+
+        // Field changes
+        assert_eq!(
+            m.changed_datatypes["Pool"].fields,
+            vec![
+                F::Retyped {
+                    name: "balance".to_owned(),
+                    old: O::U64,
+                    new: O::U128,
+                },
+                F::Added {
+                    name: "admin".to_owned(),
+                    type_: O::Address,
+                },
+            ],
+        );
+
+        assert_eq!(
+            m.changed_datatypes["Order"].fields,
+            vec![
+                F::Moved {
+                    name: "price".to_owned(),
+                    old: 0,
+                    new: 1,
+                },
+                F::Moved {
+                    name: "size".to_owned(),
+                    old: 1,
+                    new: 0,
+                },
+            ],
+        );
+
+        let Change { old, new } = m.changed_datatypes["Receipt"].abilities.clone().unwrap();
+        assert!(!old.has_copy() && new.has_copy());
+        assert!(m.changed_datatypes["Receipt"].fields.is_empty());
+
+        // Variant changes
+        assert_eq!(
+            m.changed_datatypes["Status"].variants,
+            vec![
+                V::Changed {
+                    name: "Paused".to_owned(),
+                    fields: vec![F::Added {
+                        name: "reason".to_owned(),
+                        type_: O::U8,
+                    }],
+                },
+                V::Added {
+                    name: "Closed".to_owned(),
+                },
+            ],
+        );
+
+        // Function signature changes
+        let deposit = &m.changed_functions["deposit"];
+        assert_eq!(
+            deposit.visibility,
+            Some(Change {
+                old: Visibility::Public,
+                new: Visibility::Friend,
+            }),
+        );
+        assert!(deposit.parameters.is_none());
+
+        let reset = &m.changed_functions["reset"];
+        assert_eq!(
+            reset.visibility,
+            Some(Change {
+                old: Visibility::Private,
+                new: Visibility::Public,
+            }),
+        );
+        assert!(reset.is_entry.is_none());
+
+        let swap = &m.changed_functions["swap"];
+        let params = swap.parameters.as_ref().unwrap();
+        assert_eq!((params.old.len(), params.new.len()), (2, 3));
+        assert!(swap.visibility.is_none());
+        assert!(swap.return_.is_none());
+    }
+
+    #[test]
+    fn test_module_constants() {
+        use OpenSignatureBody as O;
+
+        let g0 = build_package("g0");
+        let package = cached_package(1, BTreeMap::new(), &g0, &g0_types());
+        let module = package.module("m").unwrap();
+
+        let find = |constants: &[ConstantDef], rendered: &str| {
+            constants
+                .iter()
+                .find(|c| c.rendered.as_deref() == Some(rendered))
+                .cloned()
+                .unwrap_or_else(|| panic!("No constant rendered as {rendered}"))
+        };
+
+        // Without a source map, only error constants are named.
+        let constants = module.constants(None).unwrap();
+        let error = find(&constants, "Sender is not the admin");
+        assert_eq!(error.name.as_deref(), Some("ENotAdmin"));
+        assert_eq!(error.type_, O::Vector(Box::new(O::U8)));
+
+        let fee = find(&constants, "30");
+        assert_eq!(fee.name, None);
+        assert_eq!(fee.type_, O::U64);
+        assert_eq!(fee.bytes, bcs::to_bytes(&30u64).unwrap());
+
+        // With a source map, every declared constant is named.
+        let unit = g0
+            .package
+            .root_compiled_units
+            .iter()
+            .find(|unit| unit.unit.name.as_str() == "m")
+            .unwrap();
+
+        let source = ModuleSource {
+            source_map: unit.unit.source_map.clone(),
+            source_file: None,
+        };
+
+        let constants = module.constants(Some(&source)).unwrap();
+        for (rendered, name) in [
+            ("30", "FEE_BPS"),
+            ("2", "VERSION"),
+            (
+                "0x0000000000000000000000000000000000000000000000000000000000000042",
+                "ADMIN",
+            ),
+            ("Sender is not the admin", "ENotAdmin"),
+        ] {
+            assert_eq!(find(&constants, rendered).name.as_deref(), Some(name));
+        }
+    }
+
+    #[test]
+    fn test_read_lenient() {
+        let mut modules = serialized_modules(&build_package("a0"));
+        modules.get_mut("m").unwrap().truncate(16);
+
+        // The healthy module is still available, and the corrupt one reports why it isn't.
+        let package = move_package("0xa0", modules.clone(), a0_types());
+        let read = Package::read_lenient(&package).unwrap();
+        assert_eq!(read.runtime_id, addr("0xa0"));
+        assert!(read.module("n").is_ok());
+        assert!(matches!(
+            read.module("m").unwrap_err(),
+            Error::Deserialize(_)
+        ));
+        assert_eq!(read.module_errors().keys().collect::<Vec<_>>(), vec!["m"],);
+
+        // Packages read strictly never have module errors.
+        let package = move_package("0xa0", serialized_modules(&build_package("a0")), a0_types());
+        let read = Package::read_from_package(&package).unwrap();
+        assert!(read.module_errors().is_empty());
+
+        // A package with no readable modules still fails, with the first module's error.
+        modules.get_mut("n").unwrap().truncate(16);
+        let package = move_package("0xa0", modules, a0_types());
+        let err = Package::read_lenient(&package).unwrap_err();
+        assert!(matches!(err, Error::Deserialize(_)));
+    }
+
+    #[tokio::test]
+    async fn test_resolver_verify_package_digest() {
+        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
+        let config = ProtocolConfig::get_for_max_version_UNSAFE();
+
+        let expected = cache
+            .fetch(addr("0xa0"))
+            .await
+            .unwrap()
+            .digest(&config)
+            .unwrap();
+        let resolver = Resolver::new(cache);
+
+        resolver
+            .verify_package_digest(addr("0xa0"), expected, &config)
+            .await
+            .unwrap();
+
+        let err = resolver
+            .verify_package_digest(addr("0xa0"), [0; 32], &config)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::PackageDigestMismatch(_, _, _)));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_from_original_unsupported() {
+        struct FetchOnly(PackageStoreWithLruCache<InMemoryPackageStore>);
+
+        #[async_trait]
+        impl PackageStore for FetchOnly {
+            async fn fetch(&self, id: AccountAddress) -> Result<Arc<Package>> {
+                self.0.fetch(id).await
+            }
+        }
+
+        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
+        let resolver = Resolver::new(FetchOnly(cache));
+
+        let err = resolver
+            .resolve_from_original(addr("0xa0"), "m", "T0")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::LatestPackageUnsupported(id) if id == addr("0xa0")));
+    }
+
+    /// Refer to a type, not by its defining ID, but by the ID of some later version of that
+    /// package.  This doesn't currently work during execution but it simplifies making queries: A
+    /// type can be referred to using the ID of any package that declares it, rather than only the
+    /// package that first declared it (whose ID is its defining ID).
+    #[tokio::test]
+    async fn test_upgraded_package_non_defining_id_layout() {
+        let (_, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (2, build_package("a1"), a1_types()),
+        ]);
+        let resolver = Resolver::new(cache);
+
+        let struct_layout = resolver
+            .type_layout(type_("0xa1::m::T1<0xa1::m::T3, 0xa1::m::T0>"))
+            .await
+            .unwrap();
+        let enum_layout = resolver
+            .type_layout(type_("0xa1::m::E1<0xa1::m::E3, 0xa1::m::E0>"))
+            .await
+            .unwrap();
+        insta::assert_snapshot!(fmt(struct_layout, enum_layout));
+    }
+
+    /// A type that refers to a types in a relinked package.  C depends on B and overrides its
+    /// dependency on A from v1 to v2.  The type in C refers to types that were defined in both B, A
+    /// v1, and A v2.
+    #[tokio::test]
+    async fn test_relinking_layout() {
+        let (_, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (2, build_package("a1"), a1_types()),
+            (1, build_package("b0"), b0_types()),
+            (1, build_package("c0"), c0_types()),
+        ]);
+        let resolver = Resolver::new(cache);
+
+        let struct_layout = resolver.type_layout(type_("0xc0::m::T0")).await.unwrap();
+        let enum_layout = resolver.type_layout(type_("0xc0::m::E0")).await.unwrap();
+        insta::assert_snapshot!(fmt(struct_layout, enum_layout));
+    }
+
+    #[tokio::test]
+    async fn test_value_nesting_boundary_layout() {
+        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
+
+        let resolver = Resolver::new_with_limits(
+            cache,
+            Limits {
+                max_type_argument_width: 100,
+                max_type_argument_depth: 100,
+                max_type_nodes: 100,
+                max_move_value_depth: 3,
+                max_linkage_entries: 100,
+                max_identifier_len: None,
+            },
+        );
+
+        // The layout of this type is fine, because it is *just* at the correct depth.
+        let struct_layout = resolver
+            .type_layout(type_("0xa0::m::T1<u8, u8>"))
+            .await
+            .unwrap();
+        let enum_layout = resolver
+            .type_layout(type_("0xa0::m::E1<u8, u8>"))
+            .await
+            .unwrap();
+        insta::assert_snapshot!(fmt(struct_layout, enum_layout));
+    }
+
+    #[tokio::test]
+    async fn test_err_value_nesting_simple_layout() {
+        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
+
+        let resolver = Resolver::new_with_limits(
+            cache,
+            Limits {
+                max_type_argument_width: 100,
+                max_type_argument_depth: 100,
+                max_type_nodes: 100,
+                max_move_value_depth: 2,
+                max_linkage_entries: 100,
+                max_identifier_len: None,
+            },
+        );
+
+        // The depth limit is now too low, so this will fail.
+        let struct_err = resolver
+            .type_layout(type_("0xa0::m::T1<u8, u8>"))
+            .await
+            .unwrap_err();
+        let enum_err = resolver
+            .type_layout(type_("0xa0::m::E1<u8, u8>"))
+            .await
+            .unwrap_err();
+        assert!(matches!(struct_err, Error::ValueNesting(2)));
+        assert!(matches!(enum_err, Error::ValueNesting(2)));
+    }
+
+    #[tokio::test]
+    async fn test_err_value_nesting_big_type_param_layout() {
+        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
+
+        let resolver = Resolver::new_with_limits(
+            cache,
+            Limits {
+                max_type_argument_width: 100,
+                max_type_argument_depth: 100,
+                max_type_nodes: 100,
+                max_move_value_depth: 3,
+                max_linkage_entries: 100,
+                max_identifier_len: None,
+            },
+        );
+
+        // This layout calculation will fail early because we know that the type parameter we're
+        // calculating will eventually contribute to a layout that exceeds the max depth.
+        let struct_err = resolver
+            .type_layout(type_("0xa0::m::T1<vector<vector<u8>>, u8>"))
+            .await
+            .unwrap_err();
+        let enum_err = resolver
+            .type_layout(type_("0xa0::m::E1<vector<vector<u8>>, u8>"))
+            .await
+            .unwrap_err();
+        assert!(matches!(struct_err, Error::ValueNesting(3)));
+        assert!(matches!(enum_err, Error::ValueNesting(3)));
+    }
+
+    #[tokio::test]
+    async fn test_err_value_nesting_big_phantom_type_param_layout() {
+        let (_, cache) = package_cache([
+            (1, build_package("sui"), sui_types()),
+            (1, build_package("d0"), d0_types()),
+        ]);
+
+        let resolver = Resolver::new_with_limits(
+            cache,
+            Limits {
+                max_type_argument_width: 100,
+                max_type_argument_depth: 100,
+                max_type_nodes: 100,
+                max_move_value_depth: 3,
+                max_linkage_entries: 100,
+                max_identifier_len: None,
+            },
+        );
+
+        // Check that this layout request would succeed.
+        let _ = resolver
+            .type_layout(type_("0xd0::m::O<u8, u8>"))
+            .await
+            .unwrap();
+        let _ = resolver
+            .type_layout(type_("0xd0::m::EO<u8, u8>"))
+            .await
+            .unwrap();
+
+        // But this one fails, even though the big layout is for a phantom type parameter. This may
+        // change in future if we optimise the way we handle phantom type parameters to not
+        // calculate their full layout, just their type tag.
+        let struct_err = resolver
+            .type_layout(type_("0xd0::m::O<u8, vector<vector<u8>>>"))
+            .await
+            .unwrap_err();
+        let enum_err = resolver
+            .type_layout(type_("0xd0::m::EO<u8, vector<vector<u8>>>"))
+            .await
+            .unwrap_err();
+        assert!(matches!(struct_err, Error::ValueNesting(3)));
+        assert!(matches!(enum_err, Error::ValueNesting(3)));
+    }
+
+    #[test]
+    fn test_err_identifier_too_long() {
+        let limits = Limits {
+            max_type_argument_width: 100,
+            max_type_argument_depth: 100,
+            max_type_nodes: 100,
+            max_move_value_depth: 100,
+            max_linkage_entries: 100,
+            max_identifier_len: Some(4),
+        };
+
+        let input = TypeInput::from(type_("vector<0x2::coin::Coin<0x2::sui::SUI>>"));
+        as_type_tag_with_limits(&input, Some(&limits)).unwrap();
+
+        // The limit applies to identifiers in type parameters as well.
+        let input = TypeInput::from(type_("0x2::coin::Coin<0x2::balance::Balance<u64>>"));
+        let err = as_type_tag_with_limits(&input, Some(&limits)).unwrap_err();
+        assert!(matches!(err, Error::IdentifierTooLong(s, 4) if s == "balance"));
+
+        // Without a limit, the same input is accepted.
+        as_type_tag(&input).unwrap();
+
+        let config = ProtocolConfig::get_for_max_version_UNSAFE();
+        assert_eq!(
+            Limits::max_identifier_len_for(&config),
+            Some(config.max_move_identifier_len() as usize),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_err_too_many_linkage_entries() {
+        let (_, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (1, build_package("b0"), b0_types()),
+        ]);
+
+        let limits = |max_linkage_entries| Limits {
+            max_type_argument_width: 100,
+            max_type_argument_depth: 100,
+            max_type_nodes: 100,
+            max_move_value_depth: 100,
+            max_linkage_entries,
+            max_identifier_len: None,
+        };
+
+        // `b0` has a single dependency, so one linkage entry suffices.
+        let store: Arc<dyn PackageStore> = Arc::new(cache);
+        let resolver = Resolver::new_with_limits(store.clone(), limits(1));
+        let _ = resolver.type_layout(type_("0xb0::m::T0")).await.unwrap();
+
+        let resolver = Resolver::new_with_limits(store, limits(0));
+        let err = resolver
+            .type_layout(type_("0xb0::m::T0"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::TooManyLinkageEntries(0, 1)));
+    }
+
+    #[tokio::test]
+    async fn test_err_linkage_cycle() {
+        let (inner, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (1, build_package("b0"), b0_types()),
+        ]);
+
+        // Point `b0`'s dependency on `a` back at `b0` itself.
+        inner
+            .write()
+            .unwrap()
+            .packages
+            .get_mut(&addr("0xb0"))
+            .unwrap()
+            .linkage
+            .insert(addr("0xa0"), addr("0xb0"));
+
+        let resolver = Resolver::new(cache);
+        let err = resolver
+            .type_layout(type_("0xb0::m::T0"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::LinkageCycle(a, b) if a == addr("0xa0") && b == addr("0xb0")));
+    }
+
+    #[tokio::test]
+    async fn test_err_linkage_mismatch() {
+        let (inner, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (2, build_package("a1"), a1_types()),
+            (1, build_package("b0"), b0_types()),
+            (1, build_package("c0"), c0_types()),
+        ]);
+
+        // Point `b0`'s dependency on `a` at an unrelated package.
+        inner
+            .write()
+            .unwrap()
+            .packages
+            .get_mut(&addr("0xb0"))
+            .unwrap()
+            .linkage
+            .insert(addr("0xa0"), addr("0xc0"));
+
+        let resolver = Resolver::new(cache);
+        let err = resolver
+            .type_layout(type_("0xb0::m::T0"))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::LinkageMismatch(r, s, a)
+                if r == addr("0xa0") && s == addr("0xc0") && a == addr("0xc0")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_err_value_nesting_type_param_application_layout() {
+        let (_, cache) = package_cache([
+            (1, build_package("sui"), sui_types()),
+            (1, build_package("d0"), d0_types()),
+        ]);
+
+        let resolver = Resolver::new_with_limits(
+            cache,
+            Limits {
+                max_type_argument_width: 100,
+                max_type_argument_depth: 100,
+                max_type_nodes: 100,
+                max_move_value_depth: 3,
+                max_linkage_entries: 100,
+                max_identifier_len: None,
+            },
+        );
+
+        // Make sure that even if all type parameters individually meet the depth requirements,
+        // that we correctly fail if they extend the layout's depth on application.
+        let struct_err = resolver
+            .type_layout(type_("0xd0::m::O<vector<u8>, u8>"))
+            .await
+            .unwrap_err();
+        let enum_err = resolver
+            .type_layout(type_("0xd0::m::EO<vector<u8>, u8>"))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(struct_err, Error::ValueNesting(3)));
+        assert!(matches!(enum_err, Error::ValueNesting(3)));
+    }
+
+    #[tokio::test]
+    async fn test_system_package_invalidation() {
+        let (inner, cache) = package_cache([(1, build_package("s0"), s0_types())]);
+        let resolver = Resolver::new(cache);
+
+        let struct_not_found = resolver.type_layout(type_("0x1::m::T1")).await.unwrap_err();
+        let enum_not_found = resolver.type_layout(type_("0x1::m::E1")).await.unwrap_err();
+        assert!(matches!(struct_not_found, Error::DatatypeNotFound(_, _, _)));
+        assert!(matches!(enum_not_found, Error::DatatypeNotFound(_, _, _)));
+
+        // Add a new version of the system package into the store underlying the cache.
+        inner.write().unwrap().replace(
+            addr("0x1"),
+            cached_package(2, BTreeMap::new(), &build_package("s1"), &s1_types()),
+        );
+
+        // Evict the package from the cache
+        resolver.package_store().evict([addr("0x1")]);
+
+        let struct_layout = resolver.type_layout(type_("0x1::m::T1")).await.unwrap();
+        let enum_layout = resolver.type_layout(type_("0x1::m::E1")).await.unwrap();
+        insta::assert_snapshot!(fmt(struct_layout, enum_layout));
+    }
+
+    #[tokio::test]
+    async fn test_package_watcher() {
+        use sui_types::digests::{ObjectDigest, TransactionDigest};
+        use sui_types::execution_status::ExecutionStatus;
+        use sui_types::gas::GasCostSummary;
+
+        let (inner, cache) = package_cache([(1, build_package("s0"), s0_types())]);
+        let cache = Arc::new(cache);
+        let resolver = Resolver::new(cache.clone() as Arc<dyn PackageStore>);
 
-        match sig {
-            O::Address | O::Bool | O::U8 | O::U16 | O::U32 | O::U64 | O::U128 | O::U256 => {
-                /* nop */
-            }
+        let not_found = resolver.type_layout(type_("0x1::m::T1")).await.unwrap_err();
+        assert!(matches!(not_found, Error::DatatypeNotFound(_, _, _)));
 
-            O::TypeParameter(_) => { /* nop */ }
+        // Upgrade the system package in the store underlying the cache.
+        inner.write().unwrap().replace(
+            addr("0x1"),
+            cached_package(2, BTreeMap::new(), &build_package("s1"), &s1_types()),
+        );
 
-            O::Vector(sig) => self.relocate_signature(sig.as_mut())?,
+        let effects = |mutated| {
+            TransactionEffects::new_from_execution_v1(
+                ExecutionStatus::Success,
+                0,
+                GasCostSummary::default(),
+                vec![],
+                vec![],
+                TransactionDigest::ZERO,
+                vec![],
+                mutated,
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                (random_object_ref(), Owner::AddressOwner(SuiAddress::ZERO)),
+                None,
+                vec![],
+            )
+        };
 
-            O::Datatype(key, params) => {
-                // SAFETY: `add_signature` ensures `datatypes` has an element with this key.
-                let defining_id = &self.datatypes[key].defining_id;
-                for param in params {
-                    self.relocate_signature(param)?;
-                }
+        let system_package = (
+            ObjectID::from(addr("0x1")),
+            SequenceNumber::from_u64(2),
+            ObjectDigest::MIN,
+        );
 
-                key.package = *defining_id;
-            }
-        }
+        // Objects that aren't packages are not evicted, so the stale package is still served.
+        let stream = stream::iter([
+            effects(vec![]),
+            effects(vec![(
+                system_package,
+                Owner::AddressOwner(SuiAddress::ZERO),
+            )]),
+        ]);
+        PackageWatcher::new(cache.clone()).run(stream).await;
+        assert!(resolver.type_layout(type_("0x1::m::T1")).await.is_err());
 
-        Ok(())
+        PackageWatcher::new(cache)
+            .process_effects(&effects(vec![(system_package, Owner::Immutable)]));
+        let layout = resolver.type_layout(type_("0x1::m::T1")).await.unwrap();
+        assert!(matches!(layout, MoveTypeLayout::Struct(_)));
     }
-}
 
-impl<'s> From<&'s StructTag> for DatatypeRef<'s, 's> {
-    fn from(tag: &'s StructTag) -> Self {
-        DatatypeRef {
-            package: tag.address,
-            module: tag.module.as_str().into(),
-            name: tag.name.as_str().into(),
-        }
-    }
-}
+    #[tokio::test]
+    async fn test_caching() {
+        let (inner, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (1, build_package("s0"), s0_types()),
+        ]);
+        let resolver = Resolver::new(cache);
 
-/// Translate a string into an `Identifier`, but translating errors into this module's error type.
-fn ident(s: &str) -> Result<Identifier> {
-    Identifier::new(s).map_err(|_| Error::NotAnIdentifier(s.to_string()))
-}
+        assert_eq!(inner.read().unwrap().fetches, 0);
+        let l0 = resolver.type_layout(type_("0xa0::m::T0")).await.unwrap();
 
-pub fn as_type_tag(type_input: &TypeInput) -> Result<TypeTag> {
-    use TypeInput as I;
-    use TypeTag as T;
-    Ok(match type_input {
-        I::Bool => T::Bool,
-        I::U8 => T::U8,
-        I::U16 => T::U16,
-        I::U32 => T::U32,
-        I::U64 => T::U64,
-        I::U128 => T::U128,
-        I::U256 => T::U256,
-        I::Address => T::Address,
-        I::Signer => T::Signer,
-        I::Vector(t) => T::Vector(Box::new(as_type_tag(t)?)),
-        I::Struct(s) => {
-            let StructInput {
-                address,
-                module,
-                name,
-                type_params,
-            } = s.as_ref();
-            let type_params = type_params.iter().map(as_type_tag).collect::<Result<_>>()?;
-            T::Struct(Box::new(StructTag {
-                address: *address,
-                module: ident(module)?,
-                name: ident(name)?,
-                type_params,
-            }))
-        }
-    })
-}
+        // Load A0.
+        assert_eq!(inner.read().unwrap().fetches, 1);
 
-/// Read and deserialize a signature index (from function parameter or return types) into a vector
-/// of signatures.
-fn read_signature(idx: SignatureIndex, bytecode: &CompiledModule) -> Result<Vec<OpenSignature>> {
-    let MoveSignature(tokens) = bytecode.signature_at(idx);
-    let mut sigs = Vec::with_capacity(tokens.len());
+        // Layouts are the same, no need to reload the package.
+        let l1 = resolver.type_layout(type_("0xa0::m::T0")).await.unwrap();
+        assert_eq!(format!("{l0}"), format!("{l1}"));
+        assert_eq!(inner.read().unwrap().fetches, 1);
 
-    for token in tokens {
-        sigs.push(OpenSignature::read(token, bytecode)?);
-    }
+        // Different type, but same package, so no extra fetch.
+        let l2 = resolver.type_layout(type_("0xa0::m::T2")).await.unwrap();
+        assert_ne!(format!("{l0}"), format!("{l2}"));
+        assert_eq!(inner.read().unwrap().fetches, 1);
 
-    Ok(sigs)
-}
+        // Enum types won't trigger a fetch either.
+        resolver.type_layout(type_("0xa0::m::E0")).await.unwrap();
+        assert_eq!(inner.read().unwrap().fetches, 1);
 
-#[cfg(test)]
-mod tests {
-    use async_trait::async_trait;
-    use move_binary_format::file_format::Ability;
-    use move_core_types::ident_str;
-    use std::sync::Arc;
-    use std::{path::PathBuf, str::FromStr, sync::RwLock};
-    use sui_types::base_types::random_object_ref;
-    use sui_types::transaction::ObjectArg;
+        // New package to load.
+        let l3 = resolver.type_layout(type_("0x1::m::T0")).await.unwrap();
+        assert_eq!(inner.read().unwrap().fetches, 2);
 
-    use move_compiler::compiled_unit::NamedCompiledModule;
-    use sui_move_build::{BuildConfig, CompiledPackage};
+        // Reload the same system package type, it gets fetched from cache
+        let l4 = resolver.type_layout(type_("0x1::m::T0")).await.unwrap();
+        assert_eq!(format!("{l3}"), format!("{l4}"));
+        assert_eq!(inner.read().unwrap().fetches, 2);
 
-    use super::*;
+        // Reload a same system package type (enum), which will cause a version check.
+        let el4 = resolver.type_layout(type_("0x1::m::E0")).await.unwrap();
+        assert_ne!(format!("{el4}"), format!("{l4}"));
+        assert_eq!(inner.read().unwrap().fetches, 2);
 
-    fn fmt(struct_layout: MoveTypeLayout, enum_layout: MoveTypeLayout) -> String {
-        format!("struct:\n{struct_layout:#}\n\nenum:\n{enum_layout:#}",)
-    }
+        // Upgrade the system package
+        inner.write().unwrap().replace(
+            addr("0x1"),
+            cached_package(2, BTreeMap::new(), &build_package("s1"), &s1_types()),
+        );
 
-    #[tokio::test]
-    async fn test_simple_canonical_type() {
-        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
-        let package_resolver = Resolver::new(cache);
+        // Evict the package from the cache
+        resolver.package_store().evict([addr("0x1")]);
 
-        let input = type_("0xa0::m::T0");
-        let expect = input.clone();
-        let actual = package_resolver.canonical_type(input).await.unwrap();
-        assert_eq!(expect, actual);
+        // Reload the system system type again. It will be refetched (even though the type is the
+        // same as before). This usage pattern (layouts for system types) is why a layout cache
+        // would be particularly helpful (future optimisation).
+        let l5 = resolver.type_layout(type_("0x1::m::T0")).await.unwrap();
+        assert_eq!(format!("{l4}"), format!("{l5}"));
+        assert_eq!(inner.read().unwrap().fetches, 3);
     }
 
     #[tokio::test]
-    async fn test_upgraded_canonical_type() {
-        let (_, cache) = package_cache([
+    async fn test_at_version() {
+        let (inner, cache) = package_cache([
             (1, build_package("a0"), a0_types()),
             (2, build_package("a1"), a1_types()),
+            (1, build_package("s0"), s0_types()),
         ]);
 
-        let package_resolver = Resolver::new(cache);
+        // Upgrade the system package, so the store only has its second version.
+        inner.write().unwrap().replace(
+            addr("0x1"),
+            cached_package(2, BTreeMap::new(), &build_package("s1"), &s1_types()),
+        );
 
-        let input = type_("0xa1::m::T3");
-        let expect = input.clone();
-        let actual = package_resolver.canonical_type(input).await.unwrap();
-        assert_eq!(expect, actual);
+        let v1 = SequenceNumber::from_u64(1);
+        let v2 = SequenceNumber::from_u64(2);
+
+        // The store cannot serve the system package as of its first version...
+        let historical = Resolver::new(cache).at_version(v1);
+        let err = historical
+            .type_layout(type_("0x1::m::T0"))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::PackageVersionUnavailable(id, v) if id == addr("0x1") && v == v1)
+        );
+
+        // ...but the version bound does not apply to packages that are not system packages, which
+        // have their own versions.
+        let store = historical.package_store();
+        assert_eq!(store.fetch(addr("0xa1")).await.unwrap().version, v2);
+
+        // When the latest version is no newer than the version requested, it is served from the
+        // cache.
+        let cache = PackageStoreAtVersion::new(
+            PackageStoreWithLruCache::new(InMemoryPackageStore {
+                inner: inner.clone(),
+            }),
+            v2,
+        );
+        cache.fetch(addr("0x1")).await.unwrap();
+        let fetches = inner.read().unwrap().fetches;
+        cache.fetch(addr("0x1")).await.unwrap();
+        assert_eq!(inner.read().unwrap().fetches, fetches);
     }
 
     #[tokio::test]
-    async fn test_latest_canonical_type() {
-        let (_, cache) = package_cache([
-            (1, build_package("a0"), a0_types()),
-            (2, build_package("a1"), a1_types()),
-        ]);
+    async fn test_max_serialized_size() {
+        use SerializedSize as S;
 
-        let package_resolver = Resolver::new(cache);
+        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
+        let resolver = Resolver::new(cache);
 
-        let input = type_("0xa1::m::T0");
-        let expect = type_("0xa0::m::T0");
-        let actual = package_resolver.canonical_type(input).await.unwrap();
-        assert_eq!(expect, actual);
+        let resolver = &resolver;
+        let size =
+            |t: &'static str| async move { resolver.max_serialized_size(type_(t)).await.unwrap() };
+
+        assert_eq!(size("u64").await, S::Bounded(8));
+        assert_eq!(size("0xa0::m::T2").await, S::Bounded(1));
+        assert_eq!(size("0xa0::m::E2").await, S::Bounded(2));
+
+        // T0 { b: bool, v: vector<T1<T2, u128>> }
+        // T1<T2, u128> { a: address, p: T2, q: vector<u128> }
+        assert_eq!(
+            size("0xa0::m::T0").await,
+            S::Unbounded {
+                fixed: 1 + 5,
+                vectors: vec![S::Unbounded {
+                    fixed: 32 + 1 + 5,
+                    vectors: vec![S::Bounded(16)],
+                }],
+            },
+        );
     }
 
     #[tokio::test]
-    async fn test_type_param_canonical_type() {
-        let (_, cache) = package_cache([
-            (1, build_package("a0"), a0_types()),
-            (2, build_package("a1"), a1_types()),
-        ]);
+    async fn test_retry_missing_package() {
+        let (inner, _) = package_cache([(1, build_package("a0"), a0_types())]);
+        let policy = RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+            not_found_is_pending: false,
+        };
 
-        let package_resolver = Resolver::new(cache);
+        // Packages that are available are returned straight away.
+        let store = PackageStoreWithRetry::new(
+            InMemoryPackageStore {
+                inner: inner.clone(),
+            },
+            policy.clone(),
+        );
+        store.fetch(addr("0xa0")).await.unwrap();
+        assert_eq!(inner.read().unwrap().fetches, 1);
 
-        let input = type_("0xa1::m::T1<0xa1::m::T0, 0xa1::m::T3>");
-        let expect = type_("0xa0::m::T1<0xa0::m::T0, 0xa1::m::T3>");
-        let actual = package_resolver.canonical_type(input).await.unwrap();
-        assert_eq!(expect, actual);
+        // Packages that were not found are not retried by default.
+        let err = store.fetch(addr("0x42")).await.unwrap_err();
+        assert!(matches!(err, Error::PackageNotFound(_)));
+        assert_eq!(inner.read().unwrap().fetches, 2);
+
+        // Unless they could still become available, in which case they are retried, and then
+        // reported as pending.
+        let store = PackageStoreWithRetry::new(
+            InMemoryPackageStore {
+                inner: inner.clone(),
+            },
+            RetryPolicy {
+                not_found_is_pending: true,
+                ..policy
+            },
+        );
+        let err = store.fetch(addr("0x42")).await.unwrap_err();
+        assert!(matches!(err, Error::PackagePending(_)));
+        assert_eq!(inner.read().unwrap().fetches, 5);
     }
 
     #[tokio::test]
-    async fn test_canonical_err_package_too_old() {
-        let (_, cache) = package_cache([
-            (1, build_package("a0"), a0_types()),
-            (2, build_package("a1"), a1_types()),
-        ]);
+    async fn test_retryable_store_errors() {
+        /// Fails the first `failures` fetches with a store error.
+        struct FlakyStore {
+            inner: InMemoryPackageStore,
+            failures: Mutex<usize>,
+            retryable: bool,
+        }
 
-        let package_resolver = Resolver::new(cache);
+        #[async_trait]
+        impl PackageStore for FlakyStore {
+            async fn fetch(&self, id: AccountAddress) -> Result<Arc<Package>> {
+                let fail = {
+                    let mut failures = self.failures.lock().unwrap();
+                    let fail = *failures > 0;
+                    *failures = failures.saturating_sub(1);
+                    fail
+                };
 
-        let input = type_("0xa0::m::T3");
-        let err = package_resolver.canonical_type(input).await.unwrap_err();
-        assert!(matches!(err, Error::DatatypeNotFound(_, _, _)));
-    }
+                if !fail {
+                    return self.inner.fetch(id).await;
+                }
 
-    #[tokio::test]
-    async fn test_canonical_err_signer() {
-        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
+                Err(Error::Store {
+                    store: "flaky",
+                    error: "connection reset".to_string(),
+                    retryable: self.retryable,
+                })
+            }
+        }
 
-        let package_resolver = Resolver::new(cache);
+        let (inner, _) = package_cache([(1, build_package("a0"), a0_types())]);
+        let flaky = |failures, retryable| FlakyStore {
+            inner: InMemoryPackageStore {
+                inner: inner.clone(),
+            },
+            failures: Mutex::new(failures),
+            retryable,
+        };
 
-        let input = type_("0xa0::m::T1<0xa0::m::T0, signer>");
-        let err = package_resolver.canonical_type(input).await.unwrap_err();
-        assert!(matches!(err, Error::UnexpectedSigner));
-    }
+        let policy = RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+            not_found_is_pending: false,
+        };
 
-    /// Layout for a type that only refers to base types or other types in the same module.
-    #[tokio::test]
-    async fn test_simple_type_layout() {
-        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
-        let package_resolver = Resolver::new(cache);
-        let struct_layout = package_resolver
-            .type_layout(type_("0xa0::m::T0"))
-            .await
-            .unwrap();
-        let enum_layout = package_resolver
-            .type_layout(type_("0xa0::m::E0"))
-            .await
-            .unwrap();
-        insta::assert_snapshot!(fmt(struct_layout, enum_layout));
+        // Transient failures are retried.
+        let store = PackageStoreWithRetry::new(flaky(2, true), policy.clone());
+        store.fetch(addr("0xa0")).await.unwrap();
+
+        // ...until retries are exhausted.
+        let store = PackageStoreWithRetry::new(flaky(3, true), policy.clone());
+        let err = store.fetch(addr("0xa0")).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Store {
+                retryable: true,
+                ..
+            }
+        ));
+
+        // Other failures are not retried.
+        let store = PackageStoreWithRetry::new(flaky(1, false), policy);
+        let err = store.fetch(addr("0xa0")).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Store {
+                retryable: false,
+                ..
+            }
+        ));
+
+        // Failures are not cached.
+        let fetches = inner.read().unwrap().fetches;
+        let store = PackageStoreWithLruCache::new(flaky(1, true));
+        store.fetch(addr("0xa0")).await.unwrap_err();
+        store.fetch(addr("0xa0")).await.unwrap();
+        store.fetch(addr("0xa0")).await.unwrap();
+        assert_eq!(inner.read().unwrap().fetches, fetches + 1);
     }
 
-    /// A type that refers to types from other modules in the same package.
     #[tokio::test]
-    async fn test_cross_module_layout() {
+    async fn test_layout_err_not_a_package() {
         let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
         let resolver = Resolver::new(cache);
-        let struct_layout = resolver.type_layout(type_("0xa0::n::T0")).await.unwrap();
-        let enum_layout = resolver.type_layout(type_("0xa0::n::E0")).await.unwrap();
-        insta::assert_snapshot!(fmt(struct_layout, enum_layout));
+        let err = resolver
+            .type_layout(type_("0x42::m::T0"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::PackageNotFound(_)));
     }
 
-    /// A type that refers to types a different package.
     #[tokio::test]
-    async fn test_cross_package_layout() {
-        let (_, cache) = package_cache([
-            (1, build_package("a0"), a0_types()),
-            (1, build_package("b0"), b0_types()),
-        ]);
+    async fn test_layout_err_no_module() {
+        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
         let resolver = Resolver::new(cache);
-
-        let struct_layout = resolver.type_layout(type_("0xb0::m::T0")).await.unwrap();
-        let enum_layout = resolver.type_layout(type_("0xb0::m::E0")).await.unwrap();
-        insta::assert_snapshot!(fmt(struct_layout, enum_layout));
+        let err = resolver
+            .type_layout(type_("0xa0::l::T0"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ModuleNotFound(_, _)));
     }
 
-    /// A type from an upgraded package, mixing structs defined in the original package and the
-    /// upgraded package.
     #[tokio::test]
-    async fn test_upgraded_package_layout() {
-        let (_, cache) = package_cache([
-            (1, build_package("a0"), a0_types()),
-            (2, build_package("a1"), a1_types()),
-        ]);
+    async fn test_layout_err_no_struct() {
+        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
         let resolver = Resolver::new(cache);
 
-        let struct_layout = resolver.type_layout(type_("0xa1::n::T1")).await.unwrap();
-        let enum_layout = resolver.type_layout(type_("0xa1::n::E1")).await.unwrap();
-        insta::assert_snapshot!(fmt(struct_layout, enum_layout));
+        let err = resolver
+            .type_layout(type_("0xa0::m::T9"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::DatatypeNotFound(_, _, _)));
     }
 
-    /// A generic type instantiation where the type parameters are resolved relative to linkage
-    /// contexts from different versions of the same package.
     #[tokio::test]
-    async fn test_multiple_linkage_contexts_layout() {
-        let (_, cache) = package_cache([
-            (1, build_package("a0"), a0_types()),
-            (2, build_package("a1"), a1_types()),
-        ]);
+    async fn test_layout_err_type_arity() {
+        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
         let resolver = Resolver::new(cache);
 
-        let struct_layout = resolver
-            .type_layout(type_("0xa0::m::T1<0xa0::m::T0, 0xa1::m::T3>"))
+        // Too few
+        let err = resolver
+            .type_layout(type_("0xa0::m::T1<u8>"))
             .await
-            .unwrap();
-        let enum_layout = resolver
-            .type_layout(type_("0xa0::m::E1<0xa0::m::E0, 0xa1::m::E3>"))
+            .unwrap_err();
+        assert!(matches!(err, Error::TypeArityMismatch(2, 1)));
+
+        // Too many
+        let err = resolver
+            .type_layout(type_("0xa0::m::T1<u8, u16, u32>"))
             .await
-            .unwrap();
-        insta::assert_snapshot!(fmt(struct_layout, enum_layout));
+            .unwrap_err();
+        assert!(matches!(err, Error::TypeArityMismatch(2, 3)));
     }
 
-    /// Refer to a type, not by its defining ID, but by the ID of some later version of that
-    /// package.  This doesn't currently work during execution but it simplifies making queries: A
-    /// type can be referred to using the ID of any package that declares it, rather than only the
-    /// package that first declared it (whose ID is its defining ID).
     #[tokio::test]
-    async fn test_upgraded_package_non_defining_id_layout() {
-        let (_, cache) = package_cache([
-            (1, build_package("a0"), a0_types()),
-            (2, build_package("a1"), a1_types()),
-        ]);
-        let resolver = Resolver::new(cache);
+    async fn test_structs() {
+        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
+        let a0 = cache.fetch(addr("0xa0")).await.unwrap();
+        let m = a0.module("m").unwrap();
 
-        let struct_layout = resolver
-            .type_layout(type_("0xa1::m::T1<0xa1::m::T3, 0xa1::m::T0>"))
-            .await
-            .unwrap();
-        let enum_layout = resolver
-            .type_layout(type_("0xa1::m::E1<0xa1::m::E3, 0xa1::m::E0>"))
+        assert_eq!(
+            m.structs(None, None).collect::<Vec<_>>(),
+            vec!["T0", "T1", "T2"],
+        );
+
+        assert_eq!(m.structs(None, Some("T1")).collect::<Vec<_>>(), vec!["T0"],);
+
+        assert_eq!(
+            m.structs(Some("T0"), Some("T2")).collect::<Vec<_>>(),
+            vec!["T1"],
+        );
+
+        assert_eq!(m.structs(Some("T1"), None).collect::<Vec<_>>(), vec!["T2"],);
+
+        let t0 = m.struct_def("T0").unwrap().unwrap();
+        let t1 = m.struct_def("T1").unwrap().unwrap();
+        let t2 = m.struct_def("T2").unwrap().unwrap();
+
+        insta::assert_snapshot!(format!(
+            "a0::m::T0: {t0:#?}\n\
+             a0::m::T1: {t1:#?}\n\
+             a0::m::T2: {t2:#?}",
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_enums() {
+        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
+        let a0 = cache
+            .fetch(AccountAddress::from_str("0xa0").unwrap())
             .await
             .unwrap();
-        insta::assert_snapshot!(fmt(struct_layout, enum_layout));
+        let m = a0.module("m").unwrap();
+
+        assert_eq!(
+            m.enums(None, None).collect::<Vec<_>>(),
+            vec!["E0", "E1", "E2"],
+        );
+
+        assert_eq!(m.enums(None, Some("E1")).collect::<Vec<_>>(), vec!["E0"],);
+
+        assert_eq!(
+            m.enums(Some("E0"), Some("E2")).collect::<Vec<_>>(),
+            vec!["E1"],
+        );
+
+        assert_eq!(m.enums(Some("E1"), None).collect::<Vec<_>>(), vec!["E2"],);
+
+        let e0 = m.enum_def("E0").unwrap().unwrap();
+        let e1 = m.enum_def("E1").unwrap().unwrap();
+        let e2 = m.enum_def("E2").unwrap().unwrap();
+
+        insta::assert_snapshot!(format!(
+            "a0::m::E0: {e0:#?}\n\
+             a0::m::E1: {e1:#?}\n\
+             a0::m::E2: {e2:#?}",
+        ));
     }
 
-    /// A type that refers to a types in a relinked package.  C depends on B and overrides its
-    /// dependency on A from v1 to v2.  The type in C refers to types that were defined in both B, A
-    /// v1, and A v2.
     #[tokio::test]
-    async fn test_relinking_layout() {
+    async fn test_functions() {
         let (_, cache) = package_cache([
             (1, build_package("a0"), a0_types()),
             (2, build_package("a1"), a1_types()),
             (1, build_package("b0"), b0_types()),
             (1, build_package("c0"), c0_types()),
         ]);
-        let resolver = Resolver::new(cache);
 
-        let struct_layout = resolver.type_layout(type_("0xc0::m::T0")).await.unwrap();
-        let enum_layout = resolver.type_layout(type_("0xc0::m::E0")).await.unwrap();
-        insta::assert_snapshot!(fmt(struct_layout, enum_layout));
-    }
-
-    #[tokio::test]
-    async fn test_value_nesting_boundary_layout() {
-        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
+        let c0 = cache.fetch(addr("0xc0")).await.unwrap();
+        let m = c0.module("m").unwrap();
 
-        let resolver = Resolver::new_with_limits(
-            cache,
-            Limits {
-                max_type_argument_width: 100,
-                max_type_argument_depth: 100,
-                max_type_nodes: 100,
-                max_move_value_depth: 3,
-            },
+        assert_eq!(
+            m.functions(None, None).collect::<Vec<_>>(),
+            vec!["bar", "baz", "foo"],
         );
 
-        // The layout of this type is fine, because it is *just* at the correct depth.
-        let struct_layout = resolver
-            .type_layout(type_("0xa0::m::T1<u8, u8>"))
-            .await
-            .unwrap();
-        let enum_layout = resolver
-            .type_layout(type_("0xa0::m::E1<u8, u8>"))
-            .await
-            .unwrap();
-        insta::assert_snapshot!(fmt(struct_layout, enum_layout));
-    }
+        assert_eq!(
+            m.functions(None, Some("baz")).collect::<Vec<_>>(),
+            vec!["bar"],
+        );
 
-    #[tokio::test]
-    async fn test_err_value_nesting_simple_layout() {
-        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
+        assert_eq!(
+            m.functions(Some("bar"), Some("foo")).collect::<Vec<_>>(),
+            vec!["baz"],
+        );
 
-        let resolver = Resolver::new_with_limits(
-            cache,
-            Limits {
-                max_type_argument_width: 100,
-                max_type_argument_depth: 100,
-                max_type_nodes: 100,
-                max_move_value_depth: 2,
-            },
+        assert_eq!(
+            m.functions(Some("baz"), None).collect::<Vec<_>>(),
+            vec!["foo"],
         );
 
-        // The depth limit is now too low, so this will fail.
-        let struct_err = resolver
-            .type_layout(type_("0xa0::m::T1<u8, u8>"))
-            .await
-            .unwrap_err();
-        let enum_err = resolver
-            .type_layout(type_("0xa0::m::E1<u8, u8>"))
-            .await
-            .unwrap_err();
-        assert!(matches!(struct_err, Error::ValueNesting(2)));
-        assert!(matches!(enum_err, Error::ValueNesting(2)));
+        let foo = m.function_def("foo").unwrap().unwrap();
+        let bar = m.function_def("bar").unwrap().unwrap();
+        let baz = m.function_def("baz").unwrap().unwrap();
+
+        insta::assert_snapshot!(format!(
+            "c0::m::foo: {foo:#?}\n\
+             c0::m::bar: {bar:#?}\n\
+             c0::m::baz: {baz:#?}"
+        ));
     }
 
     #[tokio::test]
-    async fn test_err_value_nesting_big_type_param_layout() {
-        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
+    async fn test_definitions_with_tokens() {
+        use SignatureToken as S;
 
-        let resolver = Resolver::new_with_limits(
-            cache,
-            Limits {
-                max_type_argument_width: 100,
-                max_type_argument_depth: 100,
-                max_type_nodes: 100,
-                max_move_value_depth: 3,
-            },
+        let (_, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (2, build_package("a1"), a1_types()),
+            (1, build_package("b0"), b0_types()),
+            (1, build_package("c0"), c0_types()),
+        ]);
+
+        let c0 = cache.fetch(addr("0xc0")).await.unwrap();
+        let (baz, raw) = c0
+            .module("m")
+            .unwrap()
+            .function_def_with_tokens("baz")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(raw.parameters, vec![S::U8]);
+        assert_eq!(raw.return_, vec![S::U16, S::U32]);
+        assert_eq!(baz.parameters.len(), raw.parameters.len());
+        assert_eq!(baz.return_.len(), raw.return_.len());
+
+        let a0 = cache.fetch(addr("0xa0")).await.unwrap();
+        let m = a0.module("m").unwrap();
+
+        let (t1, raw) = m.data_def_with_tokens("T1").unwrap().unwrap();
+        let RawMoveData::Struct(fields) = raw.data else {
+            panic!("Expected struct, got: {:?}", raw.data);
+        };
+        assert_eq!(
+            fields,
+            vec![
+                S::Address,
+                S::TypeParameter(0),
+                S::Vector(Box::new(S::TypeParameter(1))),
+            ],
+        );
+        assert!(matches!(t1.data, MoveData::Struct(f) if f.len() == 3));
+        assert_eq!(
+            m.bytecode()
+                .identifier_at(m.bytecode().datatype_handle_at(raw.handle).name)
+                .as_str(),
+            "T1",
         );
 
-        // This layout calculation will fail early because we know that the type parameter we're
-        // calculating will eventually contribute to a layout that exceeds the max depth.
-        let struct_err = resolver
-            .type_layout(type_("0xa0::m::T1<vector<vector<u8>>, u8>"))
-            .await
-            .unwrap_err();
-        let enum_err = resolver
-            .type_layout(type_("0xa0::m::E1<vector<vector<u8>>, u8>"))
-            .await
-            .unwrap_err();
-        assert!(matches!(struct_err, Error::ValueNesting(3)));
-        assert!(matches!(enum_err, Error::ValueNesting(3)));
+        // Datatypes are referred to by their handles, rather than being resolved.
+        let (_, raw) = m.data_def_with_tokens("T0").unwrap().unwrap();
+        let RawMoveData::Struct(fields) = raw.data else {
+            panic!("Expected struct, got: {:?}", raw.data);
+        };
+        let S::Vector(inner) = &fields[1] else {
+            panic!("Expected vector, got: {:?}", fields[1]);
+        };
+        assert!(matches!(**inner, S::DatatypeInstantiation(_)));
+
+        let (_, raw) = m.data_def_with_tokens("E2").unwrap().unwrap();
+        let RawMoveData::Enum(variants) = raw.data else {
+            panic!("Expected enum, got: {:?}", raw.data);
+        };
+        assert_eq!(variants, vec![vec![S::U8]]);
+
+        assert!(m.data_def_with_tokens("NotFound").unwrap().is_none());
+        assert!(m.function_def_with_tokens("not_found").unwrap().is_none());
     }
 
     #[tokio::test]
-    async fn test_err_value_nesting_big_phantom_type_param_layout() {
+    async fn test_entry_points() {
+        use ArgumentKind as K;
+
         let (_, cache) = package_cache([
+            (1, build_package("std"), std_types()),
             (1, build_package("sui"), sui_types()),
-            (1, build_package("d0"), d0_types()),
+            (1, build_package("e0"), e0_types()),
         ]);
 
-        let resolver = Resolver::new_with_limits(
-            cache,
-            Limits {
-                max_type_argument_width: 100,
-                max_type_argument_depth: 100,
-                max_type_nodes: 100,
-                max_move_value_depth: 3,
-            },
-        );
+        let e0 = cache.fetch(addr("0xe0")).await.unwrap();
+        let entry_points = e0.entry_points().unwrap();
 
-        // Check that this layout request would succeed.
-        let _ = resolver
-            .type_layout(type_("0xd0::m::O<u8, u8>"))
-            .await
-            .unwrap();
-        let _ = resolver
-            .type_layout(type_("0xd0::m::EO<u8, u8>"))
-            .await
-            .unwrap();
+        let summary: Vec<_> = entry_points
+            .iter()
+            .map(|e| (e.module.as_str(), e.name.as_str(), e.argument_kinds.clone()))
+            .collect();
 
-        // But this one fails, even though the big layout is for a phantom type parameter. This may
-        // change in future if we optimise the way we handle phantom type parameters to not
-        // calculate their full layout, just their type tag.
-        let struct_err = resolver
-            .type_layout(type_("0xd0::m::O<u8, vector<vector<u8>>>"))
-            .await
-            .unwrap_err();
-        let enum_err = resolver
-            .type_layout(type_("0xd0::m::EO<u8, vector<vector<u8>>>"))
-            .await
-            .unwrap_err();
-        assert!(matches!(struct_err, Error::ValueNesting(3)));
-        assert!(matches!(enum_err, Error::ValueNesting(3)));
+        assert_eq!(
+            summary,
+            vec![(
+                "m",
+                "bar",
+                vec![
+                    K::Object(Some(Reference::Immutable)),
+                    K::Pure,
+                    K::Object(Some(Reference::Mutable)),
+                    K::Pure,
+                    K::Pure,
+                ],
+            )],
+        );
+
+        assert!(entry_points[0].function.is_entry);
+        assert_eq!(e0.module("m").unwrap().friends().count(), 0);
     }
 
     #[tokio::test]
-    async fn test_err_value_nesting_type_param_application_layout() {
+    async fn test_find_functions() {
+        use OpenSignatureBody as O;
+        use TypePattern as P;
+
         let (_, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (2, build_package("a1"), a1_types()),
+            (1, build_package("b0"), b0_types()),
+            (1, build_package("c0"), c0_types()),
+            (1, build_package("std"), std_types()),
             (1, build_package("sui"), sui_types()),
-            (1, build_package("d0"), d0_types()),
+            (1, build_package("e0"), e0_types()),
         ]);
 
-        let resolver = Resolver::new_with_limits(
-            cache,
-            Limits {
-                max_type_argument_width: 100,
-                max_type_argument_depth: 100,
-                max_type_nodes: 100,
-                max_move_value_depth: 3,
-            },
-        );
+        let e0 = cache.fetch(addr("0xe0")).await.unwrap();
+        let c0 = cache.fetch(addr("0xc0")).await.unwrap();
+        let find = |package: &Package, filter: FunctionFilter| {
+            let found = package.find_functions(&filter).unwrap();
+            found
+                .into_iter()
+                .map(|(m, f)| format!("{m}::{f}"))
+                .collect::<Vec<_>>()
+        };
 
-        // Make sure that even if all type parameters individually meet the depth requirements,
-        // that we correctly fail if they extend the layout's depth on application.
-        let struct_err = resolver
-            .type_layout(type_("0xd0::m::O<vector<u8>, u8>"))
-            .await
-            .unwrap_err();
-        let enum_err = resolver
-            .type_layout(type_("0xd0::m::EO<vector<u8>, u8>"))
-            .await
-            .unwrap_err();
+        let o = |ref_| SignaturePattern {
+            ref_,
+            body: P::Datatype(datakey("0xe0", "m", "O"), vec![]),
+        };
 
-        assert!(matches!(struct_err, Error::ValueNesting(3)));
-        assert!(matches!(enum_err, Error::ValueNesting(3)));
-    }
+        assert_eq!(
+            find(&e0, FunctionFilter::default()),
+            vec!["m::bar", "m::foo"]
+        );
 
-    #[tokio::test]
-    async fn test_system_package_invalidation() {
-        let (inner, cache) = package_cache([(1, build_package("s0"), s0_types())]);
-        let resolver = Resolver::new(cache);
+        let takes_mut_o = FunctionFilter {
+            parameters: vec![o(Some(Reference::Mutable))],
+            ..Default::default()
+        };
+        assert_eq!(find(&e0, takes_mut_o.clone()), vec!["m::bar", "m::foo"]);
 
-        let struct_not_found = resolver.type_layout(type_("0x1::m::T1")).await.unwrap_err();
-        let enum_not_found = resolver.type_layout(type_("0x1::m::E1")).await.unwrap_err();
-        assert!(matches!(struct_not_found, Error::DatatypeNotFound(_, _, _)));
-        assert!(matches!(enum_not_found, Error::DatatypeNotFound(_, _, _)));
+        let entry = FunctionFilter {
+            is_entry: Some(true),
+            ..takes_mut_o.clone()
+        };
+        assert_eq!(find(&e0, entry), vec!["m::bar"]);
 
-        // Add a new version of the system package into the store underlying the cache.
-        inner.write().unwrap().replace(
-            addr("0x1"),
-            cached_package(2, BTreeMap::new(), &build_package("s1"), &s1_types()),
+        let public = FunctionFilter {
+            visibility: Some(Visibility::Public),
+            ..takes_mut_o
+        };
+        assert_eq!(find(&e0, public), vec!["m::foo"]);
+
+        // All the parameter patterns must match (but not necessarily in order).
+        let takes_o_and_t = FunctionFilter {
+            parameters: vec![
+                SignaturePattern {
+                    ref_: None,
+                    body: P::TypeParameter,
+                },
+                o(None),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(find(&e0, takes_o_and_t), vec!["m::foo"]);
+
+        // Type arguments are matched too.
+        let option = |p| P::Datatype(datakey("0x1", "option", "Option"), vec![p]);
+        let utf8 = P::Datatype(datakey("0x1", "string", "String"), vec![]);
+        let takes_option = |body| FunctionFilter {
+            parameters: vec![SignaturePattern { ref_: None, body }],
+            ..Default::default()
+        };
+        assert_eq!(
+            find(&e0, takes_option(option(P::Any))),
+            vec!["m::bar", "m::foo"],
         );
-
-        // Evict the package from the cache
-        resolver.package_store().evict([addr("0x1")]);
-
-        let struct_layout = resolver.type_layout(type_("0x1::m::T1")).await.unwrap();
-        let enum_layout = resolver.type_layout(type_("0x1::m::E1")).await.unwrap();
-        insta::assert_snapshot!(fmt(struct_layout, enum_layout));
+        assert_eq!(
+            find(&e0, takes_option(P::Vector(Box::new(option(P::Any))))),
+            vec!["m::foo"],
+        );
+        assert_eq!(
+            find(&e0, takes_option(option(utf8))),
+            vec!["m::bar", "m::foo"],
+        );
+        assert!(find(&e0, takes_option(option(P::Exact(O::U64)))).is_empty());
+
+        let returns = |bodies: Vec<OpenSignatureBody>| FunctionFilter {
+            returns: bodies
+                .into_iter()
+                .map(|b| SignaturePattern {
+                    ref_: None,
+                    body: P::Exact(b),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        assert_eq!(find(&c0, returns(vec![O::U64])), vec!["m::bar"]);
+        assert_eq!(find(&c0, returns(vec![O::U32, O::U16])), vec!["m::baz"]);
+        assert!(find(&c0, returns(vec![O::U8])).is_empty());
     }
 
     #[tokio::test]
-    async fn test_caching() {
-        let (inner, cache) = package_cache([
-            (1, build_package("a0"), a0_types()),
-            (1, build_package("s0"), s0_types()),
+    async fn test_deterministic_ordering() {
+        let (_, cache) = package_cache([
+            (1, build_package("std"), std_types()),
+            (1, build_package("sui"), sui_types()),
+            (1, build_package("f0"), f0_types()),
         ]);
         let resolver = Resolver::new(cache);
 
-        assert_eq!(inner.read().unwrap().fetches, 0);
-        let l0 = resolver.type_layout(type_("0xa0::m::T0")).await.unwrap();
+        let f0 = resolver.package_store().fetch(addr("0xf0")).await.unwrap();
+        let m = f0.module("m").unwrap();
 
-        // Load A0.
-        assert_eq!(inner.read().unwrap().fetches, 1);
+        // Listings are ordered by name, regardless of declaration order.
+        assert_eq!(
+            m.datatypes(None, None).collect::<Vec<_>>(),
+            vec!["K", "Order", "Wrap"],
+        );
+        assert_eq!(
+            m.functions(None, None).collect::<Vec<_>>(),
+            vec!["borrow", "dup", "hidden", "make", "pair", "take"],
+        );
 
-        // Layouts are the same, no need to reload the package.
-        let l1 = resolver.type_layout(type_("0xa0::m::T0")).await.unwrap();
-        assert_eq!(format!("{l0}"), format!("{l1}"));
-        assert_eq!(inner.read().unwrap().fetches, 1);
+        // Variants in definitions are in declaration order.
+        let Some(DataDef {
+            data: MoveData::Enum(variants),
+            ..
+        }) = m.enum_def("Order").unwrap()
+        else {
+            panic!("Expected an enum definition");
+        };
 
-        // Different type, but same package, so no extra fetch.
-        let l2 = resolver.type_layout(type_("0xa0::m::T2")).await.unwrap();
-        assert_ne!(format!("{l0}"), format!("{l2}"));
-        assert_eq!(inner.read().unwrap().fetches, 1);
+        let names: Vec<_> = variants.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, vec!["Zed", "Alpha", "Mid"]);
 
-        // Enum types won't trigger a fetch either.
-        resolver.type_layout(type_("0xa0::m::E0")).await.unwrap();
-        assert_eq!(inner.read().unwrap().fetches, 1);
+        // Layouts order variants by name, but can be listed in tag order.
+        let tag = TypeTag::from_str("0xf0::m::Order").unwrap();
+        let MoveTypeLayout::Enum(layout) = resolver.type_layout(tag).await.unwrap() else {
+            panic!("Expected an enum layout");
+        };
 
-        // New package to load.
-        let l3 = resolver.type_layout(type_("0x1::m::T0")).await.unwrap();
-        assert_eq!(inner.read().unwrap().fetches, 2);
+        let by_name: Vec<_> = layout.variants.keys().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(by_name, vec!["Alpha", "Mid", "Zed"]);
 
-        // Reload the same system package type, it gets fetched from cache
-        let l4 = resolver.type_layout(type_("0x1::m::T0")).await.unwrap();
-        assert_eq!(format!("{l3}"), format!("{l4}"));
-        assert_eq!(inner.read().unwrap().fetches, 2);
+        let by_tag: Vec<_> = VariantLayout::of(&layout)
+            .iter()
+            .map(|v| (v.name.as_str(), v.tag, v.fields.len()))
+            .collect();
+        assert_eq!(by_tag, vec![("Zed", 0, 0), ("Alpha", 1, 1), ("Mid", 2, 1)]);
+    }
 
-        // Reload a same system package type (enum), which will cause a version check.
-        let el4 = resolver.type_layout(type_("0x1::m::E0")).await.unwrap();
-        assert_ne!(format!("{el4}"), format!("{l4}"));
-        assert_eq!(inner.read().unwrap().fetches, 2);
+    #[tokio::test]
+    async fn test_datatype_history() {
+        let (_, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (2, build_package("a1"), a1_types()),
+        ]);
+        let resolver = Resolver::new(cache);
 
-        // Upgrade the system package
-        inner.write().unwrap().replace(
-            addr("0x1"),
-            cached_package(2, BTreeMap::new(), &build_package("s1"), &s1_types()),
+        let r = &resolver;
+        let history = |module, name| async move {
+            r.datatype_history(datakey("0xa0", module, name))
+                .await
+                .map(|h| {
+                    h.into_iter()
+                        .map(|rev| (rev.storage_id, rev.version.value(), rev.def.defining_id))
+                        .collect::<Vec<_>>()
+                })
+        };
+
+        // Types introduced by the original package appear in both versions.
+        assert_eq!(
+            history("m", "T0").await.unwrap(),
+            vec![
+                (addr("0xa0"), 1, addr("0xa0")),
+                (addr("0xa1"), 2, addr("0xa0")),
+            ],
         );
 
-        // Evict the package from the cache
-        resolver.package_store().evict([addr("0x1")]);
+        // Types introduced by the upgrade only appear from that version onwards.
+        assert_eq!(
+            history("m", "T3").await.unwrap(),
+            vec![(addr("0xa1"), 2, addr("0xa1"))],
+        );
 
-        // Reload the system system type again. It will be refetched (even though the type is the
-        // same as before). This usage pattern (layouts for system types) is why a layout cache
-        // would be particularly helpful (future optimisation).
-        let l5 = resolver.type_layout(type_("0x1::m::T0")).await.unwrap();
-        assert_eq!(format!("{l4}"), format!("{l5}"));
-        assert_eq!(inner.read().unwrap().fetches, 3);
-    }
+        let err = history("m", "T5").await.unwrap_err();
+        assert!(matches!(err, Error::DatatypeNotFound(_, _, _)), "{err}");
 
-    #[tokio::test]
-    async fn test_layout_err_not_a_package() {
-        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
-        let resolver = Resolver::new(cache);
+        // Stores that cannot list package versions cannot produce a history.
+        let resolver = resolver.at_version(SequenceNumber::from_u64(1));
         let err = resolver
-            .type_layout(type_("0x42::m::T0"))
+            .datatype_history(datakey("0xa0", "m", "T0"))
             .await
             .unwrap_err();
-        assert!(matches!(err, Error::PackageNotFound(_)));
+        assert!(matches!(err, Error::PackageVersionsUnsupported(_)), "{err}");
     }
 
     #[tokio::test]
-    async fn test_layout_err_no_module() {
-        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
-        let resolver = Resolver::new(cache);
-        let err = resolver
-            .type_layout(type_("0xa0::l::T0"))
-            .await
-            .unwrap_err();
-        assert!(matches!(err, Error::ModuleNotFound(_, _)));
-    }
+    async fn test_pretty_print() {
+        use crate::pretty::Printer;
 
-    #[tokio::test]
-    async fn test_layout_err_no_struct() {
-        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
-        let resolver = Resolver::new(cache);
+        let (_, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (2, build_package("a1"), a1_types()),
+            (1, build_package("std"), std_types()),
+            (1, build_package("sui"), sui_types()),
+            (1, build_package("e0"), e0_types()),
+            (1, build_package("f0"), f0_types()),
+        ]);
 
-        let err = resolver
-            .type_layout(type_("0xa0::m::T9"))
-            .await
-            .unwrap_err();
-        assert!(matches!(err, Error::DatatypeNotFound(_, _, _)));
-    }
+        let f0 = cache.fetch(addr("0xf0")).await.unwrap();
+        let e0 = cache.fetch(addr("0xe0")).await.unwrap();
+        let a1 = cache.fetch(addr("0xa1")).await.unwrap();
 
-    #[tokio::test]
-    async fn test_layout_err_type_arity() {
-        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
-        let resolver = Resolver::new(cache);
+        let function = |printer: &Printer, package: &Package, module: &str, name: &str| {
+            let def = package.module(module).unwrap().function_def(name).unwrap();
+            printer.function(name, &def.unwrap())
+        };
+
+        let datatype = |printer: &Printer, package: &Package, module: &str, name: &str| {
+            let def = package.module(module).unwrap().data_def(name).unwrap();
+            printer.datatype(name, &def.unwrap())
+        };
+
+        let printer = Printer::new().with_named_address(addr("0x2"), "sui");
+
+        assert_eq!(
+            function(&printer, &f0, "m", "dup"),
+            "public fun dup<T0: copy + drop>(arg0: T0): T0",
+        );
+        assert_eq!(
+            function(&printer, &f0, "m", "pair"),
+            "public fun pair(): (0xf0::m::K, u64)",
+        );
+        assert_eq!(
+            function(&printer, &f0, "m", "borrow"),
+            "public fun borrow(arg0: &0xf0::m::K): &sui::object::UID",
+        );
+        assert_eq!(function(&printer, &f0, "m", "hidden"), "fun hidden()");
+        assert_eq!(
+            function(&printer, &e0, "m", "bar"),
+            "entry fun bar(\
+                arg0: &0xe0::m::O, \
+                arg1: u64, \
+                arg2: &mut 0xe0::m::O, \
+                arg3: 0x1::option::Option<0x1::string::String>, \
+                arg4: vector<0x1::ascii::String>\
+            )",
+        );
+
+        assert_eq!(
+            datatype(&printer, &f0, "m", "K"),
+            "public struct K has store, key {\n    id: sui::object::UID,\n}",
+        );
+        assert_eq!(
+            datatype(&printer, &f0, "m", "Wrap"),
+            "public struct Wrap<T0: store, phantom T1: drop> has store {\n    t: T0,\n}",
+        );
+        assert_eq!(
+            datatype(&printer, &f0, "m", "Order"),
+            "public enum Order has drop {\n    \
+                Zed,\n    \
+                Alpha { x: u8 },\n    \
+                Mid { b: bool },\n\
+            }",
+        );
 
-        // Too few
-        let err = resolver
-            .type_layout(type_("0xa0::m::T1<u8>"))
-            .await
-            .unwrap_err();
-        assert!(matches!(err, Error::TypeArityMismatch(2, 1)));
+        // Types are referred to by runtime ID by default, but can be referred to by their storage
+        // or defining IDs instead.
+        let runtime = Printer::new();
+        assert_eq!(
+            datatype(&runtime, &a1, "n", "T1"),
+            "public struct T1 {\n    t: 0xa0::m::T1<0xa0::m::T3, u32>,\n    u: 0xa0::m::T4,\n}",
+        );
 
-        // Too many
-        let err = resolver
-            .type_layout(type_("0xa0::m::T1<u8, u16, u32>"))
-            .await
-            .unwrap_err();
-        assert!(matches!(err, Error::TypeArityMismatch(2, 3)));
+        let storage = Printer::new().with_storage_ids(&a1);
+        assert_eq!(
+            datatype(&storage, &a1, "n", "T1"),
+            "public struct T1 {\n    t: 0xa1::m::T1<0xa1::m::T3, u32>,\n    u: 0xa1::m::T4,\n}",
+        );
+
+        let defining = Printer::new()
+            .with_defining_ids(&a1)
+            .with_named_address(addr("0xa0"), "a");
+        assert_eq!(
+            datatype(&defining, &a1, "n", "T1"),
+            "public struct T1 {\n    t: a::m::T1<0xa1::m::T3, u32>,\n    u: 0xa1::m::T4,\n}",
+        );
     }
 
     #[tokio::test]
-    async fn test_structs() {
-        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
+    async fn test_paginate_package() {
+        let (_, cache) = package_cache([
+            (1, build_package("a0"), a0_types()),
+            (1, build_package("c0"), c0_types()),
+        ]);
+
         let a0 = cache.fetch(addr("0xa0")).await.unwrap();
-        let m = a0.module("m").unwrap();
+        let c0 = cache.fetch(addr("0xc0")).await.unwrap();
 
-        assert_eq!(
-            m.structs(None, None).collect::<Vec<_>>(),
-            vec!["T0", "T1", "T2"],
-        );
+        let names = |cs: &mut dyn Iterator<Item = PackageCursor>| -> Vec<String> {
+            cs.map(|c| match c.name {
+                Some(name) => format!("{}::{name}", c.module),
+                None => c.module,
+            })
+            .collect()
+        };
 
-        assert_eq!(m.structs(None, Some("T1")).collect::<Vec<_>>(), vec!["T0"],);
+        let m = PackageCursor::new("m", None);
+        let m_t1 = PackageCursor::new("m", Some("T1"));
+        let n_t0 = PackageCursor::new("n", Some("T0"));
 
+        assert_eq!(names(&mut a0.paginate_modules(None, None)), vec!["m", "n"]);
+        assert_eq!(names(&mut a0.paginate_modules(Some(&m), None)), vec!["n"]);
         assert_eq!(
-            m.structs(Some("T0"), Some("T2")).collect::<Vec<_>>(),
-            vec!["T1"],
+            names(&mut a0.paginate_modules(None, Some(&m_t1))),
+            vec!["m"]
         );
 
-        assert_eq!(m.structs(Some("T1"), None).collect::<Vec<_>>(), vec!["T2"],);
-
-        let t0 = m.struct_def("T0").unwrap().unwrap();
-        let t1 = m.struct_def("T1").unwrap().unwrap();
-        let t2 = m.struct_def("T2").unwrap().unwrap();
-
-        insta::assert_snapshot!(format!(
-            "a0::m::T0: {t0:#?}\n\
-             a0::m::T1: {t1:#?}\n\
-             a0::m::T2: {t2:#?}",
-        ));
-    }
+        assert_eq!(
+            names(&mut a0.paginate_datatypes(None, None)),
+            vec!["m::E0", "m::E1", "m::E2", "m::T0", "m::T1", "m::T2", "n::E0", "n::T0"],
+        );
 
-    #[tokio::test]
-    async fn test_enums() {
-        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
-        let a0 = cache
-            .fetch(AccountAddress::from_str("0xa0").unwrap())
-            .await
-            .unwrap();
-        let m = a0.module("m").unwrap();
+        assert_eq!(
+            names(&mut a0.paginate_datatypes(Some(&m_t1), Some(&n_t0))),
+            vec!["m::T2", "n::E0"],
+        );
 
         assert_eq!(
-            m.enums(None, None).collect::<Vec<_>>(),
-            vec!["E0", "E1", "E2"],
+            names(&mut a0.paginate_datatypes(Some(&m_t1), Some(&n_t0)).rev()),
+            vec!["n::E0", "m::T2"],
         );
 
-        assert_eq!(m.enums(None, Some("E1")).collect::<Vec<_>>(), vec!["E0"],);
+        // A module's cursor orders before all its members.
+        assert_eq!(
+            names(&mut a0.paginate_datatypes(None, Some(&PackageCursor::new("n", None)))),
+            vec!["m::E0", "m::E1", "m::E2", "m::T0", "m::T1", "m::T2"],
+        );
 
+        let c0_m_bar = PackageCursor::new("m", Some("bar"));
         assert_eq!(
-            m.enums(Some("E0"), Some("E2")).collect::<Vec<_>>(),
-            vec!["E1"],
+            names(&mut c0.paginate_functions(Some(&c0_m_bar), None)),
+            vec!["m::baz", "m::foo"],
         );
 
-        assert_eq!(m.enums(Some("E1"), None).collect::<Vec<_>>(), vec!["E2"],);
+        // Bounds that are out of order produce an empty page, rather than a panic.
+        assert!(names(&mut a0.paginate_datatypes(Some(&n_t0), Some(&m_t1))).is_empty());
+        assert!(names(&mut a0.paginate_functions(Some(&n_t0), Some(&m))).is_empty());
+        assert!(names(&mut a0.paginate_datatypes(Some(&m_t1), Some(&m_t1))).is_empty());
+        assert!(names(&mut a0.paginate_modules(Some(&n_t0), Some(&m))).is_empty());
+    }
 
-        let e0 = m.enum_def("E0").unwrap().unwrap();
-        let e1 = m.enum_def("E1").unwrap().unwrap();
-        let e2 = m.enum_def("E2").unwrap().unwrap();
+    #[test]
+    fn test_package_cursor_encoding() {
+        let cursor = PackageCursor::new("m", Some("T1"));
+        let token = cursor.encode();
+        assert_eq!(PackageCursor::decode(&token).unwrap(), cursor);
 
-        insta::assert_snapshot!(format!(
-            "a0::m::E0: {e0:#?}\n\
-             a0::m::E1: {e1:#?}\n\
-             a0::m::E2: {e2:#?}",
-        ));
+        let cursor = PackageCursor::new("m", None);
+        let token = cursor.encode();
+        assert_eq!(PackageCursor::decode(&token).unwrap(), cursor);
+
+        for bad in ["", "0", "zz", "01", "016d02"] {
+            assert!(matches!(
+                PackageCursor::decode(bad),
+                Err(Error::InvalidCursor(t)) if t == bad
+            ));
+        }
     }
 
     #[tokio::test]
-    async fn test_functions() {
+    async fn test_paginate_package_with_limit() {
         let (_, cache) = package_cache([
             (1, build_package("a0"), a0_types()),
-            (2, build_package("a1"), a1_types()),
-            (1, build_package("b0"), b0_types()),
             (1, build_package("c0"), c0_types()),
         ]);
 
+        let a0 = cache.fetch(addr("0xa0")).await.unwrap();
         let c0 = cache.fetch(addr("0xc0")).await.unwrap();
-        let m = c0.module("m").unwrap();
+        let limit = |n| NonZeroUsize::new(n).unwrap();
+
+        // Paging through datatypes three at a time visits every datatype exactly once, crossing
+        // module boundaries.
+        let mut pages = vec![];
+        let mut cursor = None;
+        loop {
+            let page = a0.datatypes_paginated(cursor.as_deref(), limit(3)).unwrap();
+            pages.push(
+                page.cursors
+                    .iter()
+                    .map(|c| format!("{}::{}", c.module, c.name.as_deref().unwrap()))
+                    .collect::<Vec<_>>(),
+            );
 
-        assert_eq!(
-            m.functions(None, None).collect::<Vec<_>>(),
-            vec!["bar", "baz", "foo"],
-        );
+            let Some(next) = page.next else {
+                break;
+            };
 
-        assert_eq!(
-            m.functions(None, Some("baz")).collect::<Vec<_>>(),
-            vec!["bar"],
-        );
+            assert_eq!(
+                PackageCursor::decode(&next).unwrap(),
+                *page.cursors.last().unwrap()
+            );
+            cursor = Some(next);
+        }
 
         assert_eq!(
-            m.functions(Some("bar"), Some("foo")).collect::<Vec<_>>(),
-            vec!["baz"],
+            pages,
+            vec![
+                vec!["m::E0", "m::E1", "m::E2"],
+                vec!["m::T0", "m::T1", "m::T2"],
+                vec!["n::E0", "n::T0"],
+            ],
         );
 
+        // A page that ends exactly at the last member has no next page.
+        let page = c0.functions_paginated(None, limit(3)).unwrap();
+        assert_eq!(page.cursors.len(), 3);
+        assert_eq!(page.next, None);
+
+        // Module cursors start from the beginning of their module.
+        let token = PackageCursor::new("n", None).encode();
+        let page = a0.datatypes_paginated(Some(&token), limit(10)).unwrap();
         assert_eq!(
-            m.functions(Some("baz"), None).collect::<Vec<_>>(),
-            vec!["foo"],
+            page.cursors,
+            vec![
+                PackageCursor::new("n", Some("E0")),
+                PackageCursor::new("n", Some("T0")),
+            ],
         );
 
-        let foo = m.function_def("foo").unwrap().unwrap();
-        let bar = m.function_def("bar").unwrap().unwrap();
-        let baz = m.function_def("baz").unwrap().unwrap();
-
-        insta::assert_snapshot!(format!(
-            "c0::m::foo: {foo:#?}\n\
-             c0::m::bar: {bar:#?}\n\
-             c0::m::baz: {baz:#?}"
+        assert!(matches!(
+            a0.datatypes_paginated(Some("zz"), limit(1)),
+            Err(Error::InvalidCursor(_)),
         ));
     }
 
@@ -2549,101 +7885,382 @@ mod tests {
             .unwrap();
         assert_eq!(a1, S::EMPTY | A::Key | A::Store);
 
-        let a2 = resolver
-            .abilities(type_("0xd0::m::O<0xd0::m::S, u64>"))
+        let a2 = resolver
+            .abilities(type_("0xd0::m::O<0xd0::m::S, u64>"))
+            .await
+            .unwrap();
+        assert_eq!(a2, S::EMPTY | A::Key | A::Store);
+
+        // We would not be able to get an instance of this type, but in case the question is asked,
+        // its abilities would be empty.
+        let a3 = resolver
+            .abilities(type_("0xd0::m::O<0xd0::m::R, u64>"))
+            .await
+            .unwrap();
+        assert_eq!(a3, S::EMPTY);
+
+        // Key does not propagate up by itself, so this type is also uninhabitable.
+        let a4 = resolver
+            .abilities(type_("0xd0::m::O<0xd0::m::P, u32>"))
+            .await
+            .unwrap();
+        assert_eq!(a4, S::EMPTY);
+    }
+
+    /// Phantom types don't impact abilities
+    #[tokio::test]
+    async fn test_phantom_abilities() {
+        use Ability as A;
+        use AbilitySet as S;
+
+        let (_, cache) = package_cache([
+            (1, build_package("sui"), sui_types()),
+            (1, build_package("d0"), d0_types()),
+        ]);
+        let resolver = Resolver::new(cache);
+
+        let a1 = resolver
+            .abilities(type_("0xd0::m::O<u32, 0xd0::m::R>"))
+            .await
+            .unwrap();
+        assert_eq!(a1, S::EMPTY | A::Key | A::Store);
+    }
+
+    #[tokio::test]
+    async fn test_err_ability_arity() {
+        let (_, cache) = package_cache([
+            (1, build_package("sui"), sui_types()),
+            (1, build_package("d0"), d0_types()),
+        ]);
+        let resolver = Resolver::new(cache);
+
+        // Too few
+        let err = resolver
+            .abilities(type_("0xd0::m::T<u8>"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::TypeArityMismatch(2, 1)));
+
+        // Too many
+        let err = resolver
+            .abilities(type_("0xd0::m::T<u8, u16, u32>"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::TypeArityMismatch(2, 3)));
+    }
+
+    #[tokio::test]
+    async fn test_err_ability_signer() {
+        let (_, cache) = package_cache([]);
+        let resolver = Resolver::new(cache);
+
+        let err = resolver.abilities(type_("signer")).await.unwrap_err();
+        assert!(matches!(err, Error::UnexpectedSigner));
+    }
+
+    #[tokio::test]
+    async fn test_err_too_many_type_params() {
+        let (_, cache) = package_cache([
+            (1, build_package("sui"), sui_types()),
+            (1, build_package("d0"), d0_types()),
+        ]);
+
+        let resolver = Resolver::new_with_limits(
+            cache,
+            Limits {
+                max_type_argument_width: 1,
+                max_type_argument_depth: 100,
+                max_type_nodes: 100,
+                max_move_value_depth: 100,
+                max_linkage_entries: 100,
+                max_identifier_len: None,
+            },
+        );
+
+        let err = resolver
+            .abilities(type_("0xd0::m::O<u32, u64>"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::TooManyTypeParams(1, 2)));
+    }
+
+    #[tokio::test]
+    async fn test_error_classification() {
+        use ErrorClass as C;
+
+        let (_, cache) = package_cache([
+            (1, build_package("sui"), sui_types()),
+            (1, build_package("d0"), d0_types()),
+        ]);
+
+        let resolver = Resolver::new_with_limits(
+            cache,
+            Limits {
+                max_type_argument_width: 1,
+                max_type_argument_depth: 100,
+                max_type_nodes: 100,
+                max_move_value_depth: 100,
+                max_linkage_entries: 100,
+                max_identifier_len: None,
+            },
+        );
+
+        for (tag, class) in [
+            ("0xd0::m::O<u32, u64>", C::LimitExceeded),
+            ("0xd0::m::NotFound", C::NotFound),
+            ("0xd1::m::O", C::NotFound),
+            ("0xd0::m::T<u8>", C::UserInput),
+        ] {
+            let err = resolver.abilities(type_(tag)).await.unwrap_err();
+            assert_eq!(err.classification(), class, "{tag}: {err}");
+        }
+
+        let err = resolver.resolve_transaction(&[0xff]).await.unwrap_err();
+        assert_eq!(err.classification(), C::UserInput);
+
+        // Other BCS errors come from data the resolver relies on, not from the request.
+        let err = Error::from(bcs::from_bytes::<u64>(&[]).unwrap_err());
+        assert_eq!(err.classification(), C::Internal);
+
+        let err = Error::Store {
+            store: "test",
+            error: "connection reset".to_string(),
+            retryable: true,
+        };
+        assert_eq!(err.classification(), C::StoreFailure);
+        assert!(err.is_retryable());
+
+        let err = Error::Store {
+            store: "test",
+            error: "corrupt data".to_string(),
+            retryable: false,
+        };
+        assert_eq!(err.classification(), C::StoreFailure);
+        assert!(!err.is_retryable());
+        assert!(!Error::PackageNotFound(addr("0x42")).is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_decode_value_bounded() {
+        use DisplayValue as D;
+        use MoveValue as V;
+
+        let (_, cache) = package_cache([
+            (1, build_package("std"), std_types()),
+            (1, build_package("a0"), a0_types()),
+        ]);
+        let resolver = Resolver::new(cache);
+
+        let limits = |max_total_bytes| DecodeLimits {
+            max_vector_elements: 3,
+            max_string_length: 5,
+            max_total_bytes,
+        };
+
+        let numbers = bcs::to_bytes(&(0..10u64).collect::<Vec<_>>()).unwrap();
+        let string = bcs::to_bytes("hello world").unwrap();
+
+        // Vectors and strings are cut short, with a count of what was cut off.
+        let value = resolver
+            .decode_value_bounded(type_("vector<u64>"), &numbers, &limits(1000))
+            .await
+            .unwrap();
+        assert_eq!(
+            value,
+            D::Vector {
+                elements: vec![
+                    D::Primitive(V::U64(0)),
+                    D::Primitive(V::U64(1)),
+                    D::Primitive(V::U64(2)),
+                ],
+                omitted: 7,
+            }
+        );
+
+        let value = resolver
+            .decode_value_bounded(type_("0x1::string::String"), &string, &limits(1000))
             .await
             .unwrap();
-        assert_eq!(a2, S::EMPTY | A::Key | A::Store);
+        assert_eq!(
+            value,
+            D::String {
+                value: "hello".to_string(),
+                omitted: 6,
+            }
+        );
 
-        // We would not be able to get an instance of this type, but in case the question is asked,
-        // its abilities would be empty.
-        let a3 = resolver
-            .abilities(type_("0xd0::m::O<0xd0::m::R, u64>"))
+        // Once the budget runs out, the rest of the value is truncated.
+        let value = resolver
+            .decode_value_bounded(type_("vector<u64>"), &numbers, &limits(20))
             .await
             .unwrap();
-        assert_eq!(a3, S::EMPTY);
+        assert_eq!(
+            value,
+            D::Vector {
+                elements: vec![
+                    D::Primitive(V::U64(0)),
+                    D::Primitive(V::U64(1)),
+                    D::Truncated,
+                ],
+                omitted: 7,
+            }
+        );
 
-        // Key does not propagate up by itself, so this type is also uninhabitable.
-        let a4 = resolver
-            .abilities(type_("0xd0::m::O<0xd0::m::P, u32>"))
+        // Each event gets its own budget.
+        let t2 = StructTag::from_str("0xa0::m::T2").unwrap();
+        let event = |x| {
+            Event::new(
+                &addr("0xa0"),
+                ident_str!("m"),
+                SuiAddress::ZERO,
+                t2.clone(),
+                vec![x],
+            )
+        };
+
+        let values = resolver
+            .decode_events_bounded(&[event(1), event(2)], &limits(1))
             .await
             .unwrap();
-        assert_eq!(a4, S::EMPTY);
+
+        let x = |x| D::Struct {
+            type_: t2.clone(),
+            fields: vec![(ident_str!("x").to_owned(), D::Primitive(V::U8(x)))],
+        };
+
+        assert_eq!(values, vec![x(1), x(2)]);
     }
 
-    /// Phantom types don't impact abilities
     #[tokio::test]
-    async fn test_phantom_abilities() {
-        use Ability as A;
-        use AbilitySet as S;
-
+    async fn test_instantiate_checked() {
         let (_, cache) = package_cache([
+            (1, build_package("std"), std_types()),
             (1, build_package("sui"), sui_types()),
-            (1, build_package("d0"), d0_types()),
+            (1, build_package("f0"), f0_types()),
         ]);
         let resolver = Resolver::new(cache);
 
-        let a1 = resolver
-            .abilities(type_("0xd0::m::O<u32, 0xd0::m::R>"))
+        let f0 = resolver.package_store().fetch(addr("0xf0")).await.unwrap();
+        let dup = f0
+            .module("m")
+            .unwrap()
+            .function_def("dup")
+            .unwrap()
+            .unwrap();
+        let param = &dup.parameters[0];
+        let input = |t: &str| TypeInput::from(type_(t));
+
+        // `dup<T: copy + drop>` accepts `u64`...
+        let sig = param
+            .instantiate_checked(&[input("u64")], &dup.type_params, &resolver)
             .await
             .unwrap();
-        assert_eq!(a1, S::EMPTY | A::Key | A::Store);
-    }
+        assert_eq!(sig.body, TypeTag::U64);
 
-    #[tokio::test]
-    async fn test_err_ability_arity() {
-        let (_, cache) = package_cache([
-            (1, build_package("sui"), sui_types()),
-            (1, build_package("d0"), d0_types()),
-        ]);
-        let resolver = Resolver::new(cache);
+        // ...but not `K`, which has neither ability.
+        let err = param
+            .instantiate_checked(&[input("0xf0::m::K")], &dup.type_params, &resolver)
+            .await
+            .unwrap_err();
+
+        let Error::MissingAbilities {
+            owner: None,
+            index: 0,
+            missing,
+            ..
+        } = &err
+        else {
+            panic!("Expected missing abilities, got: {err}");
+        };
+
+        assert_eq!(*missing, AbilitySet::EMPTY | Ability::Copy | Ability::Drop);
+        assert_eq!(err.classification(), ErrorClass::UserInput);
+
+        // Type arguments are checked against the constraints of the datatypes they instantiate,
+        // including in phantom positions.
+        let store = [AbilitySet::EMPTY | Ability::Store];
+        resolver
+            .check_type_arguments(&[input("0xf0::m::Wrap<u64, u8>")], &store)
+            .await
+            .unwrap();
 
-        // Too few
         let err = resolver
-            .abilities(type_("0xd0::m::T<u8>"))
+            .check_type_arguments(&[input("vector<0xf0::m::Wrap<u64, 0xf0::m::K>>")], &store)
             .await
             .unwrap_err();
-        assert!(matches!(err, Error::TypeArityMismatch(2, 1)));
 
-        // Too many
-        let err = resolver
-            .abilities(type_("0xd0::m::T<u8, u16, u32>"))
+        let Error::MissingAbilities {
+            owner: Some(owner),
+            index: 1,
+            type_: arg,
+            missing,
+        } = &err
+        else {
+            panic!("Expected missing abilities, got: {err}");
+        };
+
+        assert_eq!(owner.name.as_str(), "Wrap");
+        assert_eq!(arg, &type_("0xf0::m::K"));
+        assert_eq!(*missing, AbilitySet::EMPTY | Ability::Drop);
+
+        // The number of type arguments must match the number of type parameters.
+        let err = param
+            .instantiate_checked(&[input("u64"), input("u64")], &dup.type_params, &resolver)
             .await
             .unwrap_err();
-        assert!(matches!(err, Error::TypeArityMismatch(2, 3)));
+        assert!(matches!(err, Error::TypeArityMismatch(1, 2)), "{err}");
     }
 
     #[tokio::test]
-    async fn test_err_ability_signer() {
-        let (_, cache) = package_cache([]);
+    async fn test_decode_value() {
+        use move_core_types::annotated_value::MoveStruct;
+
+        let (_, cache) = package_cache([(1, build_package("a0"), a0_types())]);
         let resolver = Resolver::new(cache);
 
-        let err = resolver.abilities(type_("signer")).await.unwrap_err();
-        assert!(matches!(err, Error::UnexpectedSigner));
-    }
+        let t2 = StructTag::from_str("0xa0::m::T2").unwrap();
+        let value = |x| {
+            MoveValue::Struct(MoveStruct {
+                type_: t2.clone(),
+                fields: vec![(ident_str!("x").to_owned(), MoveValue::U8(x))],
+            })
+        };
 
-    #[tokio::test]
-    async fn test_err_too_many_type_params() {
-        let (_, cache) = package_cache([
-            (1, build_package("sui"), sui_types()),
-            (1, build_package("d0"), d0_types()),
-        ]);
+        let decoded = resolver
+            .decode_value(type_("0xa0::m::T2"), &[42])
+            .await
+            .unwrap();
+        assert_eq!(decoded, value(42));
 
-        let resolver = Resolver::new_with_limits(
-            cache,
-            Limits {
-                max_type_argument_width: 1,
-                max_type_argument_depth: 100,
-                max_type_nodes: 100,
-                max_move_value_depth: 100,
-            },
-        );
+        // Trailing bytes are rejected.
+        let err = resolver
+            .decode_value(type_("0xa0::m::T2"), &[42, 43])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ValueDecode(_, _)), "{err}");
+        assert_eq!(err.classification(), ErrorClass::UserInput);
+
+        let event = |contents| {
+            Event::new(
+                &addr("0xa0"),
+                ident_str!("m"),
+                SuiAddress::ZERO,
+                t2.clone(),
+                contents,
+            )
+        };
+
+        let decoded = resolver
+            .decode_events(&[event(vec![1]), event(vec![2]), event(vec![1])])
+            .await
+            .unwrap();
+        assert_eq!(decoded, vec![value(1), value(2), value(1)]);
 
         let err = resolver
-            .abilities(type_("0xd0::m::O<u32, u64>"))
+            .decode_events(&[event(vec![1]), event(vec![])])
             .await
             .unwrap_err();
-        assert!(matches!(err, Error::TooManyTypeParams(1, 2)));
+        assert!(matches!(err, Error::ValueDecode(_, _)), "{err}");
     }
 
     #[tokio::test]
@@ -2663,6 +8280,8 @@ mod tests {
                 max_type_argument_depth: 100,
                 max_type_nodes: 2,
                 max_move_value_depth: 100,
+                max_linkage_entries: 100,
+                max_identifier_len: None,
             },
         );
 
@@ -2699,6 +8318,8 @@ mod tests {
                 max_type_argument_depth: 2,
                 max_type_nodes: 100,
                 max_move_value_depth: 100,
+                max_linkage_entries: 100,
+                max_identifier_len: None,
             },
         );
 
@@ -2902,6 +8523,269 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_resolve_transaction() {
+        use CallArg as I;
+        use ObjectArg::ImmOrOwnedObject as O;
+        use TypeTag as T;
+
+        let (_, cache) = package_cache([
+            (1, build_package("std"), std_types()),
+            (1, build_package("sui"), sui_types()),
+            (1, build_package("e0"), e0_types()),
+        ]);
+
+        let resolver = Resolver::new(cache);
+
+        let ptb = ProgrammableTransaction {
+            inputs: vec![
+                I::Object(O(random_object_ref())),
+                I::Pure(bcs::to_bytes(&42u64).unwrap()),
+                I::Object(O(random_object_ref())),
+                I::Pure(bcs::to_bytes(&43u64).unwrap()),
+                I::Object(O(random_object_ref())),
+                I::Pure(bcs::to_bytes("hello").unwrap()),
+                I::Pure(bcs::to_bytes("world").unwrap()),
+            ],
+            commands: vec![Command::move_call(
+                addr("0xe0").into(),
+                ident_str!("m").to_owned(),
+                ident_str!("foo").to_owned(),
+                vec![T::U64],
+                (0..=6).map(Argument::Input).collect(),
+            )],
+        };
+
+        let sender = SuiAddress::random_for_testing_only();
+        let tx = TransactionData::new_programmable(
+            sender,
+            vec![random_object_ref()],
+            ptb,
+            1_000_000,
+            1_000,
+        );
+
+        let resolved = resolver
+            .resolve_transaction(&bcs::to_bytes(&tx).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(resolved.sender, sender);
+        assert_eq!(resolved.move_calls.len(), 1);
+
+        let call = &resolved.move_calls[0];
+        assert_eq!(call.command, 0);
+        assert_eq!(call.package, addr("0xe0"));
+        assert_eq!(call.function, "foo");
+        assert_eq!(call.type_arguments, vec![T::U64]);
+        assert_eq!(call.parameters.len(), 7);
+
+        let kinds: Vec<_> = resolved
+            .inputs
+            .iter()
+            .map(|input| match input {
+                ResolvedInput::Pure { layout, .. } => format!("pure {}", layout.is_some()),
+                ResolvedInput::Object { kind, .. } => format!("object {kind:?}"),
+            })
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                "object Some(Object(Some(Immutable)))",
+                "pure true",
+                "object Some(Object(Some(Mutable)))",
+                "pure true",
+                "object Some(Object(None))",
+                "pure true",
+                "pure true",
+            ]
+        );
+
+        let ResolvedInput::Pure {
+            layout: Some(layout),
+            ..
+        } = &resolved.inputs[1]
+        else {
+            panic!("Expected a layout for input 1");
+        };
+
+        assert_eq!(format!("{layout}"), "u64");
+    }
+
+    #[tokio::test]
+    async fn test_resolve_transaction_err_bcs() {
+        let (_, cache) = package_cache([]);
+        let resolver = Resolver::new(cache);
+        let err = resolver.resolve_transaction(&[0xff]).await.unwrap_err();
+        assert!(matches!(err, Error::InvalidTransaction(_)));
+    }
+
+    #[tokio::test]
+    async fn test_check_ptb() {
+        use CallArg as I;
+        use PtbTypeErrorKind as K;
+        use TypeInput as TI;
+        use TypeTag as T;
+
+        let (_, cache) = package_cache([
+            (1, build_package("std"), std_types()),
+            (1, build_package("sui"), sui_types()),
+            (1, build_package("f0"), f0_types()),
+        ]);
+
+        let resolver = Resolver::new(cache);
+
+        let call = |function: &str, type_arguments: Vec<TI>, arguments: Vec<Argument>| {
+            Command::MoveCall(Box::new(ProgrammableMoveCall {
+                package: addr("0xf0").into(),
+                module: "m".to_owned(),
+                function: function.to_owned(),
+                type_arguments,
+                arguments,
+            }))
+        };
+
+        let k = T::from_str("0xf0::m::K").unwrap();
+        let gas = T::from(GasCoin::type_());
+
+        let ptb = ProgrammableTransaction {
+            inputs: vec![
+                I::Pure(bcs::to_bytes(&42u64).unwrap()),
+                I::Pure(bcs::to_bytes(&SuiAddress::ZERO).unwrap()),
+            ],
+            commands: vec![
+                call("make", vec![], vec![]),
+                call(
+                    "take",
+                    vec![],
+                    vec![Argument::Result(0), Argument::Input(0)],
+                ),
+                call("pair", vec![], vec![]),
+                call("dup", vec![TI::U64], vec![Argument::NestedResult(2, 1)]),
+                Command::TransferObjects(vec![Argument::NestedResult(2, 0)], Argument::Input(1)),
+                Command::SplitCoins(Argument::GasCoin, vec![Argument::Input(0)]),
+            ],
+        };
+
+        let report = resolver.check_ptb(&ptb).await.unwrap();
+        assert_eq!(report.errors, vec![]);
+        assert_eq!(
+            report.results,
+            vec![
+                Some(vec![Some(k.clone())]),
+                Some(vec![]),
+                Some(vec![Some(k.clone()), Some(T::U64)]),
+                Some(vec![Some(T::U64)]),
+                Some(vec![]),
+                Some(vec![Some(gas)]),
+            ],
+        );
+
+        let ptb = ProgrammableTransaction {
+            inputs: vec![I::Pure(bcs::to_bytes(&42u64).unwrap())],
+            commands: vec![
+                call("pair", vec![], vec![]),
+                call("take", vec![], vec![Argument::Input(0)]),
+                call(
+                    "take",
+                    vec![],
+                    vec![Argument::NestedResult(0, 1), Argument::Result(0)],
+                ),
+                call("dup", vec![TI::from(k.clone())], vec![Argument::Result(5)]),
+                call("hidden", vec![], vec![]),
+                call("borrow", vec![], vec![Argument::NestedResult(0, 0)]),
+                call("missing", vec![], vec![]),
+                // The first use of the pure input fixes its type as an address...
+                Command::TransferObjects(vec![Argument::NestedResult(0, 1)], Argument::Input(0)),
+                // ...which conflicts with its use as an amount.
+                Command::SplitCoins(Argument::NestedResult(0, 0), vec![Argument::Input(0)]),
+            ],
+        };
+
+        let report = resolver.check_ptb(&ptb).await.unwrap();
+        let errors: Vec<_> = report
+            .errors
+            .into_iter()
+            .map(|e| (e.command, e.kind))
+            .collect();
+        let missing = Error::FunctionNotFound(addr("0xf0"), "m".to_owned(), "missing".to_owned());
+
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    1,
+                    K::ArityMismatch {
+                        expected: 2,
+                        actual: 1
+                    }
+                ),
+                (1, K::UnexpectedPureInput { argument: 0 }),
+                (
+                    2,
+                    K::TypeMismatch {
+                        argument: 0,
+                        expected: k.clone(),
+                        actual: T::U64,
+                    },
+                ),
+                (
+                    2,
+                    K::InvalidResultArity {
+                        argument: 1,
+                        result: 0,
+                        arity: 2,
+                    },
+                ),
+                (
+                    3,
+                    K::MissingAbilities {
+                        type_argument: 0,
+                        required: AbilitySet::EMPTY | Ability::Copy | Ability::Drop,
+                        actual: AbilitySet::EMPTY | Ability::Key | Ability::Store,
+                    },
+                ),
+                (
+                    3,
+                    K::ResultOutOfBounds {
+                        argument: 0,
+                        result: 5
+                    }
+                ),
+                (4, K::NotCallable),
+                (5, K::ReferenceReturn),
+                (6, K::Unresolved(missing.to_string())),
+                (
+                    7,
+                    K::NotTransferable {
+                        argument: 0,
+                        actual: T::U64
+                    }
+                ),
+                (
+                    8,
+                    K::NotACoin {
+                        argument: 0,
+                        actual: k.clone()
+                    }
+                ),
+                (
+                    8,
+                    K::PureInputConflict {
+                        argument: 1,
+                        input: 0,
+                        first: T::Address,
+                        second: T::U64,
+                    },
+                ),
+            ],
+        );
+
+        assert_eq!(report.results[3], Some(vec![Some(k)]));
+        assert_eq!(report.results[6], None);
+    }
+
     /***** Test Helpers ***************************************************************************/
 
     type TypeOriginTable = Vec<DatatypeKey>;
@@ -2963,6 +8847,45 @@ mod tests {
         vec![datakey("0xe0", "m", "O")]
     }
 
+    fn g0_types() -> TypeOriginTable {
+        vec![]
+    }
+
+    fn h0_types() -> TypeOriginTable {
+        vec![
+            datakey("0x70", "m", "Order"),
+            datakey("0x70", "m", "Pool"),
+            datakey("0x70", "m", "Receipt"),
+            datakey("0x70", "m", "Status"),
+        ]
+    }
+
+    fn h1_types() -> TypeOriginTable {
+        h0_types()
+    }
+
+    fn i0_types() -> TypeOriginTable {
+        vec![
+            datakey("0x80", "m", "D0"),
+            datakey("0x80", "m", "D1"),
+            datakey("0x80", "m", "D2"),
+            datakey("0x80", "m", "D3"),
+            datakey("0x80", "m", "D4"),
+            datakey("0x80", "m", "D5"),
+            datakey("0x80", "m", "D6"),
+            datakey("0x80", "m", "D7"),
+            datakey("0x80", "m", "D8"),
+        ]
+    }
+
+    fn f0_types() -> TypeOriginTable {
+        vec![
+            datakey("0xf0", "m", "K"),
+            datakey("0xf0", "m", "Order"),
+            datakey("0xf0", "m", "Wrap"),
+        ]
+    }
+
     fn s0_types() -> TypeOriginTable {
         vec![datakey("0x1", "m", "T0"), datakey("0x1", "m", "E0")]
     }
@@ -3070,15 +8993,75 @@ mod tests {
             modules.insert(name.to_string(), module);
         }
 
+        let bytes: Vec<_> = package
+            .package
+            .root_compiled_units
+            .iter()
+            .map(|unit| {
+                let module = &unit.unit.module;
+                let mut bytes = vec![];
+                module
+                    .serialize_with_version(module.version, &mut bytes)
+                    .unwrap();
+                bytes
+            })
+            .collect();
+
+        // Test packages keep their digests, like packages read with `Package::read_with_digests`.
+        let deps: Vec<_> = linkage.values().map(|id| ObjectID::from(*id)).collect();
+        let digests = Some(PackageDigests::new(bytes, deps));
+
         Package {
             storage_id,
             runtime_id,
             linkage,
             version,
             modules,
+            module_errors: BTreeMap::new(),
+            digests,
         }
     }
 
+    /// Serialized modules of `package`, keyed by module name, as they would appear on-chain.
+    fn serialized_modules(package: &CompiledPackage) -> BTreeMap<String, Vec<u8>> {
+        package
+            .get_modules()
+            .map(|module| {
+                let mut bytes = vec![];
+                module
+                    .serialize_with_version(module.version, &mut bytes)
+                    .unwrap();
+                (module.self_id().name().to_string(), bytes)
+            })
+            .collect()
+    }
+
+    /// An on-chain package at `storage_id`, with no dependencies.
+    fn move_package(
+        storage_id: &str,
+        modules: BTreeMap<String, Vec<u8>>,
+        origins: TypeOriginTable,
+    ) -> MovePackage {
+        let type_origins = origins
+            .into_iter()
+            .map(|key| TypeOrigin {
+                module_name: key.module.to_string(),
+                datatype_name: key.name.to_string(),
+                package: ObjectID::from(key.package),
+            })
+            .collect();
+
+        MovePackage::new(
+            ObjectID::from(addr(storage_id)),
+            SequenceNumber::from_u64(1),
+            modules,
+            u64::MAX,
+            type_origins,
+            BTreeMap::new(),
+        )
+        .unwrap()
+    }
+
     fn package_storage_id(package: &CompiledPackage) -> AccountAddress {
         AccountAddress::from(*package.published_at.as_ref().unwrap_or_else(|_| {
             panic!(
@@ -3145,6 +9128,33 @@ mod tests {
                 .ok_or_else(|| Error::PackageNotFound(id))
                 .map(Arc::new)
         }
+
+        async fn latest_package_id(&self, original_id: AccountAddress) -> Result<AccountAddress> {
+            let inner = self.inner.as_ref().read().unwrap();
+            inner
+                .packages
+                .values()
+                .filter(|p| p.runtime_id == original_id)
+                .max_by_key(|p| p.version)
+                .map(|p| p.storage_id)
+                .ok_or_else(|| Error::PackageNotFound(original_id))
+        }
+
+        async fn package_versions(
+            &self,
+            original_id: AccountAddress,
+        ) -> Result<Vec<AccountAddress>> {
+            let inner = self.inner.as_ref().read().unwrap();
+            let mut versions: Vec<_> = inner
+                .packages
+                .values()
+                .filter(|p| p.runtime_id == original_id)
+                .map(|p| (p.version, p.storage_id))
+                .collect();
+
+            versions.sort();
+            Ok(versions.into_iter().map(|(_, id)| id).collect())
+        }
     }
 
     impl InnerStore {
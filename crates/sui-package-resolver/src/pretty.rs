@@ -0,0 +1,318 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Rendering of function and datatype definitions as Move-like declarations, e.g.
+//!
+//! ```text
+//! public entry fun swap<T0: store>(arg0: &mut 0x42::pool::Pool<T0>, arg1: u64): 0x2::coin::Coin<T0>
+//! ```
+//!
+//! Bytecode does not record the names of type parameters or function parameters, so these are
+//! rendered positionally (`T0`, `T1`, ... and `arg0`, `arg1`, ...). Function bodies are not
+//! rendered.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use move_binary_format::file_format::{AbilitySet, Visibility};
+use move_core_types::account_address::AccountAddress;
+
+use crate::{
+    DataDef, DatatypeKey, FunctionDef, MoveData, OpenSignature, OpenSignatureBody, Package,
+    Reference,
+};
+
+/// Renders definitions read by the resolver as Move source declarations.
+///
+/// Signatures refer to datatypes by the runtime IDs of the packages that contain them. By default
+/// these IDs are rendered as they are, but the printer can be configured to replace them with
+/// storage IDs or defining IDs (see `with_storage_ids` and `with_defining_ids`), and to render
+/// well-known addresses by name.
+#[derive(Clone, Debug, Default)]
+pub struct Printer {
+    /// Names to render in place of addresses, applied after IDs have been replaced.
+    named_addresses: BTreeMap<AccountAddress, String>,
+
+    /// Replacement IDs for packages, keyed by runtime ID.
+    package_ids: BTreeMap<AccountAddress, AccountAddress>,
+
+    /// Replacement IDs for individual datatypes, keyed by the datatype's runtime ID, module and
+    /// name. These take precedence over `package_ids`.
+    datatype_ids: BTreeMap<DatatypeKey, AccountAddress>,
+}
+
+impl Printer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Render `address` as `name` (e.g. `0x2` as `sui`).
+    pub fn with_named_address(mut self, address: AccountAddress, name: impl Into<String>) -> Self {
+        self.named_addresses.insert(address, name.into());
+        self
+    }
+
+    /// Refer to `package` and its dependencies by the storage IDs that `package` links against,
+    /// rather than by their runtime IDs.
+    pub fn with_storage_ids(mut self, package: &Package) -> Self {
+        self.package_ids.extend(&package.linkage);
+        self.package_ids
+            .insert(package.runtime_id, package.storage_id);
+        self
+    }
+
+    /// Refer to datatypes defined in `package` by their defining IDs (the storage ID of the
+    /// package version that introduced them), rather than by their runtime IDs. To render
+    /// datatypes from dependencies by their defining IDs as well, call this with each of the
+    /// dependencies.
+    pub fn with_defining_ids(mut self, package: &Package) -> Self {
+        for (module_name, module) in &package.modules {
+            let defining_ids = module
+                .struct_index
+                .iter()
+                .map(|(name, (id, _))| (name, id))
+                .chain(module.enum_index.iter().map(|(name, (id, _))| (name, id)));
+
+            for (name, &defining_id) in defining_ids {
+                let key = DatatypeKey {
+                    package: package.runtime_id,
+                    module: module_name.clone().into(),
+                    name: name.clone().into(),
+                };
+
+                self.datatype_ids.insert(key, defining_id);
+            }
+        }
+
+        self
+    }
+
+    /// Render the declaration of function `name`, e.g.
+    /// `public fun dup<T0: copy + drop>(arg0: T0): T0`.
+    pub fn function(&self, name: &str, def: &FunctionDef) -> String {
+        let mut out = String::new();
+
+        match def.visibility {
+            Visibility::Public => out.push_str("public "),
+            Visibility::Friend => out.push_str("public(package) "),
+            Visibility::Private => {}
+        }
+
+        if def.is_entry {
+            out.push_str("entry ");
+        }
+
+        write!(out, "fun {name}").unwrap();
+
+        if !def.type_params.is_empty() {
+            out.push('<');
+            for (ix, abilities) in def.type_params.iter().enumerate() {
+                if ix > 0 {
+                    out.push_str(", ");
+                }
+                write!(out, "T{ix}").unwrap();
+                write_constraints(&mut out, *abilities);
+            }
+            out.push('>');
+        }
+
+        out.push('(');
+        for (ix, param) in def.parameters.iter().enumerate() {
+            if ix > 0 {
+                out.push_str(", ");
+            }
+            write!(out, "arg{ix}: ").unwrap();
+            self.write_signature(&mut out, param);
+        }
+        out.push(')');
+
+        match def.return_.as_slice() {
+            [] => {}
+            [ret] => {
+                out.push_str(": ");
+                self.write_signature(&mut out, ret);
+            }
+            rets => {
+                out.push_str(": (");
+                for (ix, ret) in rets.iter().enumerate() {
+                    if ix > 0 {
+                        out.push_str(", ");
+                    }
+                    self.write_signature(&mut out, ret);
+                }
+                out.push(')');
+            }
+        }
+
+        out
+    }
+
+    /// Render the declaration of datatype `name`, including its fields (for a struct) or variants
+    /// (for an enum), one per line, e.g.
+    ///
+    /// ```text
+    /// public struct Wrap<T0: store, phantom T1: drop> has store {
+    ///     t: T0,
+    /// }
+    /// ```
+    pub fn datatype(&self, name: &str, def: &DataDef) -> String {
+        let mut out = String::new();
+
+        let kind = match &def.data {
+            MoveData::Struct(_) => "struct",
+            MoveData::Enum(_) => "enum",
+        };
+
+        write!(out, "public {kind} {name}").unwrap();
+
+        if !def.type_params.is_empty() {
+            out.push('<');
+            for (ix, param) in def.type_params.iter().enumerate() {
+                if ix > 0 {
+                    out.push_str(", ");
+                }
+                if param.is_phantom {
+                    out.push_str("phantom ");
+                }
+                write!(out, "T{ix}").unwrap();
+                write_constraints(&mut out, param.constraints);
+            }
+            out.push('>');
+        }
+
+        if def.abilities != AbilitySet::EMPTY {
+            out.push_str(" has ");
+            write_abilities(&mut out, def.abilities, ", ");
+        }
+
+        match &def.data {
+            MoveData::Struct(fields) if fields.is_empty() => out.push_str(" {}"),
+
+            MoveData::Struct(fields) => {
+                out.push_str(" {\n");
+                for (name, sig) in fields {
+                    write!(out, "    {name}: ").unwrap();
+                    self.write_body(&mut out, sig);
+                    out.push_str(",\n");
+                }
+                out.push('}');
+            }
+
+            MoveData::Enum(variants) => {
+                out.push_str(" {\n");
+                for variant in variants {
+                    write!(out, "    {}", variant.name).unwrap();
+                    if !variant.signatures.is_empty() {
+                        out.push_str(" { ");
+                        for (ix, (name, sig)) in variant.signatures.iter().enumerate() {
+                            if ix > 0 {
+                                out.push_str(", ");
+                            }
+                            write!(out, "{name}: ").unwrap();
+                            self.write_body(&mut out, sig);
+                        }
+                        out.push_str(" }");
+                    }
+                    out.push_str(",\n");
+                }
+                out.push('}');
+            }
+        }
+
+        out
+    }
+
+    /// Render a parameter or return type, e.g. `&mut 0x2::coin::Coin<T0>`.
+    pub fn signature(&self, sig: &OpenSignature) -> String {
+        let mut out = String::new();
+        self.write_signature(&mut out, sig);
+        out
+    }
+
+    /// Render a type, e.g. `vector<0x1::option::Option<u64>>`.
+    pub fn signature_body(&self, body: &OpenSignatureBody) -> String {
+        let mut out = String::new();
+        self.write_body(&mut out, body);
+        out
+    }
+
+    /// How `address` is rendered when it is not the address of a datatype, accounting for named
+    /// addresses.
+    pub fn address(&self, address: AccountAddress) -> String {
+        match self.named_addresses.get(&address) {
+            Some(name) => name.clone(),
+            None => format!("0x{}", address.short_str_lossless()),
+        }
+    }
+
+    fn write_signature(&self, out: &mut String, sig: &OpenSignature) {
+        match sig.ref_ {
+            Some(Reference::Immutable) => out.push('&'),
+            Some(Reference::Mutable) => out.push_str("&mut "),
+            None => {}
+        }
+
+        self.write_body(out, &sig.body);
+    }
+
+    fn write_body(&self, out: &mut String, body: &OpenSignatureBody) {
+        use OpenSignatureBody as O;
+
+        match body {
+            O::Address => out.push_str("address"),
+            O::Bool => out.push_str("bool"),
+            O::U8 => out.push_str("u8"),
+            O::U16 => out.push_str("u16"),
+            O::U32 => out.push_str("u32"),
+            O::U64 => out.push_str("u64"),
+            O::U128 => out.push_str("u128"),
+            O::U256 => out.push_str("u256"),
+            O::TypeParameter(ix) => write!(out, "T{ix}").unwrap(),
+
+            O::Vector(element) => {
+                out.push_str("vector<");
+                self.write_body(out, element);
+                out.push('>');
+            }
+
+            O::Datatype(key, params) => {
+                let id = self
+                    .datatype_ids
+                    .get(key)
+                    .or_else(|| self.package_ids.get(&key.package))
+                    .copied()
+                    .unwrap_or(key.package);
+
+                write!(out, "{}::{}::{}", self.address(id), key.module, key.name).unwrap();
+
+                if !params.is_empty() {
+                    out.push('<');
+                    for (ix, param) in params.iter().enumerate() {
+                        if ix > 0 {
+                            out.push_str(", ");
+                        }
+                        self.write_body(out, param);
+                    }
+                    out.push('>');
+                }
+            }
+        }
+    }
+}
+
+/// Write the ability constraints on a type parameter, if it has any (e.g. `: copy + drop`).
+fn write_constraints(out: &mut String, constraints: AbilitySet) {
+    if constraints != AbilitySet::EMPTY {
+        out.push_str(": ");
+        write_abilities(out, constraints, " + ");
+    }
+}
+
+fn write_abilities(out: &mut String, abilities: AbilitySet, sep: &str) {
+    for (ix, ability) in abilities.into_iter().enumerate() {
+        if ix > 0 {
+            out.push_str(sep);
+        }
+        write!(out, "{ability}").unwrap();
+    }
+}
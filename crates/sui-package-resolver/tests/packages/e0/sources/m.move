@@ -19,4 +19,6 @@ module e::m {
         z: Option<UTF8>,
         w: vector<Option<ASCII>>,
     );
+
+    entry fun bar(_o: &O, _x: u64, _p: &mut O, _z: Option<UTF8>, _w: vector<ASCII>) {}
 }
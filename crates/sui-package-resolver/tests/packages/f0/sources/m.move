@@ -0,0 +1,32 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+#[allow(unused_field)]
+module f::m {
+    use sui::object::UID;
+
+    public struct K has key, store { id: UID }
+
+    public enum Order has drop {
+        Zed,
+        Alpha { x: u8 },
+        Mid { b: bool },
+    }
+
+    public struct Wrap<T: store, phantom U: drop> has store {
+        t: T,
+    }
+
+    public native fun make(): K;
+
+    public native fun take(k: K, x: u64);
+
+    public native fun pair(): (K, u64);
+
+    public native fun dup<T: copy + drop>(x: T): T;
+
+    public native fun borrow(k: &K): &UID;
+
+    #[allow(unused_function)]
+    fun hidden() {}
+}
@@ -0,0 +1,20 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+module g::m {
+    const FEE_BPS: u64 = 30;
+    const VERSION: u8 = 2;
+    const ADMIN: address = @0x42;
+
+    #[error]
+    const ENotAdmin: vector<u8> = b"Sender is not the admin";
+
+    public fun check(sender: address, version: u8) {
+        assert!(sender == ADMIN, ENotAdmin);
+        assert!(version == VERSION, 0);
+    }
+
+    public fun fee(amount: u64): u64 {
+        amount * FEE_BPS / 10000
+    }
+}
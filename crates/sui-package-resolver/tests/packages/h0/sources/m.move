@@ -0,0 +1,35 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+#[allow(unused_field)]
+module h::m {
+    public struct Pool<phantom T> has store {
+        balance: u64,
+        fee: u64,
+    }
+
+    public struct Receipt has drop {
+        amount: u64,
+    }
+
+    public enum Status has copy, drop {
+        Active,
+        Paused { until: u64 },
+    }
+
+    public struct Order has drop {
+        price: u64,
+        size: u64,
+        owner: address,
+    }
+
+    public fun swap<T>(_pool: &mut Pool<T>, amount: u64): Receipt {
+        Receipt { amount }
+    }
+
+    public fun deposit<T>(_pool: &mut Pool<T>, _amount: u64) {}
+
+    entry fun reset(_epoch: u64) {}
+
+    fun helper(): u64 { 0 }
+}
@@ -0,0 +1,37 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+#[allow(unused_field)]
+module h::m {
+    public struct Pool<phantom T> has store {
+        balance: u128,
+        fee: u64,
+        admin: address,
+    }
+
+    public struct Receipt has copy, drop {
+        amount: u64,
+    }
+
+    public enum Status has copy, drop {
+        Active,
+        Paused { until: u64, reason: u8 },
+        Closed,
+    }
+
+    public struct Order has drop {
+        size: u64,
+        price: u64,
+        owner: address,
+    }
+
+    public fun swap<T>(_pool: &mut Pool<T>, amount: u64, _min_out: u64): Receipt {
+        Receipt { amount }
+    }
+
+    public(package) fun deposit<T>(_pool: &mut Pool<T>, _amount: u64) {}
+
+    public entry fun reset(_epoch: u64) {}
+
+    public fun withdraw<T>(_pool: &mut Pool<T>, _amount: u64) {}
+}
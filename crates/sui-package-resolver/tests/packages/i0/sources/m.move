@@ -0,0 +1,15 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+#[allow(unused_field)]
+module i::m {
+    public struct D0 { l: D1, r: D1 }
+    public struct D1 { l: D2, r: D2 }
+    public struct D2 { l: D3, r: D3 }
+    public struct D3 { l: D4, r: D4 }
+    public struct D4 { l: D5, r: D5 }
+    public struct D5 { l: D6, r: D6 }
+    public struct D6 { l: D7, r: D7 }
+    public struct D7 { l: D8, r: D8 }
+    public struct D8 { x: u8 }
+}
@@ -19,6 +19,10 @@ use syn::{parse_macro_input, Data, DeriveInput, Fields, Type};
 ///     pub fn new_constant_as_option(&self) -> Option<u64> {
 ///         self.new_constant
 ///     }
+///     // Fields holding milliseconds (named `*_ms`, e.g. `new_interval_ms`) also get getters that
+///     // return a `Duration`
+///     pub fn new_interval_duration(&self) -> std::time::Duration;
+///     pub fn new_interval_duration_as_option(&self) -> Option<std::time::Duration>;
 ///     // We auto derive an enum such that the variants are all the types of the fields
 ///     pub enum ProtocolConfigValue {
 ///        u32(u32),
@@ -38,8 +42,16 @@ use syn::{parse_macro_input, Data, DeriveInput, Fields, Type};
 ///
 ///     /// Returns a map of all features to values
 ///     pub fn feature_map(&self) -> std::collections::BTreeMap<String, bool>;
+///
+///     /// Returns the names of all fields marked `#[node_local_tolerant]`
+///     pub fn node_local_tolerant_attrs() -> Vec<String>;
+///
+///     // The name, type, doc comment and unit of each config, in declaration order. Units are
+///     // given by a `#[unit(Variant)]` attribute naming a variant of `ConfigUnit` (which must be
+///     // in scope), or inferred from the field's name for `*_bytes` and `*_ms` fields.
+///     const CONSTANT_METADATA: &'static [(&'static str, &'static str, &'static str, Option<ConfigUnit>)];
 /// ```
-#[proc_macro_derive(ProtocolConfigAccessors)]
+#[proc_macro_derive(ProtocolConfigAccessors, attributes(node_local_tolerant, unit))]
 pub fn accessors_macro(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
 
@@ -47,6 +59,25 @@ pub fn accessors_macro(input: TokenStream) -> TokenStream {
     let data = &ast.data;
     let mut inner_types = vec![];
 
+    // Fields whose exact value nodes do not need to agree on.
+    let node_local_tolerant_fields: Vec<_> = match data {
+        Data::Struct(data_struct) => match &data_struct.fields {
+            Fields::Named(fields_named) => fields_named
+                .named
+                .iter()
+                .filter(|field| {
+                    field
+                        .attrs
+                        .iter()
+                        .any(|attr| attr.path.is_ident("node_local_tolerant"))
+                })
+                .map(|field| field.ident.clone().expect("Field must be named"))
+                .collect(),
+            _ => panic!("Only named fields are supported."),
+        },
+        _ => panic!("Only structs supported."),
+    };
+
     let tokens = match data {
         Data::Struct(data_struct) => match &data_struct.fields {
             // Operate on each field of the ProtocolConfig struct
@@ -89,6 +120,27 @@ pub fn accessors_macro(input: TokenStream) -> TokenStream {
                         let test_setter_from_str_name: proc_macro2::TokenStream =
                             format!("set_{field_name}_from_str_for_testing").parse().unwrap();
 
+                        // Fields measured in milliseconds also get getters returning a Duration.
+                        let duration_getter = field_name
+                            .to_string()
+                            .strip_suffix("_ms")
+                            .map(|stem| {
+                                let duration_name: proc_macro2::TokenStream =
+                                    format!("{stem}_duration").parse().unwrap();
+                                let duration_as_option_name: proc_macro2::TokenStream =
+                                    format!("{stem}_duration_as_option").parse().unwrap();
+
+                                quote! {
+                                    pub fn #duration_name(&self) -> std::time::Duration {
+                                        std::time::Duration::from_millis(self.#field_name().into())
+                                    }
+
+                                    pub fn #duration_as_option_name(&self) -> Option<std::time::Duration> {
+                                        self.#field_name.map(|v| std::time::Duration::from_millis(v.into()))
+                                    }
+                                }
+                            });
+
                         let getter = quote! {
                             // Derive the getter
                             pub fn #field_name(&self) -> #inner_type {
@@ -98,6 +150,8 @@ pub fn accessors_macro(input: TokenStream) -> TokenStream {
                             pub fn #as_option_name(&self) -> #field_type {
                                 self.#field_name
                             }
+
+                            #duration_getter
                         };
 
                         let test_setter = quote! {
@@ -131,6 +185,13 @@ pub fn accessors_macro(input: TokenStream) -> TokenStream {
                             stringify!(#field_name)
                         };
 
+                        let type_str = quote!(#inner_type).to_string();
+                        let doc = doc_comment(&field.attrs);
+                        let unit = unit(field_name, &field.attrs);
+                        let metadata = quote! {
+                            (stringify!(#field_name), #type_str, #doc, #unit),
+                        };
+
                         // Track all the types seen
                         if inner_types.contains(&inner_type) {
                             None
@@ -141,7 +202,10 @@ pub fn accessors_macro(input: TokenStream) -> TokenStream {
                             })
                         };
 
-                        Some(((getter, (test_setter, value_setter)), (value_lookup, field_name_str)))
+                        Some((
+                            (getter, (test_setter, value_setter)),
+                            (value_lookup, (field_name_str, metadata)),
+                        ))
                     }
                     _ => None,
                 }
@@ -152,14 +216,20 @@ pub fn accessors_macro(input: TokenStream) -> TokenStream {
     };
 
     #[allow(clippy::type_complexity)]
-    let ((getters, (test_setters, value_setters)), (value_lookup, field_names_str)): (
+    let ((getters, (test_setters, value_setters)), (value_lookup, (field_names_str, metadata))): (
+        (Vec<_>, (Vec<_>, Vec<_>)),
         (Vec<_>, (Vec<_>, Vec<_>)),
-        (Vec<_>, Vec<_>),
     ) = tokens.unzip();
     let output = quote! {
         // For each getter, expand it out into a function in the impl block
         impl #struct_name {
             const CONSTANT_ERR_MSG: &'static str = "protocol constant not present in current protocol version";
+
+            /// The name, type, doc comment and unit of each config attribute, in declaration order.
+            #[allow(clippy::type_complexity)]
+            const CONSTANT_METADATA: &'static [(&'static str, &'static str, &'static str, Option<ConfigUnit>)] = &[
+                #(#metadata)*
+            ];
             #(#getters)*
 
             /// Lookup a config attribute by its string representation
@@ -185,6 +255,12 @@ pub fn accessors_macro(input: TokenStream) -> TokenStream {
             pub fn feature_map(&self) -> std::collections::BTreeMap<String, bool> {
                 self.feature_flags.attr_map()
             }
+
+            /// Names of the config attributes marked as `#[node_local_tolerant]`, meaning that
+            /// nodes do not need to agree on their exact value.
+            pub fn node_local_tolerant_attrs() -> Vec<String> {
+                vec![#(stringify!(#node_local_tolerant_fields).to_owned(),)*]
+            }
         }
 
         // For each attr, derive a setter from the raw value and from string repr
@@ -224,6 +300,42 @@ pub fn accessors_macro(input: TokenStream) -> TokenStream {
     TokenStream::from(output)
 }
 
+/// The contents of a field's doc comment, with each line trimmed, and lines joined by newlines.
+fn doc_comment(attrs: &[syn::Attribute]) -> String {
+    attrs
+        .iter()
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(syn::Meta::NameValue(syn::MetaNameValue {
+                path,
+                lit: syn::Lit::Str(doc),
+                ..
+            })) if path.is_ident("doc") => Some(doc.value().trim().to_owned()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The unit of a field, as an `Option<ConfigUnit>` expression: from its `#[unit(...)]` attribute,
+/// if it has one, or otherwise inferred from the suffix of its name.
+fn unit(field_name: &syn::Ident, attrs: &[syn::Attribute]) -> proc_macro2::TokenStream {
+    if let Some(attr) = attrs.iter().find(|attr| attr.path.is_ident("unit")) {
+        let unit: syn::Ident = attr
+            .parse_args()
+            .expect("#[unit(...)] expects the name of a ConfigUnit variant");
+        return quote!(Some(ConfigUnit::#unit));
+    }
+
+    let name = field_name.to_string();
+    if name.ends_with("_bytes") {
+        quote!(Some(ConfigUnit::Bytes))
+    } else if name.ends_with("_ms") {
+        quote!(Some(ConfigUnit::Milliseconds))
+    } else {
+        quote!(None)
+    }
+}
+
 #[proc_macro_derive(ProtocolConfigOverride)]
 pub fn protocol_config_override_macro(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
@@ -282,13 +394,144 @@ pub fn protocol_config_override_macro(input: TokenStream) -> TokenStream {
     TokenStream::from(output)
 }
 
-#[proc_macro_derive(ProtocolConfigFeatureFlagsGetters)]
+/// This proc macro generates a clap `Args` struct, `<Struct>LocalnetArgs`, with an optional
+/// argument for each `Option<T>` field marked `#[localnet_overridable]`, named after the field
+/// (e.g. `--protocol.max-tx-size-bytes`), and documented by the field's doc comment. Values are
+/// parsed with `parse_localnet_override`, which must be in scope where the macro is used.
+///
+/// The struct's `apply_to` method sets the fields that were supplied as arguments in a config.
+///
+/// The generated code is only compiled with the `overrides` feature of the crate that uses the
+/// macro, which must depend on `clap` under that feature.
+#[proc_macro_derive(ProtocolConfigLocalnetArgs, attributes(localnet_overridable))]
+pub fn localnet_args_macro(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+
+    let struct_name = &ast.ident;
+    let args_struct_name =
+        syn::Ident::new(&format!("{}LocalnetArgs", struct_name), struct_name.span());
+
+    let fields = match &ast.data {
+        Data::Struct(data_struct) => match &data_struct.fields {
+            Fields::Named(fields_named) => &fields_named.named,
+            _ => panic!("ProtocolConfig must have named fields"),
+        },
+        _ => panic!("ProtocolConfig must be a struct"),
+    };
+
+    let (arg_fields, update_fields): (Vec<_>, Vec<_>) = fields
+        .iter()
+        .filter(|field| {
+            field
+                .attrs
+                .iter()
+                .any(|attr| attr.path.is_ident("localnet_overridable"))
+        })
+        .map(|field| {
+            let field_name = field.ident.as_ref().expect("Field must be named");
+            let field_type = &field.ty;
+            let docs = field.attrs.iter().filter(|attr| attr.path.is_ident("doc"));
+
+            // Extract inner type T from Option<T>
+            let inner_type = match field_type {
+                Type::Path(type_path) => match type_path.path.segments.last() {
+                    Some(segment) if segment.ident == "Option" => match &segment.arguments {
+                        syn::PathArguments::AngleBracketed(args) => match args.args.first() {
+                            Some(syn::GenericArgument::Type(ty)) => ty.clone(),
+                            _ => panic!("Expected a type argument."),
+                        },
+                        _ => panic!("Expected angle bracketed arguments."),
+                    },
+                    _ => panic!("#[localnet_overridable] fields must be of type Option<T>"),
+                },
+                _ => panic!("#[localnet_overridable] fields must be of type Option<T>"),
+            };
+
+            let long = format!("protocol.{}", field_name.to_string().replace('_', "-"));
+
+            let arg_field = quote! {
+                #(#docs)*
+                #[clap(long = #long, value_parser = parse_localnet_override::<#inner_type>)]
+                pub #field_name: #field_type
+            };
+
+            let update_field = quote! {
+                if let Some(value) = self.#field_name {
+                    config.#field_name = Some(value);
+                }
+            };
+
+            (arg_field, update_field)
+        })
+        .unzip();
+
+    let output = quote! {
+        #[cfg(feature = "overrides")]
+        #[derive(clap::Args, Clone, Debug, Default)]
+        pub struct #args_struct_name {
+            #(#arg_fields,)*
+        }
+
+        #[cfg(feature = "overrides")]
+        impl #args_struct_name {
+            /// Set the fields that were supplied as arguments in `config`.
+            pub fn apply_to(&self, config: &mut #struct_name) {
+                #(#update_fields)*
+            }
+        }
+    };
+
+    TokenStream::from(output)
+}
+
+/// This proc macro generates getters for the boolean fields of a feature flags struct, and a
+/// `Feature` enum with a variant for each of them. Every boolean field must carry a unique
+/// `#[feature_id(N)]` attribute, which becomes its variant's stable numeric ID.
+///
+/// It also generates a `<Struct>Builder`, with a setter for every field of the struct (boolean or
+/// not), for tests to describe a combination of flags to apply to an existing set of flags:
+///
+/// ```rust,ignore
+/// FeatureFlagsBuilder::new()
+///     .new_protocol_feature(true)
+///     .feature(Feature::OtherFeature, false)
+///     .apply_to_flags(&mut flags);
+/// ```
+#[proc_macro_derive(ProtocolConfigFeatureFlagsGetters, attributes(feature_id))]
 pub fn feature_flag_getters_macro(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
 
     let struct_name = &ast.ident;
     let data = &ast.data;
 
+    // Stable numeric IDs of boolean feature flags, from their `#[feature_id(N)]` attributes.
+    let mut feature_ids = std::collections::BTreeMap::new();
+    let features: Vec<_> = match data {
+        Data::Struct(data_struct) => match &data_struct.fields {
+            Fields::Named(fields_named) => fields_named
+                .named
+                .iter()
+                .filter(|field| is_bool(&field.ty))
+                .map(|field| {
+                    let field_name = field.ident.clone().expect("Field must be named");
+                    let id = feature_id(field);
+                    if let Some(prev) = feature_ids.insert(id, field_name.clone()) {
+                        panic!("Feature ID {id} is used by both `{prev}` and `{field_name}`");
+                    }
+
+                    let variant = syn::Ident::new(&pascal_case(&field_name), field_name.span());
+                    (field_name, variant, id)
+                })
+                .collect(),
+            _ => panic!("Only named fields are supported."),
+        },
+        _ => panic!("Only structs supported."),
+    };
+
+    let feature_names: Vec<_> = features.iter().map(|(name, _, _)| name).collect();
+    let feature_variants: Vec<_> = features.iter().map(|(_, variant, _)| variant).collect();
+    let feature_ids: Vec<_> = features.iter().map(|(_, _, id)| id).collect();
+
     let getters = match data {
         Data::Struct(data_struct) => match &data_struct.fields {
             // Operate on each field of the ProtocolConfig struct
@@ -298,13 +541,7 @@ pub fn feature_flag_getters_macro(input: TokenStream) -> TokenStream {
                 let field_type = &field.ty;
                 // Check if field is of type bool
                 match field_type {
-                    Type::Path(type_path)
-                        if type_path
-                            .path
-                            .segments
-                            .last()
-                            .map_or(false, |segment| segment.ident == "bool") =>
-                    {
+                    field_type if is_bool(field_type) => {
                         Some((
                             quote! {
                                 // Derive the getter
@@ -333,6 +570,48 @@ pub fn feature_flag_getters_macro(input: TokenStream) -> TokenStream {
     let (by_fn_getters, (string_name_getters, field_names)): (Vec<_>, (Vec<_>, Vec<_>)) =
         getters.unzip();
 
+    let builder_name = syn::Ident::new(&format!("{struct_name}Builder"), struct_name.span());
+    let all_fields: Vec<_> = match data {
+        Data::Struct(data_struct) => match &data_struct.fields {
+            Fields::Named(fields_named) => fields_named
+                .named
+                .iter()
+                .map(|field| {
+                    (
+                        field.ident.as_ref().expect("Field must be named"),
+                        &field.ty,
+                    )
+                })
+                .collect(),
+            _ => panic!("Only named fields are supported."),
+        },
+        _ => panic!("Only structs supported."),
+    };
+
+    let builder_fields = all_fields.iter().map(|(name, ty)| {
+        quote! {
+            #name: Option<#ty>
+        }
+    });
+
+    let builder_setters = all_fields.iter().map(|(name, ty)| {
+        quote! {
+            #[doc = concat!("Set `", stringify!(#name), "` to `val`.")]
+            pub fn #name(mut self, val: #ty) -> Self {
+                self.#name = Some(val);
+                self
+            }
+        }
+    });
+
+    let builder_apply = all_fields.iter().map(|(name, _)| {
+        quote! {
+            if let Some(val) = &self.#name {
+                flags.#name = val.clone();
+            }
+        }
+    });
+
     let output = quote! {
         // For each getter, expand it out into a function in the impl block
         impl #struct_name {
@@ -353,8 +632,124 @@ pub fn feature_flag_getters_macro(input: TokenStream) -> TokenStream {
                     #(((#field_names).to_owned(), self.lookup_attr((#field_names).to_owned()).unwrap()),)*
                     ].into_iter().collect()
             }
+
+            /// Whether `feature` is enabled.
+            pub fn feature(&self, feature: Feature) -> bool {
+                match feature {
+                    #(Feature::#feature_variants => self.#feature_names,)*
+                }
+            }
+
+            /// Enable or disable `feature`.
+            pub fn set_feature_for_testing(&mut self, feature: Feature, val: bool) {
+                match feature {
+                    #(Feature::#feature_variants => self.#feature_names = val,)*
+                }
+            }
+        }
+
+        /// A combination of feature flag settings, to apply on top of an existing set of flags in
+        /// tests. Flags that are not set are left as they are.
+        #[derive(Clone, Debug, Default)]
+        pub struct #builder_name {
+            #(#builder_fields,)*
+        }
+
+        impl #builder_name {
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            #(#builder_setters)*
+
+            /// Set boolean feature flag `feature` to `val`.
+            pub fn feature(mut self, feature: Feature, val: bool) -> Self {
+                match feature {
+                    #(Feature::#feature_variants => self.#feature_names = Some(val),)*
+                }
+                self
+            }
+
+            /// Overwrite the flags in `flags` that have been set in this builder.
+            pub(crate) fn apply_to_flags(&self, flags: &mut #struct_name) {
+                #(#builder_apply)*
+            }
+        }
+
+        /// A boolean feature flag, with a stable numeric ID that identifies it compactly, e.g. in
+        /// metric labels.
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub enum Feature {
+            #(#feature_variants,)*
+        }
+
+        impl Feature {
+            /// All feature flags, in declaration order.
+            pub const ALL: &'static [Feature] = &[#(Feature::#feature_variants,)*];
+
+            /// The feature's stable numeric ID.
+            pub fn id(self) -> u32 {
+                match self {
+                    #(Feature::#feature_variants => #feature_ids,)*
+                }
+            }
+
+            /// The feature with the given numeric ID, if there is one.
+            pub fn from_id(id: u32) -> Option<Self> {
+                match id {
+                    #(#feature_ids => Some(Feature::#feature_variants),)*
+                    _ => None,
+                }
+            }
+
+            /// The feature flag's name, as used by `lookup_feature` and `feature_map`.
+            pub fn name(self) -> &'static str {
+                match self {
+                    #(Feature::#feature_variants => stringify!(#feature_names),)*
+                }
+            }
         }
     };
 
     TokenStream::from(output)
 }
+
+fn is_bool(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .map_or(false, |segment| segment.ident == "bool"),
+        _ => false,
+    }
+}
+
+/// The ID in a feature flag's `#[feature_id(N)]` attribute, which every boolean feature flag must
+/// have.
+fn feature_id(field: &syn::Field) -> u32 {
+    let field_name = field.ident.as_ref().expect("Field must be named");
+    let attr = field
+        .attrs
+        .iter()
+        .find(|attr| attr.path.is_ident("feature_id"))
+        .unwrap_or_else(|| panic!("Feature flag `{field_name}` needs a #[feature_id(N)]"));
+
+    attr.parse_args::<syn::LitInt>()
+        .and_then(|id| id.base10_parse())
+        .unwrap_or_else(|e| panic!("Bad #[feature_id(..)] on `{field_name}`: {e}"))
+}
+
+/// Convert a snake_case field name into a PascalCase identifier.
+fn pascal_case(name: &syn::Ident) -> String {
+    name.to_string()
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
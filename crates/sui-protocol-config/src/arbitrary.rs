@@ -0,0 +1,133 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Random `ProtocolConfig`s for property tests and fuzzers, which would otherwise only exercise the
+//! fixed config of each protocol version.
+//!
+//! A generated config starts as the config of a random supported version, and then has some of
+//! its feature flags toggled and some of its limits and gas costs scaled (by a factor between 0.5
+//! and 2). Each change is kept only if the config still satisfies the invariants that
+//! `ProtocolConfig::validate_transition` checks: the relationships between constants (see
+//! `check_overrides` and `check_gas_limits`), and the dependencies between feature flags. Toggling
+//! a flag also toggles the flags it depends on (when enabling it), or that depend on it (when
+//! disabling it).
+//!
+//! Constants that a feature flag relies on are not introduced when the flag is enabled at a version
+//! that predates them, so code under test should tolerate constants that are unset.
+
+use proptest::prelude::*;
+use proptest::sample::Index;
+
+use crate::{
+    transition_violations, Chain, ConstantCategory, Feature, ProtocolConfig, ProtocolConfigValue,
+    ProtocolVersion, FEATURE_FLAG_DEPENDENCIES, MAX_PROTOCOL_VERSION, MIN_PROTOCOL_VERSION,
+    MONOTONIC_CONSTANTS,
+};
+
+/// The maximum number of feature flags toggled in a generated config.
+const MAX_FEATURE_TOGGLES: usize = 8;
+
+/// The maximum number of constants scaled in a generated config.
+const MAX_SCALED_CONSTANTS: usize = 16;
+
+/// Generates configs for the chain given as the strategy's parameter (`Chain::Unknown` by
+/// default), across every protocol version supported by this binary.
+impl Arbitrary for ProtocolConfig {
+    type Parameters = Chain;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(chain: Chain) -> Self::Strategy {
+        (
+            MIN_PROTOCOL_VERSION..=MAX_PROTOCOL_VERSION,
+            prop::collection::vec((any::<Index>(), any::<bool>()), 0..=MAX_FEATURE_TOGGLES),
+            prop::collection::vec((any::<Index>(), 50u128..=200), 0..=MAX_SCALED_CONSTANTS),
+        )
+            .prop_map(move |(version, toggles, scales)| {
+                let base = ProtocolConfig::get_for_version_if_supported(
+                    ProtocolVersion::new(version),
+                    chain,
+                )
+                .expect("Version is supported");
+
+                let mut config = base.clone();
+                for (feature, val) in toggles {
+                    let feature = *feature.get(Feature::ALL);
+                    try_change(&base, &mut config, |c| {
+                        set_feature_with_dependencies(c, feature, val)
+                    });
+                }
+
+                let scalable = scalable_constants(&config);
+                if !scalable.is_empty() {
+                    for (constant, percent) in scales {
+                        let (name, value, max) = constant.get(&scalable);
+                        let scaled = (value * percent / 100).clamp(1, *max);
+                        try_change(&base, &mut config, |c| {
+                            c.set_attr_for_testing(name.to_string(), scaled.to_string())
+                        });
+                    }
+                }
+
+                config
+            })
+            .boxed()
+    }
+}
+
+/// Apply `change` to `config`, and undo it if the result violates any invariants (treating `base`
+/// as the config being upgraded from).
+fn try_change(
+    base: &ProtocolConfig,
+    config: &mut ProtocolConfig,
+    change: impl FnOnce(&mut ProtocolConfig),
+) {
+    let mut changed = config.clone();
+    change(&mut changed);
+    if transition_violations(base, &changed).is_empty() {
+        *config = changed;
+    }
+}
+
+/// Set `feature` to `val`, enabling the flags it depends on if it is being enabled, or disabling
+/// the flags that depend on it if it is being disabled.
+fn set_feature_with_dependencies(config: &mut ProtocolConfig, feature: Feature, val: bool) {
+    config.set_feature_for_testing(feature, val);
+
+    for (flag, requires) in FEATURE_FLAG_DEPENDENCIES {
+        let next = match val {
+            true if *flag == feature.name() => requires,
+            false if *requires == feature.name() => flag,
+            _ => continue,
+        };
+
+        if let Some(next) = Feature::ALL.iter().find(|f| f.name() == *next) {
+            if config.is_feature_enabled(*next) != val {
+                set_feature_with_dependencies(config, *next, val);
+            }
+        }
+    }
+}
+
+/// Limits and gas costs that are set in `config`, with their values and the maximum value of
+/// their types. Consensus, randomness and other parameters, constants that select the version of
+/// some component, and constants that are zero (often used to disable a mechanism) are excluded.
+fn scalable_constants(config: &ProtocolConfig) -> Vec<(&'static str, u128, u128)> {
+    config
+        .iter_constants()
+        .filter(|(name, category, ..)| {
+            *category != ConstantCategory::Other
+                && !MONOTONIC_CONSTANTS.contains(name)
+                && !name.ends_with("_version")
+        })
+        .filter_map(|(name, _, _, value, _)| {
+            let (value, max) = match value? {
+                ProtocolConfigValue::u16(v) => (v as u128, u16::MAX as u128),
+                ProtocolConfigValue::u32(v) => (v as u128, u32::MAX as u128),
+                ProtocolConfigValue::u64(v) => (v as u128, u64::MAX as u128),
+                _ => return None,
+            };
+
+            (value != 0).then_some((name, value, max))
+        })
+        .collect()
+}
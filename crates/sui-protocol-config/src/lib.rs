@@ -2,23 +2,38 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
-    cell::RefCell,
-    collections::BTreeSet,
-    sync::atomic::{AtomicBool, Ordering},
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet},
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, OnceLock,
+    },
+    time::Duration,
 };
 
+#[cfg(feature = "overrides")]
 use clap::*;
+use fastcrypto::hash::{Blake2b256, HashFunction};
 use move_vm_config::verifier::VerifierConfig;
 use serde::{Deserialize, Serialize};
-use serde_with::skip_serializing_none;
+use serde_with::{serde_as, skip_serializing_none, DisplayFromStr, IfIsHumanReadable};
 use sui_protocol_config_macros::{
-    ProtocolConfigAccessors, ProtocolConfigFeatureFlagsGetters, ProtocolConfigOverride,
+    ProtocolConfigAccessors, ProtocolConfigFeatureFlagsGetters, ProtocolConfigLocalnetArgs,
+    ProtocolConfigOverride,
 };
 use tracing::{info, warn};
 
+#[cfg(any(test, feature = "test-utils"))]
+mod arbitrary;
+#[cfg(any(test, msim, feature = "test-utils"))]
+pub mod testing;
+#[cfg(any(test, feature = "watcher"))]
+pub mod watcher;
+
 /// The minimum and maximum protocol versions supported by this build.
 const MIN_PROTOCOL_VERSION: u64 = 1;
-const MAX_PROTOCOL_VERSION: u64 = 71;
+const MAX_PROTOCOL_VERSION: u64 = 72;
 
 // Record history of protocol version allocations here:
 //
@@ -204,6 +219,7 @@ const MAX_PROTOCOL_VERSION: u64 = 71;
 //             Add std::uq64_64 module to Move stdlib.
 //             Improve gas/wall time efficiency of some Move stdlib vector functions
 // Version 71: [SIP-45] Enable consensus amplification.
+// Version 72: Add versioned limits for multisig, zkLogin and passkey signatures.
 
 #[derive(Copy, Clone, Debug, Hash, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ProtocolVersion(u64);
@@ -259,7 +275,8 @@ impl std::ops::Add<u64> for ProtocolVersion {
     }
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Copy, PartialOrd, Ord, Eq, ValueEnum)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Copy, PartialOrd, Ord, Eq)]
+#[cfg_attr(feature = "overrides", derive(ValueEnum))]
 pub enum Chain {
     Mainnet,
     Testnet,
@@ -282,6 +299,7 @@ impl Chain {
     }
 }
 
+#[derive(Debug)]
 pub struct Error(pub String);
 
 // TODO: There are quite a few non boolean values in the feature flags. We should move them out.
@@ -289,66 +307,89 @@ pub struct Error(pub String);
 #[derive(Default, Clone, Serialize, Deserialize, Debug, ProtocolConfigFeatureFlagsGetters)]
 struct FeatureFlags {
     // Add feature flags here, e.g.:
+    // #[feature_id(N)]
     // new_protocol_feature: bool,
+    //
+    // Every boolean feature flag has a stable numeric ID, used to identify it compactly (e.g. in
+    // telemetry). IDs must be unique, and must not be reused, even after a flag is removed.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(1)]
     package_upgrades: bool,
     // If true, validators will commit to the root state digest
     // in end of epoch checkpoint proposals
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(2)]
     commit_root_state_digest: bool,
     // Pass epoch start time to advance_epoch safe mode function.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(3)]
     advance_epoch_start_time_in_safe_mode: bool,
     // If true, apply the fix to correctly capturing loaded child object versions in execution's
     // object runtime.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(4)]
     loaded_child_objects_fixed: bool,
     // If true, treat missing types in the upgraded modules when creating an upgraded package as a
     // compatibility error.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(5)]
     missing_type_is_compatibility_error: bool,
     // If true, then the scoring decision mechanism will not get disabled when we do have more than
     // f low scoring authorities, but it will simply flag as low scoring only up to f authorities.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(6)]
     scoring_decision_with_validity_cutoff: bool,
 
     // DEPRECATED: this was an ephemeral feature flag only used by consensus handler, which has now
     // been deployed everywhere.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(7)]
     consensus_order_end_of_epoch_last: bool,
 
     // Disallow adding abilities to types during package upgrades.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(8)]
     disallow_adding_abilities_on_upgrade: bool,
     // Disables unnecessary invariant check in the Move VM when swapping the value out of a local
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(9)]
     disable_invariant_violation_check_in_swap_loc: bool,
     // advance to highest supported protocol version at epoch change, instead of the next consecutive
     // protocol version.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(10)]
     advance_to_highest_supported_protocol_version: bool,
     // If true, disallow entry modifiers on entry functions
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(11)]
     ban_entry_init: bool,
     // If true, hash module bytes individually when calculating package digests for upgrades
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(12)]
     package_digest_hash_module: bool,
     // If true, disallow changing struct type parameters during package upgrades
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(13)]
     disallow_change_struct_type_params_on_upgrade: bool,
     // If true, checks no extra bytes in a compiled module
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(14)]
     no_extraneous_module_bytes: bool,
     // If true, then use the versioned metadata format in narwhal entities.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(15)]
     narwhal_versioned_metadata: bool,
 
     // Enable zklogin auth
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(16)]
     zklogin_auth: bool,
     // How we order transactions coming out of consensus before sending to execution.
     #[serde(skip_serializing_if = "ConsensusTransactionOrdering::is_none")]
     consensus_transaction_ordering: ConsensusTransactionOrdering,
+    // How transactions with the same gas price are ordered, when ordering by gas price.
+    #[serde(skip_serializing_if = "GasPriceTieBreak::is_submission_order")]
+    consensus_gas_price_tie_break: GasPriceTieBreak,
 
     // Previously, the unwrapped_then_deleted field in TransactionEffects makes a distinction between
     // whether an object has existed in the store previously (i.e. whether there is a tombstone).
@@ -358,20 +399,25 @@ struct FeatureFlags {
     // unwrapped_then_deleted to always include unwrapped then deleted objects,
     // regardless of their previous state in the store.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(17)]
     simplified_unwrap_then_delete: bool,
     // Enable upgraded multisig support
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(18)]
     upgraded_multisig_supported: bool,
     // If true minimum txn charge is a multiplier of the gas price
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(19)]
     txn_base_cost_as_multiplier: bool,
 
     // If true, the ability to delete shared objects is in effect
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(20)]
     shared_object_deletion: bool,
 
     // If true, then the new algorithm for the leader election schedule will be used
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(21)]
     narwhal_new_leader_election_schedule: bool,
 
     // A list of supported OIDC providers that can be used for zklogin.
@@ -380,89 +426,111 @@ struct FeatureFlags {
 
     // If true, use the new child object format
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(22)]
     loaded_child_object_format: bool,
 
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(23)]
     enable_jwk_consensus_updates: bool,
 
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(24)]
     end_of_epoch_transaction_supported: bool,
 
     // Perform simple conservation checks keeping into account out of gas scenarios
     // while charging for storage.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(25)]
     simple_conservation_checks: bool,
 
     // If true, use the new child object format type logging
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(26)]
     loaded_child_object_format_type: bool,
 
     // Enable receiving sent objects
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(27)]
     receive_objects: bool,
 
     // Enable random beacon protocol
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(28)]
     random_beacon: bool,
 
     // Enable bridge protocol
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(29)]
     bridge: bool,
 
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(30)]
     enable_effects_v2: bool,
 
     // If true, then use CertificateV2 in narwhal.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(31)]
     narwhal_certificate_v2: bool,
 
     // If true, allow verify with legacy zklogin address
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(32)]
     verify_legacy_zklogin_address: bool,
 
     // Enable throughput aware consensus submission
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(33)]
     throughput_aware_consensus_submission: bool,
 
     // If true, recompute has_public_transfer from the type instead of what is stored in the object
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(34)]
     recompute_has_public_transfer_in_execution: bool,
 
     // If true, multisig containing zkLogin sig is accepted.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(35)]
     accept_zklogin_in_multisig: bool,
 
     // If true, consensus prologue transaction also includes the consensus output digest.
     // It can be used to detect consensus output folk.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(36)]
     include_consensus_digest_in_prologue: bool,
 
     // If true, use the hardened OTW check
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(37)]
     hardened_otw_check: bool,
 
     // If true allow calling receiving_object_id function
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(38)]
     allow_receiving_object_id: bool,
 
     // Enable the poseidon hash function
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(39)]
     enable_poseidon: bool,
 
     // If true, enable the coin deny list.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(40)]
     enable_coin_deny_list: bool,
 
     // Enable native functions for group operations.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(41)]
     enable_group_ops_native_functions: bool,
 
     // Enable native function for msm.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(42)]
     enable_group_ops_native_function_msm: bool,
 
     // Reject functions with mutable Random.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(43)]
     reject_mutable_random_on_entry_functions: bool,
 
     // Controls the behavior of per object congestion control in consensus handler.
@@ -483,34 +551,41 @@ struct FeatureFlags {
 
     // Controls leader scoring & schedule change in Mysticeti consensus.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(44)]
     mysticeti_leader_scoring_and_schedule: bool,
 
     // Enable resharing of shared objects using the same initial shared version
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(45)]
     reshare_at_same_initial_version: bool,
 
     // Resolve Move abort locations to the package id instead of the runtime module ID.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(46)]
     resolve_abort_locations_to_package_id: bool,
 
     // Enables the use of the Mysticeti committed sub dag digest to the `ConsensusCommitInfo` in checkpoints.
     // When disabled the default digest is used instead. It's important to have this guarded behind
     // a flag as it will lead to checkpoint forks.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(47)]
     mysticeti_use_committed_subdag_digest: bool,
 
     // Enable VDF
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(48)]
     enable_vdf: bool,
 
     // Controls whether consensus handler should record consensus determined shared object version
     // assignments in consensus commit prologue transaction.
     // The purpose of doing this is to enable replaying transaction without transaction effects.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(49)]
     record_consensus_determined_version_assignments_in_prologue: bool,
 
     // Run verification of framework upgrades using a new/fresh VM.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(50)]
     fresh_vm_on_framework_upgrade: bool,
 
     // When set to true, the consensus commit prologue transaction will be placed first
@@ -521,6 +596,7 @@ struct FeatureFlags {
     // The reason to prepose the prologue transaction is to provide information for transaction
     // cancellation.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(51)]
     prepend_prologue_tx_in_consensus_commit_in_checkpoints: bool,
 
     // Set number of leaders per round for Mysticeti commits.
@@ -529,64 +605,104 @@ struct FeatureFlags {
 
     // Enable Soft Bundle (SIP-19).
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(52)]
     soft_bundle: bool,
 
     // If true, enable the coin deny list V2.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(53)]
     enable_coin_deny_list_v2: bool,
 
     // Enable passkey auth (SIP-9)
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(54)]
     passkey_auth: bool,
 
     // Use AuthorityCapabilitiesV2
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(55)]
     authority_capabilities_v2: bool,
 
     // Rethrow type layout errors during serialization instead of trying to convert them.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(56)]
     rethrow_serialization_type_layout_errors: bool,
 
     // Use distributed vote leader scoring strategy in consensus.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(57)]
     consensus_distributed_vote_scoring_strategy: bool,
 
     // Probe rounds received by peers from every authority.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(58)]
     consensus_round_prober: bool,
 
     // Validate identifier inputs separately
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(59)]
     validate_identifier_inputs: bool,
 
     // Enables Mysticeti fastpath.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(60)]
     mysticeti_fastpath: bool,
 
     // Makes the event's sending module version-aware.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(61)]
     relocate_event_module: bool,
 
     // Enable uncompressed group elements in BLS123-81 G1
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(62)]
     uncompressed_g1_group_elements: bool,
 
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(63)]
     disallow_new_modules_in_deps_only_packages: bool,
 
     // Use smart ancestor selection in consensus.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(64)]
     consensus_smart_ancestor_selection: bool,
 
     // Probe accepted rounds in round prober.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(65)]
     consensus_round_prober_probe_accepted_rounds: bool,
 
     // Enable v2 native charging for natives.
     #[serde(skip_serializing_if = "is_false")]
+    #[feature_id(66)]
     native_charging_v2: bool,
+    // Next feature ID: 67
 }
 
+/// The protocol version that introduced `consensus_max_transaction_size_bytes`. From this version
+/// on, the constant must be set.
+const CONSENSUS_MAX_TRANSACTION_SIZE_VERSION: u64 = 36;
+
+/// Number of maximally sized checkpoints that state sync should be able to download at once.
+const STATE_SYNC_FULL_CHECKPOINTS_IN_FLIGHT: u64 = 5;
+
+/// Headroom that consensus must allow for, on top of `max_tx_size_bytes`, when bounding the size of
+/// a serialized transaction it accepts.
+const CONSENSUS_MAX_TRANSACTION_SIZE_HEADROOM_BYTES: u64 = 128 * 1024;
+
+/// Epoch that the sui-system framework resets a non-zero `stake_subsidy_start_epoch` to, on every
+/// epoch change (see `sui_system_state_inner::advance_epoch`). This, and the catch-up thresholds
+/// below, mirror literals in the framework rather than protocol config values.
+const STAKE_SUBSIDY_START_EPOCH: u64 = 20;
+
+/// Number of stake subsidy distributions that had happened on mainnet when it entered safe mode, at
+/// the change from `STAKE_SUBSIDY_CATCH_UP_FIRST_EPOCH` to the next epoch. The framework pays out
+/// the subsidies skipped while in safe mode at the first epoch change that sees this count.
+const STAKE_SUBSIDY_CATCH_UP_DISTRIBUTION_COUNT: u64 = 540;
+
+/// First epoch whose stake subsidy was skipped by safe mode on mainnet.
+const STAKE_SUBSIDY_CATCH_UP_FIRST_EPOCH: u64 = 560;
+
 fn is_false(b: &bool) -> bool {
     !b
 }
@@ -603,6 +719,10 @@ pub enum ConsensusTransactionOrdering {
     None,
     /// Order transactions by gas price, highest first.
     ByGasPrice,
+    /// Limit how many transactions each sender gets ahead of other senders: a sender's
+    /// transactions beyond `consensus_fair_ordering_per_sender_quota` are moved behind everyone
+    /// else's.
+    Fair,
 }
 
 impl ConsensusTransactionOrdering {
@@ -611,6 +731,35 @@ impl ConsensusTransactionOrdering {
     }
 }
 
+/// How to order transactions that have the same gas price, when ordering by gas price.
+#[derive(Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum GasPriceTieBreak {
+    /// Keep the order the transactions appear in the consensus output.
+    #[default]
+    SubmissionOrder,
+    /// Order by transaction digest, so that position cannot be gained by submitting early.
+    Digest,
+}
+
+impl GasPriceTieBreak {
+    pub fn is_submission_order(&self) -> bool {
+        matches!(self, GasPriceTieBreak::SubmissionOrder)
+    }
+}
+
+/// The ordering applied to transactions coming out of consensus, along with its parameters, as
+/// configured by `ConsensusTransactionOrdering` and its related protocol config.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ConsensusTransactionOrderingStrategy {
+    /// Transactions are processed in the order they appear in the consensus output.
+    None,
+    /// Order transactions by gas price, highest first, breaking ties with `tie_break`.
+    ByGasPrice { tie_break: GasPriceTieBreak },
+    /// Each sender's first `per_sender_quota` transactions keep their position, and the rest are
+    /// moved (in order) behind all other transactions.
+    Fair { per_sender_quota: u64 },
+}
+
 // The config for per object congestion control in consensus handler.
 #[derive(Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
 pub enum PerObjectCongestionControlMode {
@@ -627,6 +776,169 @@ impl PerObjectCongestionControlMode {
     }
 }
 
+/// Limits on the size of a serialized transaction, as enforced at different stages of its life.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct TransactionSizeLimits {
+    /// Maximum size of a transaction that validators will sign.
+    pub signing: u64,
+    /// Maximum size of a transaction that consensus will accept.
+    pub consensus: u64,
+    /// Maximum size of a transaction that clients can expect to be processed: the smallest of the
+    /// limits above.
+    pub client: u64,
+}
+
+/// Upper bounds on the number of items that execution can produce for a single transaction (user
+/// or system), for RPC layers to size responses by, so that a response that includes everything a
+/// transaction produced is never truncated.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct ResponseShapingLimits {
+    /// Maximum number of events a transaction can emit.
+    pub max_events: u64,
+    /// Maximum number of object changes (created, mutated, deleted, wrapped, unwrapped, ...) in a
+    /// transaction's effects.
+    pub max_object_changes: u64,
+}
+
+/// Node-level settings recommended for a protocol version, for tools that generate node configs,
+/// so that settings which depend on protocol limits keep up with them as they change.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct NodeConfigHints {
+    /// Upper bound on the number of transactions in checkpoint contents that state sync downloads
+    /// concurrently.
+    pub checkpoint_content_download_tx_concurrency: u64,
+}
+
+impl NodeConfigHints {
+    pub fn from_protocol_config(config: &ProtocolConfig) -> Self {
+        Self {
+            checkpoint_content_download_tx_concurrency: config.max_transactions_per_checkpoint()
+                * STATE_SYNC_FULL_CHECKPOINTS_IN_FLIGHT,
+        }
+    }
+}
+
+/// A rate in basis points (hundredths of a percent), no greater than 100%.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Debug)]
+pub struct BasisPoints(u16);
+
+impl BasisPoints {
+    /// The number of basis points in 100%.
+    pub const DENOMINATOR: u16 = 10_000;
+
+    /// `bps` basis points, or `None` if that exceeds 100%.
+    pub fn new(bps: u64) -> Option<Self> {
+        (bps <= Self::DENOMINATOR as u64).then_some(Self(bps as u16))
+    }
+
+    pub fn get(self) -> u16 {
+        self.0
+    }
+
+    /// This rate's share of `amount`, rounded down.
+    pub fn apply(self, amount: u64) -> u64 {
+        (amount as u128 * self.0 as u128 / Self::DENOMINATOR as u128) as u64
+    }
+}
+
+/// Economic parameters that govern storage rebates and reward distribution at epoch change.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct TokenomicsConfig {
+    /// Share of an object's storage rebate returned to the sender of a transaction that touches
+    /// it.
+    pub storage_rebate_rate: BasisPoints,
+    /// Share of the storage fund's rewards that is reinvested into the storage fund.
+    pub storage_fund_reinvest_rate: BasisPoints,
+    /// Share of a validator's rewards that is slashed and redistributed when it is reported by a
+    /// quorum of its peers.
+    pub reward_slashing_rate: BasisPoints,
+    /// Epoch thresholds for stake subsidy distribution, which do not vary by protocol version.
+    pub stake_subsidy: StakeSubsidyThresholds,
+}
+
+/// Thresholds that `sui_system_state_inner::advance_epoch` hardcodes to decide whether stake
+/// subsidies are distributed at epoch change. They are copies of the framework's literals, not
+/// protocol config values, so they do not follow protocol upgrades, and they must be kept in sync
+/// with the framework by hand.
+///
+/// The size of each distribution, and how it decreases over time (the stake subsidy's period
+/// length and decrease rate), are not included: they are stored in the on-chain `StakeSubsidy`
+/// object.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct StakeSubsidyThresholds {
+    /// Subsidies are not distributed before this epoch (unless the network was started with a
+    /// start epoch of zero).
+    pub start_epoch: u64,
+    /// Distribution count at which the subsidies skipped during mainnet's safe mode are paid out.
+    pub catch_up_distribution_count: u64,
+    /// First epoch whose subsidy was skipped during mainnet's safe mode.
+    pub catch_up_first_epoch: u64,
+}
+
+/// Parameters for per-object congestion control in the consensus handler, which defers
+/// transactions that would push the accumulated cost of any shared object they touch in a commit
+/// over budget.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct CongestionControlParams {
+    /// How the cost of a transaction is calculated.
+    pub mode: PerObjectCongestionControlMode,
+    /// Budget for the accumulated cost of transactions touching a shared object in a Mysticeti
+    /// commit, if set.
+    pub max_accumulated_txn_cost_per_object_in_commit: Option<u64>,
+    /// As above, for commits of transactions that use randomness. Falls back to the regular budget.
+    pub max_accumulated_randomness_txn_cost_per_object_in_commit: Option<u64>,
+    /// With `TotalGasBudgetWithCap`, a transaction's cost is capped at this factor times the
+    /// number of its Move calls and their inputs.
+    pub gas_budget_based_txn_cost_cap_factor: Option<u64>,
+    /// With `TotalGasBudgetWithCap`, a transaction's cost is also capped at this multiple of the
+    /// per-commit budget.
+    pub gas_budget_based_txn_cost_absolute_cap_commit_count: Option<u64>,
+    /// Amount by which a single transaction may take an object over its per-commit budget.
+    pub max_txn_cost_overage_per_object_in_commit: u64,
+    /// Amount by which an object's accumulated debt may exceed its per-commit budget, allowing
+    /// bursts across commits. No greater than `max_txn_cost_overage_per_object_in_commit`.
+    pub allowed_txn_cost_overage_burst_per_object_in_commit: u64,
+    /// Transactions deferred for more than this many consensus rounds are cancelled.
+    pub max_deferral_rounds: Option<u64>,
+}
+
+impl CongestionControlParams {
+    /// The per-object budget for commits of transactions that do (`for_randomness`) or do not use
+    /// randomness.
+    pub fn max_accumulated_txn_cost_per_object(&self, for_randomness: bool) -> Option<u64> {
+        if for_randomness {
+            self.max_accumulated_randomness_txn_cost_per_object_in_commit
+        } else {
+            self.max_accumulated_txn_cost_per_object_in_commit
+        }
+    }
+}
+
+/// Parameters for congestion control based on estimates of transaction execution time, gathered
+/// from the `execution_time_estimate_*` constants.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct ExecutionTimeEstimateParams {
+    /// Version of the table of per-command base execution time estimates.
+    pub base_estimates_table_version: u64,
+    /// Percentage of each shared object's execution time budget to target per commit.
+    pub target_utilization: u64,
+    /// Estimates are not refreshed until they are at least this old.
+    pub min_staleness: Duration,
+    /// Estimates older than this are discarded in favour of the base estimates.
+    pub max_staleness: Duration,
+}
+
+/// Parameters for JWK consensus, in which validators vote on the JWKs published by OpenID
+/// providers, and JWKs with a quorum of votes are added to the authenticator state object.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct JwkConfig {
+    /// Votes from a validator beyond this many in an epoch are ignored.
+    pub max_votes_per_validator_per_epoch: u64,
+    /// Number of epochs that a JWK stays in the authenticator state for, after the epoch it was
+    /// last active in. With an age of 1, JWKs active in one epoch remain valid throughout the next.
+    pub max_age_in_epochs: u64,
+}
+
 // Configuration options for consensus algorithm.
 #[derive(Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
 pub enum ConsensusChoice {
@@ -687,8 +999,24 @@ impl ConsensusNetwork {
 /// `pub fn new_constant_as_option(&self) -> Option<u64>` getter, which will
 /// return `None` if the field is not defined at that version.
 /// - If you want a customized getter, you can add a method in the impl.
+///
+/// Constants whose exact value nodes do not need to agree on (e.g. limits that are only enforced
+/// locally, before a transaction is submitted) can be marked with `#[node_local_tolerant]`. These
+/// are the only constants perturbed by the chaos mode used in tests (see
+/// `perturb_node_local_limits_for_testing`).
+///
+/// Constants that are safe to change on a local network can be marked with
+/// `#[localnet_overridable]`, which exposes them as `--protocol.*` arguments in
+/// `ProtocolConfigLocalnetArgs` (used by `sui start`).
 #[skip_serializing_none]
-#[derive(Clone, Serialize, Debug, ProtocolConfigAccessors, ProtocolConfigOverride)]
+#[derive(
+    Clone,
+    Serialize,
+    Debug,
+    ProtocolConfigAccessors,
+    ProtocolConfigOverride,
+    ProtocolConfigLocalnetArgs,
+)]
 pub struct ProtocolConfig {
     pub version: ProtocolVersion,
 
@@ -696,9 +1024,11 @@ pub struct ProtocolConfig {
 
     // ==== Transaction input limits ====
     /// Maximum serialized size of a transaction (in bytes).
+    #[localnet_overridable]
     max_tx_size_bytes: Option<u64>,
 
     /// Maximum number of input objects to a transaction. Enforced by the transaction input checker
+    #[localnet_overridable]
     max_input_objects: Option<u64>,
 
     /// Max size of objects a transaction can write to disk after completion. Enforce by the Sui adapter.
@@ -735,11 +1065,24 @@ pub struct ProtocolConfig {
     max_type_argument_depth: Option<u32>,
 
     /// Maximum size of a Pure CallArg.
+    #[localnet_overridable]
     max_pure_argument_size: Option<u32>,
 
     /// Maximum number of Commands in a ProgrammableTransaction.
+    #[localnet_overridable]
     max_programmable_tx_commands: Option<u32>,
 
+    /// Maximum number of public keys in a multisig public key.
+    max_multisig_signers: Option<u32>,
+
+    /// Maximum size of the zkLogin inputs (proof points, issuer and header details, and address
+    /// seed) carried by a zkLogin signature, when serialized.
+    max_zklogin_proof_size_bytes: Option<u32>,
+
+    /// Size of the challenge that a passkey signs, which is the digest of the transaction's intent
+    /// message.
+    passkey_challenge_size_bytes: Option<u32>,
+
     // ==== Move VM, Move bytecode verifier, and execution limits ===
     /// Maximum Move bytecode version the VM understands. All older versions are accepted.
     move_binary_format_version: Option<u32>,
@@ -766,6 +1109,7 @@ pub struct ProtocolConfig {
     binary_variant_instantiation_handles: Option<u16>,
 
     /// Maximum size of the `contents` part of an object, in bytes. Enforced by the Sui adapter when effects are produced.
+    #[localnet_overridable]
     max_move_object_size: Option<u64>,
 
     // TODO: Option<increase to 500 KB. currently, publishing a package > 500 KB exceeds the max computation gas cost
@@ -776,9 +1120,12 @@ pub struct ProtocolConfig {
     max_publish_or_upgrade_per_ptb: Option<u64>,
 
     /// Maximum gas budget in MIST that a transaction can use.
+    #[localnet_overridable]
+    #[unit(Mist)]
     max_tx_gas: Option<u64>,
 
     /// Maximum amount of the proposed gas price in MIST (defined in the transaction).
+    #[unit(Mist)]
     max_gas_price: Option<u64>,
 
     /// The max computation bucket for gas. This is the max that can be charged for computation.
@@ -842,6 +1189,7 @@ pub struct ProtocolConfig {
     max_num_transferred_move_object_ids_system_tx: Option<u64>,
 
     /// Maximum size of a Move user event. Enforced by the VM during execution.
+    #[localnet_overridable]
     max_event_emit_size: Option<u64>,
 
     /// Maximum size of a Move user event. Enforced by the VM during execution.
@@ -866,12 +1214,15 @@ pub struct ProtocolConfig {
     max_back_edges_per_module: Option<u64>,
 
     /// Maximum number of meter `ticks` spent verifying a Move function. Enforced by the bytecode verifier at signing.
+    #[unit(Ticks)]
     max_verifier_meter_ticks_per_function: Option<u64>,
 
     /// Maximum number of meter `ticks` spent verifying a Move module. Enforced by the bytecode verifier at signing.
+    #[unit(Ticks)]
     max_meter_ticks_per_module: Option<u64>,
 
     /// Maximum number of meter `ticks` spent verifying a Move package. Enforced by the bytecode verifier at signing.
+    #[unit(Ticks)]
     max_meter_ticks_per_package: Option<u64>,
 
     // === Object runtime internal operation limits ====
@@ -946,17 +1297,21 @@ pub struct ProtocolConfig {
     // TODO: Option<this should be changed to u64.
     /// Sender of a txn that touches an object will get this percent of the storage rebate back.
     /// In basis point.
+    #[unit(BasisPoints)]
     storage_rebate_rate: Option<u64>,
 
     /// 5% of the storage fund's share of rewards are reinvested into the storage fund.
     /// In basis point.
+    #[unit(BasisPoints)]
     storage_fund_reinvest_rate: Option<u64>,
 
     /// The share of rewards that will be slashed and redistributed is 50%.
     /// In basis point.
+    #[unit(BasisPoints)]
     reward_slashing_rate: Option<u64>,
 
     /// Unit gas price, Mist per internal gas unit.
+    #[unit(Mist)]
     storage_gas_price: Option<u64>,
 
     /// === Core Protocol ===
@@ -975,6 +1330,7 @@ pub struct ProtocolConfig {
     /// stake (as a fraction of f, expressed in basis points) that is required before an upgrade
     /// can happen automatically. 10000bps would indicate that complete unanimity is required (all
     /// 3f+1 must vote), while 0bps would indicate that 2f+1 is sufficient.
+    #[unit(BasisPoints)]
     buffer_stake_for_protocol_upgrade_bps: Option<u64>,
 
     // === Native Function Costs ===
@@ -1240,6 +1596,7 @@ pub struct ProtocolConfig {
     // Dictates the threshold (percentage of stake) that is used to calculate the "bad" nodes to be
     // swapped when creating the consensus schedule. The values should be of the range [0 - 33]. Anything
     // above 33 (f) will not be allowed.
+    #[unit(Percent)]
     consensus_bad_nodes_stake_threshold: Option<u64>,
 
     max_jwk_votes_per_validator_per_epoch: Option<u64>,
@@ -1271,10 +1628,13 @@ pub struct ProtocolConfig {
 
     /// The maximum serialised transaction size (in bytes) accepted by consensus. That should be bigger than the
     /// `max_tx_size_bytes` with some additional headroom.
+    #[localnet_overridable]
     consensus_max_transaction_size_bytes: Option<u64>,
     /// The maximum size of transactions included in a consensus block.
+    #[localnet_overridable]
     consensus_max_transactions_in_block_bytes: Option<u64>,
     /// The maximum number of transactions included in a consensus block.
+    #[localnet_overridable]
     consensus_max_num_transactions_in_block: Option<u64>,
 
     /// The maximum number of rounds where transaction voting is allowed.
@@ -1298,12 +1658,14 @@ pub struct ProtocolConfig {
     allowed_txn_cost_overage_burst_per_object_in_commit: Option<u64>,
 
     /// Minimum interval of commit timestamps between consecutive checkpoints.
+    #[localnet_overridable]
     min_checkpoint_interval_ms: Option<u64>,
 
     /// Version number to use for version_specific_data in `CheckpointSummary`.
     checkpoint_summary_version_specific_data: Option<u64>,
 
     /// The max number of transactions that can be included in a single Soft Bundle.
+    #[node_local_tolerant]
     max_soft_bundle_size: Option<u64>,
 
     /// Whether to try to form bridge committee
@@ -1338,6 +1700,25 @@ pub struct ProtocolConfig {
     /// SIP-45: K in the formula `amplification_factor = max(0, gas_price / reference_gas_price - K)`.
     /// This is the threshold for activating consensus amplification.
     sip_45_consensus_amplification_threshold: Option<u64>,
+
+    /// Version of the table of per-command base execution time estimates, used by execution time
+    /// based congestion control.
+    execution_time_estimate_table_version: Option<u64>,
+
+    /// Percentage (1 to 100) of each shared object's execution time budget that execution time
+    /// based congestion control targets in a commit.
+    #[unit(Percent)]
+    execution_time_estimate_target_utilization: Option<u64>,
+
+    /// Minimum age of an execution time estimate before it is refreshed.
+    execution_time_estimate_min_staleness_ms: Option<u64>,
+
+    /// Maximum age of an execution time estimate before it is discarded.
+    execution_time_estimate_max_staleness_ms: Option<u64>,
+
+    /// Number of transactions per sender, in each commit, that keep their position when ordering
+    /// transactions fairly (`ConsensusTransactionOrdering::Fair`).
+    consensus_fair_ordering_per_sender_quota: Option<u64>,
 }
 
 // feature flags
@@ -1365,6 +1746,12 @@ impl ProtocolConfig {
         }
     }
 
+    /// Whether the boolean feature flag `feature` is enabled, for callers that identify features
+    /// by value (e.g. by their numeric ID) rather than through their individual getters.
+    pub fn is_feature_enabled(&self, feature: Feature) -> bool {
+        self.feature_flags.feature(feature)
+    }
+
     pub fn allow_receiving_object_id(&self) -> bool {
         self.feature_flags.allow_receiving_object_id
     }
@@ -1448,6 +1835,10 @@ impl ProtocolConfig {
         self.feature_flags.consensus_transaction_ordering
     }
 
+    pub fn consensus_gas_price_tie_break(&self) -> GasPriceTieBreak {
+        self.feature_flags.consensus_gas_price_tie_break
+    }
+
     pub fn simplified_unwrap_then_delete(&self) -> bool {
         self.feature_flags.simplified_unwrap_then_delete
     }
@@ -1649,13 +2040,88 @@ impl ProtocolConfig {
     }
 
     pub fn max_transaction_size_bytes(&self) -> u64 {
-        // Provide a default value if protocol config version is too low.
-        self.consensus_max_transaction_size_bytes
-            .unwrap_or(256 * 1024)
+        self.transaction_size_limits().consensus
+    }
+
+    /// The limits on transaction size enforced during signing and by consensus, and the effective
+    /// limit that clients should observe.
+    ///
+    /// Panics if the consensus limit is not set at a version where it must be.
+    pub fn transaction_size_limits(&self) -> TransactionSizeLimits {
+        let signing = self.max_tx_size_bytes();
+        let consensus = if self.version.as_u64() < CONSENSUS_MAX_TRANSACTION_SIZE_VERSION {
+            // Provide a default value if protocol config version is too low.
+            self.consensus_max_transaction_size_bytes
+                .unwrap_or(256 * 1024)
+        } else {
+            self.consensus_max_transaction_size_bytes()
+        };
+
+        TransactionSizeLimits {
+            signing,
+            consensus,
+            client: signing.min(consensus),
+        }
+    }
+
+    /// Bounds on what a single transaction's execution can produce at this protocol version.
+    ///
+    /// Object changes are bounded by counting every object that a transaction can touch: its inputs
+    /// (including gas coins), and the objects it can create, delete, or transfer (which covers
+    /// objects that it unwraps). This over-approximates, because an object can be counted more than
+    /// once, but it is guaranteed to cover everything that execution allows.
+    pub fn response_shaping_limits(&self) -> ResponseShapingLimits {
+        let max_object_changes = self.max_input_objects()
+            + self.max_gas_payment_objects() as u64
+            + self
+                .max_num_new_move_object_ids()
+                .max(self.max_num_new_move_object_ids_system_tx())
+            + self
+                .max_num_deleted_move_object_ids()
+                .max(self.max_num_deleted_move_object_ids_system_tx())
+            + self
+                .max_num_transferred_move_object_ids()
+                .max(self.max_num_transferred_move_object_ids_system_tx());
+
+        ResponseShapingLimits {
+            max_events: self.max_num_event_emit(),
+            max_object_changes,
+        }
+    }
+
+    /// Node-level settings recommended for this protocol version.
+    pub fn node_config_hints(&self) -> NodeConfigHints {
+        NodeConfigHints::from_protocol_config(self)
+    }
+
+    /// Economic parameters at this protocol version, or an error if any of the rates exceeds 100%.
+    pub fn tokenomics_config(&self) -> Result<TokenomicsConfig, Error> {
+        let rate = |name: &str, bps: u64| {
+            BasisPoints::new(bps).ok_or_else(|| {
+                Error(format!(
+                    "{name} ({bps}) must not exceed {} basis points",
+                    BasisPoints::DENOMINATOR
+                ))
+            })
+        };
+
+        Ok(TokenomicsConfig {
+            storage_rebate_rate: rate("storage_rebate_rate", self.storage_rebate_rate())?,
+            storage_fund_reinvest_rate: rate(
+                "storage_fund_reinvest_rate",
+                self.storage_fund_reinvest_rate(),
+            )?,
+            reward_slashing_rate: rate("reward_slashing_rate", self.reward_slashing_rate())?,
+            stake_subsidy: StakeSubsidyThresholds {
+                start_epoch: STAKE_SUBSIDY_START_EPOCH,
+                catch_up_distribution_count: STAKE_SUBSIDY_CATCH_UP_DISTRIBUTION_COUNT,
+                catch_up_first_epoch: STAKE_SUBSIDY_CATCH_UP_FIRST_EPOCH,
+            },
+        })
     }
 
     pub fn max_transactions_in_block_bytes(&self) -> u64 {
-        if cfg!(msim) {
+        if Divergence::MaxTransactionsInBlockBytes.is_in_effect() {
             256 * 1024
         } else {
             self.consensus_max_transactions_in_block_bytes
@@ -1664,7 +2130,7 @@ impl ProtocolConfig {
     }
 
     pub fn max_num_transactions_in_block(&self) -> u64 {
-        if cfg!(msim) {
+        if Divergence::MaxNumTransactionsInBlock.is_in_effect() {
             8
         } else {
             self.consensus_max_num_transactions_in_block.unwrap_or(512)
@@ -1726,6 +2192,161 @@ impl ProtocolConfig {
     }
 }
 
+/// A named set of changes applied on top of the base config for a range of protocol versions on
+/// one chain. Patches are for fixes that need a combination of settings that no single protocol
+/// version provides (e.g. a fix backported to versions that are already released), so that they
+/// are defined and reviewed alongside the rest of the config, rather than applied through
+/// environment overrides.
+///
+/// Patches are part of the protocol: like the config for a protocol version, a patch must never
+/// be modified or removed once it is in effect on a network.
+#[derive(Debug)]
+pub struct ProtocolConfigPatch {
+    /// Unique name identifying the patch.
+    pub name: &'static str,
+    /// The chain the patch applies to.
+    pub chain: Chain,
+    /// The protocol versions the patch applies to.
+    pub versions: RangeInclusive<u64>,
+    /// Applies the patch's changes to a config.
+    pub apply: fn(&mut ProtocolConfig),
+}
+
+/// Patches to the base config, applied in order.
+const PROTOCOL_CONFIG_PATCHES: &[ProtocolConfigPatch] = &[
+    // Add patches here, e.g.:
+    //
+    // ProtocolConfigPatch {
+    //     name: "enable-new-feature-backport",
+    //     chain: Chain::Testnet,
+    //     versions: 68..=70,
+    //     apply: |cfg| cfg.feature_flags.new_feature = true,
+    // },
+];
+
+impl ProtocolConfigPatch {
+    /// Whether this patch applies to the config for `version` on `chain`.
+    pub fn applies_to(&self, version: ProtocolVersion, chain: Chain) -> bool {
+        self.chain == chain && self.versions.contains(&version.as_u64())
+    }
+}
+
+/// The parameters of a network other than Mainnet and Testnet (e.g. an appchain or a fork) that
+/// needs its own config without changes to this crate. Such networks run as `Chain::Unknown`, and
+/// their patches are applied on top of the config for `Chain::Unknown` (after the built-in
+/// patches), once registered with `ProtocolConfig::register_custom_chain`.
+///
+/// Like built-in patches, a custom chain's patches are part of its protocol, and must never be
+/// modified or removed once they are in effect on the network.
+#[derive(Debug)]
+pub struct CustomChain {
+    /// Name identifying the network.
+    pub name: String,
+    /// Patches to the config for `Chain::Unknown`, applied in order. Every patch must target
+    /// `Chain::Unknown`.
+    pub patches: Vec<ProtocolConfigPatch>,
+}
+
+/// The custom chain this process runs on, if any.
+static CUSTOM_CHAIN: OnceLock<CustomChain> = OnceLock::new();
+
+/// Whether this process has produced a config yet. Custom chains can only be registered before
+/// then, so that every config for `Chain::Unknown` includes their patches. The lock is held while
+/// `CUSTOM_CHAIN` is read or set, so that a config cannot be produced without the patches of a
+/// custom chain that is registered concurrently.
+static CONFIGS_PRODUCED: Mutex<bool> = Mutex::new(false);
+
+/// The `--protocol.*` arguments this process runs with, if any. Like a custom chain, they can only
+/// be registered before any config is produced.
+#[cfg(feature = "overrides")]
+static LOCALNET_OVERRIDES: OnceLock<ProtocolConfigLocalnetArgs> = OnceLock::new();
+
+impl CustomChain {
+    /// Check that patches are uniquely named (including against built-in patches), target
+    /// `Chain::Unknown`, and cover a non-empty range of versions.
+    fn validate(&self) -> Result<(), Error> {
+        let mut names: BTreeSet<_> = PROTOCOL_CONFIG_PATCHES.iter().map(|p| p.name).collect();
+        for patch in &self.patches {
+            if !names.insert(patch.name) {
+                return Err(Error(format!("Duplicate patch {:?}", patch.name)));
+            }
+
+            if patch.chain != Chain::Unknown {
+                return Err(Error(format!(
+                    "Patch {:?} targets {:?}, but custom chain patches must target Chain::Unknown",
+                    patch.name, patch.chain,
+                )));
+            }
+
+            if patch.versions.is_empty() {
+                return Err(Error(format!("Patch {:?} covers no versions", patch.name)));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Ways in which configs in simulator builds (`cfg(msim)`) differ from production builds, so that
+/// simtests exercise situations that are rare in production (e.g. full consensus blocks, protocol
+/// upgrades). None of these are in effect outside simulator builds, and simtests can switch them
+/// off on their thread (see `testing::without_divergence`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Divergence {
+    /// `max_transactions_in_block_bytes` is 256 KiB, regardless of
+    /// `consensus_max_transactions_in_block_bytes`.
+    MaxTransactionsInBlockBytes,
+    /// `max_num_transactions_in_block` is 8, regardless of
+    /// `consensus_max_num_transactions_in_block`.
+    MaxNumTransactionsInBlock,
+    /// Simulator builds support one more protocol version than `ProtocolVersion::MAX` (see
+    /// `ProtocolVersion::MAX_ALLOWED`), whose config is the maximum version's. Its
+    /// `base_tx_cost_fixed` is raised by 1000 while this divergence is in effect, so that
+    /// upgrading to it changes the config.
+    FakeMaxVersionTxCost,
+}
+
+impl Divergence {
+    pub const ALL: &'static [Divergence] = &[
+        Divergence::MaxTransactionsInBlockBytes,
+        Divergence::MaxNumTransactionsInBlock,
+        Divergence::FakeMaxVersionTxCost,
+    ];
+
+    /// The parameter whose value differs.
+    pub fn parameter(&self) -> &'static str {
+        match self {
+            Divergence::MaxTransactionsInBlockBytes => "max_transactions_in_block_bytes",
+            Divergence::MaxNumTransactionsInBlock => "max_num_transactions_in_block",
+            Divergence::FakeMaxVersionTxCost => "base_tx_cost_fixed",
+        }
+    }
+
+    /// Whether this divergence applies to configs on this thread.
+    fn is_in_effect(self) -> bool {
+        cfg!(msim) && !DISABLED_DIVERGENCES.with(|d| d.borrow().contains(&self))
+    }
+}
+
+// Divergences that have been switched off on this thread.
+thread_local! {
+    static DISABLED_DIVERGENCES: RefCell<BTreeSet<Divergence>> = const { RefCell::new(BTreeSet::new()) };
+}
+
+/// Switch `divergence` off (if `disabled`) or back on for this thread, returning whether it was
+/// switched off before.
+#[cfg(any(test, msim, feature = "test-utils"))]
+fn set_divergence_disabled(divergence: Divergence, disabled: bool) -> bool {
+    DISABLED_DIVERGENCES.with(|d| {
+        let mut d = d.borrow_mut();
+        if disabled {
+            !d.insert(divergence)
+        } else {
+            d.remove(&divergence)
+        }
+    })
+}
+
 #[cfg(not(msim))]
 static POISON_VERSION_METHODS: AtomicBool = AtomicBool::new(false);
 
@@ -1756,26 +2377,72 @@ impl ProtocolConfig {
         let mut ret = Self::get_for_version_impl(version, chain);
         ret.version = version;
 
+        #[cfg(feature = "overrides")]
+        if chain == Chain::Unknown {
+            if let Some(args) = LOCALNET_OVERRIDES.get() {
+                args.override_config(&mut ret);
+            }
+        }
+
         ret = CONFIG_OVERRIDE.with(|ovr| {
-            if let Some(override_fn) = &*ovr.borrow() {
+            let overrides = ovr.borrow();
+            if !overrides.is_empty() {
                 warn!(
                     "overriding ProtocolConfig settings with custom settings (you should not see this log outside of tests)"
                 );
-                override_fn(version, ret)
-            } else {
-                ret
             }
+
+            // Apply overrides from the outermost to the innermost.
+            overrides
+                .iter()
+                .fold(ret, |cfg, (_, override_fn)| override_fn(version, cfg))
         });
 
+        #[cfg(feature = "overrides")]
+        ret.apply_overrides_from_env();
+
+        #[cfg(any(msim, feature = "test-utils"))]
+        if let Ok(seed) = std::env::var("SUI_PROTOCOL_CONFIG_CHAOS_SEED") {
+            let seed = seed
+                .parse()
+                .expect("SUI_PROTOCOL_CONFIG_CHAOS_SEED must be a u64");
+            warn!("perturbing node-local ProtocolConfig limits with seed {seed}");
+            ret.perturb_node_local_limits_for_testing(seed);
+        }
+
+        ret
+    }
+
+    /// Apply the overrides from the `SUI_PROTOCOL_CONFIG_OVERRIDE_*` env vars (if
+    /// `SUI_PROTOCOL_CONFIG_OVERRIDE_ENABLE` is set), and then from the file that
+    /// `SUI_PROTOCOL_CONFIG_OVERRIDE_FILE` points to (if it is set).
+    #[cfg(feature = "overrides")]
+    fn apply_overrides_from_env(&mut self) {
         if std::env::var("SUI_PROTOCOL_CONFIG_OVERRIDE_ENABLE").is_ok() {
             warn!("overriding ProtocolConfig settings with custom settings; this may break non-local networks");
-            let overrides: ProtocolConfigOptional =
-                serde_env::from_env_with_prefix("SUI_PROTOCOL_CONFIG_OVERRIDE")
-                    .expect("failed to parse ProtocolConfig override env variables");
-            overrides.apply_to(&mut ret);
+            let overrides: ProtocolConfigOptional = serde_env::from_iter_with_prefix(
+                resolve_override_vars(std::env::vars(), RENAMED_CONSTANTS),
+                "SUI_PROTOCOL_CONFIG_OVERRIDE",
+            )
+            .expect("failed to parse ProtocolConfig override env variables");
+            overrides.apply_to(self);
+
+            if let Err(e) = self.check_overrides() {
+                warn!(
+                    "overridden ProtocolConfig settings are inconsistent: {}",
+                    e.0
+                );
+            }
         }
 
-        ret
+        if let Ok(path) = std::env::var("SUI_PROTOCOL_CONFIG_OVERRIDE_FILE") {
+            warn!(
+                "overriding ProtocolConfig settings from {path}; this may break non-local networks"
+            );
+            if let Err(e) = self.apply_overrides_from_file(&path) {
+                panic!("failed to apply ProtocolConfig override file: {}", e.0);
+            }
+        }
     }
 
     /// Get the value ProtocolConfig that are in effect during the given protocol version.
@@ -1810,6 +2477,16 @@ impl ProtocolConfig {
         POISON_VERSION_METHODS.with(|p| p.load(Ordering::Relaxed))
     }
 
+    /// The ways in which configs on this thread differ from production configs, because this is a
+    /// simulator build. Always empty outside simulator builds.
+    pub fn test_divergences() -> Vec<Divergence> {
+        Divergence::ALL
+            .iter()
+            .copied()
+            .filter(|d| d.is_in_effect())
+            .collect()
+    }
+
     /// Convenience to get the constants at the current minimum supported version.
     /// Mainly used by client code that may not yet be protocol-version aware.
     pub fn get_for_min_version() -> Self {
@@ -1842,11 +2519,29 @@ impl ProtocolConfig {
             // populate the fake simulator version # with a different base tx cost.
             if version == ProtocolVersion::MAX_ALLOWED {
                 let mut config = Self::get_for_version_impl(version - 1, Chain::Unknown);
-                config.base_tx_cost_fixed = Some(config.base_tx_cost_fixed() + 1000);
+                if Divergence::FakeMaxVersionTxCost.is_in_effect() {
+                    config.base_tx_cost_fixed = Some(config.base_tx_cost_fixed() + 1000);
+                }
                 return config;
             }
         }
 
+        let mut cfg = Self::get_base_for_version(version, chain);
+        let custom = {
+            let mut produced = CONFIGS_PRODUCED.lock().unwrap();
+            *produced = true;
+            CUSTOM_CHAIN.get()
+        };
+        if let Some(custom) = custom {
+            cfg.apply_patches(version, chain, &custom.patches);
+        }
+
+        cfg
+    }
+
+    /// The config for `version` on `chain`, with built-in patches but without the patches of a
+    /// custom chain. Does not count as producing a config.
+    fn get_base_for_version(version: ProtocolVersion, chain: Chain) -> Self {
         // IMPORTANT: Never modify the value of any constant for a pre-existing protocol version.
         // To change the values here you must create a new protocol version with the new values!
         let mut cfg = Self {
@@ -1869,6 +2564,9 @@ impl ProtocolConfig {
             max_type_argument_depth: Some(16),
             max_pure_argument_size: Some(16 * 1024),
             max_programmable_tx_commands: Some(1024),
+            max_multisig_signers: None,
+            max_zklogin_proof_size_bytes: None,
+            passkey_challenge_size_bytes: None,
             move_binary_format_version: Some(6),
             min_move_binary_format_version: None,
             binary_module_handles: None,
@@ -2258,6 +2956,16 @@ impl ProtocolConfig {
             gas_budget_based_txn_cost_absolute_cap_commit_count: None,
 
             sip_45_consensus_amplification_threshold: None,
+
+            execution_time_estimate_table_version: None,
+
+            execution_time_estimate_target_utilization: None,
+
+            execution_time_estimate_min_staleness_ms: None,
+
+            execution_time_estimate_max_staleness_ms: None,
+
+            consensus_fair_ordering_per_sender_quota: None,
             // When adding a new constant, set it to None in the earliest version, like this:
             // new_constant: None,
         };
@@ -3097,6 +3805,13 @@ impl ProtocolConfig {
                     // Enable bursts for congestion control. (10x the per-commit budget)
                     cfg.allowed_txn_cost_overage_burst_per_object_in_commit = Some(185_000_000);
                 }
+                72 => {
+                    // Signature limits that were previously hardcoded.
+                    cfg.max_multisig_signers = Some(MAX_MULTISIG_SIGNERS);
+                    cfg.passkey_challenge_size_bytes = Some(PASSKEY_CHALLENGE_SIZE_BYTES);
+                    // Several times the size of the inputs produced by the supported providers.
+                    cfg.max_zklogin_proof_size_bytes = Some(4096);
+                }
                 // Use this template when making changes:
                 //
                 //     // modify an existing constant.
@@ -3107,12 +3822,78 @@ impl ProtocolConfig {
                 //
                 //     // Remove a constant (ensure that it is never accessed during this version).
                 //     max_move_object_size: None,
+                //
+                //     // After changing `max_tx_size_bytes`, keep the consensus limit in step with it.
+                //     cfg.set_consensus_max_transaction_size_from_tx_limit();
                 _ => panic!("unsupported version {:?}", version),
             }
         }
+
+        cfg.apply_patches(version, chain, PROTOCOL_CONFIG_PATCHES);
         cfg
     }
 
+    /// Register the custom chain that this process runs on, so that its patches are applied to
+    /// every config for `Chain::Unknown` that this process produces. Fails if any config has
+    /// already been produced, if a custom chain is already registered, or if `chain`'s patches are
+    /// invalid.
+    pub fn register_custom_chain(chain: CustomChain) -> Result<(), Error> {
+        chain.validate()?;
+        let name = chain.name.clone();
+        let produced = CONFIGS_PRODUCED.lock().unwrap();
+        if *produced {
+            return Err(Error(format!(
+                "Cannot register custom chain {name:?}: configs have already been produced without \
+                 its patches",
+            )));
+        }
+
+        CUSTOM_CHAIN.set(chain).map_err(|_| {
+            Error(format!(
+                "Cannot register custom chain {name:?}: {:?} is already registered",
+                CUSTOM_CHAIN.get().map(|c| &c.name),
+            ))
+        })?;
+
+        info!("registered custom chain {name:?}");
+        Ok(())
+    }
+
+    /// The custom chain registered with `register_custom_chain`, if any.
+    pub fn custom_chain() -> Option<&'static CustomChain> {
+        CUSTOM_CHAIN.get()
+    }
+
+    /// All patches to the base config, on every chain.
+    pub fn patches() -> &'static [ProtocolConfigPatch] {
+        PROTOCOL_CONFIG_PATCHES
+    }
+
+    /// Names of the patches applied on top of the base config for `version` on `chain`, in the
+    /// order they are applied, including the patches of a registered custom chain.
+    pub fn active_patches(version: ProtocolVersion, chain: Chain) -> Vec<&'static str> {
+        let custom = CUSTOM_CHAIN.get().map_or(&[][..], |c| &c.patches[..]);
+        PROTOCOL_CONFIG_PATCHES
+            .iter()
+            .chain(custom)
+            .filter(|p| p.applies_to(version, chain))
+            .map(|p| p.name)
+            .collect()
+    }
+
+    fn apply_patches(
+        &mut self,
+        version: ProtocolVersion,
+        chain: Chain,
+        patches: &[ProtocolConfigPatch],
+    ) {
+        for patch in patches {
+            if patch.applies_to(version, chain) {
+                (patch.apply)(self);
+            }
+        }
+    }
+
     // Extract the bytecode verifier config from this protocol config. `for_signing` indicates
     // whether this config is used for verification during signing or execution.
     pub fn verifier_config(&self, signing_limits: Option<(usize, usize)>) -> VerifierConfig {
@@ -3154,370 +3935,3112 @@ impl ProtocolConfig {
         }
     }
 
-    /// Override one or more settings in the config, for testing.
-    /// This must be called at the beginning of the test, before get_for_(min|max)_version is
-    /// called, since those functions cache their return value.
-    pub fn apply_overrides_for_testing(
-        override_fn: impl Fn(ProtocolVersion, Self) -> Self + Send + 'static,
-    ) -> OverrideGuard {
-        CONFIG_OVERRIDE.with(|ovr| {
-            let mut cur = ovr.borrow_mut();
-            assert!(cur.is_none(), "config override already present");
-            *cur = Some(Box::new(override_fn));
-            OverrideGuard
-        })
+    /// Derive `consensus_max_transaction_size_bytes` from the current `max_tx_size_bytes`, leaving
+    /// the required headroom. Versions that change either limit should call this rather than
+    /// setting the consensus limit by hand, so that the two cannot drift apart. Existing versions
+    /// set the limit directly, and must not be changed to use this.
+    // No protocol version has needed this yet, so only localnet overrides use it.
+    #[cfg_attr(not(feature = "overrides"), allow(dead_code))]
+    fn set_consensus_max_transaction_size_from_tx_limit(&mut self) {
+        self.consensus_max_transaction_size_bytes =
+            Some(self.max_tx_size_bytes() + CONSENSUS_MAX_TRANSACTION_SIZE_HEADROOM_BYTES);
+    }
+
+    /// Parameters for per-object congestion control, exactly as the consensus handler reads them.
+    /// See `check_congestion_control_params` for the relationships they are expected to satisfy.
+    pub fn congestion_control_params(&self) -> CongestionControlParams {
+        let budget = self.max_accumulated_txn_cost_per_object_in_mysticeti_commit_as_option();
+        CongestionControlParams {
+            mode: self.per_object_congestion_control_mode(),
+            max_accumulated_txn_cost_per_object_in_commit: budget,
+            max_accumulated_randomness_txn_cost_per_object_in_commit: self
+                .max_accumulated_randomness_txn_cost_per_object_in_mysticeti_commit_as_option()
+                .or(budget),
+            gas_budget_based_txn_cost_cap_factor: self
+                .gas_budget_based_txn_cost_cap_factor_as_option(),
+            gas_budget_based_txn_cost_absolute_cap_commit_count: self
+                .gas_budget_based_txn_cost_absolute_cap_commit_count_as_option(),
+            max_txn_cost_overage_per_object_in_commit: self
+                .max_txn_cost_overage_per_object_in_commit_as_option()
+                .unwrap_or(0),
+            allowed_txn_cost_overage_burst_per_object_in_commit: self
+                .allowed_txn_cost_overage_burst_per_object_in_commit_as_option()
+                .unwrap_or(0),
+            max_deferral_rounds: self.max_deferral_rounds_for_congestion_control_as_option(),
+        }
     }
-}
 
-// Setters for tests.
-// This is only needed for feature_flags. Please suffix each setter with `_for_testing`.
-// Non-feature_flags should already have test setters defined through macros.
-impl ProtocolConfig {
-    pub fn set_advance_to_highest_supported_protocol_version_for_testing(&mut self, val: bool) {
-        self.feature_flags
-            .advance_to_highest_supported_protocol_version = val
-    }
-    pub fn set_commit_root_state_digest_supported_for_testing(&mut self, val: bool) {
-        self.feature_flags.commit_root_state_digest = val
-    }
-    pub fn set_zklogin_auth_for_testing(&mut self, val: bool) {
-        self.feature_flags.zklogin_auth = val
-    }
-    pub fn set_enable_jwk_consensus_updates_for_testing(&mut self, val: bool) {
-        self.feature_flags.enable_jwk_consensus_updates = val
-    }
-    pub fn set_random_beacon_for_testing(&mut self, val: bool) {
-        self.feature_flags.random_beacon = val
-    }
+    /// Check that the per-object congestion control parameters are consistent: a budget must be
+    /// set unless congestion control is disabled, `TotalGasBudgetWithCap` needs a cap factor,
+    /// deferral must be bounded, and bursts may not exceed the per-transaction overage.
+    pub fn check_congestion_control_params(&self) -> Result<(), Error> {
+        use PerObjectCongestionControlMode as M;
+        let params = self.congestion_control_params();
+        let mode = params.mode;
+
+        if !mode.is_none() {
+            // Versions from before Mysticeti only set the budget for Narwhal commits, which is
+            // what consensus used at the time.
+            if params
+                .max_accumulated_txn_cost_per_object_in_commit
+                .is_none()
+                && self
+                    .max_accumulated_txn_cost_per_object_in_narwhal_commit
+                    .is_none()
+            {
+                return Err(Error(format!(
+                    "congestion control mode {mode:?} requires \
+                     max_accumulated_txn_cost_per_object_in_mysticeti_commit"
+                )));
+            }
 
-    pub fn set_upgraded_multisig_for_testing(&mut self, val: bool) {
-        self.feature_flags.upgraded_multisig_supported = val
-    }
-    pub fn set_accept_zklogin_in_multisig_for_testing(&mut self, val: bool) {
-        self.feature_flags.accept_zklogin_in_multisig = val
-    }
+            if params.max_deferral_rounds.is_none() {
+                return Err(Error(format!(
+                    "congestion control mode {mode:?} requires \
+                     max_deferral_rounds_for_congestion_control"
+                )));
+            }
+        }
 
-    pub fn set_shared_object_deletion_for_testing(&mut self, val: bool) {
-        self.feature_flags.shared_object_deletion = val;
-    }
+        if mode == M::TotalGasBudgetWithCap && params.gas_budget_based_txn_cost_cap_factor.is_none()
+        {
+            return Err(Error(
+                "congestion control mode TotalGasBudgetWithCap requires \
+                 gas_budget_based_txn_cost_cap_factor"
+                    .to_string(),
+            ));
+        }
 
-    pub fn set_narwhal_new_leader_election_schedule_for_testing(&mut self, val: bool) {
-        self.feature_flags.narwhal_new_leader_election_schedule = val;
-    }
+        let overage = params.max_txn_cost_overage_per_object_in_commit;
+        let burst = params.allowed_txn_cost_overage_burst_per_object_in_commit;
+        if burst > overage {
+            return Err(Error(format!(
+                "allowed_txn_cost_overage_burst_per_object_in_commit ({burst}) must not exceed \
+                 max_txn_cost_overage_per_object_in_commit ({overage})"
+            )));
+        }
 
-    pub fn set_receive_object_for_testing(&mut self, val: bool) {
-        self.feature_flags.receive_objects = val
-    }
-    pub fn set_narwhal_certificate_v2_for_testing(&mut self, val: bool) {
-        self.feature_flags.narwhal_certificate_v2 = val
-    }
-    pub fn set_verify_legacy_zklogin_address_for_testing(&mut self, val: bool) {
-        self.feature_flags.verify_legacy_zklogin_address = val
+        Ok(())
     }
 
-    pub fn set_per_object_congestion_control_mode_for_testing(
-        &mut self,
-        val: PerObjectCongestionControlMode,
-    ) {
-        self.feature_flags.per_object_congestion_control_mode = val;
+    /// Parameters for execution time based congestion control, if they are all set in this
+    /// version.
+    pub fn execution_time_estimate_params(&self) -> Option<ExecutionTimeEstimateParams> {
+        Some(ExecutionTimeEstimateParams {
+            base_estimates_table_version: self.execution_time_estimate_table_version_as_option()?,
+            target_utilization: self.execution_time_estimate_target_utilization_as_option()?,
+            min_staleness: self.execution_time_estimate_min_staleness_duration_as_option()?,
+            max_staleness: self.execution_time_estimate_max_staleness_duration_as_option()?,
+        })
     }
 
-    pub fn set_consensus_choice_for_testing(&mut self, val: ConsensusChoice) {
-        self.feature_flags.consensus_choice = val;
-    }
+    /// Check that the execution time estimate parameters are either all unset, or all set to
+    /// consistent values.
+    pub fn check_execution_time_estimate_params(&self) -> Result<(), Error> {
+        let set = [
+            self.execution_time_estimate_table_version.is_some(),
+            self.execution_time_estimate_target_utilization.is_some(),
+            self.execution_time_estimate_min_staleness_ms.is_some(),
+            self.execution_time_estimate_max_staleness_ms.is_some(),
+        ];
+
+        let Some(params) = self.execution_time_estimate_params() else {
+            return if set.iter().any(|s| *s) {
+                Err(Error(
+                    "execution time estimate parameters are only partially set".to_string(),
+                ))
+            } else {
+                Ok(())
+            };
+        };
 
-    pub fn set_consensus_network_for_testing(&mut self, val: ConsensusNetwork) {
-        self.feature_flags.consensus_network = val;
-    }
+        if params.base_estimates_table_version == 0 {
+            return Err(Error(
+                "execution time estimate table version must be at least 1".to_string(),
+            ));
+        }
 
-    pub fn set_zklogin_max_epoch_upper_bound_delta_for_testing(&mut self, val: Option<u64>) {
-        self.feature_flags.zklogin_max_epoch_upper_bound_delta = val
-    }
+        if !(1..=100).contains(&params.target_utilization) {
+            return Err(Error(format!(
+                "execution time estimate target utilization ({}) must be between 1 and 100",
+                params.target_utilization
+            )));
+        }
 
-    pub fn set_disable_bridge_for_testing(&mut self) {
-        self.feature_flags.bridge = false
-    }
+        if params.min_staleness > params.max_staleness {
+            return Err(Error(format!(
+                "execution time estimate min staleness ({:?}) exceeds max staleness ({:?})",
+                params.min_staleness, params.max_staleness
+            )));
+        }
 
-    pub fn set_mysticeti_num_leaders_per_round_for_testing(&mut self, val: Option<usize>) {
-        self.feature_flags.mysticeti_num_leaders_per_round = val;
+        Ok(())
     }
 
-    pub fn set_enable_soft_bundle_for_testing(&mut self, val: bool) {
-        self.feature_flags.soft_bundle = val;
+    /// Parameters for JWK consensus, or `None` if JWK updates through consensus are not enabled
+    /// at this version.
+    pub fn jwk_config(&self) -> Option<JwkConfig> {
+        if !self.enable_jwk_consensus_updates() {
+            return None;
+        }
+
+        Some(JwkConfig {
+            max_votes_per_validator_per_epoch: self.max_jwk_votes_per_validator_per_epoch(),
+            max_age_in_epochs: self.max_age_of_jwk_in_epochs(),
+        })
     }
 
-    pub fn set_passkey_auth_for_testing(&mut self, val: bool) {
-        self.feature_flags.passkey_auth = val
+    /// Check that the JWK consensus parameters are sensible for a network whose epochs last
+    /// `epoch_duration`: validators must be able to vote, and JWKs must survive the epoch they
+    /// become active in.
+    pub fn check_jwk_config(&self, epoch_duration: Duration) -> Result<(), Error> {
+        let Some(jwk) = self.jwk_config() else {
+            return Ok(());
+        };
+
+        if epoch_duration.is_zero() {
+            return Err(Error("epoch duration must be non-zero".to_string()));
+        }
+
+        if jwk.max_votes_per_validator_per_epoch == 0 {
+            return Err(Error(
+                "max_jwk_votes_per_validator_per_epoch must be at least 1".to_string(),
+            ));
+        }
+
+        if jwk.max_age_in_epochs == 0 {
+            return Err(Error(
+                "max_age_of_jwk_in_epochs must be at least 1, or JWKs expire at the end of the \
+                 epoch they become active in"
+                    .to_string(),
+            ));
+        }
+
+        Ok(())
     }
 
-    pub fn set_consensus_distributed_vote_scoring_strategy_for_testing(&mut self, val: bool) {
-        self.feature_flags
-            .consensus_distributed_vote_scoring_strategy = val;
+    /// How long an active JWK stays in the authenticator state object on a network whose epochs
+    /// last `epoch_duration`, or `None` if JWK updates through consensus are not enabled at this
+    /// version.
+    pub fn jwk_retention(&self, epoch_duration: Duration) -> Option<Duration> {
+        let jwk = self.jwk_config()?;
+        Some(
+            u32::try_from(jwk.max_age_in_epochs)
+                .map_or(Duration::MAX, |age| epoch_duration.saturating_mul(age)),
+        )
     }
 
-    pub fn set_consensus_round_prober_for_testing(&mut self, val: bool) {
-        self.feature_flags.consensus_round_prober = val;
+    /// The ordering to apply to transactions coming out of consensus, with its parameters.
+    ///
+    /// Panics if fair ordering is enabled without a per-sender quota.
+    pub fn consensus_transaction_ordering_strategy(&self) -> ConsensusTransactionOrderingStrategy {
+        use ConsensusTransactionOrdering as O;
+        use ConsensusTransactionOrderingStrategy as S;
+        match self.consensus_transaction_ordering() {
+            O::None => S::None,
+            O::ByGasPrice => S::ByGasPrice {
+                tie_break: self.consensus_gas_price_tie_break(),
+            },
+            O::Fair => S::Fair {
+                per_sender_quota: self.consensus_fair_ordering_per_sender_quota(),
+            },
+        }
     }
 
-    pub fn set_disallow_new_modules_in_deps_only_packages_for_testing(&mut self, val: bool) {
-        self.feature_flags
-            .disallow_new_modules_in_deps_only_packages = val;
+    /// Check that the parameters for the configured consensus transaction ordering are set.
+    pub fn check_consensus_transaction_ordering(&self) -> Result<(), Error> {
+        if self.consensus_transaction_ordering() != ConsensusTransactionOrdering::Fair {
+            return Ok(());
+        }
+
+        match self.consensus_fair_ordering_per_sender_quota_as_option() {
+            None => Err(Error(
+                "fair consensus transaction ordering requires a per-sender quota".to_string(),
+            )),
+            Some(0) => Err(Error(
+                "fair consensus transaction ordering per-sender quota must be at least 1"
+                    .to_string(),
+            )),
+            Some(_) => Ok(()),
+        }
     }
 
-    pub fn set_consensus_round_prober_probe_accepted_rounds(&mut self, val: bool) {
-        self.feature_flags
-            .consensus_round_prober_probe_accepted_rounds = val;
+    /// Check the relationships between constants that overrides (from the environment or the
+    /// command line) could break.
+    pub fn check_overrides(&self) -> Result<(), Error> {
+        self.check_consensus_limits()?;
+        self.check_congestion_control_params()?;
+        self.check_execution_time_estimate_params()?;
+        self.check_consensus_transaction_ordering()?;
+        self.tokenomics_config().map(|_| ())
+    }
+
+    /// Check that a transaction paying the maximum gas price can afford the fixed base cost of a
+    /// transaction within the maximum gas budget. Protocol versions before gas model v2 (version 3)
+    /// predate this invariant, so it is only enforced on override files.
+    pub fn check_gas_limits(&self) -> Result<(), Error> {
+        let (Some(max_gas_price), Some(base_cost), Some(max_tx_gas)) =
+            (self.max_gas_price, self.base_tx_cost_fixed, self.max_tx_gas)
+        else {
+            return Ok(());
+        };
+
+        match max_gas_price.checked_mul(base_cost) {
+            Some(cost) if cost <= max_tx_gas => Ok(()),
+            _ => Err(Error(format!(
+                "max_gas_price ({max_gas_price}) * base_tx_cost_fixed ({base_cost}) must not \
+                 exceed max_tx_gas ({max_tx_gas})"
+            ))),
+        }
     }
-}
 
-type OverrideFn = dyn Fn(ProtocolVersion, ProtocolConfig) -> ProtocolConfig + Send;
+    /// Check that the consensus limits on transaction and block sizes are consistent with the
+    /// transaction size limit: a transaction of the maximum allowed size (plus headroom) must be
+    /// accepted by consensus, and must fit in a block.
+    pub fn check_consensus_limits(&self) -> Result<(), Error> {
+        let tx_limit = self.max_tx_size_bytes();
+        let consensus_tx_limit = self.max_transaction_size_bytes();
+        let block_limit = self.max_transactions_in_block_bytes();
+
+        if consensus_tx_limit < tx_limit + CONSENSUS_MAX_TRANSACTION_SIZE_HEADROOM_BYTES {
+            return Err(Error(format!(
+                "consensus transaction size limit ({consensus_tx_limit}) must be at least \
+                 max_tx_size_bytes ({tx_limit}) plus {CONSENSUS_MAX_TRANSACTION_SIZE_HEADROOM_BYTES} \
+                 bytes of headroom"
+            )));
+        }
 
-thread_local! {
-    static CONFIG_OVERRIDE: RefCell<Option<Box<OverrideFn>>> = RefCell::new(None);
-}
+        if block_limit < consensus_tx_limit {
+            return Err(Error(format!(
+                "consensus block size limit ({block_limit}) must be at least the consensus \
+                 transaction size limit ({consensus_tx_limit})"
+            )));
+        }
 
-#[must_use]
-pub struct OverrideGuard;
+        Ok(())
+    }
 
-impl Drop for OverrideGuard {
-    fn drop(&mut self) {
-        info!("restoring override fn");
+    /// Override constants in this config with the values in the file at `path`, for networks
+    /// that manage their protocol parameters declaratively (e.g. private networks). The file is
+    /// parsed as TOML or YAML, based on its extension, and maps constants to their values, e.g.
+    /// `max_tx_gas = 50000000000`. Constants can be referred to by any key accepted by
+    /// `resolve_attr` (their name as it appears in `attr_map`, their address, or an old name).
+    /// Feature flags cannot be overridden.
+    ///
+    /// The file is rejected if it names a constant that does not exist, names the same constant
+    /// twice, or if the resulting config fails `check_overrides`. The config is left unchanged on error.
+    ///
+    /// When the `SUI_PROTOCOL_CONFIG_OVERRIDE_FILE` env var is set, the file it points to is
+    /// applied to every config returned by `get_for_version`.
+    #[cfg(feature = "overrides")]
+    pub fn apply_overrides_from_file(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<(), Error> {
+        use std::path::Path;
+
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| Error(format!("failed to read {}: {e}", path.display())))?;
+
+        let parse_err = |e: &dyn std::fmt::Display| Error(format!("{}: {e}", path.display()));
+
+        // Key every entry by the name of the constant it refers to.
+        fn resolve_keys<V>(
+            entries: impl IntoIterator<Item = (String, V)>,
+            path: &Path,
+        ) -> Result<BTreeMap<&'static str, V>, Error> {
+            let mut resolved = BTreeMap::new();
+            for (key, value) in entries {
+                let Some(name) = ProtocolConfig::resolve_attr(&key) else {
+                    return Err(Error(format!(
+                        "{}: unknown protocol config constant '{key}'",
+                        path.display()
+                    )));
+                };
+
+                if resolved.insert(name, value).is_some() {
+                    return Err(Error(format!(
+                        "{}: protocol config constant '{name}' is overridden more than once",
+                        path.display()
+                    )));
+                }
+            }
+
+            Ok(resolved)
+        }
+
+        let overrides: ProtocolConfigOptional = match path.extension().and_then(|ext| ext.to_str())
+        {
+            Some("toml") => {
+                let table = toml::from_str::<toml::Table>(&contents).map_err(|e| parse_err(&e))?;
+                let resolved = resolve_keys(table, path)?
+                    .into_iter()
+                    .map(|(name, value)| (name.to_owned(), value))
+                    .collect();
+
+                toml::Value::Table(resolved)
+                    .try_into()
+                    .map_err(|e| parse_err(&e))?
+            }
+
+            Some("yaml" | "yml") => {
+                let map = serde_yaml::from_str::<BTreeMap<String, serde_yaml::Value>>(&contents)
+                    .map_err(|e| parse_err(&e))?;
+                let resolved = resolve_keys(map, path)?;
+
+                serde_yaml::to_value(resolved)
+                    .and_then(serde_yaml::from_value)
+                    .map_err(|e| parse_err(&e))?
+            }
+
+            _ => {
+                return Err(Error(format!(
+                    "{}: override file must have a .toml, .yaml or .yml extension",
+                    path.display()
+                )))
+            }
+        };
+
+        let mut config = self.clone();
+        overrides.apply_to(&mut config);
+        config
+            .check_overrides()
+            .and_then(|()| config.check_gas_limits())
+            .map_err(|e| parse_err(&e.0))?;
+
+        *self = config;
+        Ok(())
+    }
+
+    /// Override one or more settings in the config, for testing.
+    /// This must be called at the beginning of the test, before get_for_(min|max)_version is
+    /// called, since those functions cache their return value.
+    ///
+    /// Panics if an override is already present. See the `testing` module for overrides that
+    /// nest.
+    pub fn apply_overrides_for_testing(
+        override_fn: impl Fn(ProtocolVersion, Self) -> Self + Send + 'static,
+    ) -> OverrideGuard {
         CONFIG_OVERRIDE.with(|ovr| {
-            *ovr.borrow_mut() = None;
+            assert!(ovr.borrow().is_empty(), "config override already present");
         });
+
+        push_config_override(Box::new(override_fn))
     }
 }
 
-/// Defines which limit got crossed.
-/// The value which crossed the limit and value of the limit crossed are embedded
-#[derive(PartialEq, Eq)]
-pub enum LimitThresholdCrossed {
-    None,
-    Soft(u128, u128),
-    Hard(u128, u128),
-}
+#[cfg(feature = "overrides")]
+impl ProtocolConfigLocalnetArgs {
+    /// Register these arguments, so that they are applied to every config for `Chain::Unknown` that
+    /// this process fetches through `ProtocolConfig::get_for_version`. Fails if the overridden
+    /// config is inconsistent at any supported protocol version, if any config has already been
+    /// produced, or if overrides are already registered.
+    pub fn register(self) -> Result<(), Error> {
+        let produced = CONFIGS_PRODUCED.lock().unwrap();
+        if *produced {
+            return Err(Error(
+                "Cannot register protocol config overrides: configs have already been produced \
+                 without them"
+                    .to_string(),
+            ));
+        }
 
-/// Convenience function for comparing limit ranges
-/// V::MAX must be at >= U::MAX and T::MAX
-pub fn check_limit_in_range<T: Into<V>, U: Into<V>, V: PartialOrd + Into<u128>>(
-    x: T,
-    soft_limit: U,
-    hard_limit: V,
-) -> LimitThresholdCrossed {
-    let x: V = x.into();
-    let soft_limit: V = soft_limit.into();
+        let custom = CUSTOM_CHAIN.get().map_or(&[][..], |c| &c.patches[..]);
+        for version in MIN_PROTOCOL_VERSION..=MAX_PROTOCOL_VERSION {
+            let version = ProtocolVersion::new(version);
+            let mut config = ProtocolConfig::get_base_for_version(version, Chain::Unknown);
+            config.apply_patches(version, Chain::Unknown, custom);
+            self.override_config(&mut config);
+            config.check_overrides().map_err(|e| {
+                Error(format!(
+                    "Invalid overrides for protocol version {}: {}",
+                    version.as_u64(),
+                    e.0
+                ))
+            })?;
+        }
 
-    debug_assert!(soft_limit <= hard_limit);
+        LOCALNET_OVERRIDES
+            .set(self)
+            .map_err(|_| Error("Protocol config overrides are already registered".to_string()))?;
 
-    // It is important to preserve this comparison order because if soft_limit == hard_limit
-    // we want LimitThresholdCrossed::Hard
-    if x >= hard_limit {
-        LimitThresholdCrossed::Hard(x.into(), hard_limit.into())
-    } else if x < soft_limit {
-        LimitThresholdCrossed::None
-    } else {
-        LimitThresholdCrossed::Soft(x.into(), soft_limit.into())
+        info!("registered protocol config overrides");
+        Ok(())
     }
-}
 
-#[macro_export]
-macro_rules! check_limit {
-    ($x:expr, $hard:expr) => {
-        check_limit!($x, $hard, $hard)
-    };
-    ($x:expr, $soft:expr, $hard:expr) => {
-        check_limit_in_range($x as u64, $soft, $hard)
-    };
-}
+    /// Apply these arguments to `config`. Consensus size limits that were not supplied are raised
+    /// to accommodate a larger `max_tx_size_bytes`, if necessary.
+    fn override_config(&self, config: &mut ProtocolConfig) {
+        self.apply_to(config);
 
-/// Used to check which limits were crossed if the TX is metered (not system tx)
-/// Args are: is_metered, value_to_check, metered_limit, unmetered_limit
-/// metered_limit is always less than or equal to unmetered_hard_limit
-#[macro_export]
-macro_rules! check_limit_by_meter {
-    ($is_metered:expr, $x:expr, $metered_limit:expr, $unmetered_hard_limit:expr, $metric:expr) => {{
-        // If this is metered, we use the metered_limit limit as the upper bound
-        let (h, metered_str) = if $is_metered {
-            ($metered_limit, "metered")
-        } else {
-            // Unmetered gets more headroom
-            ($unmetered_hard_limit, "unmetered")
-        };
-        use sui_protocol_config::check_limit_in_range;
-        let result = check_limit_in_range($x as u64, $metered_limit, h);
-        match result {
-            LimitThresholdCrossed::None => {}
-            LimitThresholdCrossed::Soft(_, _) => {
-                $metric.with_label_values(&[metered_str, "soft"]).inc();
-            }
-            LimitThresholdCrossed::Hard(_, _) => {
-                $metric.with_label_values(&[metered_str, "hard"]).inc();
-            }
-        };
-        result
-    }};
-}
+        if self.consensus_max_transaction_size_bytes.is_none()
+            && config.max_transaction_size_bytes()
+                < config.max_tx_size_bytes() + CONSENSUS_MAX_TRANSACTION_SIZE_HEADROOM_BYTES
+        {
+            config.set_consensus_max_transaction_size_from_tx_limit();
+        }
 
-pub fn is_mysticeti_fpc_enabled_in_env() -> Option<bool> {
-    if let Ok(v) = std::env::var("CONSENSUS") {
-        if v == "mysticeti_fpc" {
-            return Some(true);
-        } else if v == "mysticeti" {
-            return Some(false);
+        if self.consensus_max_transactions_in_block_bytes.is_none()
+            && config.max_transactions_in_block_bytes() < config.max_transaction_size_bytes()
+        {
+            config.consensus_max_transactions_in_block_bytes =
+                Some(config.max_transaction_size_bytes());
         }
     }
-    None
 }
 
-#[cfg(all(test, not(msim)))]
-mod test {
-    use insta::assert_yaml_snapshot;
+/// Parse the value of a `--protocol.*` argument, which is an integer, optionally followed by a
+/// binary size suffix (`KB`, `MB` or `GB`, e.g. `--protocol.max-tx-size-bytes 1MB`).
+#[cfg(feature = "overrides")]
+fn parse_localnet_override<T: TryFrom<u64>>(s: &str) -> Result<T, String> {
+    let s = s.trim();
+    let (digits, multiplier) = [("KB", 1 << 10), ("MB", 1 << 20), ("GB", 1 << 30)]
+        .into_iter()
+        .find_map(|(suffix, multiplier)| Some((s.strip_suffix(suffix)?, multiplier)))
+        .unwrap_or((s, 1));
+
+    let value: u64 = digits
+        .trim()
+        .replace('_', "")
+        .parse()
+        .map_err(|e| format!("invalid value '{s}': {e}"))?;
+
+    value
+        .checked_mul(multiplier)
+        .and_then(|v| T::try_from(v).ok())
+        .ok_or_else(|| format!("value '{s}' is out of range"))
+}
 
-    use super::*;
+/// The changes to constants and feature flags that take effect at a given protocol version,
+/// relative to the version before it. The delta for `ProtocolVersion::MIN` is relative to an empty
+/// config, so it contains every constant and feature flag.
+///
+/// Deltas cover the same view of the config that is exposed by `attr_map` and `feature_map`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ProtocolConfigDelta {
+    /// The protocol version these changes take effect at.
+    pub version: ProtocolVersion,
 
-    #[test]
-    fn snapshot_tests() {
-        println!("\n============================================================================");
-        println!("!                                                                          !");
-        println!("! IMPORTANT: never update snapshots from this test. only add new versions! !");
-        println!("!                                                                          !");
-        println!("============================================================================\n");
-        for chain_id in &[Chain::Unknown, Chain::Mainnet, Chain::Testnet] {
-            // make Chain::Unknown snapshots compatible with pre-chain-id snapshots so that we
-            // don't break the release-time compatibility tests. Once Chain Id configs have been
-            // released everywhere, we can remove this and only test Mainnet and Testnet
-            let chain_str = match chain_id {
-                Chain::Unknown => "".to_string(),
-                _ => format!("{:?}_", chain_id),
-            };
-            for i in MIN_PROTOCOL_VERSION..=MAX_PROTOCOL_VERSION {
-                let cur = ProtocolVersion::new(i);
-                assert_yaml_snapshot!(
-                    format!("{}version_{}", chain_str, cur.as_u64()),
-                    ProtocolConfig::get_for_version(cur, *chain_id)
-                );
-            }
+    /// Constants that changed at this version, mapped to their new value (`None` if the constant
+    /// is no longer set).
+    pub constants: BTreeMap<String, Option<ProtocolConfigValue>>,
+
+    /// Feature flags that changed at this version, mapped to their new value.
+    pub feature_flags: BTreeMap<String, bool>,
+}
+
+/// Maximum number of public keys in a multisig public key before `max_multisig_signers` was
+/// versioned. Multisig public keys in sui-types are never constructed with more keys than this.
+pub const MAX_MULTISIG_SIGNERS: u32 = 10;
+
+/// Size of the challenge that a passkey signs before `passkey_challenge_size_bytes` was versioned.
+/// Passkey authenticators in sui-types are parsed with challenges of this size.
+pub const PASSKEY_CHALLENGE_SIZE_BYTES: u32 = 32;
+
+/// Limits on the signatures that authenticate transactions, gathered in one place so that
+/// validation at the RPC layer uses the same numbers as signature verification. Limits for new
+/// authentication schemes should be added here, backed by versioned constants.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct SignatureLimits {
+    /// Maximum number of public keys in a multisig public key.
+    pub max_multisig_signers: u32,
+
+    /// Maximum size of the serialized zkLogin inputs in a zkLogin signature, or `None` if this
+    /// version does not limit it.
+    pub max_zklogin_proof_size_bytes: Option<u32>,
+
+    /// Size of the challenge that a passkey signs.
+    pub passkey_challenge_size_bytes: u32,
+}
+
+/// The limits that were hardcoded before they were versioned.
+impl Default for SignatureLimits {
+    fn default() -> Self {
+        Self {
+            max_multisig_signers: MAX_MULTISIG_SIGNERS,
+            max_zklogin_proof_size_bytes: None,
+            passkey_challenge_size_bytes: PASSKEY_CHALLENGE_SIZE_BYTES,
         }
     }
+}
 
-    #[test]
-    fn test_getters() {
-        let prot: ProtocolConfig =
-            ProtocolConfig::get_for_version(ProtocolVersion::new(1), Chain::Unknown);
-        assert_eq!(
-            prot.max_arguments(),
-            prot.max_arguments_as_option().unwrap()
-        );
+impl ProtocolConfig {
+    /// The signature limits in effect at this version. Versions that predate the limits being
+    /// versioned report the values that were hardcoded at the time.
+    pub fn signature_limits(&self) -> SignatureLimits {
+        SignatureLimits {
+            max_multisig_signers: self.max_multisig_signers.unwrap_or(MAX_MULTISIG_SIGNERS),
+            max_zklogin_proof_size_bytes: self.max_zklogin_proof_size_bytes,
+            passkey_challenge_size_bytes: self
+                .passkey_challenge_size_bytes
+                .unwrap_or(PASSKEY_CHALLENGE_SIZE_BYTES),
+        }
     }
+}
 
-    #[test]
-    fn test_setters() {
-        let mut prot: ProtocolConfig =
-            ProtocolConfig::get_for_version(ProtocolVersion::new(1), Chain::Unknown);
-        prot.set_max_arguments_for_testing(123);
-        assert_eq!(prot.max_arguments(), 123);
+// Deltas between consecutive protocol versions.
+impl ProtocolConfig {
+    /// Changes to constants and feature flags introduced at `version` on `chain`, relative to
+    /// `version - 1`. Overrides (for testing or from the environment) are not applied.
+    pub fn delta_for_version(version: ProtocolVersion, chain: Chain) -> ProtocolConfigDelta {
+        let curr = Self::get_for_version_impl(version, chain);
+        if version <= ProtocolVersion::MIN {
+            return ProtocolConfigDelta {
+                version,
+                constants: curr.attr_map(),
+                feature_flags: curr.feature_map(),
+            };
+        }
 
-        prot.set_max_arguments_from_str_for_testing("321".to_string());
-        assert_eq!(prot.max_arguments(), 321);
+        let prev = Self::get_for_version_impl(version - 1, chain);
+        let prev_constants = prev.attr_map();
+        let prev_flags = prev.feature_map();
 
-        prot.disable_max_arguments_for_testing();
-        assert_eq!(prot.max_arguments_as_option(), None);
+        ProtocolConfigDelta {
+            version,
+            constants: curr
+                .attr_map()
+                .into_iter()
+                .filter(|(k, v)| prev_constants.get(k) != Some(v))
+                .collect(),
+            feature_flags: curr
+                .feature_map()
+                .into_iter()
+                .filter(|(k, v)| prev_flags.get(k) != Some(v))
+                .collect(),
+        }
+    }
 
-        prot.set_attr_for_testing("max_arguments".to_string(), "456".to_string());
-        assert_eq!(prot.max_arguments(), 456);
+    /// Deltas for every protocol version supported by this binary on `chain`, in version order.
+    pub fn deltas(chain: Chain) -> Vec<ProtocolConfigDelta> {
+        (MIN_PROTOCOL_VERSION..=MAX_PROTOCOL_VERSION)
+            .map(|v| Self::delta_for_version(ProtocolVersion::new(v), chain))
+            .collect()
     }
+}
 
-    #[test]
-    #[should_panic(expected = "unsupported version")]
-    fn max_version_test() {
-        // When this does not panic, version higher than MAX_PROTOCOL_VERSION exists.
-        // To fix, bump MAX_PROTOCOL_VERSION or disable this check for the version.
-        let _ = ProtocolConfig::get_for_version_impl(
-            ProtocolVersion::new(MAX_PROTOCOL_VERSION + 1),
-            Chain::Unknown,
-        );
+impl ProtocolConfigDelta {
+    /// Apply this delta on top of the constants and feature flags of the previous version.
+    pub fn apply(
+        &self,
+        constants: &mut BTreeMap<String, Option<ProtocolConfigValue>>,
+        feature_flags: &mut BTreeMap<String, bool>,
+    ) {
+        constants.extend(self.constants.clone());
+        feature_flags.extend(self.feature_flags.clone());
+    }
+
+    /// Reconstruct the constants and feature flags (as returned by `attr_map` and `feature_map`)
+    /// of a version by folding the deltas of every version up to and including it, starting from
+    /// `ProtocolVersion::MIN`.
+    pub fn fold<'d>(
+        deltas: impl IntoIterator<Item = &'d ProtocolConfigDelta>,
+    ) -> (
+        BTreeMap<String, Option<ProtocolConfigValue>>,
+        BTreeMap<String, bool>,
+    ) {
+        let mut constants = BTreeMap::new();
+        let mut feature_flags = BTreeMap::new();
+        for delta in deltas {
+            delta.apply(&mut constants, &mut feature_flags);
+        }
+        (constants, feature_flags)
     }
+}
 
-    #[test]
-    fn lookup_by_string_test() {
-        let prot: ProtocolConfig =
-            ProtocolConfig::get_for_version(ProtocolVersion::new(1), Chain::Unknown);
-        // Does not exist
-        assert!(prot.lookup_attr("some random string".to_string()).is_none());
+/// A snapshot of every constant and feature flag in a `ProtocolConfig`, for clients that consume
+/// it as JSON (e.g. from JavaScript). Constants are rendered as strings, because not all of them
+/// can be represented as JSON numbers without losing precision.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtocolConfigExport {
+    pub protocol_version: String,
 
-        assert!(
-            prot.lookup_attr("max_arguments".to_string())
-                == Some(ProtocolConfigValue::u32(prot.max_arguments())),
-        );
+    /// Every constant, by name, or `None` if the constant is not set at this version.
+    pub attributes: BTreeMap<String, Option<String>>,
 
-        // We didnt have this in version 1
-        assert!(prot
-            .lookup_attr("max_move_identifier_len".to_string())
-            .is_none());
+    /// Every feature flag, by name.
+    pub feature_flags: BTreeMap<String, bool>,
+}
 
-        // But we did in version 9
-        let prot: ProtocolConfig =
-            ProtocolConfig::get_for_version(ProtocolVersion::new(9), Chain::Unknown);
-        assert!(
-            prot.lookup_attr("max_move_identifier_len".to_string())
-                == Some(ProtocolConfigValue::u64(prot.max_move_identifier_len()))
-        );
+/// Constants in `ConstantCategory::Other` that transaction execution depends on.
+const OTHER_EXECUTION_CONSTANTS: &[&str] = &["execution_version"];
 
-        let prot: ProtocolConfig =
-            ProtocolConfig::get_for_version(ProtocolVersion::new(1), Chain::Unknown);
+/// The parameters of a `ProtocolConfig` that transaction execution depends on, for replay tools
+/// and alternative execution clients that pin and exchange them without the consensus and
+/// networking parameters. These are:
+/// - the execution version;
+/// - the transaction input, Move VM, bytecode verifier and object runtime limits;
+/// - gas costs, including the costs of native functions;
+/// - the feature flags that are enabled.
+///
+/// Constants that are not set and flags that are not enabled are omitted. As a result, adding a
+/// constant or feature flag for a future version does not change the profile (or its `digest`)
+/// at existing versions. In human-readable formats (like JSON), constants are rendered as
+/// strings, as in `ProtocolConfigExport`. In BCS, they are integers.
+#[serde_as]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionProfile {
+    #[serde_as(as = "IfIsHumanReadable<DisplayFromStr>")]
+    pub protocol_version: u64,
+
+    /// Execution constants that are set at this version, by name.
+    #[serde_as(as = "BTreeMap<_, IfIsHumanReadable<DisplayFromStr>>")]
+    pub constants: BTreeMap<String, u64>,
+
+    /// Names of the feature flags that are enabled. Flags are not grouped by the component they
+    /// affect, so this includes flags that do not affect execution.
+    pub feature_flags: BTreeSet<String>,
+}
+
+/// The values of a constant or feature flag before and after a protocol upgrade.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ProtocolConfigChange<T> {
+    pub old: T,
+    pub new: T,
+}
+
+/// The constants and feature flags whose values differ between two protocol versions on the same
+/// chain, with their values at both versions.
+///
+/// Diffs cover the same view of the config that is exposed by `attr_map` and `feature_map`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ProtocolConfigDiff {
+    pub old_version: ProtocolVersion,
+    pub new_version: ProtocolVersion,
+
+    /// Constants that differ between the two versions (`None` if the constant is not set at that
+    /// version).
+    pub constants: BTreeMap<String, ProtocolConfigChange<Option<ProtocolConfigValue>>>,
+
+    /// Feature flags that differ between the two versions.
+    pub feature_flags: BTreeMap<String, ProtocolConfigChange<bool>>,
+}
+
+impl ProtocolConfig {
+    /// Every constant and feature flag in this config, in a form suitable for exporting as JSON.
+    pub fn export(&self) -> ProtocolConfigExport {
+        ProtocolConfigExport {
+            protocol_version: self.version.as_u64().to_string(),
+            attributes: self
+                .attr_map()
+                .into_iter()
+                .map(|(name, value)| (name, value.map(|v| v.to_string())))
+                .collect(),
+            feature_flags: self.feature_map(),
+        }
+    }
+
+    /// This config, exported as a JSON string (see `export`).
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.export()).expect("exported config is always serializable")
+    }
+
+    /// The parameters in this config that transaction execution depends on. Fails if an execution
+    /// constant is not an integer.
+    pub fn execution_profile(&self) -> Result<ExecutionProfile, Error> {
+        let mut constants = BTreeMap::new();
+        for (name, category, _, value, _) in self.iter_constants() {
+            if category == ConstantCategory::Other && !OTHER_EXECUTION_CONSTANTS.contains(&name) {
+                continue;
+            }
+
+            let value = match value {
+                None => continue,
+                Some(ProtocolConfigValue::u16(v)) => v as u64,
+                Some(ProtocolConfigValue::u32(v)) => v as u64,
+                Some(ProtocolConfigValue::u64(v)) => v,
+                Some(v) => {
+                    return Err(Error(format!(
+                        "Execution constant {name} is not an integer: {v}"
+                    )))
+                }
+            };
+
+            constants.insert(name.to_owned(), value);
+        }
+
+        Ok(ExecutionProfile {
+            protocol_version: self.version.as_u64(),
+            constants,
+            feature_flags: self
+                .feature_map()
+                .into_iter()
+                .filter_map(|(name, enabled)| enabled.then_some(name))
+                .collect(),
+        })
+    }
+
+    /// Constants and feature flags that differ between `old` and `new` on `chain`. The versions
+    /// need not be consecutive, or in increasing order. Overrides (for testing or from the
+    /// environment) are not applied.
+    pub fn diff(old: ProtocolVersion, new: ProtocolVersion, chain: Chain) -> ProtocolConfigDiff {
+        let old_config = Self::get_for_version_impl(old, chain);
+        let new_config = Self::get_for_version_impl(new, chain);
+
+        ProtocolConfigDiff {
+            old_version: old,
+            new_version: new,
+            constants: diff_maps(old_config.attr_map(), new_config.attr_map()),
+            feature_flags: diff_maps(old_config.feature_map(), new_config.feature_map()),
+        }
+    }
+}
+
+impl ExecutionProfile {
+    /// The profile's BCS serialization.
+    pub fn to_bcs(&self) -> Vec<u8> {
+        bcs::to_bytes(self).expect("execution profile is always serializable")
+    }
+
+    /// The Blake2b-256 hash of the profile's BCS serialization, for checking that two parties
+    /// agree on the execution parameters of a protocol version.
+    pub fn digest(&self) -> [u8; 32] {
+        Blake2b256::digest(self.to_bcs()).digest
+    }
+}
+
+impl ProtocolConfigDiff {
+    /// Whether the two versions are configured identically.
+    pub fn is_empty(&self) -> bool {
+        self.constants.is_empty() && self.feature_flags.is_empty()
+    }
+}
+
+/// A protocol version at which a feature flag changed state on a chain.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct FeatureFlagTransition {
+    pub chain: Chain,
+    pub version: ProtocolVersion,
+    /// The flag's value from this version on (until its next transition).
+    pub enabled: bool,
+}
+
+impl ProtocolConfig {
+    /// Every change of state of the feature flag called `flag`, on each chain, in chain and then
+    /// version order, reconstructed by replaying the deltas between consecutive protocol versions.
+    /// Flags are disabled before `ProtocolVersion::MIN`, so a flag that is enabled from the first
+    /// version has a transition at that version. Fails if there is no feature flag called `flag`.
+    pub fn feature_flag_transitions(flag: &str) -> Result<Vec<FeatureFlagTransition>, Error> {
+        let min = Self::get_for_version_impl(ProtocolVersion::MIN, Chain::Unknown);
+        if min.lookup_feature(flag.to_string()).is_none() {
+            return Err(Error(format!("unknown feature flag '{flag}'")));
+        }
+
+        let mut transitions = vec![];
+        for chain in [Chain::Mainnet, Chain::Testnet, Chain::Unknown] {
+            let mut enabled = false;
+            for delta in Self::deltas(chain) {
+                match delta.feature_flags.get(flag) {
+                    Some(&value) if value != enabled => {
+                        enabled = value;
+                        transitions.push(FeatureFlagTransition {
+                            chain,
+                            version: delta.version,
+                            enabled,
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(transitions)
+    }
+
+    /// The first protocol version supported by this binary at which feature flag `flag` is enabled
+    /// on `chain`, found by scanning versions from `ProtocolVersion::MIN` to
+    /// `ProtocolVersion::MAX`. Returns `None` if the flag is never enabled, or there is no such
+    /// flag. A flag that was later disabled again still reports the version it was first enabled
+    /// at (see `feature_flag_transitions` for its full history).
+    pub fn feature_activation_version(flag: &str, chain: Chain) -> Option<ProtocolVersion> {
+        (MIN_PROTOCOL_VERSION..=MAX_PROTOCOL_VERSION)
+            .map(ProtocolVersion::new)
+            .find(|&version| {
+                Self::get_for_version_impl(version, chain).lookup_feature(flag.to_string())
+                    == Some(true)
+            })
+    }
+
+    /// The activation version (as returned by `feature_activation_version`) of every feature flag
+    /// on `chain`, keyed by flag name.
+    pub fn feature_timeline(chain: Chain) -> BTreeMap<String, Option<ProtocolVersion>> {
+        let mut timeline = BTreeMap::new();
+        for version in (MIN_PROTOCOL_VERSION..=MAX_PROTOCOL_VERSION).map(ProtocolVersion::new) {
+            let config = Self::get_for_version_impl(version, chain);
+            for (flag, enabled) in config.feature_map() {
+                let activation = timeline.entry(flag).or_insert(None);
+                if enabled && activation.is_none() {
+                    *activation = Some(version);
+                }
+            }
+        }
+
+        timeline
+    }
+}
+
+/// Feature flags that only work when another flag is also enabled, as pairs of the flag and the
+/// flag it requires.
+const FEATURE_FLAG_DEPENDENCIES: &[(&str, &str)] = &[
+    ("accept_zklogin_in_multisig", "zklogin_auth"),
+    (
+        "consensus_round_prober_probe_accepted_rounds",
+        "consensus_round_prober",
+    ),
+    ("enable_coin_deny_list_v2", "enable_coin_deny_list"),
+    (
+        "enable_group_ops_native_function_msm",
+        "enable_group_ops_native_functions",
+    ),
+    (
+        "loaded_child_object_format_type",
+        "loaded_child_object_format",
+    ),
+    (
+        "uncompressed_g1_group_elements",
+        "enable_group_ops_native_functions",
+    ),
+    ("verify_legacy_zklogin_address", "zklogin_auth"),
+];
+
+/// Constants that must never decrease across a protocol upgrade, because they select the version
+/// of behavior (or of formats) that the network follows.
+const MONOTONIC_CONSTANTS: &[&str] = &[
+    "execution_version",
+    "gas_model_version",
+    "min_move_binary_format_version",
+    "move_binary_format_version",
+];
+
+/// A reason why upgrading between two protocol versions is not internally consistent, found by
+/// `ProtocolConfig::validate_transition`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum TransitionViolation {
+    /// The binary does not support this protocol version.
+    UnsupportedVersion(ProtocolVersion),
+
+    /// The upgrade goes back to an earlier protocol version.
+    Downgrade {
+        from: ProtocolVersion,
+        to: ProtocolVersion,
+    },
+
+    /// A relationship between constants that must hold does not hold in the version being
+    /// upgraded to.
+    Invariant(String),
+
+    /// A feature flag is enabled in the version being upgraded to, but a flag it requires is not.
+    MissingDependency { flag: String, requires: String },
+
+    /// A constant that must never decrease is lower (or unset) in the version being upgraded to.
+    Regression {
+        constant: String,
+        from: Option<u64>,
+        to: Option<u64>,
+    },
+}
+
+impl ProtocolConfig {
+    /// Check that upgrading from protocol version `from` straight to `to` on `chain` leaves the
+    /// config internally consistent, for operators of custom networks who skip versions. The
+    /// config at `to` must satisfy the invariants between its constants (see `check_overrides`,
+    /// and `check_gas_limits` from gas model v2 onwards), every feature flag it enables must have
+    /// the flags it depends on enabled, and constants that select the version of execution, gas
+    /// model or binary format must not go backwards. Overrides (for testing or from the
+    /// environment) are not applied.
+    ///
+    /// Returns every violation found, or `Ok(())` if there are none.
+    pub fn validate_transition(
+        from: ProtocolVersion,
+        to: ProtocolVersion,
+        chain: Chain,
+    ) -> Result<(), Vec<TransitionViolation>> {
+        use TransitionViolation as V;
+
+        let (Some(old), Some(new)) = (
+            Self::get_for_version_if_supported(from, chain),
+            Self::get_for_version_if_supported(to, chain),
+        ) else {
+            return Err([from, to]
+                .into_iter()
+                .filter(|v| Self::get_for_version_if_supported(*v, chain).is_none())
+                .map(V::UnsupportedVersion)
+                .collect());
+        };
+
+        let mut violations = vec![];
+        if to < from {
+            violations.push(V::Downgrade { from, to });
+        }
+
+        violations.extend(transition_violations(&old, &new));
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+/// Problems with upgrading from config `old` to config `new`, other than with their versions.
+fn transition_violations(old: &ProtocolConfig, new: &ProtocolConfig) -> Vec<TransitionViolation> {
+    use TransitionViolation as V;
+
+    let mut violations = vec![];
+
+    // The gas limits invariant holds from gas model v2 onwards.
+    let gas_limits = if new.gas_model_version_as_option() >= Some(2) {
+        new.check_gas_limits()
+    } else {
+        Ok(())
+    };
+
+    for check in [new.check_overrides(), gas_limits] {
+        if let Err(e) = check {
+            violations.push(V::Invariant(e.0));
+        }
+    }
+
+    for (flag, requires) in FEATURE_FLAG_DEPENDENCIES {
+        if new.lookup_feature(flag.to_string()) == Some(true)
+            && new.lookup_feature(requires.to_string()) != Some(true)
+        {
+            violations.push(V::MissingDependency {
+                flag: flag.to_string(),
+                requires: requires.to_string(),
+            });
+        }
+    }
+
+    for constant in MONOTONIC_CONSTANTS {
+        let value = |config: &ProtocolConfig| match config.lookup_attr(constant.to_string())? {
+            ProtocolConfigValue::u16(v) => Some(v as u64),
+            ProtocolConfigValue::u32(v) => Some(v as u64),
+            ProtocolConfigValue::u64(v) => Some(v),
+            _ => None,
+        };
+
+        let (old, new) = (value(old), value(new));
+        if old.is_some() && new < old {
+            violations.push(V::Regression {
+                constant: constant.to_string(),
+                from: old,
+                to: new,
+            });
+        }
+    }
+
+    violations
+}
+
+/// The sections that `ProtocolConfig`'s constants are grouped into in its declaration.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Debug)]
+pub enum ConstantCategory {
+    /// Transaction input limits.
+    TransactionInputLimits,
+    /// Move VM, Move bytecode verifier, and execution limits.
+    ExecutionLimits,
+    /// Object runtime internal operation limits (e.g. for dynamic fields).
+    ObjectRuntimeLimits,
+    /// Execution gas costs, and the gas model's other parameters.
+    ExecutionGasCosts,
+    /// Costs of Move native functions.
+    NativeFunctionCosts,
+    /// Everything declared after the native function costs, which is mostly consensus,
+    /// randomness, checkpoint and congestion control parameters.
+    Other,
+}
+
+impl ConstantCategory {
+    /// The first constant declared in each category. Constants declared after it (up to the first
+    /// constant of the next category) belong to the same category.
+    const FIRST_CONSTANTS: &'static [(&'static str, ConstantCategory)] = &[
+        (
+            "max_tx_size_bytes",
+            ConstantCategory::TransactionInputLimits,
+        ),
+        (
+            "move_binary_format_version",
+            ConstantCategory::ExecutionLimits,
+        ),
+        (
+            "object_runtime_max_num_cached_objects",
+            ConstantCategory::ObjectRuntimeLimits,
+        ),
+        ("base_tx_cost_fixed", ConstantCategory::ExecutionGasCosts),
+        (
+            "address_from_bytes_cost_base",
+            ConstantCategory::NativeFunctionCosts,
+        ),
+        ("execution_version", ConstantCategory::Other),
+    ];
+}
+
+/// The unit a constant is measured in, declared with a `#[unit(...)]` attribute on its field, or
+/// inferred from its name for fields named `*_bytes` or `*_ms`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub enum ConfigUnit {
+    Bytes,
+    Milliseconds,
+    /// The smallest unit of SUI (10^-9 SUI).
+    Mist,
+    /// Steps of the Move bytecode verifier's meter.
+    Ticks,
+    /// Hundredths of a percent.
+    BasisPoints,
+    Percent,
+}
+
+/// Static information about a constant, for tools that describe the config to operators.
+#[derive(Clone, PartialEq, Eq, Serialize, Debug)]
+pub struct FieldMetadata {
+    pub name: &'static str,
+    pub category: ConstantCategory,
+    /// The constant's Rust type, e.g. `u64`.
+    pub type_: &'static str,
+    /// The constant's doc comment, empty if it has none.
+    pub doc: &'static str,
+    pub unit: Option<ConfigUnit>,
+}
+
+impl ProtocolConfig {
+    /// Static information about the constant that `key` refers to (by name, address, or a name it
+    /// had before it was renamed, see `resolve_attr`), or `None` if there is no such constant.
+    pub fn field_metadata(key: &str) -> Option<FieldMetadata> {
+        let name = Self::resolve_attr(key)?;
+        Self::constant_metadata().find(|m| m.name == name)
+    }
+
+    /// Static information about every constant, in declaration order.
+    fn constant_metadata() -> impl Iterator<Item = FieldMetadata> {
+        let mut category = ConstantCategory::TransactionInputLimits;
+        Self::CONSTANT_METADATA
+            .iter()
+            .map(move |&(name, type_, doc, unit)| {
+                if let Some((_, c)) = ConstantCategory::FIRST_CONSTANTS
+                    .iter()
+                    .find(|(first, _)| *first == name)
+                {
+                    category = *c;
+                }
+
+                FieldMetadata {
+                    name,
+                    category,
+                    type_,
+                    doc,
+                    unit,
+                }
+            })
+    }
+}
+
+impl ProtocolConfig {
+    /// Every constant, in declaration order, as its name, category, type, value at this version
+    /// (`None` if it is not set), and doc comment (empty if it has none). Intended for tooling
+    /// that renders the config, which would otherwise have to parse this file.
+    pub fn iter_constants(
+        &self,
+    ) -> impl Iterator<
+        Item = (
+            &'static str,
+            ConstantCategory,
+            &'static str,
+            Option<ProtocolConfigValue>,
+            &'static str,
+        ),
+    > + '_ {
+        Self::constant_metadata().map(|m| {
+            (
+                m.name,
+                m.category,
+                m.type_,
+                self.lookup_attr(m.name.to_owned()),
+                m.doc,
+            )
+        })
+    }
+}
+
+/// Groups of constants that are addressed together, by the prefix that their names share. A
+/// constant whose name starts with a group's prefix has the address `/<group>/<rest of name>`
+/// (e.g. `consensus_gc_depth` is at `/consensus/gc_depth`), and other constants have the address
+/// `/<name>`. Feature flags are addressed as `/feature_flags/<name>`.
+///
+/// Addresses are part of the interface that external automation relies on (through overrides and
+/// RPC responses), so existing groups must not be changed.
+const CONFIG_ADDRESS_GROUPS: &[(&str, &str)] = &[
+    ("consensus_", "consensus"),
+    ("execution_time_estimate_", "execution_time_estimate"),
+    ("random_beacon_", "random_beacon"),
+];
+
+/// Constants that have been renamed, as pairs of their old and new names. A renamed constant keeps
+/// the address derived from its old name, and its old name is accepted as an alias for it, so
+/// that renaming a field does not break overrides or tools that refer to it.
+const RENAMED_CONSTANTS: &[(&str, &str)] = &[];
+
+impl ProtocolConfig {
+    /// The stable address of constant `name`, e.g. `/consensus/gc_depth` for `consensus_gc_depth`,
+    /// or `None` if there is no such constant. Unlike field names, addresses do not change when
+    /// constants are renamed.
+    pub fn attr_address(name: &str) -> Option<String> {
+        Self::CONSTANT_METADATA
+            .iter()
+            .any(|(n, ..)| *n == name)
+            .then(|| constant_address(name, RENAMED_CONSTANTS))
+    }
+
+    /// The name of the constant that `key` refers to, where `key` is either the constant's
+    /// address, its name, or a name it had before it was renamed. Returns `None` if `key` does
+    /// not refer to a constant.
+    pub fn resolve_attr(key: &str) -> Option<&'static str> {
+        resolve_constant(key, RENAMED_CONSTANTS)
+    }
+
+    /// Lookup a config attribute by its address (or any other key accepted by `resolve_attr`).
+    pub fn lookup_attr_by_address(&self, key: &str) -> Option<ProtocolConfigValue> {
+        self.lookup_attr(Self::resolve_attr(key)?.to_owned())
+    }
+
+    /// The address of every constant and feature flag, keyed by the name it appears under in
+    /// `attr_map` and `feature_map`.
+    pub fn addresses(&self) -> BTreeMap<String, String> {
+        let constants = Self::CONSTANT_METADATA
+            .iter()
+            .map(|(name, ..)| (name.to_string(), constant_address(name, RENAMED_CONSTANTS)));
+
+        let flags = self
+            .feature_map()
+            .into_keys()
+            .map(|name| (name.clone(), format!("/feature_flags/{name}")));
+
+        constants.chain(flags).collect()
+    }
+}
+
+/// The address of the constant currently called `name`, given the constants that have been
+/// `renamed`.
+fn constant_address(name: &str, renamed: &[(&str, &str)]) -> String {
+    let name = renamed
+        .iter()
+        .find_map(|(old, new)| (*new == name).then_some(*old))
+        .unwrap_or(name);
+
+    CONFIG_ADDRESS_GROUPS
+        .iter()
+        .find_map(|(prefix, group)| {
+            let rest = name.strip_prefix(prefix)?;
+            Some(format!("/{group}/{rest}"))
+        })
+        .unwrap_or_else(|| format!("/{name}"))
+}
+
+/// The current name of the constant that `key` refers to, by address, current name, or old name,
+/// given the constants that have been `renamed`.
+fn resolve_constant(key: &str, renamed: &[(&str, &str)]) -> Option<&'static str> {
+    let mut names = ProtocolConfig::CONSTANT_METADATA.iter().map(|(n, ..)| *n);
+    if key.starts_with('/') {
+        return names.find(|name| constant_address(name, renamed) == key);
+    }
+
+    let key = renamed
+        .iter()
+        .find_map(|(old, new)| (*old == key).then_some(*new))
+        .unwrap_or(key);
+
+    names.find(|name| *name == key)
+}
+
+/// Rewrite override env vars that refer to constants by an old name, to use the constant's current
+/// name, leaving all other env vars untouched.
+#[cfg(feature = "overrides")]
+fn resolve_override_vars(
+    vars: impl IntoIterator<Item = (String, String)>,
+    renamed: &[(&str, &str)],
+) -> Vec<(String, String)> {
+    const PREFIX: &str = "SUI_PROTOCOL_CONFIG_OVERRIDE_";
+    vars.into_iter()
+        .map(|(var, value)| {
+            let resolved = var
+                .strip_prefix(PREFIX)
+                .and_then(|key| resolve_constant(&key.to_lowercase(), renamed));
+
+            match resolved {
+                Some(name) => (format!("{PREFIX}{name}"), value),
+                None => (var, value),
+            }
+        })
+        .collect()
+}
+
+/// Pair up the entries of `old` and `new` whose values differ. Both maps are expected to contain
+/// the same keys, as `attr_map` and `feature_map` list every field regardless of version.
+fn diff_maps<T: PartialEq>(
+    old: BTreeMap<String, T>,
+    mut new: BTreeMap<String, T>,
+) -> BTreeMap<String, ProtocolConfigChange<T>> {
+    old.into_iter()
+        .filter_map(|(k, old)| {
+            let new = new.remove(&k)?;
+            (old != new).then_some((k, ProtocolConfigChange { old, new }))
+        })
+        .collect()
+}
+
+// Chaos mode for tests, used to flush out hidden dependencies on the exact value of limits that
+// nodes do not need to agree on.
+#[cfg(any(test, msim, feature = "test-utils"))]
+impl ProtocolConfig {
+    /// Deterministically perturb every constant marked `#[node_local_tolerant]` that is set at
+    /// this version, scaling it by a factor between 0.5 and 2 derived from `seed` and the
+    /// constant's name. All other constants and feature flags are left untouched.
+    ///
+    /// In simtests (or with the `test-utils` feature), this is applied to every config returned
+    /// by `get_for_version` when the `SUI_PROTOCOL_CONFIG_CHAOS_SEED` env var is set.
+    pub fn perturb_node_local_limits_for_testing(&mut self, seed: u64) {
+        for attr in Self::node_local_tolerant_attrs() {
+            let Some(value) = self.lookup_attr(attr.clone()) else {
+                continue;
+            };
+
+            let mut state = attr
+                .bytes()
+                .fold(seed, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+            let percent = 50 + (splitmix64(&mut state) % 151) as u128;
+            let scale = |v: u128, max: u128| {
+                if v == 0 {
+                    0
+                } else {
+                    (v * percent / 100).clamp(1, max)
+                }
+            };
+
+            let perturbed = match value {
+                ProtocolConfigValue::u16(v) => scale(v as u128, u16::MAX as u128).to_string(),
+                ProtocolConfigValue::u32(v) => scale(v as u128, u32::MAX as u128).to_string(),
+                ProtocolConfigValue::u64(v) => scale(v as u128, u64::MAX as u128).to_string(),
+                _ => continue,
+            };
+
+            self.set_attr_for_testing(attr, perturbed);
+        }
+    }
+}
+
+#[cfg(any(test, msim, feature = "test-utils"))]
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+// Setters for tests.
+// Non-feature_flags have test setters defined through macros, and every feature flag can be set
+// through `FeatureFlagsBuilder` (or `set_feature_for_testing`, for boolean flags), which are also
+// generated, so there is no need to add setters here for new flags.
+impl ProtocolConfig {
+    pub fn set_advance_to_highest_supported_protocol_version_for_testing(&mut self, val: bool) {
+        self.feature_flags
+            .advance_to_highest_supported_protocol_version = val
+    }
+    pub fn set_commit_root_state_digest_supported_for_testing(&mut self, val: bool) {
+        self.feature_flags.commit_root_state_digest = val
+    }
+    pub fn set_zklogin_auth_for_testing(&mut self, val: bool) {
+        self.feature_flags.zklogin_auth = val
+    }
+    pub fn set_enable_jwk_consensus_updates_for_testing(&mut self, val: bool) {
+        self.feature_flags.enable_jwk_consensus_updates = val
+    }
+    pub fn set_random_beacon_for_testing(&mut self, val: bool) {
+        self.feature_flags.random_beacon = val
+    }
+
+    pub fn set_upgraded_multisig_for_testing(&mut self, val: bool) {
+        self.feature_flags.upgraded_multisig_supported = val
+    }
+    pub fn set_accept_zklogin_in_multisig_for_testing(&mut self, val: bool) {
+        self.feature_flags.accept_zklogin_in_multisig = val
+    }
+
+    pub fn set_shared_object_deletion_for_testing(&mut self, val: bool) {
+        self.feature_flags.shared_object_deletion = val;
+    }
+
+    pub fn set_narwhal_new_leader_election_schedule_for_testing(&mut self, val: bool) {
+        self.feature_flags.narwhal_new_leader_election_schedule = val;
+    }
+
+    pub fn set_receive_object_for_testing(&mut self, val: bool) {
+        self.feature_flags.receive_objects = val
+    }
+    pub fn set_narwhal_certificate_v2_for_testing(&mut self, val: bool) {
+        self.feature_flags.narwhal_certificate_v2 = val
+    }
+    pub fn set_verify_legacy_zklogin_address_for_testing(&mut self, val: bool) {
+        self.feature_flags.verify_legacy_zklogin_address = val
+    }
+
+    pub fn set_per_object_congestion_control_mode_for_testing(
+        &mut self,
+        val: PerObjectCongestionControlMode,
+    ) {
+        self.feature_flags.per_object_congestion_control_mode = val;
+    }
+
+    pub fn set_consensus_transaction_ordering_for_testing(
+        &mut self,
+        val: ConsensusTransactionOrdering,
+    ) {
+        self.feature_flags.consensus_transaction_ordering = val;
+    }
+
+    pub fn set_consensus_gas_price_tie_break_for_testing(&mut self, val: GasPriceTieBreak) {
+        self.feature_flags.consensus_gas_price_tie_break = val;
+    }
+
+    pub fn set_consensus_choice_for_testing(&mut self, val: ConsensusChoice) {
+        self.feature_flags.consensus_choice = val;
+    }
+
+    pub fn set_consensus_network_for_testing(&mut self, val: ConsensusNetwork) {
+        self.feature_flags.consensus_network = val;
+    }
+
+    pub fn set_zklogin_max_epoch_upper_bound_delta_for_testing(&mut self, val: Option<u64>) {
+        self.feature_flags.zklogin_max_epoch_upper_bound_delta = val
+    }
+
+    pub fn set_disable_bridge_for_testing(&mut self) {
+        self.feature_flags.bridge = false
+    }
+
+    pub fn set_mysticeti_num_leaders_per_round_for_testing(&mut self, val: Option<usize>) {
+        self.feature_flags.mysticeti_num_leaders_per_round = val;
+    }
+
+    pub fn set_enable_soft_bundle_for_testing(&mut self, val: bool) {
+        self.feature_flags.soft_bundle = val;
+    }
+
+    pub fn set_passkey_auth_for_testing(&mut self, val: bool) {
+        self.feature_flags.passkey_auth = val
+    }
+
+    pub fn set_consensus_distributed_vote_scoring_strategy_for_testing(&mut self, val: bool) {
+        self.feature_flags
+            .consensus_distributed_vote_scoring_strategy = val;
+    }
+
+    pub fn set_consensus_round_prober_for_testing(&mut self, val: bool) {
+        self.feature_flags.consensus_round_prober = val;
+    }
+
+    pub fn set_disallow_new_modules_in_deps_only_packages_for_testing(&mut self, val: bool) {
+        self.feature_flags
+            .disallow_new_modules_in_deps_only_packages = val;
+    }
+
+    pub fn set_consensus_round_prober_probe_accepted_rounds(&mut self, val: bool) {
+        self.feature_flags
+            .consensus_round_prober_probe_accepted_rounds = val;
+    }
+
+    pub fn set_feature_for_testing(&mut self, feature: Feature, val: bool) {
+        self.feature_flags.set_feature_for_testing(feature, val)
+    }
+}
+
+impl FeatureFlagsBuilder {
+    /// Overwrite the feature flags in `config` that have been set in this builder.
+    pub fn apply_to(&self, config: &mut ProtocolConfig) {
+        self.apply_to_flags(&mut config.feature_flags)
+    }
+}
+
+type OverrideFn = dyn Fn(ProtocolVersion, ProtocolConfig) -> ProtocolConfig + Send;
+
+// Overrides in effect on this thread, from the outermost to the innermost, each with the ID of the
+// guard that removes it.
+thread_local! {
+    static CONFIG_OVERRIDE: RefCell<Vec<(u64, Box<OverrideFn>)>> =
+        const { RefCell::new(Vec::new()) };
+    static NEXT_OVERRIDE_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Removes a config override when dropped. Overrides installed before or after it are unaffected,
+/// so guards can be dropped in any order.
+#[must_use]
+pub struct OverrideGuard {
+    /// Identifies the override that this guard installed.
+    id: u64,
+}
+
+/// Install `override_fn` on this thread, on top of any overrides already in effect.
+fn push_config_override(override_fn: Box<OverrideFn>) -> OverrideGuard {
+    let id = NEXT_OVERRIDE_ID.with(|next| next.replace(next.get() + 1));
+    CONFIG_OVERRIDE.with(|ovr| ovr.borrow_mut().push((id, override_fn)));
+    OverrideGuard { id }
+}
+
+impl Drop for OverrideGuard {
+    fn drop(&mut self) {
+        info!("restoring override fn");
+        CONFIG_OVERRIDE.with(|ovr| {
+            ovr.borrow_mut().retain(|(id, _)| *id != self.id);
+        });
+    }
+}
+
+/// Defines which limit got crossed.
+/// The value which crossed the limit and value of the limit crossed are embedded
+#[derive(PartialEq, Eq)]
+pub enum LimitThresholdCrossed {
+    None,
+    Soft(u128, u128),
+    Hard(u128, u128),
+}
+
+/// Convenience function for comparing limit ranges
+/// V::MAX must be at >= U::MAX and T::MAX
+pub fn check_limit_in_range<T: Into<V>, U: Into<V>, V: PartialOrd + Into<u128>>(
+    x: T,
+    soft_limit: U,
+    hard_limit: V,
+) -> LimitThresholdCrossed {
+    let x: V = x.into();
+    let soft_limit: V = soft_limit.into();
+
+    debug_assert!(soft_limit <= hard_limit);
+
+    // It is important to preserve this comparison order because if soft_limit == hard_limit
+    // we want LimitThresholdCrossed::Hard
+    if x >= hard_limit {
+        LimitThresholdCrossed::Hard(x.into(), hard_limit.into())
+    } else if x < soft_limit {
+        LimitThresholdCrossed::None
+    } else {
+        LimitThresholdCrossed::Soft(x.into(), soft_limit.into())
+    }
+}
+
+#[macro_export]
+macro_rules! check_limit {
+    ($x:expr, $hard:expr) => {
+        check_limit!($x, $hard, $hard)
+    };
+    ($x:expr, $soft:expr, $hard:expr) => {
+        check_limit_in_range($x as u64, $soft, $hard)
+    };
+}
+
+/// Used to check which limits were crossed if the TX is metered (not system tx)
+/// Args are: is_metered, value_to_check, metered_limit, unmetered_limit
+/// metered_limit is always less than or equal to unmetered_hard_limit
+#[macro_export]
+macro_rules! check_limit_by_meter {
+    ($is_metered:expr, $x:expr, $metered_limit:expr, $unmetered_hard_limit:expr, $metric:expr) => {{
+        // If this is metered, we use the metered_limit limit as the upper bound
+        let (h, metered_str) = if $is_metered {
+            ($metered_limit, "metered")
+        } else {
+            // Unmetered gets more headroom
+            ($unmetered_hard_limit, "unmetered")
+        };
+        use sui_protocol_config::check_limit_in_range;
+        let result = check_limit_in_range($x as u64, $metered_limit, h);
+        match result {
+            LimitThresholdCrossed::None => {}
+            LimitThresholdCrossed::Soft(_, _) => {
+                $metric.with_label_values(&[metered_str, "soft"]).inc();
+            }
+            LimitThresholdCrossed::Hard(_, _) => {
+                $metric.with_label_values(&[metered_str, "hard"]).inc();
+            }
+        };
+        result
+    }};
+}
+
+pub fn is_mysticeti_fpc_enabled_in_env() -> Option<bool> {
+    if let Ok(v) = std::env::var("CONSENSUS") {
+        if v == "mysticeti_fpc" {
+            return Some(true);
+        } else if v == "mysticeti" {
+            return Some(false);
+        }
+    }
+    None
+}
+
+#[cfg(all(test, not(msim)))]
+mod test {
+    use insta::assert_yaml_snapshot;
+
+    use super::*;
+
+    #[test]
+    fn snapshot_tests() {
+        println!("\n============================================================================");
+        println!("!                                                                          !");
+        println!("! IMPORTANT: never update snapshots from this test. only add new versions! !");
+        println!("!                                                                          !");
+        println!("============================================================================\n");
+        for chain_id in &[Chain::Unknown, Chain::Mainnet, Chain::Testnet] {
+            // make Chain::Unknown snapshots compatible with pre-chain-id snapshots so that we
+            // don't break the release-time compatibility tests. Once Chain Id configs have been
+            // released everywhere, we can remove this and only test Mainnet and Testnet
+            let chain_str = match chain_id {
+                Chain::Unknown => "".to_string(),
+                _ => format!("{:?}_", chain_id),
+            };
+            for i in MIN_PROTOCOL_VERSION..=MAX_PROTOCOL_VERSION {
+                let cur = ProtocolVersion::new(i);
+                assert_yaml_snapshot!(
+                    format!("{}version_{}", chain_str, cur.as_u64()),
+                    ProtocolConfig::get_for_version(cur, *chain_id)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_getters() {
+        let prot: ProtocolConfig =
+            ProtocolConfig::get_for_version(ProtocolVersion::new(1), Chain::Unknown);
+        assert_eq!(
+            prot.max_arguments(),
+            prot.max_arguments_as_option().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_duration_getters() {
+        let mut prot: ProtocolConfig =
+            ProtocolConfig::get_for_version(ProtocolVersion::new(1), Chain::Unknown);
+        assert_eq!(prot.min_checkpoint_interval_duration_as_option(), None);
+
+        prot.set_min_checkpoint_interval_ms_for_testing(250);
+        assert_eq!(
+            prot.min_checkpoint_interval_duration(),
+            std::time::Duration::from_millis(250)
+        );
+        assert_eq!(
+            prot.min_checkpoint_interval_duration_as_option(),
+            Some(std::time::Duration::from_millis(250))
+        );
+    }
+
+    #[test]
+    fn test_setters() {
+        let mut prot: ProtocolConfig =
+            ProtocolConfig::get_for_version(ProtocolVersion::new(1), Chain::Unknown);
+        prot.set_max_arguments_for_testing(123);
+        assert_eq!(prot.max_arguments(), 123);
+
+        prot.set_max_arguments_from_str_for_testing("321".to_string());
+        assert_eq!(prot.max_arguments(), 321);
+
+        prot.disable_max_arguments_for_testing();
+        assert_eq!(prot.max_arguments_as_option(), None);
+
+        prot.set_attr_for_testing("max_arguments".to_string(), "456".to_string());
+        assert_eq!(prot.max_arguments(), 456);
+    }
+
+    #[test]
+    #[should_panic(expected = "unsupported version")]
+    fn max_version_test() {
+        // When this does not panic, version higher than MAX_PROTOCOL_VERSION exists.
+        // To fix, bump MAX_PROTOCOL_VERSION or disable this check for the version.
+        let _ = ProtocolConfig::get_for_version_impl(
+            ProtocolVersion::new(MAX_PROTOCOL_VERSION + 1),
+            Chain::Unknown,
+        );
+    }
+
+    #[test]
+    fn lookup_by_string_test() {
+        let prot: ProtocolConfig =
+            ProtocolConfig::get_for_version(ProtocolVersion::new(1), Chain::Unknown);
+        // Does not exist
+        assert!(prot.lookup_attr("some random string".to_string()).is_none());
+
+        assert!(
+            prot.lookup_attr("max_arguments".to_string())
+                == Some(ProtocolConfigValue::u32(prot.max_arguments())),
+        );
+
+        // We didnt have this in version 1
+        assert!(prot
+            .lookup_attr("max_move_identifier_len".to_string())
+            .is_none());
+
+        // But we did in version 9
+        let prot: ProtocolConfig =
+            ProtocolConfig::get_for_version(ProtocolVersion::new(9), Chain::Unknown);
+        assert!(
+            prot.lookup_attr("max_move_identifier_len".to_string())
+                == Some(ProtocolConfigValue::u64(prot.max_move_identifier_len()))
+        );
+
+        let prot: ProtocolConfig =
+            ProtocolConfig::get_for_version(ProtocolVersion::new(1), Chain::Unknown);
         // We didnt have this in version 1
         assert!(prot
             .attr_map()
             .get("max_move_identifier_len")
             .unwrap()
-            .is_none());
-        // We had this in version 1
-        assert!(
-            prot.attr_map().get("max_arguments").unwrap()
-                == &Some(ProtocolConfigValue::u32(prot.max_arguments()))
+            .is_none());
+        // We had this in version 1
+        assert!(
+            prot.attr_map().get("max_arguments").unwrap()
+                == &Some(ProtocolConfigValue::u32(prot.max_arguments()))
+        );
+
+        // Check feature flags
+        let prot: ProtocolConfig =
+            ProtocolConfig::get_for_version(ProtocolVersion::new(1), Chain::Unknown);
+        // Does not exist
+        assert!(prot
+            .feature_flags
+            .lookup_attr("some random string".to_owned())
+            .is_none());
+        assert!(!prot
+            .feature_flags
+            .attr_map()
+            .contains_key("some random string"));
+
+        // Was false in v1
+        assert!(
+            prot.feature_flags
+                .lookup_attr("package_upgrades".to_owned())
+                == Some(false)
+        );
+        assert!(
+            prot.feature_flags
+                .attr_map()
+                .get("package_upgrades")
+                .unwrap()
+                == &false
+        );
+        let prot: ProtocolConfig =
+            ProtocolConfig::get_for_version(ProtocolVersion::new(4), Chain::Unknown);
+        // Was true from v3 and up
+        assert!(
+            prot.feature_flags
+                .lookup_attr("package_upgrades".to_owned())
+                == Some(true)
+        );
+        assert!(
+            prot.feature_flags
+                .attr_map()
+                .get("package_upgrades")
+                .unwrap()
+                == &true
+        );
+    }
+
+    #[test]
+    fn delta_fold_test() {
+        for chain in [Chain::Unknown, Chain::Mainnet, Chain::Testnet] {
+            let deltas = ProtocolConfig::deltas(chain);
+            assert_eq!(
+                deltas.len() as u64,
+                MAX_PROTOCOL_VERSION - MIN_PROTOCOL_VERSION + 1
+            );
+
+            for (i, delta) in deltas.iter().enumerate() {
+                let (constants, feature_flags) = ProtocolConfigDelta::fold(&deltas[..=i]);
+                let expect = ProtocolConfig::get_for_version_impl(delta.version, chain);
+                assert_eq!(
+                    constants,
+                    expect.attr_map(),
+                    "{chain:?} {:?}",
+                    delta.version
+                );
+                assert_eq!(
+                    feature_flags,
+                    expect.feature_map(),
+                    "{chain:?} {:?}",
+                    delta.version
+                );
+            }
+        }
+
+        // Version 9 introduced the identifier length limit.
+        let delta = ProtocolConfig::delta_for_version(ProtocolVersion::new(9), Chain::Unknown);
+        assert_eq!(
+            delta.constants.get("max_move_identifier_len"),
+            Some(&Some(ProtocolConfigValue::u64(128)))
+        );
+        assert!(!delta.constants.contains_key("max_arguments"));
+    }
+
+    #[test]
+    fn feature_flag_transitions_test() {
+        let transition = |chain, version, enabled| FeatureFlagTransition {
+            chain,
+            version: ProtocolVersion::new(version),
+            enabled,
+        };
+
+        // Soft bundles were turned on for testnet (and devnet) before mainnet.
+        assert_eq!(
+            ProtocolConfig::feature_flag_transitions("soft_bundle").unwrap(),
+            vec![
+                transition(Chain::Mainnet, 54, true),
+                transition(Chain::Testnet, 52, true),
+                transition(Chain::Unknown, 52, true),
+            ],
+        );
+
+        // Each transition flips the flag, and the last one matches the flag's current state.
+        for flag in [
+            "package_digest_hash_module",
+            "random_beacon",
+            "zklogin_auth",
+        ] {
+            let transitions = ProtocolConfig::feature_flag_transitions(flag).unwrap();
+            for chain in [Chain::Mainnet, Chain::Testnet, Chain::Unknown] {
+                let mut enabled = false;
+                for t in transitions.iter().filter(|t| t.chain == chain) {
+                    assert_ne!(t.enabled, enabled, "{flag} {chain:?} {:?}", t.version);
+                    enabled = t.enabled;
+                }
+
+                let config = ProtocolConfig::get_for_version_impl(ProtocolVersion::MAX, chain);
+                assert_eq!(config.lookup_feature(flag.into()), Some(enabled), "{flag}");
+            }
+        }
+
+        assert!(ProtocolConfig::feature_flag_transitions("not_a_flag").is_err());
+    }
+
+    #[test]
+    fn signature_limits_test() {
+        let limits = |v| {
+            ProtocolConfig::get_for_version_impl(ProtocolVersion::new(v), Chain::Unknown)
+                .signature_limits()
+        };
+
+        // Versions before the limits were versioned report the values that were hardcoded.
+        assert_eq!(
+            SignatureLimits::default(),
+            SignatureLimits {
+                max_multisig_signers: 10,
+                max_zklogin_proof_size_bytes: None,
+                passkey_challenge_size_bytes: 32,
+            },
+        );
+        for v in MIN_PROTOCOL_VERSION..72 {
+            assert_eq!(limits(v), SignatureLimits::default(), "version {v}");
+        }
+
+        assert_eq!(
+            limits(72),
+            SignatureLimits {
+                max_multisig_signers: 10,
+                max_zklogin_proof_size_bytes: Some(4096),
+                passkey_challenge_size_bytes: 32,
+            },
+        );
+    }
+
+    #[test]
+    fn transition_names_test() {
+        // Dependencies and monotonic constants are listed by name, so check that the names refer
+        // to real feature flags and constants.
+        let features: BTreeSet<_> = Feature::ALL.iter().map(|f| f.name()).collect();
+        for (flag, requires) in FEATURE_FLAG_DEPENDENCIES {
+            assert!(features.contains(flag), "Unknown feature flag {flag:?}");
+            assert!(
+                features.contains(requires),
+                "Unknown feature flag {requires:?}"
+            );
+        }
+
+        let constants = ProtocolConfig::get_for_max_version_UNSAFE().attr_map();
+        for constant in MONOTONIC_CONSTANTS {
+            assert!(
+                constants.contains_key(*constant),
+                "Unknown constant {constant:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn validate_transition_test() {
+        let v = ProtocolVersion::new;
+        for chain in [Chain::Unknown, Chain::Mainnet, Chain::Testnet] {
+            for version in MIN_PROTOCOL_VERSION + 1..=MAX_PROTOCOL_VERSION {
+                assert_eq!(
+                    ProtocolConfig::validate_transition(v(version - 1), v(version), chain),
+                    Ok(()),
+                    "{chain:?} {version}",
+                );
+            }
+
+            // Skipping versions is fine, as long as it only moves forward.
+            assert_eq!(
+                ProtocolConfig::validate_transition(v(10), ProtocolVersion::MAX, chain),
+                Ok(()),
+            );
+        }
+
+        let err = ProtocolConfig::validate_transition(v(40), v(20), Chain::Mainnet).unwrap_err();
+        assert!(err.contains(&TransitionViolation::Downgrade {
+            from: v(40),
+            to: v(20),
+        }));
+        assert!(err.contains(&TransitionViolation::Regression {
+            constant: "execution_version".to_string(),
+            from: Some(3),
+            to: Some(1),
+        }));
+
+        // Flags that are enabled without the flags they depend on, and broken invariants between
+        // constants are reported.
+        let old = ProtocolConfig::get_for_max_version_UNSAFE();
+        let mut new = old.clone();
+        new.set_zklogin_auth_for_testing(false);
+        new.set_accept_zklogin_in_multisig_for_testing(true);
+        new.set_max_gas_price_for_testing(u64::MAX);
+        assert_eq!(
+            transition_violations(&old, &new),
+            vec![
+                TransitionViolation::Invariant(new.check_gas_limits().unwrap_err().0),
+                TransitionViolation::MissingDependency {
+                    flag: "accept_zklogin_in_multisig".to_string(),
+                    requires: "zklogin_auth".to_string(),
+                },
+                TransitionViolation::MissingDependency {
+                    flag: "verify_legacy_zklogin_address".to_string(),
+                    requires: "zklogin_auth".to_string(),
+                },
+            ],
+        );
+
+        let unsupported = ProtocolVersion::MAX_ALLOWED + 1;
+        assert_eq!(
+            ProtocolConfig::validate_transition(v(1), unsupported, Chain::Unknown),
+            Err(vec![TransitionViolation::UnsupportedVersion(unsupported)]),
+        );
+    }
+
+    #[test]
+    fn arbitrary_config_test() {
+        use proptest::prelude::*;
+        use proptest::strategy::ValueTree;
+        use proptest::test_runner::TestRunner;
+
+        let strategy = any_with::<ProtocolConfig>(Chain::Mainnet);
+        let mut runner = TestRunner::deterministic();
+
+        let mut changed = 0;
+        for _ in 0..64 {
+            let config = strategy.new_tree(&mut runner).unwrap().current();
+            let base = ProtocolConfig::get_for_version_if_supported(config.version, Chain::Mainnet)
+                .unwrap();
+
+            // Generated configs are consistent, as if upgraded to from their base version.
+            assert_eq!(
+                transition_violations(&base, &config),
+                vec![],
+                "{:?}",
+                config.version,
+            );
+
+            if config.to_json() != base.to_json() {
+                changed += 1;
+            }
+        }
+
+        assert!(
+            changed > 0,
+            "No generated config differs from its base config"
+        );
+    }
+
+    #[test]
+    fn divergences_test() {
+        use crate::testing::without_divergence;
+
+        let parameters: Vec<_> = Divergence::ALL.iter().map(|d| d.parameter()).collect();
+        assert_eq!(
+            parameters,
+            vec![
+                "max_transactions_in_block_bytes",
+                "max_num_transactions_in_block",
+                "base_tx_cost_fixed",
+            ],
+        );
+
+        // Outside simulator builds, configs never diverge from production.
+        let config = ProtocolConfig::get_for_max_version_UNSAFE();
+        assert_eq!(ProtocolConfig::test_divergences(), vec![]);
+        assert_eq!(config.max_num_transactions_in_block(), 512);
+
+        // Switching a divergence off is scoped to the closure, and nests.
+        without_divergence(Divergence::MaxNumTransactionsInBlock, || {
+            without_divergence(Divergence::MaxNumTransactionsInBlock, || {});
+            assert!(!Divergence::MaxNumTransactionsInBlock.is_in_effect());
+            assert!(set_divergence_disabled(
+                Divergence::MaxNumTransactionsInBlock,
+                true
+            ));
+        });
+
+        assert!(!set_divergence_disabled(
+            Divergence::MaxNumTransactionsInBlock,
+            false
+        ));
+    }
+
+    #[test]
+    fn protocol_config_watcher_test() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::Arc;
+
+        use futures::executor::block_on;
+        use futures::stream;
+
+        use crate::watcher::{EpochChange, ProtocolConfigWatcher};
+
+        let change = |version| EpochChange {
+            protocol_version: ProtocolVersion::new(version),
+            chain: Chain::Unknown,
+        };
+
+        let watcher = ProtocolConfigWatcher::new(change(1)).unwrap();
+        let config = watcher.config();
+        assert_eq!(config.load().version, ProtocolVersion::new(1));
+
+        let notified = Arc::new(AtomicU64::new(0));
+        let n = notified.clone();
+        watcher.on_change(move |config| n.store(config.version.as_u64(), Ordering::SeqCst));
+
+        // Repeated notifications for the same epoch don't trigger callbacks.
+        block_on(watcher.watch(stream::iter([change(1), change(2), change(2), change(3)])));
+        assert_eq!(config.load().version, ProtocolVersion::new(3));
+        assert_eq!(watcher.load().version, ProtocolVersion::new(3));
+        assert_eq!(notified.load(Ordering::SeqCst), 3);
+
+        notified.store(0, Ordering::SeqCst);
+        assert!(!watcher.update(change(3)).unwrap());
+        assert_eq!(notified.load(Ordering::SeqCst), 0);
+
+        // A change of chain at the same version is still a change.
+        assert!(watcher
+            .update(EpochChange {
+                protocol_version: ProtocolVersion::new(3),
+                chain: Chain::Testnet,
+            })
+            .unwrap());
+        assert_eq!(notified.load(Ordering::SeqCst), 3);
+
+        // Unsupported versions leave the config in effect untouched, whether they are applied
+        // directly, or through a stream of changes.
+        let unsupported = ProtocolVersion::MAX_ALLOWED.as_u64() + 1;
+        notified.store(0, Ordering::SeqCst);
+        assert!(watcher.update(change(unsupported)).is_err());
+        block_on(watcher.watch(stream::iter([change(unsupported)])));
+        assert_eq!(watcher.load().version, ProtocolVersion::new(3));
+        assert_eq!(notified.load(Ordering::SeqCst), 0);
+
+        // ...and later changes still apply.
+        block_on(watcher.watch(stream::iter([change(unsupported), change(4)])));
+        assert_eq!(watcher.load().version, ProtocolVersion::new(4));
+        assert_eq!(notified.load(Ordering::SeqCst), 4);
+
+        assert!(ProtocolConfigWatcher::new(change(unsupported)).is_err());
+    }
+
+    #[test]
+    fn feature_timeline_test() {
+        let v = |v| Some(ProtocolVersion::new(v));
+        assert_eq!(
+            ProtocolConfig::feature_activation_version("soft_bundle", Chain::Mainnet),
+            v(54),
+        );
+        assert_eq!(
+            ProtocolConfig::feature_activation_version("soft_bundle", Chain::Testnet),
+            v(52),
+        );
+        assert_eq!(
+            ProtocolConfig::feature_activation_version("not_a_flag", Chain::Mainnet),
+            None,
+        );
+
+        // The timeline covers every flag, and agrees with the first transition that enables it.
+        let chains = [Chain::Mainnet, Chain::Testnet, Chain::Unknown];
+        let timelines = chains.map(ProtocolConfig::feature_timeline);
+        let max = ProtocolConfig::get_for_version_impl(ProtocolVersion::MAX, Chain::Unknown);
+        for flag in [
+            "package_digest_hash_module",
+            "random_beacon",
+            "soft_bundle",
+            "zklogin_auth",
+        ] {
+            let transitions = ProtocolConfig::feature_flag_transitions(flag).unwrap();
+            for (chain, timeline) in chains.iter().zip(&timelines) {
+                let first = transitions
+                    .iter()
+                    .find(|t| t.chain == *chain && t.enabled)
+                    .map(|t| t.version);
+                assert_eq!(timeline[flag], first, "{flag} {chain:?}");
+            }
+        }
+
+        assert!(timelines.iter().all(|t| t.len() == max.feature_map().len()));
+    }
+
+    #[test]
+    fn patches_test() {
+        // Patches are uniquely named, and cover a non-empty range of versions that this binary
+        // still supports.
+        let mut names = BTreeSet::new();
+        for patch in ProtocolConfig::patches() {
+            assert!(names.insert(patch.name), "duplicate patch {}", patch.name);
+            assert!(!patch.versions.is_empty(), "{}", patch.name);
+            assert!(
+                *patch.versions.end() <= MAX_PROTOCOL_VERSION,
+                "{}",
+                patch.name
+            );
+
+            // Patched configs are still consistent.
+            for version in patch.versions.clone() {
+                let version = ProtocolVersion::new(version);
+                if version >= ProtocolVersion::MIN {
+                    let config = ProtocolConfig::get_for_version_impl(version, patch.chain);
+                    config.check_overrides().unwrap();
+                }
+            }
+        }
+
+        let patches = [
+            ProtocolConfigPatch {
+                name: "raise-max-arguments",
+                chain: Chain::Testnet,
+                versions: 40..=41,
+                apply: |cfg| cfg.max_arguments = Some(1024),
+            },
+            ProtocolConfigPatch {
+                name: "enable-soft-bundle",
+                chain: Chain::Testnet,
+                versions: 41..=60,
+                apply: |cfg| cfg.feature_flags.soft_bundle = true,
+            },
+        ];
+
+        let patched = |version, chain| {
+            let version = ProtocolVersion::new(version);
+            let mut cfg = ProtocolConfig::get_for_version_impl(version, chain);
+            cfg.apply_patches(version, chain, &patches);
+            cfg
+        };
+
+        // Patches only apply to their own chain and versions, and only change what they set.
+        let base = ProtocolConfig::get_for_version_impl(ProtocolVersion::new(41), Chain::Testnet);
+        let cfg = patched(41, Chain::Testnet);
+        assert_eq!(cfg.max_arguments(), 1024);
+        assert!(cfg.soft_bundle());
+        assert_eq!(cfg.max_tx_size_bytes(), base.max_tx_size_bytes());
+        assert!(!base.soft_bundle());
+
+        let cfg = patched(42, Chain::Testnet);
+        assert_eq!(cfg.max_arguments(), base.max_arguments());
+        assert!(cfg.soft_bundle());
+
+        let cfg = patched(41, Chain::Mainnet);
+        assert_eq!(cfg.max_arguments(), base.max_arguments());
+        assert!(!cfg.soft_bundle());
+
+        // Custom chains only patch `Chain::Unknown`. Registering one would affect every other
+        // test in this process, so only their validation is tested here (see `tests/` for
+        // registration).
+        let custom = |chain, versions| CustomChain {
+            name: "appchain".to_string(),
+            patches: vec![ProtocolConfigPatch {
+                name: "appchain-max-arguments",
+                chain,
+                versions,
+                apply: |cfg| cfg.max_arguments = Some(1024),
+            }],
+        };
+
+        custom(Chain::Unknown, 1..=MAX_PROTOCOL_VERSION)
+            .validate()
+            .unwrap();
+        custom(Chain::Testnet, 1..=MAX_PROTOCOL_VERSION)
+            .validate()
+            .unwrap_err();
+        ProtocolConfig::register_custom_chain(custom(Chain::Mainnet, 1..=1)).unwrap_err();
+        assert!(ProtocolConfig::custom_chain().is_none());
+
+        // Patches do not carry over to versions they do not cover.
+        let cfg = patched(61, Chain::Testnet);
+        let base = ProtocolConfig::get_for_version_impl(ProtocolVersion::new(61), Chain::Testnet);
+        assert_eq!(cfg.soft_bundle(), base.soft_bundle());
+    }
+
+    #[test]
+    fn iter_constants_test() {
+        let config = ProtocolConfig::get_for_version_impl(ProtocolVersion::MAX, Chain::Unknown);
+        let constants: Vec<_> = config.iter_constants().collect();
+
+        // Every constant is listed once, with its value.
+        let attrs = config.attr_map();
+        assert_eq!(constants.len(), attrs.len());
+        for (name, _, _, value, _) in &constants {
+            assert_eq!(Some(value), attrs.get(*name), "{name}");
+        }
+
+        // Every category starts at a constant that exists, and categories appear in order.
+        let mut categories: Vec<_> = constants.iter().map(|(_, c, _, _, _)| *c).collect();
+        assert!(categories.windows(2).all(|w| w[0] <= w[1]));
+        categories.dedup();
+        assert_eq!(
+            categories,
+            ConstantCategory::FIRST_CONSTANTS
+                .iter()
+                .map(|(_, c)| *c)
+                .collect::<Vec<_>>(),
+        );
+
+        let constant = |name: &str| {
+            constants
+                .iter()
+                .find(|(n, _, _, _, _)| *n == name)
+                .cloned()
+                .unwrap()
+        };
+
+        assert_eq!(
+            constant("max_tx_size_bytes"),
+            (
+                "max_tx_size_bytes",
+                ConstantCategory::TransactionInputLimits,
+                "u64",
+                Some(ProtocolConfigValue::u64(config.max_tx_size_bytes())),
+                "Maximum serialized size of a transaction (in bytes).",
+            ),
+        );
+
+        let (_, category, type_, _, doc) = constant("max_size_written_objects");
+        assert_eq!(category, ConstantCategory::TransactionInputLimits);
+        assert_eq!(type_, "u64");
+        assert_eq!(doc.lines().count(), 3);
+
+        assert_eq!(
+            constant("obj_access_cost_read_per_byte").1,
+            ConstantCategory::ExecutionGasCosts
+        );
+        assert_eq!(
+            constant("hash_sha3_256_base_cost").1,
+            ConstantCategory::NativeFunctionCosts
+        );
+        assert_eq!(constant("consensus_gc_depth").1, ConstantCategory::Other);
+    }
+
+    #[test]
+    fn field_metadata_test() {
+        assert_eq!(
+            ProtocolConfig::field_metadata("max_tx_gas"),
+            Some(FieldMetadata {
+                name: "max_tx_gas",
+                category: ConstantCategory::ExecutionLimits,
+                type_: "u64",
+                doc: "Maximum gas budget in MIST that a transaction can use.",
+                unit: Some(ConfigUnit::Mist),
+            }),
+        );
+
+        // Constants can be looked up by address, and units are inferred from name suffixes.
+        let gc_depth = ProtocolConfig::field_metadata("/consensus/gc_depth").unwrap();
+        assert_eq!(gc_depth.name, "consensus_gc_depth");
+        assert_eq!(gc_depth.unit, None);
+
+        let unit = |name| ProtocolConfig::field_metadata(name).unwrap().unit;
+        assert_eq!(unit("max_tx_size_bytes"), Some(ConfigUnit::Bytes));
+        assert_eq!(
+            unit("execution_time_estimate_min_staleness_ms"),
+            Some(ConfigUnit::Milliseconds)
+        );
+        assert_eq!(unit("storage_rebate_rate"), Some(ConfigUnit::BasisPoints));
+        assert_eq!(
+            unit("max_verifier_meter_ticks_per_function"),
+            Some(ConfigUnit::Ticks)
+        );
+
+        assert_eq!(ProtocolConfig::field_metadata("not_a_constant"), None);
+    }
+
+    #[test]
+    fn tokenomics_config_test() {
+        for chain in [Chain::Unknown, Chain::Mainnet, Chain::Testnet] {
+            for version in MIN_PROTOCOL_VERSION..=MAX_PROTOCOL_VERSION {
+                let config =
+                    ProtocolConfig::get_for_version_impl(ProtocolVersion::new(version), chain);
+                let tokenomics = config.tokenomics_config().unwrap();
+                assert_eq!(
+                    tokenomics.storage_rebate_rate.get() as u64,
+                    config.storage_rebate_rate()
+                );
+                assert_eq!(
+                    tokenomics.reward_slashing_rate.get() as u64,
+                    config.reward_slashing_rate()
+                );
+            }
+        }
+
+        let mut config = ProtocolConfig::get_for_max_version_UNSAFE();
+        let tokenomics = config.tokenomics_config().unwrap();
+        assert_eq!(
+            tokenomics.storage_fund_reinvest_rate.apply(1_000_000),
+            50_000
+        );
+        assert_eq!(tokenomics.stake_subsidy.start_epoch, 20);
+
+        config.set_reward_slashing_rate_for_testing(10_000);
+        assert!(config.tokenomics_config().is_ok());
+
+        config.set_reward_slashing_rate_for_testing(10_001);
+        let err = config.tokenomics_config().unwrap_err();
+        assert!(err.0.contains("reward_slashing_rate"), "{}", err.0);
+        assert!(config.check_overrides().is_err());
+
+        assert_eq!(BasisPoints::new(10_001), None);
+        assert_eq!(BasisPoints::new(u64::MAX), None);
+        assert_eq!(
+            BasisPoints::new(2_500).unwrap().apply(u64::MAX),
+            u64::MAX / 4
+        );
+    }
+
+    #[test]
+    fn gas_limits_test() {
+        for chain in [Chain::Unknown, Chain::Mainnet, Chain::Testnet] {
+            // Versions 1 and 2 used gas model v1, which predates the invariant.
+            for version in 3..=MAX_PROTOCOL_VERSION {
+                let config =
+                    ProtocolConfig::get_for_version_impl(ProtocolVersion::new(version), chain);
+                config.check_gas_limits().unwrap();
+            }
+        }
+
+        let mut config = ProtocolConfig::get_for_max_version_UNSAFE();
+        config.set_max_tx_gas_for_testing(config.max_gas_price() * config.base_tx_cost_fixed() - 1);
+        assert!(config.check_gas_limits().is_err());
+
+        config.set_max_gas_price_for_testing(u64::MAX);
+        assert!(config.check_gas_limits().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "overrides")]
+    fn override_file_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, contents: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, contents).unwrap();
+            path
+        };
+
+        let base = ProtocolConfig::get_for_max_version_UNSAFE();
+
+        let mut config = base.clone();
+        let path = write(
+            "ok.toml",
+            "max_tx_gas = 60000000000\nmax_input_objects = 4096\n",
+        );
+        config.apply_overrides_from_file(&path).unwrap();
+        assert_eq!(config.max_tx_gas(), 60_000_000_000);
+        assert_eq!(config.max_input_objects(), 4096);
+        assert_eq!(config.max_gas_price(), base.max_gas_price());
+
+        let mut config = base.clone();
+        let path = write(
+            "ok.yaml",
+            "max_tx_gas: 60000000000\nmax_input_objects: 4096\n",
+        );
+        config.apply_overrides_from_file(&path).unwrap();
+        assert_eq!(config.max_tx_gas(), 60_000_000_000);
+        assert_eq!(config.max_input_objects(), 4096);
+
+        // Constants can also be referred to by their address.
+        let mut config = base.clone();
+        let path = write(
+            "address.toml",
+            "\"/consensus/gc_depth\" = 100\nmax_tx_gas = 60000000000\n",
+        );
+        config.apply_overrides_from_file(&path).unwrap();
+        assert_eq!(config.gc_depth(), 100);
+        assert_eq!(config.max_tx_gas(), 60_000_000_000);
+
+        // Files that are rejected leave the config unchanged.
+        for (name, contents) in [
+            ("unknown.toml", "max_tx_gas = 1\nmax_tx_gaz = 1\n"),
+            ("unknown.yaml", "feature_flags:\n  zklogin_auth: true\n"),
+            ("address.yaml", "/feature_flags/zklogin_auth: true\n"),
+            (
+                "duplicate.toml",
+                "consensus_gc_depth = 1\n\"/consensus/gc_depth\" = 2\n",
+            ),
+            ("invariant.yaml", "max_gas_price: 100000000000\n"),
+            ("bad_value.toml", "max_tx_gas = \"lots\"\n"),
+            ("no_extension", "max_tx_gas: 1\n"),
+        ] {
+            let mut config = base.clone();
+            let path = write(name, contents);
+            let err = config.apply_overrides_from_file(&path).unwrap_err();
+            assert!(err.0.contains(name), "{name}: {}", err.0);
+            assert_eq!(config.attr_map(), base.attr_map(), "{name}");
+        }
+    }
+
+    #[test]
+    fn addresses_test() {
+        let address = |name| ProtocolConfig::attr_address(name);
+        assert_eq!(address("max_tx_gas").as_deref(), Some("/max_tx_gas"));
+        assert_eq!(
+            address("consensus_gc_depth").as_deref(),
+            Some("/consensus/gc_depth")
+        );
+        assert_eq!(address("not_a_constant"), None);
+
+        // Every constant can be looked up by its address, and no two fields share an address.
+        let config = ProtocolConfig::get_for_max_version_UNSAFE();
+        let addresses = config.addresses();
+        let mut seen = BTreeSet::new();
+        for (name, value) in config.attr_map() {
+            let address = &addresses[&name];
+            assert!(seen.insert(address.clone()), "{address}");
+            assert_eq!(ProtocolConfig::resolve_attr(address), Some(name.as_str()));
+            assert_eq!(ProtocolConfig::resolve_attr(&name), Some(name.as_str()));
+            assert_eq!(config.lookup_attr_by_address(address), value);
+        }
+
+        for name in config.feature_map().keys() {
+            assert!(seen.insert(addresses[name].clone()), "{name}");
+        }
+
+        assert_eq!(addresses["zklogin_auth"], "/feature_flags/zklogin_auth");
+        assert_eq!(
+            ProtocolConfig::resolve_attr("/feature_flags/zklogin_auth"),
+            None
+        );
+        assert_eq!(ProtocolConfig::resolve_attr("/consensus_gc_depth"), None);
+
+        // Renamed constants keep the address of their old name, and the old name refers to them.
+        let renamed = &[("max_transaction_gas", "max_tx_gas")];
+        assert_eq!(
+            constant_address("max_tx_gas", renamed),
+            "/max_transaction_gas"
+        );
+        assert_eq!(
+            resolve_constant("max_transaction_gas", renamed),
+            Some("max_tx_gas")
+        );
+        assert_eq!(
+            resolve_constant("/max_transaction_gas", renamed),
+            Some("max_tx_gas")
+        );
+        assert_eq!(resolve_constant("/max_tx_gas", renamed), None);
+
+        #[cfg(feature = "overrides")]
+        {
+            let var = |k: &str, v: &str| (k.to_owned(), v.to_owned());
+            assert_eq!(
+                resolve_override_vars(
+                    [
+                        var("SUI_PROTOCOL_CONFIG_OVERRIDE_MAX_TRANSACTION_GAS", "1"),
+                        var("SUI_PROTOCOL_CONFIG_OVERRIDE_ENABLE", "1"),
+                        var("MAX_TRANSACTION_GAS", "2"),
+                    ],
+                    renamed,
+                ),
+                vec![
+                    var("SUI_PROTOCOL_CONFIG_OVERRIDE_max_tx_gas", "1"),
+                    var("SUI_PROTOCOL_CONFIG_OVERRIDE_ENABLE", "1"),
+                    var("MAX_TRANSACTION_GAS", "2"),
+                ],
+            );
+        }
+    }
+
+    #[test]
+    fn export_test() {
+        let config = ProtocolConfig::get_for_version(ProtocolVersion::MAX, Chain::Unknown);
+        let export = config.export();
+        assert_eq!(export.protocol_version, MAX_PROTOCOL_VERSION.to_string());
+        assert_eq!(export.attributes.len(), config.attr_map().len());
+        assert_eq!(export.feature_flags, config.feature_map());
+        assert_eq!(
+            export.attributes["max_tx_gas"],
+            Some(config.max_tx_gas().to_string()),
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&config.to_json()).unwrap();
+        assert_eq!(json["protocolVersion"], MAX_PROTOCOL_VERSION.to_string());
+        assert_eq!(
+            json["attributes"]["max_tx_gas"],
+            config.max_tx_gas().to_string(),
+        );
+        assert_eq!(json["featureFlags"]["zklogin_auth"], config.zklogin_auth(),);
+
+        // Constants that are not set at a version are exported as null.
+        let config = ProtocolConfig::get_for_version(ProtocolVersion::MIN, Chain::Unknown);
+        assert!(config.max_move_identifier_len_as_option().is_none());
+        let json: serde_json::Value = serde_json::from_str(&config.to_json()).unwrap();
+        assert!(json["attributes"]["max_move_identifier_len"].is_null());
+    }
+
+    #[test]
+    fn execution_profile_test() {
+        let config = ProtocolConfig::get_for_version(ProtocolVersion::MAX, Chain::Unknown);
+        let profile = config.execution_profile().unwrap();
+        assert_eq!(profile.protocol_version, MAX_PROTOCOL_VERSION);
+        assert_eq!(profile.constants["max_tx_gas"], config.max_tx_gas());
+        assert_eq!(
+            profile.constants["execution_version"],
+            config.execution_version(),
+        );
+        assert!(!profile.constants.contains_key("consensus_gc_depth"));
+        assert!(profile.feature_flags.contains("zklogin_auth"));
+
+        // The profile round-trips through BCS, and through JSON (where constants are strings).
+        let bcs = profile.to_bcs();
+        assert_eq!(bcs::from_bytes::<ExecutionProfile>(&bcs).unwrap(), profile);
+
+        let json = serde_json::to_value(&profile).unwrap();
+        assert_eq!(
+            json["constants"]["max_tx_gas"],
+            config.max_tx_gas().to_string(),
+        );
+        assert_eq!(
+            serde_json::from_value::<ExecutionProfile>(json).unwrap(),
+            profile,
+        );
+
+        // Only execution parameters affect the digest.
+        let mut changed = config.clone();
+        changed.set_consensus_gc_depth_for_testing(100);
+        assert_eq!(
+            changed.execution_profile().unwrap().digest(),
+            profile.digest()
+        );
+
+        changed.set_max_tx_gas_for_testing(config.max_tx_gas() + 1);
+        assert_ne!(
+            changed.execution_profile().unwrap().digest(),
+            profile.digest()
+        );
+
+        // Every execution constant is an integer, at every version.
+        for chain in [Chain::Mainnet, Chain::Testnet, Chain::Unknown] {
+            for v in MIN_PROTOCOL_VERSION..=MAX_PROTOCOL_VERSION {
+                let config = ProtocolConfig::get_for_version_impl(ProtocolVersion::new(v), chain);
+                config.execution_profile().unwrap();
+            }
+        }
+
+        // The profile at an existing version must never change.
+        let digest = ProtocolConfig::get_for_version(ProtocolVersion::MIN, Chain::Unknown)
+            .execution_profile()
+            .unwrap()
+            .digest();
+        let hex: String = digest.iter().map(|b| format!("{b:02x}")).collect();
+        assert_eq!(
+            hex,
+            "4b992dfb018abf3a38c9e28813125311b68d1254e684d3ba1312bed95b2ed0b2",
         );
+    }
 
-        // Check feature flags
-        let prot: ProtocolConfig =
-            ProtocolConfig::get_for_version(ProtocolVersion::new(1), Chain::Unknown);
-        // Does not exist
-        assert!(prot
-            .feature_flags
-            .lookup_attr("some random string".to_owned())
-            .is_none());
-        assert!(!prot
-            .feature_flags
-            .attr_map()
-            .contains_key("some random string"));
+    #[test]
+    fn diff_test() {
+        let v = |v| ProtocolVersion::new(v);
+        for chain in [Chain::Unknown, Chain::Mainnet, Chain::Testnet] {
+            let max = ProtocolVersion::MAX;
+            assert!(ProtocolConfig::diff(max, max, chain).is_empty());
+
+            // Between consecutive versions, the diff agrees with the delta.
+            for version in MIN_PROTOCOL_VERSION + 1..=MAX_PROTOCOL_VERSION {
+                let diff = ProtocolConfig::diff(v(version - 1), v(version), chain);
+                let delta = ProtocolConfig::delta_for_version(v(version), chain);
+                let constants = diff
+                    .constants
+                    .iter()
+                    .map(|(k, c)| (k.clone(), c.new.clone()))
+                    .collect::<BTreeMap<_, _>>();
+                let flags = diff
+                    .feature_flags
+                    .iter()
+                    .map(|(k, c)| (k.clone(), c.new))
+                    .collect::<BTreeMap<_, _>>();
+                assert_eq!(constants, delta.constants, "{chain:?} {version}");
+                assert_eq!(flags, delta.feature_flags, "{chain:?} {version}");
+            }
+        }
 
-        // Was false in v1
-        assert!(
-            prot.feature_flags
-                .lookup_attr("package_upgrades".to_owned())
-                == Some(false)
+        // Version 9 introduced the identifier length limit; diffing backwards swaps the values.
+        let diff = ProtocolConfig::diff(v(10), v(8), Chain::Unknown);
+        assert_eq!(diff.old_version, v(10));
+        assert_eq!(
+            diff.constants.get("max_move_identifier_len"),
+            Some(&ProtocolConfigChange {
+                old: Some(ProtocolConfigValue::u64(128)),
+                new: None,
+            })
         );
-        assert!(
-            prot.feature_flags
-                .attr_map()
-                .get("package_upgrades")
-                .unwrap()
-                == &false
+        assert!(!diff.constants.contains_key("max_arguments"));
+    }
+
+    #[test]
+    fn chaos_mode_test() {
+        let base = ProtocolConfig::get_for_version(ProtocolVersion::MAX, Chain::Unknown);
+        let tolerant = ProtocolConfig::node_local_tolerant_attrs();
+        assert!(tolerant.contains(&"max_soft_bundle_size".to_string()));
+        // Consensus commits decide whether to reserve a randomness round based on this value, so
+        // validators must agree on it.
+        assert!(!tolerant.contains(&"random_beacon_min_round_interval_ms".to_string()));
+
+        let perturbed = |seed| {
+            let mut config = base.clone();
+            config.perturb_node_local_limits_for_testing(seed);
+            config
+        };
+
+        // Perturbation is deterministic.
+        assert_eq!(perturbed(42).attr_map(), perturbed(42).attr_map());
+
+        // Only node-local tolerant constants change, and all feature flags stay the same.
+        let mut changed = false;
+        for seed in 0..8 {
+            let config = perturbed(seed);
+            assert_eq!(config.feature_map(), base.feature_map());
+            for (attr, value) in config.attr_map() {
+                if tolerant.contains(&attr) {
+                    changed |= value != base.lookup_attr(attr.clone());
+                } else {
+                    assert_eq!(value, base.lookup_attr(attr.clone()), "{attr} changed");
+                }
+            }
+        }
+
+        assert!(changed, "no node-local tolerant constant was perturbed");
+    }
+
+    #[test]
+    fn consensus_limits_test() {
+        for chain in &[Chain::Unknown, Chain::Mainnet, Chain::Testnet] {
+            for i in MIN_PROTOCOL_VERSION..=MAX_PROTOCOL_VERSION {
+                let cfg = ProtocolConfig::get_for_version_impl(ProtocolVersion::new(i), *chain);
+                if let Err(e) = cfg.check_consensus_limits() {
+                    panic!("version {i} on {chain:?}: {}", e.0);
+                }
+            }
+        }
+
+        // Raising the transaction size limit on its own breaks the invariant, until the consensus
+        // limit is derived again.
+        let mut cfg = ProtocolConfig::get_for_version(ProtocolVersion::MAX, Chain::Unknown);
+        cfg.set_max_tx_size_bytes_for_testing(cfg.max_tx_size_bytes() * 2);
+        assert!(cfg.check_consensus_limits().is_err());
+
+        cfg.set_consensus_max_transaction_size_from_tx_limit();
+        cfg.set_consensus_max_transactions_in_block_bytes_for_testing(
+            cfg.max_transaction_size_bytes(),
         );
-        let prot: ProtocolConfig =
-            ProtocolConfig::get_for_version(ProtocolVersion::new(4), Chain::Unknown);
-        // Was true from v3 and up
-        assert!(
-            prot.feature_flags
-                .lookup_attr("package_upgrades".to_owned())
-                == Some(true)
+        assert!(cfg.check_consensus_limits().is_ok());
+    }
+
+    #[test]
+    fn transaction_size_limits_test() {
+        let cfg = ProtocolConfig::get_for_version(ProtocolVersion::new(35), Chain::Unknown);
+        assert_eq!(
+            cfg.transaction_size_limits(),
+            TransactionSizeLimits {
+                signing: 128 * 1024,
+                consensus: 256 * 1024,
+                client: 128 * 1024,
+            }
         );
-        assert!(
-            prot.feature_flags
-                .attr_map()
-                .get("package_upgrades")
-                .unwrap()
-                == &true
+
+        let mut cfg = ProtocolConfig::get_for_version(ProtocolVersion::MAX, Chain::Unknown);
+        cfg.set_consensus_max_transaction_size_bytes_for_testing(64 * 1024);
+        assert_eq!(cfg.transaction_size_limits().client, 64 * 1024);
+        assert_eq!(cfg.max_transaction_size_bytes(), 64 * 1024);
+    }
+
+    #[test]
+    #[should_panic(expected = "protocol constant not present")]
+    fn transaction_size_limits_missing_test() {
+        let mut cfg = ProtocolConfig::get_for_version(ProtocolVersion::MAX, Chain::Unknown);
+        cfg.disable_consensus_max_transaction_size_bytes_for_testing();
+        cfg.transaction_size_limits();
+    }
+
+    #[test]
+    #[cfg(feature = "overrides")]
+    fn localnet_args_test() {
+        use clap::Parser;
+
+        #[derive(Parser)]
+        struct Cli {
+            #[clap(flatten)]
+            protocol: ProtocolConfigLocalnetArgs,
+        }
+
+        let args = Cli::parse_from(["sui", "--protocol.max-tx-size-bytes", "1MB"]).protocol;
+        assert_eq!(args.max_tx_size_bytes, Some(1024 * 1024));
+
+        // The consensus limits are raised to accommodate the larger transaction size limit.
+        let mut config = ProtocolConfig::get_for_max_version_UNSAFE();
+        args.override_config(&mut config);
+
+        let limit = (1024 + 128) * 1024;
+        assert_eq!(config.max_tx_size_bytes(), 1024 * 1024);
+        assert_eq!(config.max_transaction_size_bytes(), limit);
+        assert_eq!(config.max_transactions_in_block_bytes(), limit);
+        config.check_overrides().unwrap();
+
+        // Explicitly supplied limits are validated rather than adjusted.
+        let args = Cli::parse_from([
+            "sui",
+            "--protocol.max-tx-size-bytes",
+            "1MB",
+            "--protocol.consensus-max-transaction-size-bytes",
+            "512KB",
+        ])
+        .protocol;
+        let mut config = ProtocolConfig::get_for_max_version_UNSAFE();
+        args.override_config(&mut config);
+        assert_eq!(config.max_transaction_size_bytes(), 512 * 1024);
+        assert!(config.check_overrides().is_err());
+
+        // No arguments, no overrides.
+        let args = Cli::parse_from(["sui"]).protocol;
+        let mut config = ProtocolConfig::get_for_max_version_UNSAFE();
+        args.override_config(&mut config);
+        assert_eq!(
+            config.attr_map(),
+            ProtocolConfig::get_for_max_version_UNSAFE().attr_map()
+        );
+
+        // Overrides cannot be registered once configs have been produced without them.
+        assert!(args.register().is_err());
+
+        // Values must fit in the constant's type.
+        assert!(Cli::try_parse_from(["sui", "--protocol.max-pure-argument-size", "8GB"]).is_err());
+    }
+
+    #[test]
+    fn jwk_config_test() {
+        const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+        for chain in [Chain::Unknown, Chain::Mainnet, Chain::Testnet] {
+            for version in MIN_PROTOCOL_VERSION..=MAX_PROTOCOL_VERSION {
+                let cfg =
+                    ProtocolConfig::get_for_version_impl(ProtocolVersion::new(version), chain);
+                assert_eq!(
+                    cfg.jwk_config().is_some(),
+                    cfg.enable_jwk_consensus_updates(),
+                    "version {version} on {chain:?}",
+                );
+
+                if let Err(e) = cfg.check_jwk_config(DAY) {
+                    panic!("version {version} on {chain:?}: {}", e.0);
+                }
+            }
+        }
+
+        let mut cfg = ProtocolConfig::get_for_version(ProtocolVersion::MAX, Chain::Unknown);
+        assert_eq!(
+            cfg.jwk_config(),
+            Some(JwkConfig {
+                max_votes_per_validator_per_epoch: 240,
+                max_age_in_epochs: 1,
+            })
+        );
+
+        assert!(cfg.check_jwk_config(Duration::ZERO).is_err());
+        assert_eq!(cfg.jwk_retention(DAY), Some(DAY));
+
+        cfg.set_max_age_of_jwk_in_epochs_for_testing(30);
+        assert!(cfg.check_jwk_config(DAY).is_ok());
+        assert!(cfg.check_jwk_config(DAY * 2).is_ok());
+        assert_eq!(cfg.jwk_retention(DAY * 2), Some(DAY * 60));
+
+        cfg.set_max_age_of_jwk_in_epochs_for_testing(u64::MAX);
+        assert_eq!(cfg.jwk_retention(DAY), Some(Duration::MAX));
+
+        cfg.set_max_age_of_jwk_in_epochs_for_testing(0);
+        assert!(cfg.check_jwk_config(DAY).is_err());
+
+        cfg.set_max_age_of_jwk_in_epochs_for_testing(1);
+        cfg.set_max_jwk_votes_per_validator_per_epoch_for_testing(0);
+        assert!(cfg.check_jwk_config(DAY).is_err());
+
+        cfg.set_enable_jwk_consensus_updates_for_testing(false);
+        assert_eq!(cfg.jwk_config(), None);
+        assert_eq!(cfg.jwk_retention(DAY), None);
+        assert!(cfg.check_jwk_config(Duration::ZERO).is_ok());
+    }
+
+    #[test]
+    fn nested_overrides_test() {
+        use crate::testing::{override_config, with_constant, with_feature};
+
+        let get = || ProtocolConfig::get_for_version(ProtocolVersion::MAX, Chain::Unknown);
+        let max_tx_gas = get().max_tx_gas();
+        let flag = Feature::PackageUpgrades;
+        assert!(get().is_feature_enabled(flag));
+
+        with_feature(flag, false, || {
+            assert!(!get().is_feature_enabled(flag));
+            with_constant("max_tx_gas", "1", || {
+                let cfg = get();
+                assert!(!cfg.is_feature_enabled(flag));
+                assert_eq!(cfg.max_tx_gas(), 1);
+            });
+            assert_eq!(get().max_tx_gas(), max_tx_gas);
+        });
+        assert!(get().is_feature_enabled(flag));
+
+        // Inner overrides are applied on top of outer ones.
+        let outer = override_config(|_, mut cfg| {
+            cfg.set_max_tx_gas_for_testing(1);
+            cfg
+        });
+        let inner = override_config(|_, mut cfg| {
+            cfg.set_max_tx_gas_for_testing(cfg.max_tx_gas() + 1);
+            cfg
+        });
+        assert_eq!(get().max_tx_gas(), 2);
+
+        // Dropping an outer guard only removes its own override, even if it is dropped before
+        // the overrides installed after it.
+        drop(outer);
+        assert_eq!(get().max_tx_gas(), max_tx_gas + 1);
+
+        // Overrides installed after that still stack on top of the remaining ones, and are not
+        // removed by dropping earlier guards.
+        let later = override_config(|_, mut cfg| {
+            cfg.set_max_tx_gas_for_testing(cfg.max_tx_gas() * 2);
+            cfg
+        });
+        assert_eq!(get().max_tx_gas(), (max_tx_gas + 1) * 2);
+        drop(inner);
+        assert_eq!(get().max_tx_gas(), max_tx_gas * 2);
+        drop(later);
+        assert_eq!(get().max_tx_gas(), max_tx_gas);
+
+        // Scoped overrides can be layered on top of a test-wide override.
+        let _guard = ProtocolConfig::apply_overrides_for_testing(|_, mut cfg| {
+            cfg.set_max_tx_gas_for_testing(1);
+            cfg
+        });
+        with_feature(flag, false, || {
+            let cfg = get();
+            assert!(!cfg.is_feature_enabled(flag));
+            assert_eq!(cfg.max_tx_gas(), 1);
+        });
+        assert_eq!(get().max_tx_gas(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "config override already present")]
+    fn apply_overrides_twice_test() {
+        let _guard = crate::testing::override_config(|_, cfg| cfg);
+        let _guard = ProtocolConfig::apply_overrides_for_testing(|_, cfg| cfg);
+    }
+
+    #[test]
+    fn congestion_control_params_test() {
+        use PerObjectCongestionControlMode as M;
+
+        for chain in &[Chain::Unknown, Chain::Mainnet, Chain::Testnet] {
+            for i in MIN_PROTOCOL_VERSION..=MAX_PROTOCOL_VERSION {
+                let cfg = ProtocolConfig::get_for_version_impl(ProtocolVersion::new(i), *chain);
+                if let Err(e) = cfg.check_congestion_control_params() {
+                    panic!("version {i} on {chain:?}: {}", e.0);
+                }
+            }
+        }
+
+        let mut cfg = ProtocolConfig::get_for_version(ProtocolVersion::MAX, Chain::Unknown);
+        cfg.set_per_object_congestion_control_mode_for_testing(M::TotalGasBudgetWithCap);
+        cfg.set_max_accumulated_txn_cost_per_object_in_mysticeti_commit_for_testing(100);
+        cfg.set_max_accumulated_randomness_txn_cost_per_object_in_mysticeti_commit_for_testing(10);
+        cfg.set_gas_budget_based_txn_cost_cap_factor_for_testing(5);
+        cfg.set_gas_budget_based_txn_cost_absolute_cap_commit_count_for_testing(3);
+        cfg.set_max_txn_cost_overage_per_object_in_commit_for_testing(50);
+        cfg.set_allowed_txn_cost_overage_burst_per_object_in_commit_for_testing(20);
+        cfg.set_max_deferral_rounds_for_congestion_control_for_testing(7);
+
+        let params = cfg.congestion_control_params();
+        assert_eq!(
+            params,
+            CongestionControlParams {
+                mode: M::TotalGasBudgetWithCap,
+                max_accumulated_txn_cost_per_object_in_commit: Some(100),
+                max_accumulated_randomness_txn_cost_per_object_in_commit: Some(10),
+                gas_budget_based_txn_cost_cap_factor: Some(5),
+                gas_budget_based_txn_cost_absolute_cap_commit_count: Some(3),
+                max_txn_cost_overage_per_object_in_commit: 50,
+                allowed_txn_cost_overage_burst_per_object_in_commit: 20,
+                max_deferral_rounds: Some(7),
+            }
         );
+        assert_eq!(params.max_accumulated_txn_cost_per_object(false), Some(100));
+        assert_eq!(params.max_accumulated_txn_cost_per_object(true), Some(10));
+        assert!(cfg.check_congestion_control_params().is_ok());
+
+        // Randomness budget falls back to the regular budget.
+        cfg.max_accumulated_randomness_txn_cost_per_object_in_mysticeti_commit = None;
+        let params = cfg.congestion_control_params();
+        assert_eq!(params.max_accumulated_txn_cost_per_object(true), Some(100));
+
+        cfg.set_allowed_txn_cost_overage_burst_per_object_in_commit_for_testing(51);
+        assert!(cfg.check_congestion_control_params().is_err());
+        cfg.set_allowed_txn_cost_overage_burst_per_object_in_commit_for_testing(50);
+        assert!(cfg.check_congestion_control_params().is_ok());
+
+        cfg.gas_budget_based_txn_cost_cap_factor = None;
+        assert!(cfg.check_congestion_control_params().is_err());
+        cfg.set_per_object_congestion_control_mode_for_testing(M::TotalTxCount);
+        assert!(cfg.check_congestion_control_params().is_ok());
+
+        // Inconsistent parameters are still reported as they are, without failing.
+        cfg.max_deferral_rounds_for_congestion_control = None;
+        assert!(cfg.check_congestion_control_params().is_err());
+        assert_eq!(cfg.congestion_control_params().max_deferral_rounds, None);
+        cfg.set_max_deferral_rounds_for_congestion_control_for_testing(7);
+
+        // The Narwhal budget satisfies the check, but is not used as the Mysticeti budget.
+        cfg.max_accumulated_txn_cost_per_object_in_mysticeti_commit = None;
+        cfg.max_accumulated_txn_cost_per_object_in_narwhal_commit = Some(100);
+        assert!(cfg.check_congestion_control_params().is_ok());
+        let params = cfg.congestion_control_params();
+        assert_eq!(params.max_accumulated_txn_cost_per_object(false), None);
+        assert_eq!(params.max_accumulated_txn_cost_per_object(true), None);
+
+        cfg.max_accumulated_txn_cost_per_object_in_narwhal_commit = None;
+        assert!(cfg.check_congestion_control_params().is_err());
+
+        // Nothing is required when congestion control is disabled.
+        cfg.set_per_object_congestion_control_mode_for_testing(M::None);
+        cfg.max_deferral_rounds_for_congestion_control = None;
+        assert!(cfg.check_congestion_control_params().is_ok());
+    }
+
+    #[test]
+    fn execution_time_estimate_params_test() {
+        for chain in &[Chain::Unknown, Chain::Mainnet, Chain::Testnet] {
+            for i in MIN_PROTOCOL_VERSION..=MAX_PROTOCOL_VERSION {
+                let cfg = ProtocolConfig::get_for_version_impl(ProtocolVersion::new(i), *chain);
+                if let Err(e) = cfg.check_execution_time_estimate_params() {
+                    panic!("version {i} on {chain:?}: {}", e.0);
+                }
+            }
+        }
+
+        let mut cfg = ProtocolConfig::get_for_version(ProtocolVersion::MAX, Chain::Unknown);
+        cfg.set_execution_time_estimate_table_version_for_testing(1);
+        cfg.set_execution_time_estimate_target_utilization_for_testing(50);
+        cfg.set_execution_time_estimate_min_staleness_ms_for_testing(1_000);
+        assert_eq!(cfg.execution_time_estimate_params(), None);
+        assert!(cfg.check_execution_time_estimate_params().is_err());
+
+        cfg.set_execution_time_estimate_max_staleness_ms_for_testing(60_000);
+        assert_eq!(
+            cfg.execution_time_estimate_params(),
+            Some(ExecutionTimeEstimateParams {
+                base_estimates_table_version: 1,
+                target_utilization: 50,
+                min_staleness: Duration::from_secs(1),
+                max_staleness: Duration::from_secs(60),
+            })
+        );
+        assert!(cfg.check_execution_time_estimate_params().is_ok());
+
+        cfg.set_execution_time_estimate_target_utilization_for_testing(101);
+        assert!(cfg.check_execution_time_estimate_params().is_err());
+    }
+
+    #[test]
+    fn feature_ids_test() {
+        let cfg = ProtocolConfig::get_for_version(ProtocolVersion::MAX, Chain::Unknown);
+        let features = cfg.feature_map();
+        assert_eq!(Feature::ALL.len(), features.len());
+
+        for feature in Feature::ALL {
+            assert_eq!(Feature::from_id(feature.id()), Some(*feature));
+            assert_eq!(
+                cfg.is_feature_enabled(*feature),
+                features[feature.name()],
+                "{}",
+                feature.name(),
+            );
+        }
+
+        // IDs are part of the telemetry contract and must not change.
+        assert_eq!(Feature::PackageUpgrades.id(), 1);
+        assert_eq!(Feature::NativeChargingV2.id(), 66);
+        assert_eq!(Feature::from_id(0), None);
+    }
+
+    #[test]
+    fn feature_flags_builder_test() {
+        let base = ProtocolConfig::get_for_version(ProtocolVersion::MAX, Chain::Unknown);
+
+        // Flip every boolean flag, and set some of the others.
+        let builder = Feature::ALL
+            .iter()
+            .fold(FeatureFlagsBuilder::new(), |builder, feature| {
+                builder.feature(*feature, !base.is_feature_enabled(*feature))
+            })
+            .consensus_choice(ConsensusChoice::SwapEachEpoch)
+            .zklogin_max_epoch_upper_bound_delta(Some(7));
+
+        let mut cfg = base.clone();
+        builder.apply_to(&mut cfg);
+
+        for feature in Feature::ALL {
+            assert_ne!(
+                cfg.is_feature_enabled(*feature),
+                base.is_feature_enabled(*feature),
+                "{}",
+                feature.name(),
+            );
+        }
+
+        assert_eq!(cfg.consensus_choice(), ConsensusChoice::SwapEachEpoch);
+        assert_eq!(cfg.zklogin_max_epoch_upper_bound_delta(), Some(7));
+
+        // Flags that were not set are left alone.
+        assert_eq!(
+            cfg.mysticeti_num_leaders_per_round(),
+            base.mysticeti_num_leaders_per_round(),
+        );
+        assert_eq!(cfg.consensus_network(), base.consensus_network());
+
+        // Later settings for the same flag win.
+        let mut cfg = base.clone();
+        FeatureFlagsBuilder::new()
+            .zklogin_auth(false)
+            .feature(Feature::ZkloginAuth, true)
+            .apply_to(&mut cfg);
+        assert!(cfg.zklogin_auth());
+
+        // The builder can be installed for a whole test.
+        let flags = FeatureFlagsBuilder::new().consensus_choice(ConsensusChoice::Narwhal);
+        testing::with_feature_flags(flags, || {
+            let cfg = ProtocolConfig::get_for_version(ProtocolVersion::MAX, Chain::Unknown);
+            assert_eq!(cfg.consensus_choice(), ConsensusChoice::Narwhal);
+        });
+    }
+
+    #[test]
+    fn response_shaping_limits_test() {
+        for chain in &[Chain::Unknown, Chain::Mainnet, Chain::Testnet] {
+            let mut prev: Option<ResponseShapingLimits> = None;
+            for i in MIN_PROTOCOL_VERSION..=MAX_PROTOCOL_VERSION {
+                let cfg = ProtocolConfig::get_for_version_impl(ProtocolVersion::new(i), *chain);
+                let limits = cfg.response_shaping_limits();
+
+                assert_eq!(limits.max_events, cfg.max_num_event_emit());
+                assert!(limits.max_object_changes >= cfg.max_input_objects());
+                assert!(limits.max_object_changes >= cfg.max_num_new_move_object_ids_system_tx());
+
+                // Limits have only ever grown, so an RPC layer sized for the latest version also
+                // covers earlier ones.
+                if let Some(prev) = prev {
+                    assert!(prev.max_events <= limits.max_events, "version {i}");
+                    assert!(
+                        prev.max_object_changes <= limits.max_object_changes,
+                        "version {i}"
+                    );
+                }
+
+                prev = Some(limits);
+            }
+        }
+    }
+
+    #[test]
+    fn node_config_hints_test() {
+        let cfg = ProtocolConfig::get_for_version(ProtocolVersion::MAX, Chain::Unknown);
+        let hints = cfg.node_config_hints();
+
+        // Room for five full checkpoints, in line with state sync's default.
+        assert_eq!(
+            hints.checkpoint_content_download_tx_concurrency,
+            5 * cfg.max_transactions_per_checkpoint(),
+        );
+
+        let mut cfg = cfg;
+        cfg.set_max_transactions_per_checkpoint_for_testing(20_000);
+        assert_eq!(
+            NodeConfigHints::from_protocol_config(&cfg).checkpoint_content_download_tx_concurrency,
+            100_000,
+        );
+    }
+
+    #[test]
+    fn consensus_transaction_ordering_strategy_test() {
+        use ConsensusTransactionOrderingStrategy as S;
+
+        for chain in &[Chain::Unknown, Chain::Mainnet, Chain::Testnet] {
+            for i in MIN_PROTOCOL_VERSION..=MAX_PROTOCOL_VERSION {
+                let cfg = ProtocolConfig::get_for_version_impl(ProtocolVersion::new(i), *chain);
+                if let Err(e) = cfg.check_consensus_transaction_ordering() {
+                    panic!("version {i} on {chain:?}: {}", e.0);
+                }
+            }
+        }
+
+        let mut cfg = ProtocolConfig::get_for_version(ProtocolVersion::MAX, Chain::Unknown);
+        assert_eq!(
+            cfg.consensus_transaction_ordering_strategy(),
+            S::ByGasPrice {
+                tie_break: GasPriceTieBreak::SubmissionOrder
+            },
+        );
+
+        cfg.set_consensus_gas_price_tie_break_for_testing(GasPriceTieBreak::Digest);
+        assert_eq!(
+            cfg.consensus_transaction_ordering_strategy(),
+            S::ByGasPrice {
+                tie_break: GasPriceTieBreak::Digest
+            },
+        );
+
+        cfg.set_consensus_transaction_ordering_for_testing(ConsensusTransactionOrdering::Fair);
+        assert!(cfg.check_consensus_transaction_ordering().is_err());
+
+        cfg.set_consensus_fair_ordering_per_sender_quota_for_testing(0);
+        assert!(cfg.check_consensus_transaction_ordering().is_err());
+
+        cfg.set_consensus_fair_ordering_per_sender_quota_for_testing(2);
+        assert!(cfg.check_consensus_transaction_ordering().is_ok());
+        assert_eq!(
+            cfg.consensus_transaction_ordering_strategy(),
+            S::Fair {
+                per_sender_quota: 2
+            },
+        );
+
+        cfg.set_consensus_transaction_ordering_for_testing(ConsensusTransactionOrdering::None);
+        assert_eq!(cfg.consensus_transaction_ordering_strategy(), S::None);
     }
 
     #[test]
@@ -0,0 +1,86 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Helpers for tests that need to run against a modified `ProtocolConfig`.
+//!
+//! Overrides installed here apply to every config returned by `ProtocolConfig::get_for_version`
+//! (and the functions built on it) on the current thread, until their guard is dropped. Unlike
+//! `ProtocolConfig::apply_overrides_for_testing`, they nest: an override installed while others
+//! are in effect is applied on top of them, so helpers from different crates can each add the
+//! settings they need.
+//!
+//! Overrides are thread-local. In simtests, every simulated node in a test runs on the test's
+//! thread, so an override applies to all of them, but not to other tests.
+
+use crate::{
+    push_config_override, set_divergence_disabled, Divergence, Feature, FeatureFlagsBuilder,
+    OverrideGuard, ProtocolConfig, ProtocolVersion,
+};
+
+/// Apply `override_fn` to configs on this thread, on top of any overrides already in effect,
+/// until the returned guard is dropped. Dropping the guard only removes this override, even if
+/// overrides installed after it are still in effect.
+pub fn override_config(
+    override_fn: impl Fn(ProtocolVersion, ProtocolConfig) -> ProtocolConfig + Send + 'static,
+) -> OverrideGuard {
+    push_config_override(Box::new(override_fn))
+}
+
+/// Run `f` with `override_fn` applied to configs on this thread.
+pub fn with_overrides<R>(
+    override_fn: impl Fn(ProtocolVersion, ProtocolConfig) -> ProtocolConfig + Send + 'static,
+    f: impl FnOnce() -> R,
+) -> R {
+    let _guard = override_config(override_fn);
+    f()
+}
+
+/// Run `f` with feature flag `feature` set to `val` in configs on this thread.
+pub fn with_feature<R>(feature: Feature, val: bool, f: impl FnOnce() -> R) -> R {
+    with_overrides(
+        move |_, mut config| {
+            config.set_feature_for_testing(feature, val);
+            config
+        },
+        f,
+    )
+}
+
+/// Run `f` with the feature flags set in `flags` applied to configs on this thread.
+pub fn with_feature_flags<R>(flags: FeatureFlagsBuilder, f: impl FnOnce() -> R) -> R {
+    with_overrides(
+        move |_, mut config| {
+            flags.apply_to(&mut config);
+            config
+        },
+        f,
+    )
+}
+
+/// Run `f` with the constant `attr` set to `val` (parsed as the constant's type) in configs on
+/// this thread. Panics when a config is fetched if there is no constant called `attr`, or `val`
+/// cannot be parsed.
+pub fn with_constant<R>(attr: &str, val: &str, f: impl FnOnce() -> R) -> R {
+    let (attr, val) = (attr.to_owned(), val.to_owned());
+    with_overrides(
+        move |_, mut config| {
+            config.set_attr_for_testing(attr.clone(), val.clone());
+            config
+        },
+        f,
+    )
+}
+
+/// Run `f` with `divergence` switched off on this thread, so that configs behave like production
+/// configs in that respect (see `ProtocolConfig::test_divergences`).
+pub fn without_divergence<R>(divergence: Divergence, f: impl FnOnce() -> R) -> R {
+    struct Restore(Divergence, bool);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            set_divergence_disabled(self.0, self.1);
+        }
+    }
+
+    let _restore = Restore(divergence, set_divergence_disabled(divergence, true));
+    f()
+}
@@ -0,0 +1,121 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Tracking the `ProtocolConfig` in effect, for services outside the validator (indexers, RPC,
+//! bridge nodes, ...) that follow epoch changes and need to keep their config up-to-date.
+//!
+//! The watcher holds the config in an `ArcSwap`, so that readers always see a complete config and
+//! never block on updates. Updates are serialized, so the config in effect and the notifications
+//! sent to callbacks always reflect the latest epoch change fed to the watcher.
+
+use std::sync::{Arc, Mutex};
+
+use arc_swap::ArcSwap;
+use futures::{Stream, StreamExt};
+use tracing::warn;
+
+use crate::{Chain, Error, ProtocolConfig, ProtocolVersion};
+
+/// Notification that an epoch started, running the given protocol version on the given chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EpochChange {
+    pub protocol_version: ProtocolVersion,
+    pub chain: Chain,
+}
+
+type Callback = Box<dyn Fn(&ProtocolConfig) + Send + Sync>;
+
+/// Maintains the `ProtocolConfig` for the latest epoch change it has been told about, and notifies
+/// registered callbacks when that config changes.
+pub struct ProtocolConfigWatcher {
+    config: Arc<ArcSwap<ProtocolConfig>>,
+
+    /// The epoch change that `config` was computed for, and the callbacks to notify on changes.
+    /// Held for the duration of an update, so that updates are applied in order.
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    current: EpochChange,
+    callbacks: Vec<Callback>,
+}
+
+impl ProtocolConfigWatcher {
+    /// Start watching from the config for `initial`. Fails if this binary does not support its
+    /// protocol version.
+    pub fn new(initial: EpochChange) -> Result<Self, Error> {
+        let config = initial.config()?;
+        Ok(Self {
+            config: Arc::new(ArcSwap::from_pointee(config)),
+            inner: Mutex::new(Inner {
+                current: initial,
+                callbacks: vec![],
+            }),
+        })
+    }
+
+    /// A handle to the config in effect, which stays up-to-date as the watcher is updated.
+    pub fn config(&self) -> Arc<ArcSwap<ProtocolConfig>> {
+        self.config.clone()
+    }
+
+    /// A snapshot of the config currently in effect.
+    pub fn load(&self) -> Arc<ProtocolConfig> {
+        self.config.load_full()
+    }
+
+    /// Call `callback` with the new config every time it changes. Callbacks are called in the
+    /// order they were registered, while the update is in progress, so they must not update the
+    /// watcher or register other callbacks themselves.
+    pub fn on_change(&self, callback: impl Fn(&ProtocolConfig) + Send + Sync + 'static) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.callbacks.push(Box::new(callback));
+    }
+
+    /// Switch to the config for `change`, notifying callbacks. Does nothing if `change` has the
+    /// same protocol version and chain as the config already in effect. Returns whether the config
+    /// changed. Fails, keeping the config already in effect, if this binary does not support the
+    /// protocol version in `change`.
+    pub fn update(&self, change: EpochChange) -> Result<bool, Error> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.current == change {
+            return Ok(false);
+        }
+
+        let config = Arc::new(change.config()?);
+
+        self.config.store(config.clone());
+        inner.current = change;
+
+        for callback in &inner.callbacks {
+            callback(&config);
+        }
+
+        Ok(true)
+    }
+
+    /// Apply every epoch change from `changes`, in order, until the stream is exhausted. Changes to
+    /// protocol versions that this binary does not support are logged and skipped, keeping the
+    /// config already in effect.
+    pub async fn watch(&self, changes: impl Stream<Item = EpochChange>) {
+        let mut changes = std::pin::pin!(changes);
+        while let Some(change) = changes.next().await {
+            if let Err(Error(e)) = self.update(change) {
+                warn!("Failed to update protocol config: {e}");
+            }
+        }
+    }
+}
+
+impl EpochChange {
+    fn config(&self) -> Result<ProtocolConfig, Error> {
+        ProtocolConfig::get_for_version_if_supported(self.protocol_version, self.chain).ok_or_else(
+            || {
+                Error(format!(
+                    "protocol version {:?} is not supported on {:?}",
+                    self.protocol_version, self.chain
+                ))
+            },
+        )
+    }
+}
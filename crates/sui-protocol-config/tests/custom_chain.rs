@@ -0,0 +1,47 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Registering a custom chain affects every config the process produces, so these tests run in
+//! their own process, and in a single test, to control the order of registration and fetching.
+
+use sui_protocol_config::{
+    Chain, CustomChain, ProtocolConfig, ProtocolConfigPatch, ProtocolVersion,
+};
+
+fn appchain(name: &'static str) -> CustomChain {
+    CustomChain {
+        name: "appchain".to_string(),
+        patches: vec![ProtocolConfigPatch {
+            name,
+            chain: Chain::Unknown,
+            versions: 1..=ProtocolVersion::MAX.as_u64(),
+            apply: |cfg| cfg.set_max_arguments_for_testing(1024),
+        }],
+    }
+}
+
+#[test]
+fn custom_chain_patches_unknown_configs() {
+    ProtocolConfig::register_custom_chain(appchain("appchain-max-arguments")).unwrap();
+    assert_eq!(ProtocolConfig::custom_chain().unwrap().name, "appchain");
+
+    let max = ProtocolVersion::MAX;
+    let cfg = ProtocolConfig::get_for_version(max, Chain::Unknown);
+    assert_eq!(cfg.max_arguments(), 1024);
+    assert_eq!(
+        ProtocolConfig::active_patches(max, Chain::Unknown),
+        vec!["appchain-max-arguments"],
+    );
+
+    // Other chains are unaffected.
+    let cfg = ProtocolConfig::get_for_version(max, Chain::Mainnet);
+    assert_ne!(cfg.max_arguments(), 1024);
+    assert!(ProtocolConfig::active_patches(max, Chain::Mainnet).is_empty());
+
+    // Only one custom chain can be registered.
+    ProtocolConfig::register_custom_chain(appchain("appchain-again")).unwrap_err();
+    assert_eq!(
+        ProtocolConfig::active_patches(max, Chain::Unknown),
+        vec!["appchain-max-arguments"],
+    );
+}
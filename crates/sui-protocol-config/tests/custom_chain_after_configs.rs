@@ -0,0 +1,32 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Registering a custom chain after configs have been produced must fail, so this test runs in its
+//! own process, to control whether configs have been produced.
+
+use sui_protocol_config::{
+    Chain, CustomChain, ProtocolConfig, ProtocolConfigPatch, ProtocolVersion,
+};
+
+#[test]
+fn custom_chain_after_configs() {
+    let max = ProtocolVersion::MAX;
+    let before = ProtocolConfig::get_for_version(max, Chain::Unknown);
+
+    let err = ProtocolConfig::register_custom_chain(CustomChain {
+        name: "appchain".to_string(),
+        patches: vec![ProtocolConfigPatch {
+            name: "appchain-max-arguments",
+            chain: Chain::Unknown,
+            versions: 1..=max.as_u64(),
+            apply: |cfg| cfg.set_max_arguments_for_testing(1024),
+        }],
+    })
+    .unwrap_err();
+
+    assert!(err.0.contains("already been produced"), "{}", err.0);
+    assert!(ProtocolConfig::custom_chain().is_none());
+
+    let after = ProtocolConfig::get_for_version(max, Chain::Unknown);
+    assert_eq!(before.max_arguments(), after.max_arguments());
+}
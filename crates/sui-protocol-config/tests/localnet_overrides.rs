@@ -0,0 +1,57 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Registering `--protocol.*` overrides affects every config the process produces, so these tests
+//! run in their own process, and in a single test, to control the order of registration and
+//! fetching.
+
+#![cfg(feature = "overrides")]
+
+use clap::Parser;
+use sui_protocol_config::{Chain, ProtocolConfig, ProtocolConfigLocalnetArgs, ProtocolVersion};
+
+#[derive(Parser)]
+struct Cli {
+    #[clap(flatten)]
+    protocol: ProtocolConfigLocalnetArgs,
+}
+
+fn args(argv: &[&str]) -> ProtocolConfigLocalnetArgs {
+    Cli::parse_from(std::iter::once("sui").chain(argv.iter().copied())).protocol
+}
+
+#[test]
+fn localnet_overrides_apply_to_unknown_configs() {
+    // Overrides are validated against every version they apply to, starting with the oldest.
+    let err = args(&[
+        "--protocol.max-tx-size-bytes",
+        "1MB",
+        "--protocol.consensus-max-transaction-size-bytes",
+        "512KB",
+    ])
+    .register()
+    .unwrap_err();
+    assert!(err.0.contains("protocol version 1:"), "{}", err.0);
+
+    args(&["--protocol.max-tx-size-bytes", "1MB"])
+        .register()
+        .unwrap();
+
+    // The overrides apply at every version, and consensus limits are raised to accommodate them.
+    let limit = (1024 + 128) * 1024;
+    for version in [ProtocolVersion::MIN, ProtocolVersion::MAX] {
+        let cfg = ProtocolConfig::get_for_version(version, Chain::Unknown);
+        assert_eq!(cfg.max_tx_size_bytes(), 1024 * 1024);
+        assert_eq!(cfg.max_transaction_size_bytes(), limit);
+        assert_eq!(cfg.max_transactions_in_block_bytes(), limit);
+    }
+
+    // Other chains are unaffected.
+    let cfg = ProtocolConfig::get_for_version(ProtocolVersion::MAX, Chain::Mainnet);
+    assert_eq!(cfg.max_tx_size_bytes(), 128 * 1024);
+
+    // Overrides can only be registered once.
+    assert!(args(&["--protocol.max-tx-size-bytes", "2MB"])
+        .register()
+        .is_err());
+}
@@ -589,6 +589,10 @@ pub struct ProtocolConfigResponse {
     pub protocol_version: u64,
     pub feature_flags: BTreeMap<String, bool>,
     pub attributes: BTreeMap<String, String>,
+    /// The stable address of each attribute and feature flag, which does not change if it is
+    /// renamed.
+    #[serde(default)]
+    pub addresses: BTreeMap<String, String>,
 }
 
 impl From<ProtocolConfig> for ProtocolConfigResponse {
@@ -612,6 +616,7 @@ impl From<ProtocolConfig> for ProtocolConfigResponse {
             protocol_version: config.version.as_u64(),
             attributes,
             feature_flags: config.feature_map(),
+            addresses: config.addresses(),
         }
     }
 }
@@ -21,6 +21,7 @@ use sui_config::{
     local_ip_utils, ConsensusConfig, NodeConfig, AUTHORITIES_DB_NAME, CONSENSUS_DB_NAME,
     FULL_NODE_DB_PATH,
 };
+use sui_protocol_config::{Chain, NodeConfigHints, ProtocolConfig};
 use sui_types::crypto::{AuthorityKeyPair, AuthorityPublicKeyBytes, NetworkKeyPair, SuiKeyPair};
 use sui_types::multiaddr::Multiaddr;
 use sui_types::supported_protocol_versions::SupportedProtocolVersions;
@@ -139,6 +140,7 @@ impl ValidatorConfigBuilder {
         let network_address = validator.network_address;
         let consensus_db_path = config_directory.join(CONSENSUS_DB_NAME).join(key_path);
         let localhost = local_ip_utils::localhost_for_testing();
+        let hints = node_config_hints(self.supported_protocol_versions);
         let consensus_config = ConsensusConfig {
             db_path: consensus_db_path,
             db_retention_epochs: None,
@@ -160,6 +162,9 @@ impl ValidatorConfigBuilder {
             // Set a shorter timeout for checkpoint content download in tests, since
             // checkpoint pruning also happens much faster, and network is local.
             state_sync: Some(StateSyncConfig {
+                checkpoint_content_download_tx_concurrency: Some(
+                    hints.checkpoint_content_download_tx_concurrency,
+                ),
                 checkpoint_content_timeout_ms: Some(10_000),
                 ..Default::default()
             }),
@@ -410,6 +415,7 @@ impl FullnodeConfigBuilder {
             .config_directory
             .unwrap_or_else(|| tempfile::tempdir().unwrap().into_path());
 
+        let hints = node_config_hints(self.supported_protocol_versions);
         let p2p_config = {
             let seed_peers = network_config
                 .validator_configs
@@ -438,6 +444,9 @@ impl FullnodeConfigBuilder {
                 // Set a shorter timeout for checkpoint content download in tests, since
                 // checkpoint pruning also happens much faster, and network is local.
                 state_sync: Some(StateSyncConfig {
+                    checkpoint_content_download_tx_concurrency: Some(
+                        hints.checkpoint_content_download_tx_concurrency,
+                    ),
                     checkpoint_content_timeout_ms: Some(10_000),
                     ..Default::default()
                 }),
@@ -542,6 +551,16 @@ impl FullnodeConfigBuilder {
     }
 }
 
+/// Node-level settings recommended for the highest protocol version that the node supports.
+fn node_config_hints(
+    supported_protocol_versions: Option<SupportedProtocolVersions>,
+) -> NodeConfigHints {
+    let version = supported_protocol_versions
+        .unwrap_or(SupportedProtocolVersions::SYSTEM_DEFAULT)
+        .max;
+    ProtocolConfig::get_for_version(version, Chain::Unknown).node_config_hints()
+}
+
 /// Given a validator keypair, return a path that can be used to identify the validator.
 fn get_key_path(key_pair: &AuthorityKeyPair) -> String {
     let public_key: AuthorityPublicKeyBytes = key_pair.public().into();
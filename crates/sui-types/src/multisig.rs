@@ -42,7 +42,7 @@ mod multisig_tests;
 pub type WeightUnit = u8;
 pub type ThresholdUnit = u16;
 pub type BitmapUnit = u16;
-pub const MAX_SIGNER_IN_MULTISIG: usize = 10;
+pub const MAX_SIGNER_IN_MULTISIG: usize = sui_protocol_config::MAX_MULTISIG_SIGNERS as usize;
 pub const MAX_BITMAP_VALUE: BitmapUnit = 0b1111111111;
 /// The struct that contains signatures and public keys necessary for authenticating a MultiSig.
 #[serde_as]
@@ -113,6 +113,14 @@ impl AuthenticatorTrait for MultiSig {
             });
         }
 
+        if self.multisig_pk.pk_map.len()
+            > verify_params.signature_limits.max_multisig_signers as usize
+        {
+            return Err(SuiError::InvalidSignature {
+                error: "Too many public keys in multisig".to_string(),
+            });
+        }
+
         if !self.get_zklogin_sigs()?.is_empty() && !verify_params.accept_zklogin_in_multisig {
             return Err(SuiError::InvalidSignature {
                 error: "zkLogin sig not supported inside multisig".to_string(),
@@ -34,7 +34,7 @@ use crate::{
 
 pub type WeightUnit = u8;
 pub type ThresholdUnit = u16;
-pub const MAX_SIGNER_IN_MULTISIG: usize = 10;
+pub const MAX_SIGNER_IN_MULTISIG: usize = sui_protocol_config::MAX_MULTISIG_SIGNERS as usize;
 
 /// Deprecated, use [struct MultiSig] instead.
 /// The struct that contains signatures and public keys necessary for authenticating a MultiSigLegacy.
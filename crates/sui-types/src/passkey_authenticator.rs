@@ -29,6 +29,10 @@ use std::sync::Arc;
 #[path = "unit_tests/passkey_authenticator_test.rs"]
 mod passkey_authenticator_test;
 
+// The protocol config reports the size of the challenge that passkey verification expects.
+const _: () =
+    assert!(DefaultHash::OUTPUT_SIZE == sui_protocol_config::PASSKEY_CHALLENGE_SIZE_BYTES as usize);
+
 /// An passkey authenticator with parsed fields. See field defition below. Can be initialized from [struct RawPasskeyAuthenticator].
 #[derive(Debug, Clone, JsonSchema)]
 pub struct PasskeyAuthenticator {
@@ -214,12 +218,22 @@ impl AuthenticatorTrait for PasskeyAuthenticator {
         &self,
         intent_msg: &IntentMessage<T>,
         author: SuiAddress,
-        _aux_verify_data: &VerifyParams,
+        aux_verify_data: &VerifyParams,
         _zklogin_inputs_cache: Arc<VerifiedDigestCache<ZKLoginInputsDigest>>,
     ) -> SuiResult
     where
         T: Serialize,
     {
+        if self.challenge.len()
+            != aux_verify_data
+                .signature_limits
+                .passkey_challenge_size_bytes as usize
+        {
+            return Err(SuiError::InvalidSignature {
+                error: "Invalid challenge size".to_string(),
+            });
+        }
+
         // Check the intent and signing is consisted from what's parsed from client_data_json.challenge
         if self.challenge != to_signing_message(intent_msg) {
             return Err(SuiError::InvalidSignature {
@@ -28,6 +28,7 @@ use serde::Serialize;
 use shared_crypto::intent::IntentMessage;
 use std::hash::Hash;
 use std::sync::Arc;
+use sui_protocol_config::SignatureLimits;
 #[derive(Default, Debug, Clone)]
 pub struct VerifyParams {
     // map from JwkId (iss, kid) => JWK
@@ -37,6 +38,7 @@ pub struct VerifyParams {
     pub verify_legacy_zklogin_address: bool,
     pub accept_zklogin_in_multisig: bool,
     pub zklogin_max_epoch_upper_bound_delta: Option<u64>,
+    pub signature_limits: SignatureLimits,
 }
 
 impl VerifyParams {
@@ -47,6 +49,7 @@ impl VerifyParams {
         verify_legacy_zklogin_address: bool,
         accept_zklogin_in_multisig: bool,
         zklogin_max_epoch_upper_bound_delta: Option<u64>,
+        signature_limits: SignatureLimits,
     ) -> Self {
         Self {
             oidc_provider_jwks,
@@ -55,6 +58,7 @@ impl VerifyParams {
             verify_legacy_zklogin_address,
             accept_zklogin_in_multisig,
             zklogin_max_epoch_upper_bound_delta,
+            signature_limits,
         }
     }
 }
@@ -2394,7 +2394,7 @@ impl SenderSignedData {
 
         // Enforce overall transaction size limit.
         let tx_size = self.serialized_size()?;
-        let max_tx_size_bytes = config.max_tx_size_bytes();
+        let max_tx_size_bytes = config.transaction_size_limits().signing;
         fp_ensure!(
             tx_size as u64 <= max_tx_size_bytes,
             SuiError::UserInputError {
@@ -33,6 +33,7 @@ use rand::{rngs::StdRng, SeedableRng};
 use roaring::RoaringBitmap;
 use shared_crypto::intent::{Intent, IntentMessage, PersonalMessage};
 use std::{str::FromStr, sync::Arc};
+use sui_protocol_config::{ProtocolConfig, SignatureLimits};
 #[test]
 fn test_combine_sigs() {
     let kp1: SuiKeyPair = SuiKeyPair::Ed25519(get_key_pair().1);
@@ -418,7 +419,16 @@ fn zklogin_in_multisig_works_with_both_addresses() {
         .into_iter()
         .collect();
 
-    let aux_verify_data = VerifyParams::new(parsed, vec![], ZkLoginEnv::Test, true, true, Some(30));
+    let signature_limits = ProtocolConfig::get_for_max_version_UNSAFE().signature_limits();
+    let aux_verify_data = VerifyParams::new(
+        parsed.clone(),
+        vec![],
+        ZkLoginEnv::Test,
+        true,
+        true,
+        Some(30),
+        signature_limits.clone(),
+    );
     let res = multisig.verify_claims(
         intent_msg,
         multisig_address,
@@ -430,6 +440,29 @@ fn zklogin_in_multisig_works_with_both_addresses() {
         matches!(res, Err(crate::error::SuiError::InvalidSignature { error }) if error.contains("General cryptographic error: Groth16 proof verify failed"))
     );
 
+    // The multisig is rejected if it has more public keys than the limit.
+    let aux_verify_data = VerifyParams::new(
+        parsed,
+        vec![],
+        ZkLoginEnv::Test,
+        true,
+        true,
+        Some(30),
+        SignatureLimits {
+            max_multisig_signers: 0,
+            ..signature_limits
+        },
+    );
+    let res = multisig.verify_claims(
+        intent_msg,
+        multisig_address,
+        &aux_verify_data,
+        Arc::new(VerifiedDigestCache::new_empty()),
+    );
+    assert!(
+        matches!(res, Err(crate::error::SuiError::InvalidSignature { error }) if error == "Too many public keys in multisig")
+    );
+
     // initialize zklogin pk (pk1_padd) with padded address seed
     let pk1_padded = PublicKey::ZkLogin(
         ZkLoginPublicIdentifier::new(
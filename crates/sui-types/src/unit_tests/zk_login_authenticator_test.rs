@@ -21,6 +21,7 @@ use fastcrypto_zkp::bn254::zk_login_api::ZkLoginEnv;
 use fastcrypto_zkp::zk_login_utils::Bn254FrElement;
 use im::hashmap::HashMap as ImHashMap;
 use shared_crypto::intent::{Intent, IntentMessage, PersonalMessage};
+use sui_protocol_config::{ProtocolConfig, SignatureLimits};
 
 #[test]
 fn test_serde_zk_login_signature() {
@@ -100,7 +101,16 @@ fn zklogin_sign_personal_message() {
         .collect();
 
     // Construct the required info to verify a zk login authenticator, jwks, supported providers list and env (prod/test).
-    let aux_verify_data = VerifyParams::new(parsed, vec![], ZkLoginEnv::Test, true, true, Some(30));
+    let signature_limits = ProtocolConfig::get_for_max_version_UNSAFE().signature_limits();
+    let aux_verify_data = VerifyParams::new(
+        parsed.clone(),
+        vec![],
+        ZkLoginEnv::Test,
+        true,
+        true,
+        Some(30),
+        signature_limits.clone(),
+    );
     let res = authenticator.verify_authenticator(
         &intent_msg,
         user_address,
@@ -110,4 +120,28 @@ fn zklogin_sign_personal_message() {
     );
     // Verify passes.
     assert!(res.is_ok());
+
+    // Verify fails if the zkLogin inputs are larger than the limit.
+    let aux_verify_data = VerifyParams::new(
+        parsed,
+        vec![],
+        ZkLoginEnv::Test,
+        true,
+        true,
+        Some(30),
+        SignatureLimits {
+            max_zklogin_proof_size_bytes: Some(16),
+            ..signature_limits
+        },
+    );
+    let res = authenticator.verify_authenticator(
+        &intent_msg,
+        user_address,
+        0,
+        &aux_verify_data,
+        Arc::new(VerifiedDigestCache::new_empty()),
+    );
+    assert!(
+        matches!(res, Err(crate::error::SuiError::InvalidSignature { error }) if error.contains("more than the maximum of 16"))
+    );
 }
@@ -163,6 +163,23 @@ impl AuthenticatorTrait for ZkLoginAuthenticator {
     where
         T: Serialize,
     {
+        if let Some(max_size) = aux_verify_data
+            .signature_limits
+            .max_zklogin_proof_size_bytes
+        {
+            let size =
+                bcs::serialized_size(&self.inputs).map_err(|_| SuiError::InvalidSignature {
+                    error: "Cannot serialize zkLogin inputs".to_string(),
+                })?;
+            if size > max_size as usize {
+                return Err(SuiError::InvalidSignature {
+                    error: format!(
+                        "zkLogin inputs are {size} bytes, more than the maximum of {max_size}"
+                    ),
+                });
+            }
+        }
+
         // Always evaluate the unpadded address derivation.
         if author != SuiAddress::try_from_unpadded(&self.inputs)? {
             // If the verify_legacy_zklogin_address flag is set, also evaluate the padded address derivation.
@@ -39,6 +39,7 @@ use sui_keys::keypair_file::{
     write_keypair_to_file,
 };
 use sui_keys::keystore::{AccountKeystore, Keystore};
+use sui_protocol_config::ProtocolConfig;
 use sui_types::base_types::SuiAddress;
 use sui_types::committee::EpochId;
 use sui_types::crypto::{
@@ -1240,8 +1241,15 @@ impl KeyToolCommand {
                             "mainnet" | "testnet" => ZkLoginEnv::Prod,
                             _ => return Err(anyhow!("Invalid network")),
                         };
-                        let verify_params =
-                            VerifyParams::new(parsed, vec![], env, true, true, Some(2));
+                        let verify_params = VerifyParams::new(
+                            parsed,
+                            vec![],
+                            env,
+                            true,
+                            true,
+                            Some(2),
+                            ProtocolConfig::get_for_max_version_UNSAFE().signature_limits(),
+                        );
 
                         let (serialized, res) = match IntentScope::try_from(intent_scope)
                             .map_err(|_| anyhow!("Invalid scope"))?
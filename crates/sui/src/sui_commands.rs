@@ -47,6 +47,7 @@ use sui_keys::keypair_file::read_key;
 use sui_keys::keystore::{AccountKeystore, FileBasedKeystore, Keystore};
 use sui_move::{self, execute_move_command};
 use sui_move_build::SuiPackageHooks;
+use sui_protocol_config::ProtocolConfigLocalnetArgs;
 use sui_sdk::sui_client_config::{SuiClientConfig, SuiEnv};
 use sui_sdk::wallet_context::WalletContext;
 use sui_swarm::memory::Swarm;
@@ -157,6 +158,10 @@ pub enum SuiCommand {
     ///   checkpoint creation to >1/s, you might set:
     ///   SUI_PROTOCOL_CONFIG_OVERRIDE_min_checkpoint_interval_ms=1000
     ///
+    /// Alternatively, parameters that are safe to change on a local network can be overridden
+    /// with `--protocol.*` flags, which are validated before the network starts, e.g.:
+    ///   sui start --protocol.max-tx-size-bytes 1MB
+    ///
     /// Note that ProtocolConfig parameters must match between all nodes, or the network
     /// may break. Changing these values outside of local networks is very dangerous.
     #[clap(name = "start", verbatim_doc_comment)]
@@ -218,6 +223,9 @@ pub enum SuiCommand {
         /// genesis with the desired number of validators.
         #[clap(long)]
         committee_size: Option<usize>,
+
+        #[clap(flatten)]
+        protocol_config_overrides: ProtocolConfigLocalnetArgs,
     },
     #[clap(name = "network")]
     Network {
@@ -387,7 +395,14 @@ impl SuiCommand {
                 no_full_node,
                 epoch_duration_ms,
                 committee_size,
+                protocol_config_overrides,
             } => {
+                // Overrides apply to every protocol config produced after they are registered, so
+                // they must be in place before the network starts.
+                protocol_config_overrides
+                    .register()
+                    .map_err(|e| anyhow!("Invalid protocol config override: {}", e.0))?;
+
                 start(
                     config_dir.clone(),
                     with_faucet,
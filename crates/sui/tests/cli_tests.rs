@@ -78,6 +78,7 @@ async fn test_genesis() -> Result<(), anyhow::Error> {
         no_full_node: false,
         committee_size: None,
         indexer_feature_args: IndexerArgs::for_testing(),
+        protocol_config_overrides: Default::default(),
     }
     .execute()
     .await;
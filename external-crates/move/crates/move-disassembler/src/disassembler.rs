@@ -8,7 +8,7 @@ use inline_colorization as IC;
 use move_abstract_interpreter::control_flow_graph::{ControlFlowGraph, VMControlFlowGraph};
 use move_binary_format::{
     file_format::{
-        Ability, AbilitySet, Bytecode, CodeUnit, Constant, DatatypeTyParameter,
+        Ability, AbilitySet, Bytecode, CodeOffset, CodeUnit, Constant, DatatypeTyParameter,
         EnumDefinitionIndex, FieldHandleIndex, FunctionDefinitionIndex, FunctionHandle,
         JumpTableInner, ModuleHandle, Signature, SignatureIndex, SignatureToken,
         StructDefinitionIndex, StructFieldInformation, TableIndex, TypeSignature, Visibility,
@@ -19,7 +19,10 @@ use move_bytecode_source_map::{
     mapping::SourceMapping,
     source_map::{FunctionSourceMap, SourceName},
 };
-use move_command_line_common::display::{try_render_constant, RenderResult};
+use move_command_line_common::{
+    display::{try_render_constant, RenderResult},
+    files::FileHash,
+};
 use move_compiler::compiled_unit::CompiledUnit;
 use move_core_types::{identifier::IdentStr, language_storage::ModuleId};
 use move_coverage::coverage_map::{ExecCoverageMap, FunctionCoverage};
@@ -55,6 +58,11 @@ pub struct DisassemblerOptions {
     /// an error.
     #[clap(long = "max-output-size")]
     pub max_output_size: Option<usize>,
+
+    /// Annotate each instruction with the line of source code it was compiled from, when the
+    /// source code is available.
+    #[clap(skip)]
+    pub print_source_lines: bool,
 }
 
 impl DisassemblerOptions {
@@ -65,6 +73,7 @@ impl DisassemblerOptions {
             print_basic_blocks: true,
             print_locals: true,
             max_output_size: Some(MAX_OUTPUT_SIZE),
+            print_source_lines: false,
         }
     }
 }
@@ -79,6 +88,8 @@ pub struct Disassembler<'a> {
     /// `module_alias` will contain an entry for each distinct a
     /// e.g., for `use 0xA::M; use 0xB::M`, this will contain [(0xA, M) -> M, (0xB, M) -> 1M]
     module_aliases: HashMap<ModuleId, String>,
+    // Hash of the source code, if source lines are being printed.
+    source_file_hash: Option<FileHash>,
 }
 
 struct BoundedBuffer<'a> {
@@ -159,11 +170,17 @@ impl<'a> Disassembler<'a> {
                 })
                 .or_insert(0);
         }
+        let source_file_hash = options
+            .print_source_lines
+            .then_some(source_mapper.source_code.as_ref())
+            .flatten()
+            .map(|(_, source_code)| FileHash::new(source_code));
         Self {
             source_mapper,
             options,
             coverage_map: None,
             module_aliases,
+            source_file_hash,
         }
     }
 
@@ -210,6 +227,32 @@ impl<'a> Disassembler<'a> {
         };
         Ok(buffer)
     }
+
+    /// Disassemble just the definition of the function called `name` in this module.
+    pub fn disassemble_function(&self, name: &IdentStr) -> Result<String> {
+        let bytecode = self.source_mapper.bytecode;
+        let Some(idx) = bytecode.function_defs().iter().position(|def| {
+            bytecode.identifier_at(bytecode.function_handle_at(def.function).name) == name
+        }) else {
+            anyhow::bail!("Function {name} not found in module {}", bytecode.self_id());
+        };
+
+        let idx = FunctionDefinitionIndex(idx as TableIndex);
+        let mut buffer = String::new();
+        if let Some(budget) = self.options.max_output_size {
+            self.disassemble_function_definition(
+                &mut BoundedBuffer {
+                    buf: &mut buffer,
+                    budget,
+                },
+                idx,
+            )
+            .map_err(|e| anyhow::anyhow!("{e}: Function exceeded max allowed disassembly size"))?;
+        } else {
+            self.disassemble_function_definition(&mut buffer, idx)?;
+        };
+        Ok(buffer)
+    }
 }
 
 // Note on naming:
@@ -687,6 +730,10 @@ impl<'a> Disassembler<'a> {
                 instruction,
             )?;
 
+            if let Some(line) = self.source_line(function_source_map, pc as CodeOffset) {
+                any_write!(buffer, "\t// line {line}")?;
+            }
+
             any_writeln!(
                 buffer,
                 "{}",
@@ -1432,6 +1479,25 @@ impl<'a> Disassembler<'a> {
         }
     }
 
+    /// The (1-based) line in the source code that the instruction at `pc` was compiled from, if
+    /// source lines are being printed and the source code is available. Locations from other
+    /// files, such as the placeholder locations in a dummy source map, are ignored.
+    fn source_line(
+        &self,
+        function_source_map: &FunctionSourceMap,
+        pc: CodeOffset,
+    ) -> Option<usize> {
+        let source_file_hash = self.source_file_hash?;
+        let (_, source_code) = self.source_mapper.source_code.as_ref()?;
+        let loc = function_source_map.get_code_location(pc)?;
+        if loc.file_hash() != source_file_hash {
+            return None;
+        }
+
+        let prefix = source_code.get(..loc.start() as usize)?;
+        Some(prefix.matches('\n').count() + 1)
+    }
+
     //***************************************************************************
     // Code Coverage Helpers
     //***************************************************************************
@@ -2128,6 +2128,7 @@ impl<'env> ModuleEnv<'env> {
                 print_basic_blocks: true,
                 print_locals: true,
                 max_output_size: None,
+                print_source_lines: false,
             },
         );
         disas